@@ -0,0 +1,179 @@
+use ropey::Rope;
+
+use crate::CharIndex;
+
+/// A limit for the distance to a matching bracket that keeps searching fast in
+/// large files
+pub const SEARCH_LIMIT: usize = 100_000;
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// Finds the bracket matching the one at `char_index`, skipping over nested
+/// pairs of the same kind. Returns `None` if there is no bracket at
+/// `char_index` or if it's unmatched within `max_distance` characters.
+///
+/// Brackets inside strings and comments are not treated specially.
+pub fn matching_bracket(
+    text: &Rope,
+    char_index: CharIndex,
+    max_distance: usize,
+) -> Option<CharIndex> {
+    let character = text.get_char(char_index)?;
+    let (open, close, forward) = BRACKETS.iter().find_map(|&(open, close)| {
+        if character == open {
+            Some((open, close, true))
+        } else if character == close {
+            Some((open, close, false))
+        } else {
+            None
+        }
+    })?;
+
+    let mut depth = 0usize;
+    if forward {
+        for (offset, current) in text.chars_at(char_index).enumerate().take(max_distance) {
+            if current == open {
+                depth += 1;
+            } else if current == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(char_index + offset);
+                }
+            }
+        }
+    } else {
+        let mut chars = text.chars_at(char_index + 1);
+        for offset in 0..max_distance {
+            match chars.prev()? {
+                current if current == close => depth += 1,
+                current if current == open => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(char_index - offset);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// What typing a character does when brackets and quotes are closed
+/// automatically
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutoPair {
+    /// Insert the character followed by the given closing character
+    Close(char),
+    /// Move over the same closing character after the cursor instead of
+    /// inserting another one
+    Skip,
+}
+
+/// The character closing `character` if it's an opening bracket or one of
+/// `quotes`, which close themselves
+pub fn closing_pair(character: char, quotes: &[char]) -> Option<char> {
+    BRACKETS
+        .iter()
+        .find(|&&(open, _)| open == character)
+        .map(|&(_, close)| close)
+        .or_else(|| quotes.contains(&character).then(|| character))
+}
+
+/// Decides whether typing `character` at `char_index` closes a pair, moves
+/// over a closing character or just inserts it, in which case `None` is
+/// returned. Pairs are only closed before whitespace, a closing bracket or
+/// the end of the text, and quotes aren't closed right after a word, e.g. in
+/// `don't`.
+pub fn auto_pair(
+    text: &Rope,
+    char_index: CharIndex,
+    character: char,
+    quotes: &[char],
+) -> Option<AutoPair> {
+    let is_closing = |character: char| BRACKETS.iter().any(|&(_, close)| close == character);
+    let next = text.get_char(char_index);
+    if next == Some(character) && (is_closing(character) || quotes.contains(&character)) {
+        return Some(AutoPair::Skip);
+    }
+
+    let close = closing_pair(character, quotes)?;
+    if !next.map_or(true, |next| next.is_whitespace() || is_closing(next)) {
+        return None;
+    }
+    let after_word = char_index > 0 && text.char(char_index - 1).is_alphanumeric();
+    if close == character && after_word {
+        return None;
+    }
+    Some(AutoPair::Close(close))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_in_both_directions() {
+        let text = Rope::from("fn main() { let x = [1, (2)]; }");
+        assert_eq!(matching_bracket(&text, 7, 100), Some(8));
+        assert_eq!(matching_bracket(&text, 8, 100), Some(7));
+        assert_eq!(matching_bracket(&text, 10, 100), Some(30));
+        assert_eq!(matching_bracket(&text, 30, 100), Some(10));
+        assert_eq!(matching_bracket(&text, 20, 100), Some(27));
+        assert_eq!(matching_bracket(&text, 27, 100), Some(20));
+    }
+
+    #[test]
+    fn skips_nested_pairs_of_the_same_kind() {
+        let text = Rope::from("((a)(b))");
+        assert_eq!(matching_bracket(&text, 0, 100), Some(7));
+        assert_eq!(matching_bracket(&text, 7, 100), Some(0));
+        assert_eq!(matching_bracket(&text, 4, 100), Some(6));
+    }
+
+    #[test]
+    fn unmatched_or_not_a_bracket() {
+        let text = Rope::from("(a [b)");
+        assert_eq!(matching_bracket(&text, 1, 100), None);
+        assert_eq!(matching_bracket(&text, 3, 100), None);
+        assert_eq!(matching_bracket(&text, 6, 100), None);
+        // Too far away
+        assert_eq!(matching_bracket(&text, 0, 3), None);
+        assert_eq!(matching_bracket(&text, 0, 6), Some(5));
+    }
+
+    #[test]
+    fn closes_brackets_and_quotes() {
+        let quotes = ['"'];
+        let text = Rope::from("f() x");
+        assert_eq!(
+            auto_pair(&text, 2, '(', &quotes),
+            Some(AutoPair::Close(')'))
+        );
+        assert_eq!(
+            auto_pair(&text, 3, '[', &quotes),
+            Some(AutoPair::Close(']'))
+        );
+        assert_eq!(
+            auto_pair(&text, 3, '"', &quotes),
+            Some(AutoPair::Close('"'))
+        );
+        // Not a quote in this mode
+        assert_eq!(auto_pair(&text, 3, '\'', &quotes), None);
+        // Before a word
+        assert_eq!(auto_pair(&text, 4, '(', &quotes), None);
+        // After a word
+        assert_eq!(auto_pair(&text, 5, '"', &quotes), None);
+    }
+
+    #[test]
+    fn skips_over_closing_characters() {
+        let quotes = ['"'];
+        let text = Rope::from("f(\"\")");
+        assert_eq!(auto_pair(&text, 4, ')', &quotes), Some(AutoPair::Skip));
+        assert_eq!(auto_pair(&text, 3, '"', &quotes), Some(AutoPair::Skip));
+        assert_eq!(auto_pair(&text, 4, ']', &quotes), None);
+        assert_eq!(closing_pair('{', &quotes), Some('}'));
+        assert_eq!(closing_pair(')', &quotes), None);
+    }
+}
@@ -47,6 +47,83 @@ impl OpaqueDiff {
         *self == OpaqueDiff::empty()
     }
 
+    /// Combines this diff with the one of an edit applied right after it, if
+    /// the two edits are contiguous insertions or contiguous deletions. The
+    /// resulting diff transforms the text before this edit into the text after
+    /// the `next` one.
+    pub fn coalesce(&self, next: &OpaqueDiff) -> Option<Self> {
+        let is_insertion =
+            |diff: &OpaqueDiff| diff.old_char_length == 0 && diff.new_char_length > 0;
+        let is_deletion = |diff: &OpaqueDiff| diff.old_char_length > 0 && diff.new_char_length == 0;
+
+        if is_insertion(self)
+            && is_insertion(next)
+            && next.char_index == self.char_index + self.new_char_length
+        {
+            // Typing forward
+            Some(Self::new(
+                self.byte_index,
+                0,
+                self.new_byte_length + next.new_byte_length,
+                self.char_index,
+                0,
+                self.new_char_length + next.new_char_length,
+            ))
+        } else if is_deletion(self) && is_deletion(next) && next.char_index == self.char_index {
+            // Deleting forward
+            Some(Self::new(
+                self.byte_index,
+                self.old_byte_length + next.old_byte_length,
+                0,
+                self.char_index,
+                self.old_char_length + next.old_char_length,
+                0,
+            ))
+        } else if is_deletion(self)
+            && is_deletion(next)
+            && next.char_index + next.old_char_length == self.char_index
+        {
+            // Deleting backward
+            Some(Self::new(
+                next.byte_index,
+                self.old_byte_length + next.old_byte_length,
+                0,
+                next.char_index,
+                self.old_char_length + next.old_char_length,
+                0,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Combines this diff with the one of an edit applied right after it,
+    /// anywhere in the text. The result spans both edits, so it also covers
+    /// any unchanged text between them, e.g. when typing with several cursors.
+    pub fn compose(&self, next: &OpaqueDiff) -> Self {
+        if self.is_empty() {
+            return next.clone();
+        } else if next.is_empty() {
+            return self.clone();
+        }
+        let (byte_index, old_byte_length, new_byte_length) = compose_ranges(
+            (self.byte_index, self.old_byte_length, self.new_byte_length),
+            (next.byte_index, next.old_byte_length, next.new_byte_length),
+        );
+        let (char_index, old_char_length, new_char_length) = compose_ranges(
+            (self.char_index, self.old_char_length, self.new_char_length),
+            (next.char_index, next.old_char_length, next.new_char_length),
+        );
+        Self::new(
+            byte_index,
+            old_byte_length,
+            new_byte_length,
+            char_index,
+            old_char_length,
+            new_char_length,
+        )
+    }
+
     #[inline]
     pub fn reverse(&self) -> Self {
         Self {
@@ -60,6 +137,24 @@ impl OpaqueDiff {
     }
 }
 
+/// Composes two edits given as `(start, old_length, new_length)`, where the
+/// second one applies to the text produced by the first
+fn compose_ranges(
+    first: (usize, usize, usize),
+    second: (usize, usize, usize),
+) -> (usize, usize, usize) {
+    let (start, old_length, new_length) = first;
+    let (next_start, next_old_length, next_new_length) = second;
+
+    // The span covering both edits in the text between them, mapped back to
+    // the original text and forward to the final one
+    let middle_start = start.min(next_start);
+    let middle_end = (start + new_length).max(next_start + next_old_length);
+    let old_end = middle_end - new_length + old_length;
+    let new_end = middle_end - next_old_length + next_new_length;
+    (middle_start, old_end - middle_start, new_end - middle_start)
+}
+
 pub struct DeleteOperation {
     pub diff: OpaqueDiff,
     pub deleted: Rope,
@@ -79,6 +174,69 @@ mod tests {
     use super::*;
     use ropey::Rope;
 
+    #[test]
+    fn coalesce_contiguous_insertions() {
+        let first = OpaqueDiff::new(3, 0, 1, 3, 0, 1);
+        let second = OpaqueDiff::new(4, 0, 2, 4, 0, 1);
+        assert_eq!(
+            Some(OpaqueDiff::new(3, 0, 3, 3, 0, 2)),
+            first.coalesce(&second)
+        );
+    }
+
+    #[test]
+    fn coalesce_deletions_in_both_directions() {
+        let first = OpaqueDiff::new(5, 1, 0, 5, 1, 0);
+        let backward = OpaqueDiff::new(4, 1, 0, 4, 1, 0);
+        assert_eq!(
+            Some(OpaqueDiff::new(4, 2, 0, 4, 2, 0)),
+            first.coalesce(&backward)
+        );
+
+        let forward = OpaqueDiff::new(5, 2, 0, 5, 1, 0);
+        assert_eq!(
+            Some(OpaqueDiff::new(5, 3, 0, 5, 2, 0)),
+            first.coalesce(&forward)
+        );
+    }
+
+    #[test]
+    fn coalesce_rejects_unrelated_edits() {
+        let insertion = OpaqueDiff::new(3, 0, 1, 3, 0, 1);
+        let deletion = OpaqueDiff::new(3, 1, 0, 3, 1, 0);
+        let distant_insertion = OpaqueDiff::new(10, 0, 1, 10, 0, 1);
+        assert_eq!(None, insertion.coalesce(&deletion));
+        assert_eq!(None, deletion.coalesce(&insertion));
+        assert_eq!(None, insertion.coalesce(&distant_insertion));
+        assert_eq!(None, OpaqueDiff::empty().coalesce(&insertion));
+    }
+
+    #[test]
+    fn compose_distant_edits() {
+        // "abc" -> "aXbc" -> "aXb"
+        let insertion = OpaqueDiff::new(1, 0, 1, 1, 0, 1);
+        let deletion = OpaqueDiff::new(3, 1, 0, 3, 1, 0);
+        assert_eq!(
+            OpaqueDiff::new(1, 2, 2, 1, 2, 2),
+            insertion.compose(&deletion)
+        );
+
+        // "abcdef" -> "abcdXef" -> "YabcdXef"
+        let insertion = OpaqueDiff::new(4, 0, 1, 4, 0, 1);
+        let before = OpaqueDiff::new(0, 0, 1, 0, 0, 1);
+        assert_eq!(
+            OpaqueDiff::new(0, 4, 6, 0, 4, 6),
+            insertion.compose(&before)
+        );
+    }
+
+    #[test]
+    fn compose_with_empty_diffs() {
+        let insertion = OpaqueDiff::new(3, 0, 2, 3, 0, 1);
+        assert_eq!(insertion, OpaqueDiff::empty().compose(&insertion));
+        assert_eq!(insertion, insertion.compose(&OpaqueDiff::empty()));
+    }
+
     #[test]
     fn mem_size_of_diffs() {
         assert_eq!(std::mem::size_of::<OpaqueDiff>(), 48);
@@ -1,19 +1,33 @@
 use ropey::{iter::Chunks, str_utils, Rope, RopeSlice};
-use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete, UnicodeSegmentation};
 use unicode_width::UnicodeWidthStr;
 
+use crate::line_ending;
+
 pub type ByteIndex = usize;
 pub type CharIndex = usize;
 pub type LineIndex = usize;
 
+/// The number of terminal cells taken up by a slice of text. Wide characters
+/// take up two cells and combining marks none, as part of the grapheme cluster
+/// they modify. Grapheme clusters without a glyph of their own, e.g. control
+/// characters, are drawn as a blank cell, while line endings take up no space.
 pub fn width(tab_width: usize, slice: &RopeSlice) -> usize {
     rope_slice_as_str(slice, |text| {
-        if text == "\t" {
-            tab_width
-        } else {
-            text.chars().filter(|character| *character == '\t').count() * tab_width
-                + UnicodeWidthStr::width(text)
-        }
+        text.graphemes(true)
+            .map(|grapheme| match grapheme {
+                "\t" => tab_width,
+                "\n" | "\r\n" | "\r" => 0,
+                _ => UnicodeWidthStr::width(grapheme).max(1),
+            })
+            .sum()
+    })
+}
+
+/// Whether a grapheme cluster has a glyph to draw, rather than a blank cell
+pub fn has_glyph(slice: &RopeSlice) -> bool {
+    rope_slice_as_str(slice, |text| {
+        !text.chars().all(char::is_control) && UnicodeWidthStr::width(text) > 0
     })
 }
 
@@ -119,8 +133,12 @@ impl<'a> Iterator for RopeGraphemes<'a> {
     }
 }
 
+/// Removes the whitespace at the end of lines and the empty lines at the end
+/// of the text. Whether the text ends with a line break is preserved, so
+/// saving doesn't add or remove one behind the user's back.
 pub fn strip_trailing_whitespace(mut text: Rope) -> Rope {
     // Pretty inefficient (t)
+    let lacked_final_line_break = line_ending::lacks_final_line_break(&text);
 
     let mut trailing_empty_line = true;
     for line_index in (0..text.len_lines()).rev() {
@@ -134,7 +152,15 @@ pub fn strip_trailing_whitespace(mut text: Rope) -> Rope {
             continue;
         }
 
-        let mut cursor = end - 1;
+        // The end of the line's content, before its line break if it has one
+        let mut cursor = end;
+        if text.char(cursor - 1) == '\n' {
+            cursor -= 1;
+            // Keep the carriage return of a `\r\n` line break
+            if cursor > start && text.char(cursor - 1) == '\r' {
+                cursor -= 1;
+            }
+        }
         while cursor > start {
             cursor -= 1;
             let character = text.char(cursor);
@@ -150,8 +176,15 @@ pub fn strip_trailing_whitespace(mut text: Rope) -> Rope {
         }
     }
 
-    if text.len_chars() > 1 && text.char(text.len_chars() - 1) != '\n' {
-        text.insert_char(text.len_chars(), '\n');
+    // Stripping whitespace-only lines at the end can leave the line before
+    // them last, with its line break
+    if lacked_final_line_break && !line_ending::lacks_final_line_break(&text) {
+        let end = text.len_chars();
+        let start = match text.get_char(end.wrapping_sub(2)) {
+            Some('\r') => end - 2,
+            _ => end - 1,
+        };
+        text.remove(start..end);
     }
 
     text
@@ -291,5 +324,57 @@ mod tests {
         assert_eq!(text.len_chars(), grapheme_end);
     }
 
+    #[test]
+    fn strip_trailing_whitespace_keeps_line_endings() {
+        assert_eq!(
+            strip_trailing_whitespace(Rope::from("one  \ntwo\t\n\n")),
+            "one\ntwo\n"
+        );
+        assert_eq!(
+            strip_trailing_whitespace(Rope::from("one  \r\ntwo\t\r\n\r\n")),
+            "one\r\ntwo\r\n"
+        );
+    }
+
+    #[test]
+    fn strip_trailing_whitespace_keeps_the_final_line_break_or_its_absence() {
+        for text in ["one\ntwo\n", "one\ntwo", "one\r\ntwo\r\n", "one\r\ntwo", ""] {
+            assert_eq!(strip_trailing_whitespace(Rope::from(text)), text);
+        }
+        assert_eq!(
+            strip_trailing_whitespace(Rope::from("one \ntwo ")),
+            "one\ntwo"
+        );
+        assert_eq!(strip_trailing_whitespace(Rope::from("one\n\n  ")), "one");
+        assert_eq!(strip_trailing_whitespace(Rope::from("one\r\n\t")), "one");
+        // Only empty lines are left
+        assert_eq!(strip_trailing_whitespace(Rope::from("\n")), "");
+    }
+
+    #[test]
+    fn width_of_wide_and_combining_characters() {
+        let width = |text: &str| width(4, &Rope::from(text).slice(..));
+        assert_eq!(width("abc"), 3);
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(width("a日b"), 4);
+        // `e` followed by a combining acute accent
+        assert_eq!(width("e\u{301}t\u{301}"), 2);
+        assert_eq!(width("\tx"), 5);
+        assert_eq!(width("a\tb\t"), 10);
+        assert_eq!(width("ab\r\n"), 2);
+        // Control characters and zero width spaces are drawn as a blank cell
+        assert_eq!(width("a\u{1}b\u{200b}"), 4);
+    }
+
+    #[test]
+    fn grapheme_clusters_without_a_glyph() {
+        let has_glyph = |text: &str| has_glyph(&Rope::from(text).slice(..));
+        assert!(has_glyph("a"));
+        assert!(has_glyph("語"));
+        assert!(has_glyph("e\u{301}"));
+        assert!(!has_glyph("\u{1}"));
+        assert!(!has_glyph("\u{200b}"));
+    }
+
     const MULTI_CHAR_EMOJI: &str = r#"👨‍👨‍👧‍👧"#;
 }
@@ -0,0 +1,60 @@
+use ropey::Rope;
+
+use crate::CharIndex;
+
+/// Returns the indentation for a line break inserted at `char_index`. The new
+/// line keeps the leading whitespace of the current line, up to the insertion
+/// point. One more `indent_unit` is added if the text before the insertion
+/// point ends in one of the `indent_after` characters, e.g. `{` or `:`.
+pub fn new_line_indentation(
+    text: &Rope,
+    char_index: CharIndex,
+    indent_unit: &str,
+    indent_after: &[char],
+) -> String {
+    let line_start = text.line_to_char(text.char_to_line(char_index));
+    let mut indentation: String = text
+        .slice(line_start..char_index)
+        .chars()
+        .take_while(|&character| character == ' ' || character == '\t')
+        .collect();
+
+    let last_character = text
+        .slice(line_start..char_index)
+        .chars_at(char_index - line_start)
+        .reversed()
+        .find(|character| !character.is_whitespace());
+    if matches!(last_character, Some(character) if indent_after.contains(&character)) {
+        indentation.push_str(indent_unit);
+    }
+    indentation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_leading_whitespace() {
+        let text = Rope::from("fn main() {\n    let x = 1;\n\tfoo\n");
+        assert_eq!(new_line_indentation(&text, 26, "    ", &[]), "    ");
+        assert_eq!(new_line_indentation(&text, 31, "    ", &[]), "\t");
+        assert_eq!(new_line_indentation(&text, 0, "    ", &['{']), "");
+    }
+
+    #[test]
+    fn only_whitespace_before_the_insertion_point_is_kept() {
+        let text = Rope::from("        foo");
+        assert_eq!(new_line_indentation(&text, 4, "  ", &[]), "    ");
+    }
+
+    #[test]
+    fn indents_after_opening_characters() {
+        let text = Rope::from("fn main() {\n    if x:  \n");
+        assert_eq!(new_line_indentation(&text, 11, "    ", &['{']), "    ");
+        assert_eq!(new_line_indentation(&text, 23, "    ", &[':']), "        ");
+        assert_eq!(new_line_indentation(&text, 23, "    ", &['{']), "    ");
+        // Only the text before the insertion point counts
+        assert_eq!(new_line_indentation(&text, 10, "    ", &['{']), "");
+    }
+}
@@ -1,6 +1,12 @@
+pub mod brackets;
 pub mod graphemes;
+pub mod indent;
+pub mod line_editor;
+pub mod line_ending;
 pub mod movement;
+pub mod search;
 pub mod tree;
+pub mod wrap;
 
 mod diff;
 
@@ -189,6 +195,31 @@ impl Cursor {
         )
     }
 
+    /// Surrounds the selection with `open` and `close`, e.g. to put it in
+    /// brackets. The selected text stays selected.
+    pub fn wrap_selection(&mut self, text: &mut Rope, open: char, close: char) -> OpaqueDiff {
+        let selection = self.selection();
+        let byte_range = text.char_to_byte(selection.start)..text.char_to_byte(selection.end);
+        text.insert_char(selection.end, close);
+        text.insert_char(selection.start, open);
+        self.shift_forward(text, 1);
+        OpaqueDiff::new(
+            byte_range.start,
+            byte_range.len(),
+            byte_range.len() + open.len_utf8() + close.len_utf8(),
+            selection.start,
+            selection.len(),
+            selection.len() + 2,
+        )
+    }
+
+    /// Whether some text is selected, as opposed to a selection which was
+    /// started but hasn't been extended yet
+    pub fn has_selection(&self) -> bool {
+        self.selection
+            .map_or(false, |selection| selection != self.range.start)
+    }
+
     pub fn delete_forward(&mut self, text: &mut Rope) -> DeleteOperation {
         if text.len_chars() == 0 || text.len_chars() == self.range.start {
             return DeleteOperation::empty();
@@ -283,6 +314,266 @@ impl Cursor {
         DeleteOperation { diff, deleted }
     }
 
+    /// Inserts a copy of the lines spanned by the selection, or of the line
+    /// under the cursor, below them. The cursor and the selection move to the
+    /// copy.
+    pub fn duplicate_lines(&mut self, text: &mut Rope, line_ending: &str) -> OpaqueDiff {
+        let lines = self.selected_lines(text);
+        if lines.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        // The last line of the text may not end in a line break
+        let mut copy: String = text.slice(lines.clone()).into();
+        let (_, ending) = split_line_ending(&copy);
+        if ending.is_empty() {
+            copy.insert_str(0, line_ending);
+        }
+        let byte_index = text.char_to_byte(lines.end);
+        text.insert(lines.end, &copy);
+        let num_chars = copy.chars().count();
+        self.shift_forward(text, num_chars);
+        OpaqueDiff::new(byte_index, 0, copy.len(), lines.end, 0, num_chars)
+    }
+
+    /// Swaps the lines spanned by the selection, or the line under the cursor,
+    /// with the line above or below them. The cursor and the selection move
+    /// with the lines.
+    pub fn move_lines(&mut self, text: &mut Rope, direction: Direction) -> OpaqueDiff {
+        let lines = self.selected_lines(text);
+        let line_range = text.char_to_line(lines.start)..text.char_to_line(lines.end);
+        let (upper, lower) = match direction {
+            Direction::Backward if line_range.start > 0 && !lines.is_empty() => (
+                text.line_to_char(line_range.start - 1)..lines.start,
+                lines.clone(),
+            ),
+            Direction::Forward if line_range.end + 1 < text.len_lines() => (
+                lines.clone(),
+                lines.end..text.line_to_char(line_range.end + 1),
+            ),
+            // The line after the last line break is left in place, as it has
+            // no line break to swap with
+            Direction::Forward
+                if line_range.end + 1 == text.len_lines() && lines.end < text.len_chars() =>
+            {
+                (lines.clone(), lines.end..text.len_chars())
+            }
+            _ => return OpaqueDiff::empty(),
+        };
+
+        let upper_text: String = text.slice(upper.clone()).into();
+        let lower_text: String = text.slice(lower.clone()).into();
+        let (upper_content, upper_ending) = split_line_ending(&upper_text);
+        let (lower_content, lower_ending) = split_line_ending(&lower_text);
+        let swapped = [lower_content, upper_ending, upper_content, lower_ending].concat();
+
+        let byte_start = text.char_to_byte(upper.start);
+        let byte_length = text.char_to_byte(lower.end) - byte_start;
+        text.remove(upper.start..lower.end);
+        text.insert(upper.start, &swapped);
+
+        match direction {
+            Direction::Backward => self.shift_backward(text, lower.start - upper.start),
+            Direction::Forward => self.shift_forward(
+                text,
+                lower_content.chars().count() + upper_ending.chars().count(),
+            ),
+        }
+        OpaqueDiff::new(
+            byte_start,
+            byte_length,
+            swapped.len(),
+            upper.start,
+            lower.end - upper.start,
+            lower.end - upper.start,
+        )
+    }
+
+    /// Comments out the lines spanned by the selection, or the line under the
+    /// cursor, by inserting `token` at their common indentation. If all of them
+    /// are commented out already, the comment tokens are removed instead.
+    /// Blank lines are left unchanged.
+    pub fn toggle_comment(&mut self, text: &mut Rope, token: &str) -> OpaqueDiff {
+        let lines = self.selected_lines(text);
+        let trimmed_token = token.trim_end();
+        if lines.is_empty() || trimmed_token.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        // The start of each non-blank line and the length of its indentation
+        let line_range = text.char_to_line(lines.start)..text.char_to_line(lines.end - 1) + 1;
+        let indented_lines: Vec<_> = line_range
+            .filter_map(|line_index| {
+                let line = text.line(line_index);
+                let indentation = line
+                    .chars()
+                    .take_while(|character| *character == ' ' || *character == '\t')
+                    .count();
+                let blank = line
+                    .chars()
+                    .skip(indentation)
+                    .all(|character| character == '\n' || character == '\r');
+                (!blank).then(|| (text.line_to_char(line_index), indentation))
+            })
+            .collect();
+        if indented_lines.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        let starts_with = |char_index: CharIndex, prefix: &str| {
+            text.chars_at(char_index)
+                .zip(prefix.chars())
+                .filter(|(character, expected)| character == expected)
+                .count()
+                == prefix.chars().count()
+        };
+        let uncomment = indented_lines
+            .iter()
+            .all(|&(line_start, indentation)| starts_with(line_start + indentation, trimmed_token));
+
+        // Edits as (position, number of characters removed, text inserted), in
+        // the order of the lines
+        let edits: Vec<(CharIndex, usize, &str)> = if uncomment {
+            indented_lines
+                .iter()
+                .map(|&(line_start, indentation)| {
+                    let position = line_start + indentation;
+                    let removed = if starts_with(position, token) {
+                        token
+                    } else {
+                        trimmed_token
+                    };
+                    (position, removed.chars().count(), "")
+                })
+                .collect()
+        } else {
+            let column = indented_lines
+                .iter()
+                .map(|&(_, indentation)| indentation)
+                .min()
+                .unwrap_or(0);
+            indented_lines
+                .iter()
+                .map(|&(line_start, _)| (line_start + column, 0, token))
+                .collect()
+        };
+
+        self.apply_line_edits(text, lines, &edits)
+    }
+
+    /// Indents the lines spanned by the selection, or the line under the
+    /// cursor, by inserting `unit` at their start, e.g. four spaces or a tab.
+    /// Blank lines are left unchanged.
+    pub fn indent_lines(&mut self, text: &mut Rope, unit: &str) -> OpaqueDiff {
+        let lines = self.selected_lines(text);
+        if lines.is_empty() || unit.is_empty() {
+            return OpaqueDiff::empty();
+        }
+        let line_range = text.char_to_line(lines.start)..text.char_to_line(lines.end - 1) + 1;
+        let edits: Vec<(CharIndex, usize, &str)> = line_range
+            .filter(|&line_index| {
+                !text
+                    .line(line_index)
+                    .chars()
+                    .all(|character| character.is_whitespace())
+            })
+            .map(|line_index| (text.line_to_char(line_index), 0, unit))
+            .collect();
+        self.apply_line_edits(text, lines, &edits)
+    }
+
+    /// Removes one level of indentation from the lines spanned by the
+    /// selection, or the line under the cursor: a leading tab or up to `width`
+    /// leading spaces.
+    pub fn dedent_lines(&mut self, text: &mut Rope, width: usize) -> OpaqueDiff {
+        let lines = self.selected_lines(text);
+        if lines.is_empty() {
+            return OpaqueDiff::empty();
+        }
+        let line_range = text.char_to_line(lines.start)..text.char_to_line(lines.end - 1) + 1;
+        let edits: Vec<(CharIndex, usize, &str)> = line_range
+            .filter_map(|line_index| {
+                let line = text.line(line_index);
+                let removed = match line.chars().next() {
+                    Some('\t') => 1,
+                    _ => line
+                        .chars()
+                        .take(width)
+                        .take_while(|character| *character == ' ')
+                        .count(),
+                };
+                (removed > 0).then(|| (text.line_to_char(line_index), removed, ""))
+            })
+            .collect();
+        self.apply_line_edits(text, lines, &edits)
+    }
+
+    /// Applies `edits` to the characters of `lines`, keeping the cursor and the
+    /// selection on the same text. The edits are given as (position, number of
+    /// characters removed, text inserted), in the order of the lines.
+    fn apply_line_edits(
+        &mut self,
+        text: &mut Rope,
+        lines: Range<CharIndex>,
+        edits: &[(CharIndex, usize, &str)],
+    ) -> OpaqueDiff {
+        if edits.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        let byte_start = text.char_to_byte(lines.start);
+        let old_byte_length = text.char_to_byte(lines.end) - byte_start;
+        for &(position, removed, inserted) in edits.iter().rev() {
+            text.remove(position..position + removed);
+            text.insert(position, inserted);
+        }
+        let new_end = map_position(edits, lines.end);
+        let new_byte_length = text.char_to_byte(new_end) - byte_start;
+
+        let start = map_position(edits, self.range.start);
+        self.range = start..text.next_grapheme_boundary(start);
+        self.selection = self
+            .selection
+            .map(|selection| map_position(edits, selection));
+        self.visual_horizontal_offset = None;
+
+        OpaqueDiff::new(
+            byte_start,
+            old_byte_length,
+            new_byte_length,
+            lines.start,
+            lines.end - lines.start,
+            new_end - lines.start,
+        )
+    }
+
+    /// The characters of the lines spanned by the selection, including the
+    /// line break of the last one
+    fn selected_lines(&self, text: &Rope) -> Range<CharIndex> {
+        let selection = self.selection();
+        let first_line = text.char_to_line(selection.start);
+        let last_line = if selection.end > selection.start {
+            text.char_to_line(selection.end - 1)
+        } else {
+            first_line
+        };
+        text.line_to_char(first_line)..text.line_to_char(last_line + 1)
+    }
+
+    fn shift_forward(&mut self, text: &Rope, num_chars: usize) {
+        let start = self.range.start + num_chars;
+        self.range = start..text.next_grapheme_boundary(start);
+        self.selection = self.selection.map(|selection| selection + num_chars);
+        self.visual_horizontal_offset = None;
+    }
+
+    fn shift_backward(&mut self, text: &Rope, num_chars: usize) {
+        let start = self.range.start - num_chars;
+        self.range = start..text.next_grapheme_boundary(start);
+        self.selection = self.selection.map(|selection| selection - num_chars);
+        self.visual_horizontal_offset = None;
+    }
+
     pub fn sync(&mut self, current_text: &Rope, new_text: &Rope) {
         let current_line = current_text.char_to_line(self.range.start);
         let current_line_offset = self.range.start - current_text.line_to_char(current_line);
@@ -300,6 +591,31 @@ impl Cursor {
     }
 }
 
+/// Maps a position in a text to the same position after applying `edits`, given
+/// as (position, number of characters removed, text inserted) in increasing
+/// order of position. Positions inside removed text move to its start.
+fn map_position(edits: &[(CharIndex, usize, &str)], position: CharIndex) -> CharIndex {
+    let mut mapped = position;
+    for &(edit_position, removed, inserted) in edits {
+        if position >= edit_position + removed {
+            mapped = mapped + inserted.chars().count() - removed;
+        } else if position > edit_position {
+            mapped -= position - edit_position;
+        }
+    }
+    mapped
+}
+
+/// Splits a line into its content and its line break, which is empty on the
+/// last line of a text
+fn split_line_ending(line: &str) -> (&str, &str) {
+    let content = line
+        .strip_suffix("\r\n")
+        .or_else(|| line.strip_suffix('\n'))
+        .unwrap_or(line);
+    line.split_at(content.len())
+}
+
 #[cfg(test)]
 mod tests {
     use ropey::Rope;
@@ -376,6 +692,139 @@ mod tests {
         assert_eq!(expected, text);
     }
 
+    // Wrap the selection
+    #[test]
+    fn wrap_selection_in_brackets() {
+        let (mut text, mut cursor) = text_with_cursor("let x = a + b;");
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 8);
+        assert!(!cursor.has_selection());
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 5);
+        assert!(cursor.has_selection());
+        let diff = cursor.wrap_selection(&mut text, '(', ')');
+        assert_eq!(text, "let x = (a + b);");
+        assert_eq!(cursor.selection(), 9..14);
+        assert_eq!(diff, OpaqueDiff::new(8, 5, 7, 8, 5, 7));
+    }
+
+    // Indentation
+    #[test]
+    fn indent_and_dedent_selected_lines() {
+        let (mut text, mut cursor) = text_with_cursor("fn f() {\na();\n\n\tb();\n}\n");
+        movement::move_to_line(&text, &mut cursor, 1, 1);
+        cursor.begin_selection();
+        movement::move_to_line(&text, &mut cursor, 3, 2);
+        let diff = cursor.indent_lines(&mut text, "    ");
+        assert_eq!(text, "fn f() {\n    a();\n\n    \tb();\n}\n");
+        assert_eq!(cursor.selection(), 14..25);
+        assert_eq!(diff, OpaqueDiff::new(9, 12, 20, 9, 12, 20));
+
+        cursor.dedent_lines(&mut text, 4);
+        cursor.dedent_lines(&mut text, 4);
+        assert_eq!(text, "fn f() {\na();\n\nb();\n}\n");
+        assert_eq!(cursor.selection(), 10..16);
+
+        // Nothing left to remove
+        assert_eq!(cursor.dedent_lines(&mut text, 4), OpaqueDiff::empty());
+    }
+
+    // Duplicate and move lines
+    #[test]
+    fn duplicate_the_line_under_the_cursor() {
+        let (mut text, mut cursor) = text_with_cursor("one\ntwo\nthree");
+        movement::move_to_line(&text, &mut cursor, 1, 1);
+        cursor.duplicate_lines(&mut text, "\n");
+        assert_eq!(text, "one\ntwo\ntwo\nthree");
+        assert_eq!(cursor.range(), 9..10);
+
+        // The last line has no line break
+        movement::move_to_end_of_buffer(&text, &mut cursor);
+        let diff = cursor.duplicate_lines(&mut text, "\n");
+        assert_eq!(text, "one\ntwo\ntwo\nthree\nthree");
+        assert_eq!(cursor.range(), 23..23);
+        assert_eq!(diff, OpaqueDiff::new(17, 0, 6, 17, 0, 6));
+    }
+
+    #[test]
+    fn duplicate_selected_lines() {
+        let (mut text, mut cursor) = text_with_cursor("one\ntwo\nthree\n");
+        movement::move_to_line(&text, &mut cursor, 0, 1);
+        cursor.begin_selection();
+        movement::move_to_line(&text, &mut cursor, 1, 1);
+        cursor.duplicate_lines(&mut text, "\n");
+        assert_eq!(text, "one\ntwo\none\ntwo\nthree\n");
+        assert_eq!(cursor.selection(), 9..13);
+    }
+
+    #[test]
+    fn move_lines_up_and_down() {
+        let (mut text, mut cursor) = text_with_cursor("one\ntwo\nthree");
+        movement::move_to_line(&text, &mut cursor, 1, 2);
+        cursor.move_lines(&mut text, Direction::Backward);
+        assert_eq!(text, "two\none\nthree");
+        assert_eq!(cursor.range(), 2..3);
+
+        // Already at the top
+        let diff = cursor.move_lines(&mut text, Direction::Backward);
+        assert!(diff.is_empty());
+
+        cursor.move_lines(&mut text, Direction::Forward);
+        cursor.move_lines(&mut text, Direction::Forward);
+        assert_eq!(text, "one\nthree\ntwo");
+        assert_eq!(cursor.range(), 12..13);
+
+        // Already at the bottom
+        let diff = cursor.move_lines(&mut text, Direction::Forward);
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn move_lines_keeps_the_trailing_line_break() {
+        let (mut text, mut cursor) = text_with_cursor("one\ntwo\n");
+        cursor.move_lines(&mut text, Direction::Forward);
+        assert_eq!(text, "two\none\n");
+        assert_eq!(cursor.range(), 4..5);
+        let diff = cursor.move_lines(&mut text, Direction::Forward);
+        assert!(diff.is_empty());
+        assert_eq!(text, "two\none\n");
+    }
+
+    // Comments
+    #[test]
+    fn toggle_comment_on_the_line_under_the_cursor() {
+        let (mut text, mut cursor) = text_with_cursor("fn main() {\n    let x = 1;\n}\n");
+        movement::move_to_line(&text, &mut cursor, 1, 8);
+        cursor.toggle_comment(&mut text, "// ");
+        assert_eq!(text, "fn main() {\n    // let x = 1;\n}\n");
+        assert_eq!(cursor.range(), 23..24);
+
+        let diff = cursor.toggle_comment(&mut text, "// ");
+        assert_eq!(text, "fn main() {\n    let x = 1;\n}\n");
+        assert_eq!(cursor.range(), 20..21);
+        assert_eq!(diff, OpaqueDiff::new(12, 18, 15, 12, 18, 15));
+    }
+
+    #[test]
+    fn toggle_comment_on_selected_lines() {
+        let (mut text, mut cursor) = text_with_cursor("  a\n\n    b\n  #c\n");
+        movement::move_to_line(&text, &mut cursor, 0, 1);
+        cursor.begin_selection();
+        movement::move_to_line(&text, &mut cursor, 3, 1);
+
+        // Not all lines are commented out, so comment out all of them at the
+        // common indentation, skipping blank lines
+        cursor.toggle_comment(&mut text, "# ");
+        assert_eq!(text, "  # a\n\n  #   b\n  # #c\n");
+
+        cursor.toggle_comment(&mut text, "# ");
+        assert_eq!(text, "  a\n\n    b\n  #c\n");
+
+        // A token without the trailing space is removed as well
+        let (mut text, mut cursor) = text_with_cursor("#a\n");
+        cursor.toggle_comment(&mut text, "# ");
+        assert_eq!(text, "a\n");
+    }
+
     const TEXT: &str = r#"
 Basic Latin
     ! " # $ % & ' ( ) *+,-./012ABCDEFGHI` a m  t u v z { | } ~
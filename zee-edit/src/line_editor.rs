@@ -0,0 +1,376 @@
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::ByteIndex;
+
+/// A single line of editable text with a cursor, e.g. the input of a prompt.
+/// It can also go back to previously entered lines, which are kept by the
+/// owner of the editor and passed in with [`LineEditor::with_history`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineEditor {
+    text: String,
+    // Always at a grapheme cluster boundary
+    cursor: ByteIndex,
+    // The previously entered lines, the most recent last
+    history: Vec<String>,
+    // The history entry being shown, if any, and the text typed before going
+    // back in the history
+    history_index: Option<usize>,
+    draft: String,
+}
+
+impl LineEditor {
+    /// An editor with the cursor at the end of `text`
+    pub fn new(text: impl Into<String>) -> Self {
+        let mut editor = Self::default();
+        editor.set_text(text);
+        editor
+    }
+
+    pub fn with_history(mut self, history: Vec<String>) -> Self {
+        self.history = history;
+        self.history_index = None;
+        self
+    }
+
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The byte offset of the cursor in the text
+    #[inline]
+    pub fn cursor(&self) -> ByteIndex {
+        self.cursor
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The grapheme clusters of the text with their byte offsets
+    pub fn graphemes(&self) -> impl Iterator<Item = (ByteIndex, &str)> {
+        self.text.grapheme_indices(true)
+    }
+
+    /// Replaces the text and moves the cursor to its end
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = single_line(text.into());
+        self.cursor = self.text.len();
+    }
+
+    /// The number of terminal cells taken up by the text before the cursor
+    pub fn cursor_column(&self) -> usize {
+        display_width(&self.text[..self.cursor])
+    }
+
+    pub fn insert_char(&mut self, character: char) {
+        let mut buffer = [0; 4];
+        self.insert_str(character.encode_utf8(&mut buffer));
+    }
+
+    /// Inserts text at the cursor and moves the cursor after it. Line breaks
+    /// are replaced by spaces.
+    pub fn insert_str(&mut self, text: &str) {
+        let text = single_line(text.to_owned());
+        self.text.insert_str(self.cursor, &text);
+        self.cursor = self.align(self.cursor + text.len());
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.previous_boundary(self.cursor);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary(self.cursor);
+    }
+
+    pub fn move_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_to_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Moves the cursor to the start of the previous word
+    pub fn move_backward_word(&mut self) {
+        self.cursor = self.backward_word_start();
+    }
+
+    /// Moves the cursor to the end of the next word
+    pub fn move_forward_word(&mut self) {
+        self.cursor = self.forward_word_end();
+    }
+
+    pub fn delete_backward(&mut self) {
+        self.delete(self.previous_boundary(self.cursor)..self.cursor);
+    }
+
+    pub fn delete_forward(&mut self) {
+        self.delete(self.cursor..self.next_boundary(self.cursor));
+    }
+
+    /// Deletes from the start of the previous word to the cursor
+    pub fn delete_backward_word(&mut self) {
+        self.delete(self.backward_word_start()..self.cursor);
+    }
+
+    /// Deletes from the cursor to the end of the next word
+    pub fn delete_forward_word(&mut self) {
+        self.delete(self.cursor..self.forward_word_end());
+    }
+
+    pub fn delete_to_start(&mut self) {
+        self.delete(0..self.cursor);
+    }
+
+    pub fn delete_to_end(&mut self) {
+        self.delete(self.cursor..self.text.len());
+    }
+
+    /// Shows the previous history entry. The text being typed is kept to come
+    /// back to after the most recent entry.
+    pub fn previous_history(&mut self) {
+        let index = match self.history_index {
+            Some(0) => return,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.text.clone();
+                self.history.len() - 1
+            }
+        };
+        self.history_index = Some(index);
+        self.set_text(self.history[index].clone());
+    }
+
+    /// Shows the next history entry, or the text typed before going back in
+    /// the history after the most recent one
+    pub fn next_history(&mut self) {
+        match self.history_index {
+            Some(index) if index + 1 < self.history.len() => {
+                self.history_index = Some(index + 1);
+                self.set_text(self.history[index + 1].clone());
+            }
+            Some(_) => {
+                self.history_index = None;
+                let draft = std::mem::take(&mut self.draft);
+                self.set_text(draft);
+            }
+            None => {}
+        }
+    }
+
+    fn delete(&mut self, range: Range<ByteIndex>) {
+        if range.is_empty() {
+            return;
+        }
+        self.text.replace_range(range.clone(), "");
+        self.cursor = self.align(range.start);
+    }
+
+    fn previous_boundary(&self, offset: ByteIndex) -> ByteIndex {
+        self.text[..offset]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(start, _)| start)
+    }
+
+    fn next_boundary(&self, offset: ByteIndex) -> ByteIndex {
+        self.text[offset..]
+            .graphemes(true)
+            .next()
+            .map_or(offset, |grapheme| offset + grapheme.len())
+    }
+
+    // The start of the grapheme cluster containing `offset`, as editing can
+    // join a combining mark with the grapheme before it
+    fn align(&self, offset: ByteIndex) -> ByteIndex {
+        if offset >= self.text.len() {
+            return self.text.len();
+        }
+        self.text
+            .grapheme_indices(true)
+            .map(|(start, _)| start)
+            .take_while(|&start| start <= offset)
+            .last()
+            .unwrap_or(0)
+    }
+
+    fn backward_word_start(&self) -> ByteIndex {
+        words(&self.text)
+            .rev()
+            .map(|word| word.start)
+            .find(|&start| start < self.cursor)
+            .unwrap_or(0)
+    }
+
+    fn forward_word_end(&self) -> ByteIndex {
+        words(&self.text)
+            .map(|word| word.end)
+            .find(|&end| end > self.cursor)
+            .unwrap_or(self.text.len())
+    }
+}
+
+/// Adds an entry to a history of entered lines, the most recent last.
+/// Earlier copies of the same entry are removed and the oldest entries are
+/// dropped once there are more than `max_entries`.
+pub fn add_to_history(history: &mut Vec<String>, entry: &str, max_entries: usize) {
+    if entry.is_empty() {
+        return;
+    }
+    history.retain(|previous| previous != entry);
+    history.push(entry.to_owned());
+    if history.len() > max_entries {
+        history.drain(..history.len() - max_entries);
+    }
+}
+
+/// The number of terminal cells taken up by a line of text. Grapheme
+/// clusters without a glyph of their own take up a cell like in buffers.
+pub fn display_width(text: &str) -> usize {
+    text.graphemes(true)
+        .map(|grapheme| UnicodeWidthStr::width(grapheme).max(1))
+        .sum()
+}
+
+// Words are delimited like when moving by words in a buffer
+fn words(text: &str) -> impl DoubleEndedIterator<Item = Range<ByteIndex>> + '_ {
+    text.split_word_bound_indices()
+        .filter(|(_, segment)| segment.chars().any(char::is_alphanumeric))
+        .map(|(start, segment)| start..start + segment.len())
+}
+
+fn single_line(text: String) -> String {
+    if text.contains(&['\n', '\r'][..]) {
+        text.replace("\r\n", " ").replace(&['\n', '\r'][..], " ")
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_move_by_graphemes() {
+        let mut editor = LineEditor::new("ab");
+        editor.move_left();
+        editor.insert_char('é');
+        assert_eq!(editor.text(), "aéb");
+        assert_eq!(editor.cursor(), 3);
+
+        editor.move_to_start();
+        editor.move_left();
+        assert_eq!(editor.cursor(), 0);
+        editor.move_to_end();
+        editor.move_right();
+        assert_eq!(editor.cursor(), editor.text().len());
+    }
+
+    #[test]
+    fn cursor_skips_combining_marks() {
+        // `e` followed by a combining acute accent is a single grapheme
+        let mut editor = LineEditor::new("ae\u{301}b");
+        editor.move_to_start();
+        editor.move_right();
+        editor.move_right();
+        assert_eq!(editor.cursor(), 4);
+        editor.delete_backward();
+        assert_eq!(editor.text(), "ab");
+        assert_eq!(editor.cursor(), 1);
+    }
+
+    #[test]
+    fn cursor_column_counts_wide_characters() {
+        let mut editor = LineEditor::new("日本a");
+        assert_eq!(editor.cursor_column(), 5);
+        editor.move_left();
+        editor.move_left();
+        assert_eq!(editor.cursor_column(), 2);
+        editor.move_to_start();
+        assert_eq!(editor.cursor_column(), 0);
+    }
+
+    #[test]
+    fn delete_around_the_cursor() {
+        let mut editor = LineEditor::new("hello world");
+        editor.move_to_start();
+        editor.delete_forward();
+        assert_eq!(editor.text(), "ello world");
+        editor.move_to_end();
+        editor.delete_backward();
+        assert_eq!(editor.text(), "ello worl");
+
+        editor.move_backward_word();
+        editor.delete_to_end();
+        assert_eq!(editor.text(), "ello ");
+        editor.delete_to_start();
+        assert_eq!(editor.text(), "");
+        editor.delete_backward();
+        editor.delete_forward();
+        assert_eq!(editor.cursor(), 0);
+    }
+
+    #[test]
+    fn move_and_delete_by_words() {
+        let mut editor = LineEditor::new("/home/user/file.rs");
+        editor.delete_backward_word();
+        assert_eq!(editor.text(), "/home/user/");
+        editor.move_backward_word();
+        assert_eq!(editor.cursor(), 6);
+        editor.move_backward_word();
+        assert_eq!(editor.cursor(), 1);
+        editor.delete_forward_word();
+        assert_eq!(editor.text(), "//user/");
+        editor.move_forward_word();
+        assert_eq!(editor.cursor(), 6);
+        editor.move_forward_word();
+        assert_eq!(editor.cursor(), 7);
+    }
+
+    #[test]
+    fn line_breaks_are_replaced() {
+        let mut editor = LineEditor::new("a\nb");
+        editor.insert_str("c\r\nd");
+        assert_eq!(editor.text(), "a bc d");
+        assert_eq!(editor.cursor(), 6);
+    }
+
+    #[test]
+    fn browse_history() {
+        let mut editor = LineEditor::new("draft").with_history(vec!["one".into(), "two".into()]);
+        editor.next_history();
+        assert_eq!(editor.text(), "draft");
+
+        editor.previous_history();
+        assert_eq!(editor.text(), "two");
+        editor.previous_history();
+        assert_eq!(editor.text(), "one");
+        editor.previous_history();
+        assert_eq!(editor.text(), "one");
+        assert_eq!(editor.cursor(), 3);
+
+        editor.next_history();
+        assert_eq!(editor.text(), "two");
+        editor.next_history();
+        assert_eq!(editor.text(), "draft");
+    }
+
+    #[test]
+    fn add_entries_to_history() {
+        let mut history = Vec::new();
+        add_to_history(&mut history, "a", 2);
+        add_to_history(&mut history, "", 2);
+        add_to_history(&mut history, "b", 2);
+        add_to_history(&mut history, "a", 2);
+        assert_eq!(history, ["b", "a"]);
+        add_to_history(&mut history, "c", 2);
+        assert_eq!(history, ["a", "c"]);
+    }
+}
@@ -0,0 +1,205 @@
+use ropey::Rope;
+
+use crate::OpaqueDiff;
+
+/// The character sequence used to terminate lines in a text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, used on Unix-like systems
+    Lf,
+    /// `\r\n`, used on Windows
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+        }
+    }
+
+    /// The other line ending
+    pub fn toggle(&self) -> Self {
+        match self {
+            Self::Lf => Self::CrLf,
+            Self::CrLf => Self::Lf,
+        }
+    }
+}
+
+/// The line ending style of a text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DetectedLineEnding {
+    /// The line ending used by the majority of lines, `LineEnding::Lf` if the
+    /// text has no line breaks or if there is a tie
+    pub line_ending: LineEnding,
+    /// Whether the text uses both `\n` and `\r\n`
+    pub mixed: bool,
+}
+
+/// Detects whether `text` uses `\n` or `\r\n` line endings
+pub fn detect(text: &Rope) -> DetectedLineEnding {
+    let (mut num_lf, mut num_crlf) = (0usize, 0usize);
+    let mut previous = None;
+    for character in text.chars() {
+        if character == '\n' {
+            if previous == Some('\r') {
+                num_crlf += 1;
+            } else {
+                num_lf += 1;
+            }
+        }
+        previous = Some(character);
+    }
+    DetectedLineEnding {
+        line_ending: if num_crlf > num_lf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        },
+        mixed: num_lf > 0 && num_crlf > 0,
+    }
+}
+
+/// Whether the last line of a text doesn't end with a line break. Empty
+/// texts don't have a last line to end.
+pub fn lacks_final_line_break(text: &Rope) -> bool {
+    text.len_chars() > 0 && text.char(text.len_chars() - 1) != '\n'
+}
+
+/// Ends the last line of `text` with a line break if it doesn't have one, e.g.
+/// for the `insert_final_newline` setting of EditorConfig
+pub fn insert_final_line_break(text: &mut Rope, line_ending: LineEnding) {
+    if lacks_final_line_break(text) {
+        text.insert(text.len_chars(), line_ending.as_str());
+    }
+}
+
+/// Rewrites all line breaks in `text` to use `line_ending`. Returns a diff
+/// spanning the whole text, or an empty diff if nothing had to change.
+pub fn convert(text: &mut Rope, line_ending: LineEnding) -> OpaqueDiff {
+    let mut converted = String::with_capacity(text.len_bytes());
+    let mut previous = None;
+    for character in text.chars() {
+        match character {
+            '\n' if previous == Some('\r') => {
+                converted.pop();
+                converted.push_str(line_ending.as_str());
+            }
+            '\n' => converted.push_str(line_ending.as_str()),
+            _ => converted.push(character),
+        }
+        previous = Some(character);
+    }
+
+    if *text == converted.as_str() {
+        return OpaqueDiff::empty();
+    }
+    let old_byte_length = text.len_bytes();
+    let old_char_length = text.len_chars();
+    *text = Rope::from(converted);
+    OpaqueDiff::new(
+        0,
+        old_byte_length,
+        text.len_bytes(),
+        0,
+        old_char_length,
+        text.len_chars(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_line_endings() {
+        assert_eq!(
+            detect(&Rope::from("no line breaks")),
+            DetectedLineEnding {
+                line_ending: LineEnding::Lf,
+                mixed: false
+            }
+        );
+        assert_eq!(
+            detect(&Rope::from("one\ntwo\n")),
+            DetectedLineEnding {
+                line_ending: LineEnding::Lf,
+                mixed: false
+            }
+        );
+        assert_eq!(
+            detect(&Rope::from("one\r\ntwo\r\n")),
+            DetectedLineEnding {
+                line_ending: LineEnding::CrLf,
+                mixed: false
+            }
+        );
+    }
+
+    #[test]
+    fn detect_mixed_line_endings_picks_the_majority() {
+        assert_eq!(
+            detect(&Rope::from("one\r\ntwo\r\nthree\n")),
+            DetectedLineEnding {
+                line_ending: LineEnding::CrLf,
+                mixed: true
+            }
+        );
+        assert_eq!(
+            detect(&Rope::from("one\r\ntwo\n")),
+            DetectedLineEnding {
+                line_ending: LineEnding::Lf,
+                mixed: true
+            }
+        );
+    }
+
+    #[test]
+    fn final_line_breaks() {
+        assert!(!lacks_final_line_break(&Rope::new()));
+        assert!(!lacks_final_line_break(&Rope::from("one\r\n")));
+        assert!(lacks_final_line_break(&Rope::from("one\ntwo")));
+
+        let mut text = Rope::from("one\r\ntwo");
+        insert_final_line_break(&mut text, LineEnding::CrLf);
+        assert_eq!(text, "one\r\ntwo\r\n");
+        insert_final_line_break(&mut text, LineEnding::CrLf);
+        assert_eq!(text, "one\r\ntwo\r\n");
+
+        let mut text = Rope::new();
+        insert_final_line_break(&mut text, LineEnding::Lf);
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn convert_line_endings() {
+        let mut text = Rope::from("one\ntwo\r\nthree\n");
+        let diff = convert(&mut text, LineEnding::CrLf);
+        assert_eq!(text, "one\r\ntwo\r\nthree\r\n");
+        assert_eq!(diff, OpaqueDiff::new(0, 15, 17, 0, 15, 17));
+
+        let diff = convert(&mut text, LineEnding::Lf);
+        assert_eq!(text, "one\ntwo\nthree\n");
+        assert_eq!(diff, OpaqueDiff::new(0, 17, 14, 0, 17, 14));
+    }
+
+    #[test]
+    fn convert_is_empty_if_nothing_changes() {
+        let mut text = Rope::from("one\ntwo\n");
+        assert!(convert(&mut text, LineEnding::Lf).is_empty());
+        assert_eq!(text, "one\ntwo\n");
+
+        // A lone carriage return isn't a line break
+        let mut text = Rope::from("one\rtwo");
+        assert!(convert(&mut text, LineEnding::CrLf).is_empty());
+    }
+}
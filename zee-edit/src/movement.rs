@@ -1,8 +1,10 @@
-use ropey::Rope;
+use ropey::{Rope, RopeSlice};
+use std::{borrow::Cow, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
-    graphemes::{RopeExt, RopeGraphemes},
-    Cursor,
+    graphemes::{CharIndex, LineIndex, RopeExt, RopeGraphemes},
+    wrap, Cursor,
 };
 
 /// The movement direction
@@ -83,6 +85,78 @@ pub fn move_vertically(
     cursor.range = char_offset..text.next_grapheme_boundary(char_offset);
 }
 
+/// Move the cursor vertically in the specified direction by `count` visual
+/// rows, with lines soft wrapped to `width` columns
+#[inline]
+pub fn move_vertically_wrapped(
+    text: &Rope,
+    cursor: &mut Cursor,
+    tab_width: usize,
+    width: usize,
+    direction: Direction,
+    count: usize,
+) {
+    let mut line_index = text.char_to_line(cursor.range.start);
+    let line_start = text.line_to_char(line_index);
+    let mut row_starts = wrap::row_starts(&text.line(line_index), tab_width, width);
+    let mut row = wrap::row_index(&row_starts, cursor.range.start - line_start);
+
+    let current_visual_x = *cursor.visual_horizontal_offset.get_or_insert_with(|| {
+        let row_to_cursor = text.slice(line_start + row_starts[row]..cursor.range.start);
+        crate::graphemes::width(tab_width, &row_to_cursor)
+    });
+
+    for moved in 0..count {
+        match direction {
+            Direction::Forward if row + 1 < row_starts.len() => row += 1,
+            Direction::Forward if line_index + 1 < text.len_lines() => {
+                line_index += 1;
+                row_starts = wrap::row_starts(&text.line(line_index), tab_width, width);
+                row = 0;
+            }
+            Direction::Backward if row > 0 => row -= 1,
+            Direction::Backward if line_index > 0 => {
+                line_index -= 1;
+                row_starts = wrap::row_starts(&text.line(line_index), tab_width, width);
+                row = row_starts.len() - 1;
+            }
+            // If the cursor is on the last row and moving forward (down), move
+            // the cursor to the end of the line instead.
+            Direction::Forward if moved == 0 => {
+                move_to_end_of_line(text, cursor);
+                return;
+            }
+            _ if moved == 0 => return,
+            _ => break,
+        }
+    }
+
+    // Find the grapheme at the same visual offset in the new row, without
+    // going past the end of the row
+    let line = text.line(line_index);
+    let row_end = row_starts
+        .get(row + 1)
+        .copied()
+        .unwrap_or_else(|| line.len_chars());
+    let mut char_offset = row_starts[row];
+    let mut new_visual_x = 0;
+    for grapheme in RopeGraphemes::new(&line.slice(row_starts[row]..row_end)) {
+        let width = crate::graphemes::width(tab_width, &grapheme);
+        let is_last_in_row = char_offset + grapheme.len_chars() == row_end;
+        if new_visual_x + width > current_visual_x
+            || wrap::is_line_ending(&grapheme.slice)
+            || (is_last_in_row && row + 1 < row_starts.len())
+        {
+            break;
+        }
+        char_offset += grapheme.len_chars();
+        new_visual_x += width;
+    }
+
+    let char_index = text.line_to_char(line_index) + char_offset;
+    cursor.range = char_index..text.next_grapheme_boundary(char_index);
+}
+
 /// Move the cursor in the specified direction by `count` words
 #[inline]
 pub fn move_word(text: &Rope, cursor: &mut Cursor, direction: Direction, count: usize) {
@@ -100,29 +174,112 @@ pub fn move_word(text: &Rope, cursor: &mut Cursor, direction: Direction, count:
     }
 }
 
-/// Move the cursor forward by one word
+/// Move the cursor forward to the end of the next word
 #[inline]
 pub fn move_forward_word(text: &Rope, cursor: &mut Cursor) {
-    let first_word_character =
-        skip_while_forward(text, cursor.range.start, |c| !is_word_character(c))
-            .unwrap_or_else(|| text.len_chars());
-    let grapheme_start = skip_while_forward(text, first_word_character, is_word_character)
-        .unwrap_or_else(|| text.len_chars());
-    cursor.range = grapheme_start..text.next_grapheme_boundary(grapheme_start);
+    let mut line_index = text.char_to_line(cursor.range.start);
+    let mut offset = cursor.range.start - text.line_to_char(line_index);
+    let char_index = loop {
+        let word_end = words_in_line(&text.line(line_index))
+            .into_iter()
+            .map(|word| word.end)
+            .find(|&end| end > offset);
+        if let Some(end) = word_end {
+            break text.line_to_char(line_index) + end;
+        }
+        line_index += 1;
+        if line_index >= text.len_lines() {
+            break text.len_chars();
+        }
+        offset = 0;
+    };
+    cursor.range = char_index..text.next_grapheme_boundary(char_index);
     cursor.visual_horizontal_offset = None;
 }
 
-/// Move the cursor backward by one word
+/// Move the cursor backward to the start of the previous word
 #[inline]
 pub fn move_backward_word(text: &Rope, cursor: &mut Cursor) {
-    let first_word_character =
-        skip_while_backward(text, cursor.range.start, |c| !is_word_character(c)).unwrap_or(0);
-    let grapheme_start =
-        skip_while_backward(text, first_word_character, is_word_character).unwrap_or(0);
-    cursor.range = grapheme_start..text.next_grapheme_boundary(grapheme_start);
+    let mut line_index = text.char_to_line(cursor.range.start);
+    let mut offset = cursor.range.start - text.line_to_char(line_index);
+    let char_index = loop {
+        let word_start = words_in_line(&text.line(line_index))
+            .into_iter()
+            .rev()
+            .map(|word| word.start)
+            .find(|&start| start < offset);
+        if let Some(start) = word_start {
+            break text.line_to_char(line_index) + start;
+        }
+        if line_index == 0 {
+            break 0;
+        }
+        line_index -= 1;
+        offset = usize::MAX;
+    };
+    cursor.range = char_index..text.next_grapheme_boundary(char_index);
     cursor.visual_horizontal_offset = None;
 }
 
+/// Returns the range of the word containing the character at `char_index`, or
+/// `None` if it's not part of a word
+pub fn word_at(text: &Rope, char_index: usize) -> Option<Range<usize>> {
+    if char_index >= text.len_chars() {
+        return None;
+    }
+    let line_index = text.char_to_line(char_index);
+    let line_start = text.line_to_char(line_index);
+    words_in_line(&text.line(line_index))
+        .into_iter()
+        .find(|word| word.contains(&(char_index - line_start)))
+        .map(|word| line_start + word.start..line_start + word.end)
+}
+
+/// Returns the range of the file path or URL containing the character at
+/// `char_index`, e.g. `src/main.rs:12:4` in a compiler's output. It stops at
+/// whitespace, quotes and brackets, and punctuation ending a sentence isn't
+/// included.
+pub fn path_at(text: &Rope, char_index: usize) -> Option<Range<usize>> {
+    let is_part =
+        |character: char| !character.is_whitespace() && !"\"'`()[]{}<>".contains(character);
+    if char_index >= text.len_chars() || !is_part(text.char(char_index)) {
+        return None;
+    }
+    let line_index = text.char_to_line(char_index);
+    let line_start = text.line_to_char(line_index);
+    let line = text.line(line_index);
+    let offset = char_index - line_start;
+    let start = (0..offset)
+        .rev()
+        .find(|&index| !is_part(line.char(index)))
+        .map_or(0, |index| index + 1);
+    let mut end = (offset..line.len_chars())
+        .find(|&index| !is_part(line.char(index)))
+        .unwrap_or_else(|| line.len_chars());
+    while end > offset + 1 && ".,;:!?".contains(line.char(end - 1)) {
+        end -= 1;
+    }
+    Some(line_start + start..line_start + end)
+}
+
+/// The words of a line as ranges of characters within it. Words are delimited
+/// following the Unicode word boundary rules, e.g. `can't` and `foo_bar` are
+/// single words and each CJK ideograph is a word. Whitespace and punctuation
+/// between words is not included.
+fn words_in_line(line: &RopeSlice) -> Vec<Range<usize>> {
+    let line: Cow<str> = (*line).into();
+    let mut words = Vec::new();
+    let mut char_offset = 0;
+    for segment in line.split_word_bounds() {
+        let length = segment.chars().count();
+        if segment.chars().any(char::is_alphanumeric) {
+            words.push(char_offset..char_offset + length);
+        }
+        char_offset += length;
+    }
+    words
+}
+
 /// Move the cursor in the specified direction by `count` paragraphs
 #[inline]
 pub fn move_paragraph(text: &Rope, cursor: &mut Cursor, direction: Direction, count: usize) {
@@ -207,48 +364,116 @@ pub fn move_to_end_of_line(text: &Rope, cursor: &mut Cursor) {
     cursor.visual_horizontal_offset = None;
 }
 
-/// Move the cursor to the beginning of the text
+/// Move the cursor to a line and column, both zero-based. The column is
+/// counted in grapheme clusters. Positions past the end of the text or of the
+/// line are clamped to the last line and the end of the line respectively.
 #[inline]
-pub fn move_to_start_of_buffer(text: &Rope, cursor: &mut Cursor) {
-    cursor.range = 0..text.next_grapheme_boundary(0);
+pub fn move_to_line(text: &Rope, cursor: &mut Cursor, line_index: LineIndex, column: usize) {
+    let line_index = line_index.min(text.len_lines().saturating_sub(1));
+    let line_start = text.line_to_char(line_index);
+    let line = text.line(line_index);
+    let line_end = line_start + line.len_chars()
+        - if line.len_chars() > 0 && line.char(line.len_chars() - 1) == '\n' {
+            1
+        } else {
+            0
+        };
+    let grapheme_start = text
+        .next_grapheme_boundary_n(line_start, column)
+        .min(line_end);
+    cursor.range = grapheme_start..text.next_grapheme_boundary(grapheme_start);
     cursor.visual_horizontal_offset = None;
 }
 
-/// Move the cursor to the end of the text
-#[inline]
-pub fn move_to_end_of_buffer(text: &Rope, cursor: &mut Cursor) {
-    let length = text.len_chars();
-    cursor.range = length..length;
-    cursor.visual_horizontal_offset = None;
+/// The line of the cursor and its column counted in grapheme clusters, the
+/// position [`move_to_line`] moves to
+pub fn line_and_column(text: &Rope, cursor: &Cursor) -> (LineIndex, usize) {
+    let line_index = text.char_to_line(cursor.range.start);
+    let mut char_index = text.line_to_char(line_index);
+    let mut column = 0;
+    while char_index < cursor.range.start {
+        char_index = text.next_grapheme_boundary(char_index);
+        column += 1;
+    }
+    (line_index, column)
 }
 
-#[inline]
-fn skip_while_forward(
+/// The column of the cursor counted in characters, the logical column
+/// compilers report in their errors
+pub fn char_column(text: &Rope, cursor: &Cursor) -> usize {
+    cursor.range.start - text.line_to_char(text.char_to_line(cursor.range.start))
+}
+
+/// The visual column of the cursor, where tabs are `tab_width` columns wide
+/// and wide characters take up two columns. With lines soft wrapped to
+/// `wrap_width` columns, the column is counted from the start of the cursor's
+/// row rather than of its line.
+pub fn display_column(
     text: &Rope,
-    position: usize,
-    predicate: impl Fn(char) -> bool,
-) -> Option<usize> {
-    text.chars_at(position)
-        .enumerate()
-        .find_map(|(index, character)| (!predicate(character)).then(|| position + index))
+    cursor: &Cursor,
+    tab_width: usize,
+    wrap_width: Option<usize>,
+) -> usize {
+    let line_index = text.char_to_line(cursor.range.start);
+    let line_start = text.line_to_char(line_index);
+    let row_start = match wrap_width {
+        Some(width) => {
+            let row_starts = wrap::row_starts(&text.line(line_index), tab_width, width);
+            line_start + row_starts[wrap::row_index(&row_starts, cursor.range.start - line_start)]
+        }
+        None => line_start,
+    };
+    crate::graphemes::width(tab_width, &text.slice(row_start..cursor.range.start))
 }
 
-#[inline]
-fn skip_while_backward(
+/// The start of the grapheme cluster at a visual column of a line, where tabs
+/// are `tab_width` columns wide, or the end of the line if it's shorter. A
+/// column in the middle of a wide grapheme cluster maps to its start.
+pub fn char_at_column(
     text: &Rope,
-    position: usize,
-    predicate: impl Fn(char) -> bool,
-) -> Option<usize> {
-    let mut chars = text.chars_at(position);
-    chars.reverse();
-    chars.enumerate().find_map(|(index, character)| {
-        (!predicate(character)).then(|| position.saturating_sub(index))
-    })
+    line_index: LineIndex,
+    tab_width: usize,
+    column: usize,
+) -> CharIndex {
+    let mut char_index = text.line_to_char(line_index);
+    let mut visual_x = 0;
+    for grapheme in RopeGraphemes::new(&text.line(line_index)) {
+        let width = crate::graphemes::width(tab_width, &grapheme);
+        if visual_x + width > column || grapheme.slice == "\n" || grapheme.slice == "\r\n" {
+            break;
+        }
+        char_index += grapheme.slice.len_chars();
+        visual_x += width;
+    }
+    char_index
 }
 
+/// Move the cursor to the grapheme cluster containing the character at
+/// `char_index`
 #[inline]
-fn is_word_character(character: char) -> bool {
-    character == '_' || (!character.is_whitespace() && !character.is_ascii_punctuation())
+pub fn move_to_char(text: &Rope, cursor: &mut Cursor, char_index: usize) {
+    let grapheme_start = if char_index >= text.len_chars() {
+        text.len_chars()
+    } else {
+        text.prev_grapheme_boundary(text.next_grapheme_boundary(char_index))
+    };
+    cursor.range = grapheme_start..text.next_grapheme_boundary(grapheme_start);
+    cursor.visual_horizontal_offset = None;
+}
+
+/// Move the cursor to the beginning of the text
+#[inline]
+pub fn move_to_start_of_buffer(text: &Rope, cursor: &mut Cursor) {
+    cursor.range = 0..text.next_grapheme_boundary(0);
+    cursor.visual_horizontal_offset = None;
+}
+
+/// Move the cursor to the end of the text
+#[inline]
+pub fn move_to_end_of_buffer(text: &Rope, cursor: &mut Cursor) {
+    let length = text.len_chars();
+    cursor.range = length..length;
+    cursor.visual_horizontal_offset = None;
 }
 
 #[cfg(test)]
@@ -326,6 +551,90 @@ mod tests {
         assert_eq!(cursor.range, 1..2);
     }
 
+    #[test]
+    fn move_to_line_and_column() {
+        let (text, mut cursor) = text_with_cursor("one\ntwo\nthree");
+        move_to_line(&text, &mut cursor, 1, 0);
+        assert_eq!(cursor.range(), 4..5);
+        move_to_line(&text, &mut cursor, 2, 3);
+        assert_eq!(cursor.range(), 11..12);
+    }
+
+    #[test]
+    fn line_and_column_round_trips_with_move_to_line() {
+        let (text, mut cursor) = text_with_cursor(format!("one\n{}two\n", MULTI_CHAR_EMOJI));
+        assert_eq!(line_and_column(&text, &cursor), (0, 0));
+        move_to_line(&text, &mut cursor, 1, 2);
+        assert_eq!(line_and_column(&text, &cursor), (1, 2));
+        move_to_end_of_buffer(&text, &mut cursor);
+        assert_eq!(line_and_column(&text, &cursor), (2, 0));
+    }
+
+    #[test]
+    fn move_to_line_clamps_out_of_range_positions() {
+        let (text, mut cursor) = text_with_cursor("one\ntwo\n");
+        move_to_line(&text, &mut cursor, 0, 10);
+        assert_eq!(cursor.range(), 3..4);
+        move_to_line(&text, &mut cursor, 10, 0);
+        assert_eq!(cursor.range(), 8..8);
+
+        let (text, mut cursor) = text_with_cursor("");
+        move_to_line(&text, &mut cursor, 3, 3);
+        assert_eq!(cursor.range(), 0..0);
+    }
+
+    #[test]
+    fn move_to_char_aligns_to_graphemes() {
+        let (text, mut cursor) = text_with_cursor("a🇬🇧b");
+        move_to_char(&text, &mut cursor, 2);
+        assert_eq!(cursor.range(), 1..3);
+        move_to_char(&text, &mut cursor, 3);
+        assert_eq!(cursor.range(), 3..4);
+        move_to_char(&text, &mut cursor, 10);
+        assert_eq!(cursor.range(), 4..4);
+    }
+
+    #[test]
+    fn move_by_words() {
+        let (text, mut cursor) = text_with_cursor("let x = foo_bar(can't);\n  — 日本\n");
+        let mut ends = Vec::new();
+        for _ in 0..7 {
+            move_forward_word(&text, &mut cursor);
+            ends.push(cursor.range().start);
+        }
+        assert_eq!(ends, vec![3, 5, 15, 21, 29, 30, 31]);
+
+        let mut starts = Vec::new();
+        for _ in 0..7 {
+            move_backward_word(&text, &mut cursor);
+            starts.push(cursor.range().start);
+        }
+        assert_eq!(starts, vec![29, 28, 16, 8, 4, 0, 0]);
+    }
+
+    #[test]
+    fn word_at_a_position() {
+        let text = Rope::from("let foo_bar = baz;");
+        assert_eq!(word_at(&text, 0), Some(0..3));
+        assert_eq!(word_at(&text, 6), Some(4..11));
+        assert_eq!(word_at(&text, 16), Some(14..17));
+        assert_eq!(word_at(&text, 3), None);
+        assert_eq!(word_at(&text, 17), None);
+        assert_eq!(word_at(&text, 18), None);
+    }
+
+    #[test]
+    fn path_at_a_position() {
+        let text = Rope::from("error: (src/main.rs:12:4), see <https://zee.rs/a?b=c>.\n");
+        assert_eq!(path_at(&text, 0), Some(0..5));
+        assert_eq!(path_at(&text, 10), Some(8..24));
+        assert_eq!(path_at(&text, 23), Some(8..24));
+        assert_eq!(path_at(&text, 40), Some(32..52));
+        assert_eq!(path_at(&text, 7), None);
+        assert_eq!(path_at(&text, 26), None);
+        assert_eq!(path_at(&text, 54), None);
+    }
+
     #[test]
     fn move_backward_on_empty_text() {
         let (text, mut cursor) = text_with_cursor("");
@@ -348,4 +657,96 @@ CJK
     豈 更 車 Ⅷ
 "#;
     const MULTI_CHAR_EMOJI: &str = r#"👨‍👨‍👧‍👧"#;
+
+    #[test]
+    fn move_vertically_by_wrapped_rows() {
+        let text = Rope::from("abcdefghij\nxy\n");
+        let mut cursor = Cursor::with_range(3..4);
+
+        move_vertically_wrapped(&text, &mut cursor, 4, 4, Direction::Forward, 1);
+        assert_eq!(cursor.range(), 7..8);
+        // The last row is shorter, the cursor stops at its end
+        move_vertically_wrapped(&text, &mut cursor, 4, 4, Direction::Forward, 1);
+        assert_eq!(cursor.range(), 10..11);
+        move_vertically_wrapped(&text, &mut cursor, 4, 4, Direction::Forward, 1);
+        assert_eq!(cursor.range(), 13..14);
+        // The visual offset is remembered
+        move_vertically_wrapped(&text, &mut cursor, 4, 4, Direction::Backward, 2);
+        assert_eq!(cursor.range(), 7..8);
+        move_vertically_wrapped(&text, &mut cursor, 4, 4, Direction::Backward, 5);
+        assert_eq!(cursor.range(), 3..4);
+    }
+
+    #[test]
+    fn move_vertically_wrapped_stays_in_the_row() {
+        let text = Rope::from("ab\nabcdefgh");
+        let mut cursor = Cursor::with_range(2..3);
+        move_vertically_wrapped(&text, &mut cursor, 4, 4, Direction::Forward, 1);
+        assert_eq!(cursor.range(), 5..6);
+        // A line filling its last row has an extra row for its end
+        move_vertically_wrapped(&text, &mut cursor, 4, 4, Direction::Forward, 2);
+        assert_eq!(cursor.range(), 11..11);
+    }
+
+    #[test]
+    fn char_at_column_clamps_to_the_line() {
+        let text = Rope::from("ab\tc\nx\r\n");
+        assert_eq!(char_at_column(&text, 0, 4, 0), 0);
+        assert_eq!(char_at_column(&text, 0, 4, 2), 2);
+        // Inside the tab, which is 4 columns wide
+        assert_eq!(char_at_column(&text, 0, 4, 5), 2);
+        assert_eq!(char_at_column(&text, 0, 4, 6), 3);
+        assert_eq!(char_at_column(&text, 0, 4, 10), 4);
+        assert_eq!(char_at_column(&text, 1, 4, 5), 6);
+        assert_eq!(char_at_column(&text, 2, 4, 5), 8);
+    }
+
+    #[test]
+    fn display_column_expands_tabs_and_wide_characters() {
+        let (text, mut cursor) = text_with_cursor("\tab\n日本語x\n");
+        move_to_char(&text, &mut cursor, 2);
+        assert_eq!(char_column(&text, &cursor), 2);
+        assert_eq!(display_column(&text, &cursor, 4, None), 5);
+        assert_eq!(display_column(&text, &cursor, 8, None), 9);
+
+        move_to_char(&text, &mut cursor, 7);
+        assert_eq!(char_column(&text, &cursor), 3);
+        assert_eq!(display_column(&text, &cursor, 4, None), 6);
+
+        let (text, mut cursor) = text_with_cursor(format!("{}a\n", MULTI_CHAR_EMOJI));
+        move_to_end_of_line(&text, &mut cursor);
+        assert_eq!(
+            char_column(&text, &cursor),
+            MULTI_CHAR_EMOJI.chars().count() + 1
+        );
+    }
+
+    #[test]
+    fn columns_of_wide_and_combining_characters() {
+        // A wide character, then `e` with a combining acute accent
+        let text = Rope::from("日e\u{301}x\n");
+        assert_eq!(char_at_column(&text, 0, 4, 0), 0);
+        assert_eq!(char_at_column(&text, 0, 4, 1), 0);
+        assert_eq!(char_at_column(&text, 0, 4, 2), 1);
+        assert_eq!(char_at_column(&text, 0, 4, 3), 3);
+
+        let mut cursor = Cursor::new();
+        move_to_char(&text, &mut cursor, 3);
+        assert_eq!(char_column(&text, &cursor), 3);
+        assert_eq!(display_column(&text, &cursor, 4, None), 3);
+        assert_eq!(line_and_column(&text, &cursor), (0, 2));
+    }
+
+    #[test]
+    fn display_column_counts_from_the_start_of_the_wrapped_row() {
+        let (text, mut cursor) = text_with_cursor("abcdef\tgh\n");
+        move_to_char(&text, &mut cursor, 5);
+        assert_eq!(display_column(&text, &cursor, 4, None), 5);
+        assert_eq!(display_column(&text, &cursor, 4, Some(4)), 1);
+        // The tab doesn't fit after `ef` and fills the third row on its own
+        move_to_char(&text, &mut cursor, 8);
+        assert_eq!(char_column(&text, &cursor), 8);
+        assert_eq!(display_column(&text, &cursor, 4, None), 11);
+        assert_eq!(display_column(&text, &cursor, 4, Some(4)), 1);
+    }
 }
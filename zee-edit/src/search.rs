@@ -0,0 +1,350 @@
+use ropey::Rope;
+use std::{collections::VecDeque, ops::Range};
+
+use crate::{graphemes::CharIndex, movement::Direction, OpaqueDiff};
+
+/// A match returned by a search
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// The range of characters that matched the query
+    pub range: Range<CharIndex>,
+    /// Whether the search went past the end (or start, if searching backward)
+    /// of the text to find the match
+    pub wrapped: bool,
+}
+
+/// A literal text query. Matching is case insensitive unless the query
+/// contains an uppercase character ("smart case").
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query {
+    pattern: Vec<char>,
+    case_sensitive: bool,
+}
+
+impl Query {
+    pub fn new(pattern: &str) -> Self {
+        let case_sensitive = pattern.chars().any(char::is_uppercase);
+        Self {
+            pattern: pattern
+                .chars()
+                .map(|character| fold_case(character, case_sensitive))
+                .collect(),
+            case_sensitive,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_empty()
+    }
+
+    /// The number of characters in the query
+    pub fn len_chars(&self) -> usize {
+        self.pattern.len()
+    }
+
+    /// Finds the closest match in the given direction. Searching forward
+    /// returns the first match starting at or after `position`, searching
+    /// backward returns the last match starting strictly before `position`. If
+    /// there are no such matches, the search wraps around the text.
+    pub fn find(
+        &self,
+        text: &Rope,
+        position: CharIndex,
+        direction: Direction,
+    ) -> Option<SearchMatch> {
+        let wrap_position = match direction {
+            Direction::Forward => 0,
+            Direction::Backward => text.len_chars(),
+        };
+        self.find_next(text, position, direction)
+            .map(|range| SearchMatch {
+                range,
+                wrapped: false,
+            })
+            .or_else(|| {
+                self.find_next(text, wrap_position, direction)
+                    .map(|range| SearchMatch {
+                        range,
+                        wrapped: true,
+                    })
+            })
+    }
+
+    /// Like `find`, but stops at the end (or start) of the text instead of
+    /// wrapping around
+    pub fn find_next(
+        &self,
+        text: &Rope,
+        position: CharIndex,
+        direction: Direction,
+    ) -> Option<Range<CharIndex>> {
+        match direction {
+            Direction::Forward => self.find_forward(text, position, text.len_chars()),
+            Direction::Backward => self.find_backward(text, position),
+        }
+    }
+
+    /// Returns all non-overlapping matches which lie inside `range`
+    pub fn find_all(&self, text: &Rope, range: Range<CharIndex>) -> Vec<Range<CharIndex>> {
+        let mut matches = Vec::new();
+        let mut position = range.start;
+        while let Some(found) = self.find_forward(text, position, range.end) {
+            position = found.end;
+            matches.push(found);
+        }
+        matches
+    }
+
+    /// Returns the first match starting at or after `start` and ending at or
+    /// before `end`
+    fn find_forward(
+        &self,
+        text: &Rope,
+        start: CharIndex,
+        end: CharIndex,
+    ) -> Option<Range<CharIndex>> {
+        let length = self.pattern.len();
+        if length == 0 || start + length > end.min(text.len_chars()) {
+            return None;
+        }
+
+        let mut window = VecDeque::with_capacity(length + 1);
+        let mut match_start = start;
+        for character in text.chars_at(start).take(end - start) {
+            window.push_back(fold_case(character, self.case_sensitive));
+            if window.len() > length {
+                window.pop_front();
+                match_start += 1;
+            }
+            if window.len() == length && window.iter().eq(self.pattern.iter()) {
+                return Some(match_start..match_start + length);
+            }
+        }
+        None
+    }
+
+    /// Returns the last match starting strictly before `position`
+    fn find_backward(&self, text: &Rope, position: CharIndex) -> Option<Range<CharIndex>> {
+        let length = self.pattern.len();
+        if length == 0 || position == 0 {
+            return None;
+        }
+
+        let mut window = VecDeque::with_capacity(length + 1);
+        let mut match_end = (position + length - 1).min(text.len_chars());
+        let mut chars = text.chars_at(match_end);
+        while let Some(character) = chars.prev() {
+            window.push_front(fold_case(character, self.case_sensitive));
+            if window.len() > length {
+                window.pop_back();
+                match_end -= 1;
+            }
+            if window.len() == length && window.iter().eq(self.pattern.iter()) {
+                return Some(match_end - length..match_end);
+            }
+        }
+        None
+    }
+}
+
+/// Replaces the characters in `range` with `replacement`
+pub fn replace(text: &mut Rope, range: Range<CharIndex>, replacement: &str) -> OpaqueDiff {
+    let byte_index = text.char_to_byte(range.start);
+    let old_byte_length = text.char_to_byte(range.end) - byte_index;
+    text.remove(range.clone());
+    text.insert(range.start, replacement);
+    OpaqueDiff::new(
+        byte_index,
+        old_byte_length,
+        replacement.len(),
+        range.start,
+        range.end - range.start,
+        replacement.chars().count(),
+    )
+}
+
+/// Replaces all matches of `query` which start at or after `position`. The
+/// edits are described by a single diff spanning from the first to the last
+/// match. Returns the number of replaced matches and the diff.
+pub fn replace_all(
+    text: &mut Rope,
+    query: &Query,
+    replacement: &str,
+    position: CharIndex,
+) -> (usize, OpaqueDiff) {
+    let matches = query.find_all(text, position..text.len_chars());
+    let (first, last) = match (matches.first(), matches.last()) {
+        (Some(first), Some(last)) => (first.start, last.end),
+        _ => return (0, OpaqueDiff::empty()),
+    };
+
+    let byte_index = text.char_to_byte(first);
+    let old_byte_length = text.char_to_byte(last) - byte_index;
+    let replacement_length = replacement.chars().count();
+
+    // Replace back to front, so the ranges of the remaining matches are
+    // still valid
+    for range in matches.iter().rev() {
+        text.remove(range.clone());
+        text.insert(range.start, replacement);
+    }
+
+    let old_char_length = last - first;
+    let new_char_length =
+        old_char_length - matches.len() * query.len_chars() + matches.len() * replacement_length;
+    let new_byte_length = text.char_to_byte(first + new_char_length) - byte_index;
+    (
+        matches.len(),
+        OpaqueDiff::new(
+            byte_index,
+            old_byte_length,
+            new_byte_length,
+            first,
+            old_char_length,
+            new_char_length,
+        ),
+    )
+}
+
+#[inline]
+fn fold_case(character: char, case_sensitive: bool) -> char {
+    if case_sensitive {
+        character
+    } else {
+        character.to_lowercase().next().unwrap_or(character)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_forward_from_position() {
+        let text = Rope::from("one two one two");
+        let query = Query::new("two");
+        assert_eq!(
+            query.find(&text, 0, Direction::Forward),
+            Some(SearchMatch {
+                range: 4..7,
+                wrapped: false
+            })
+        );
+        assert_eq!(
+            query.find(&text, 5, Direction::Forward),
+            Some(SearchMatch {
+                range: 12..15,
+                wrapped: false
+            })
+        );
+    }
+
+    #[test]
+    fn find_backward_from_position() {
+        let text = Rope::from("one two one two");
+        let query = Query::new("one");
+        assert_eq!(
+            query.find(&text, 15, Direction::Backward),
+            Some(SearchMatch {
+                range: 8..11,
+                wrapped: false
+            })
+        );
+        assert_eq!(
+            query.find(&text, 8, Direction::Backward),
+            Some(SearchMatch {
+                range: 0..3,
+                wrapped: false
+            })
+        );
+    }
+
+    #[test]
+    fn find_wraps_around() {
+        let text = Rope::from("one two one");
+        let query = Query::new("two");
+        assert_eq!(
+            query.find(&text, 5, Direction::Forward),
+            Some(SearchMatch {
+                range: 4..7,
+                wrapped: true
+            })
+        );
+        assert_eq!(
+            query.find(&text, 4, Direction::Backward),
+            Some(SearchMatch {
+                range: 4..7,
+                wrapped: true
+            })
+        );
+        assert_eq!(Query::new("three").find(&text, 0, Direction::Forward), None);
+    }
+
+    #[test]
+    fn smart_case() {
+        let text = Rope::from("Zee zee");
+        assert_eq!(
+            Query::new("zee").find_all(&text, 0..text.len_chars()),
+            vec![0..3, 4..7]
+        );
+        assert_eq!(
+            Query::new("Zee").find_all(&text, 0..text.len_chars()),
+            vec![0..3]
+        );
+    }
+
+    #[test]
+    fn find_all_in_range() {
+        let text = Rope::from("aaaa\nbaaab\n");
+        let query = Query::new("aa");
+        assert_eq!(
+            query.find_all(&text, 0..text.len_chars()),
+            vec![0..2, 2..4, 6..8]
+        );
+        assert_eq!(query.find_all(&text, 5..8), vec![6..8]);
+        assert_eq!(query.find_all(&text, 5..7), vec![]);
+    }
+
+    #[test]
+    fn empty_query_never_matches() {
+        let text = Rope::from("text");
+        let query = Query::new("");
+        assert!(query.is_empty());
+        assert_eq!(query.find(&text, 0, Direction::Forward), None);
+        assert_eq!(query.find(&text, 4, Direction::Backward), None);
+        assert!(query.find_all(&text, 0..4).is_empty());
+    }
+
+    #[test]
+    fn match_across_lines() {
+        let text = Rope::from("ab\ncd");
+        let query = Query::new("b\nc");
+        assert_eq!(
+            query
+                .find(&text, 0, Direction::Forward)
+                .map(|found| found.range),
+            Some(1..4)
+        );
+    }
+
+    #[test]
+    fn replace_range() {
+        let mut text = Rope::from("one two three");
+        let diff = replace(&mut text, 4..7, "2");
+        assert_eq!(text, "one 2 three");
+        assert_eq!(diff, OpaqueDiff::new(4, 3, 1, 4, 3, 1));
+    }
+
+    #[test]
+    fn replace_all_after_position() {
+        let mut text = Rope::from("aé aé aé");
+        let (count, diff) = replace_all(&mut text, &Query::new("AÉ"), "bcd", 1);
+        assert_eq!(count, 0);
+        assert!(diff.is_empty());
+
+        let (count, diff) = replace_all(&mut text, &Query::new("aé"), "bcd", 1);
+        assert_eq!(count, 2);
+        assert_eq!(text, "aé bcd bcd");
+        assert_eq!(diff, OpaqueDiff::new(4, 7, 7, 3, 5, 7));
+    }
+}
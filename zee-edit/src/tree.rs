@@ -40,6 +40,10 @@ pub struct EditTree {
     pub head_index: usize,
     staged: Rope,
     has_staged_changes: bool,
+    saved_index: usize,
+    // The text of the saved revision, sharing its unchanged parts with the
+    // other revisions
+    saved_text: Rope,
 }
 
 impl EditTree {
@@ -47,8 +51,10 @@ impl EditTree {
         Self {
             revisions: vec![Revision::root(text.clone())],
             head_index: 0,
-            staged: text,
+            staged: text.clone(),
             has_staged_changes: false,
+            saved_index: 0,
+            saved_text: text,
         }
     }
 
@@ -95,6 +101,61 @@ impl EditTree {
         self.has_staged_changes = false;
     }
 
+    /// Merges the staged changes into the head revision instead of creating a
+    /// new revision, so that a burst of edits can be undone as a unit. Returns
+    /// `false`, leaving the tree unchanged, if the head revision is the root,
+    /// has children, is the saved revision or if `diff` is not contiguous
+    /// with the head's edit.
+    pub fn amend_revision(&mut self, diff: OpaqueDiff, cursor: Cursor) -> bool {
+        let head_index = self.head_index;
+        let head = &self.revisions[head_index];
+        if !head.children.is_empty() || head_index == self.saved_index {
+            return false;
+        }
+        let (parent_index, merged) = match head.parent {
+            Some(Reference {
+                index,
+                diff: ref child_to_parent_diff,
+            }) => match child_to_parent_diff.reverse().coalesce(&diff) {
+                Some(merged) => (index, merged),
+                None => return false,
+            },
+            None => return false,
+        };
+
+        if let Some(reference) = self.revisions[parent_index]
+            .children
+            .iter_mut()
+            .find(|reference| reference.index == head_index)
+        {
+            reference.diff = merged.clone();
+        }
+        let head = &mut self.revisions[head_index];
+        head.text = self.staged.clone();
+        head.cursor = cursor;
+        head.parent = Some(Reference {
+            index: parent_index,
+            diff: merged.reverse(),
+        });
+        self.has_staged_changes = false;
+        true
+    }
+
+    /// Marks the head revision as the one matching the file on disk.
+    pub fn mark_saved(&mut self) {
+        self.saved_index = self.head_index;
+        self.saved_text = self.staged.clone();
+    }
+
+    /// Returns `true` if the staged text is the saved revision, or is the same
+    /// as its text after edits which cancel out, e.g. typing a character and
+    /// deleting it again.
+    pub fn is_saved(&self) -> bool {
+        (!self.has_staged_changes && self.head_index == self.saved_index)
+            || (self.staged.len_bytes() == self.saved_text.len_bytes()
+                && self.staged == self.saved_text)
+    }
+
     pub fn undo(&mut self) -> Option<(OpaqueDiff, Cursor)> {
         if let Some(Reference {
             ref diff,
@@ -281,6 +342,88 @@ mod tests {
         assert_eq!("", &tree.to_string());
     }
 
+    #[test]
+    fn amend_revision_undoes_as_a_unit() {
+        let mut tree = EditTree::new("The flowers are\n".into());
+        for (index, character) in " violet".chars().enumerate() {
+            let cursor = Cursor::new();
+            let char_index = 15 + index;
+            tree.insert_char(char_index, character);
+            let diff = OpaqueDiff::new(char_index, 0, 1, char_index, 0, 1);
+            if !tree.amend_revision(diff.clone(), cursor.clone()) {
+                tree.create_revision(diff, cursor);
+            }
+        }
+        assert_eq!("The flowers are violet\n", &tree.to_string());
+        assert_eq!(2, tree.revisions.len());
+
+        let (diff, _) = tree.undo().unwrap();
+        assert_eq!(OpaqueDiff::new(15, 7, 0, 15, 7, 0), diff);
+        assert_eq!("The flowers are\n", &tree.to_string());
+        tree.redo();
+        assert_eq!("The flowers are violet\n", &tree.to_string());
+    }
+
+    #[test]
+    fn amend_revision_keeps_saved_revision() {
+        let mut tree = EditTree::new(Rope::new());
+        tree.insert_char(0, 'a');
+        tree.create_revision(OpaqueDiff::new(0, 0, 1, 0, 0, 1), Cursor::new());
+        tree.mark_saved();
+
+        tree.insert_char(1, 'b');
+        assert!(!tree.amend_revision(OpaqueDiff::new(1, 0, 1, 1, 0, 1), Cursor::new()));
+    }
+
+    #[test]
+    fn undo_to_saved_revision_is_saved() {
+        let mut tree = EditTree::new("The flowers\n".into());
+        assert!(tree.is_saved());
+
+        tree.insert(11, " are");
+        assert!(!tree.is_saved());
+        tree.create_revision(OpaqueDiff::new(11, 0, 4, 11, 0, 4), Cursor::new());
+        assert!(!tree.is_saved());
+
+        tree.undo();
+        assert!(tree.is_saved());
+        tree.redo();
+        assert!(!tree.is_saved());
+        tree.mark_saved();
+        assert!(tree.is_saved());
+    }
+
+    #[test]
+    fn edits_cancelling_out_are_saved() {
+        let mut tree = EditTree::new("The flowers\n".into());
+
+        // Type a character, then delete it
+        tree.insert_char(11, 's');
+        tree.create_revision(OpaqueDiff::new(11, 0, 1, 11, 0, 1), Cursor::new());
+        assert!(!tree.is_saved());
+        tree.remove(11..12);
+        assert!(tree.is_saved());
+        tree.create_revision(OpaqueDiff::new(11, 1, 0, 11, 1, 0), Cursor::new());
+        assert!(tree.is_saved());
+
+        // Type a character, then undo it
+        tree.insert_char(0, 'A');
+        tree.create_revision(OpaqueDiff::new(0, 0, 1, 0, 0, 1), Cursor::new());
+        assert!(!tree.is_saved());
+        tree.undo();
+        assert!(tree.is_saved());
+
+        // Replacing a character with a different one of the same length
+        tree.remove(0..1);
+        tree.insert_char(0, 't');
+        tree.create_revision(OpaqueDiff::new(0, 1, 1, 0, 1, 1), Cursor::new());
+        assert!(!tree.is_saved());
+        tree.mark_saved();
+        assert!(tree.is_saved());
+        tree.undo();
+        assert!(!tree.is_saved());
+    }
+
     #[test]
     fn render_undo_tree() {}
 }
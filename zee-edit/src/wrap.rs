@@ -0,0 +1,78 @@
+use ropey::RopeSlice;
+
+use crate::{graphemes, CharIndex, RopeGraphemes};
+
+/// Returns the offsets within `line` at which its visual rows start when the
+/// line is soft wrapped to `width` columns. The first row always starts at
+/// offset 0 and the line ending doesn't take up any space. A line which fills
+/// its last row completely gets an extra, empty row for the position at its
+/// end.
+pub fn row_starts(line: &RopeSlice, tab_width: usize, width: usize) -> Vec<CharIndex> {
+    let mut starts = vec![0];
+    let mut visual_x = 0;
+    let mut char_offset = 0;
+    for grapheme in RopeGraphemes::new(line) {
+        if is_line_ending(&grapheme.slice) {
+            break;
+        }
+        let grapheme_width = graphemes::width(tab_width, &grapheme);
+        if visual_x > 0 && visual_x + grapheme_width > width {
+            starts.push(char_offset);
+            visual_x = 0;
+        }
+        visual_x += grapheme_width;
+        char_offset += grapheme.len_chars();
+    }
+    if visual_x >= width && char_offset > 0 {
+        starts.push(char_offset);
+    }
+    starts
+}
+
+/// Returns the index of the visual row containing `char_offset`, given the
+/// starts of the rows as returned by [`row_starts`]
+pub fn row_index(row_starts: &[CharIndex], char_offset: CharIndex) -> usize {
+    row_starts
+        .partition_point(|&start| start <= char_offset)
+        .saturating_sub(1)
+}
+
+#[inline]
+pub(crate) fn is_line_ending(slice: &RopeSlice) -> bool {
+    matches!(slice.get_char(0), Some('\n') | Some('\r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ropey::Rope;
+
+    #[test]
+    fn short_lines_take_up_one_row() {
+        let text = Rope::from("abc\n\n");
+        assert_eq!(row_starts(&text.line(0), 4, 10), vec![0]);
+        assert_eq!(row_starts(&text.line(1), 4, 10), vec![0]);
+        assert_eq!(row_starts(&text.line(2), 4, 10), vec![0]);
+    }
+
+    #[test]
+    fn long_lines_wrap_at_the_width() {
+        let text = Rope::from("abcdefghij\n");
+        assert_eq!(row_starts(&text.line(0), 4, 4), vec![0, 4, 8]);
+        assert_eq!(row_index(&[0, 4, 8], 0), 0);
+        assert_eq!(row_index(&[0, 4, 8], 5), 1);
+        assert_eq!(row_index(&[0, 4, 8], 10), 2);
+    }
+
+    #[test]
+    fn full_rows_get_an_extra_row_for_the_end_of_the_line() {
+        let text = Rope::from("abcdefgh\n");
+        assert_eq!(row_starts(&text.line(0), 4, 4), vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn wide_graphemes_are_not_split() {
+        let text = Rope::from("a\tb日本");
+        assert_eq!(row_starts(&text.line(0), 4, 4), vec![0, 1, 2, 4]);
+    }
+}
@@ -13,9 +13,28 @@ pub struct ModeConfig {
     pub scope: String,
     pub injection_regex: String,
     pub patterns: Vec<FilenamePattern>,
+    /// Interpreters named in the `#!` line of scripts without a matching file
+    /// name, e.g. `["python", "python3"]`
+    #[serde(default)]
+    pub interpreters: Vec<String>,
     #[serde(default)]
     pub comment: Option<CommentConfig>,
+    /// Quote characters which are closed automatically like brackets when
+    /// `auto_pairs` is enabled, e.g. `['"', '\'']`
+    #[serde(default)]
+    pub quotes: Vec<char>,
     pub indentation: IndentationConfig,
+    /// Columns at which vertical rulers are drawn, e.g. `[80, 100]`
+    #[serde(default)]
+    pub rulers: Vec<usize>,
+    /// The language server started for files of the mode when
+    /// `language_servers` is enabled
+    #[serde(default)]
+    pub language_server: Option<LanguageServerConfig>,
+    /// A shell command which formats the text given on its stdin, run before
+    /// saving when `format_on_save` is enabled, e.g. `rustfmt`
+    #[serde(default)]
+    pub formatter: Option<String>,
     pub grammar: Option<GrammarConfig>,
 }
 
@@ -25,11 +44,25 @@ pub struct CommentConfig {
     pub token: String,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename = "LanguageServer")]
+pub struct LanguageServerConfig {
+    /// The server's executable, which communicates over stdin and stdout
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The identifier of the language sent to the server, e.g. `rust`
+    pub language_id: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename = "Indentation")]
 pub struct IndentationConfig {
     pub width: usize,
     pub unit: IndentationUnit,
+    /// Characters after which new lines are indented one more level, e.g. `{`
+    #[serde(default)]
+    pub indent_after: Vec<char>,
 }
 
 impl IndentationConfig {
@@ -47,6 +80,13 @@ impl IndentationConfig {
     pub fn tab_width(&self) -> usize {
         self.width
     }
+
+    /// The whitespace inserted for one level of indentation
+    pub fn unit_str(&self) -> String {
+        std::iter::repeat(self.to_char())
+            .take(self.char_count())
+            .collect()
+    }
 }
 
 impl Default for IndentationConfig {
@@ -54,6 +94,7 @@ impl Default for IndentationConfig {
         Self {
             width: 4,
             unit: IndentationUnit::Space,
+            indent_after: Vec::new(),
         }
     }
 }
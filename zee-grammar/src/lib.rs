@@ -8,7 +8,9 @@ use once_cell::sync::Lazy;
 use std::path::Path;
 use tree_sitter::{Language, Query};
 
-use self::config::{CommentConfig, FilenamePattern, IndentationConfig, ModeConfig};
+use self::config::{
+    CommentConfig, FilenamePattern, IndentationConfig, LanguageServerConfig, ModeConfig,
+};
 
 #[derive(Debug)]
 pub struct Mode {
@@ -16,8 +18,13 @@ pub struct Mode {
     pub scope: String,
     pub injection_regex: String,
     pub patterns: Vec<FilenamePattern>,
+    pub interpreters: Vec<String>,
     pub comment: Option<CommentConfig>,
+    pub quotes: Vec<char>,
     pub indentation: IndentationConfig,
+    pub rulers: Vec<usize>,
+    pub language_server: Option<LanguageServerConfig>,
+    pub formatter: Option<String>,
     grammar: LazyGrammar,
 }
 
@@ -28,8 +35,13 @@ impl Mode {
             scope,
             injection_regex,
             patterns,
+            interpreters,
             comment,
+            quotes,
             indentation,
+            rulers,
+            language_server,
+            formatter,
             grammar: grammar_config,
         } = config;
         Self {
@@ -37,8 +49,13 @@ impl Mode {
             scope,
             injection_regex,
             patterns,
+            interpreters,
             comment,
+            quotes,
             indentation,
+            rulers,
+            language_server,
+            formatter,
             grammar: Lazy::new(Box::new(move || {
                 grammar_config
                     .map(|grammar_config| grammar_config.grammar_id)
@@ -53,6 +70,16 @@ impl Mode {
             .any(|pattern| pattern.matches(filename.as_ref()))
     }
 
+    /// Whether the first line of a file is a `#!` line running one of the
+    /// mode's interpreters, e.g. `#!/usr/bin/env python3`
+    pub fn matches_by_shebang(&self, first_line: &str) -> bool {
+        shebang_interpreter(first_line).map_or(false, |interpreter| {
+            self.interpreters
+                .iter()
+                .any(|expected| expected == interpreter)
+        })
+    }
+
     pub fn language(&self) -> Option<Result<Language, &anyhow::Error>> {
         Some(self.grammar()?.map(|parser| parser.language))
     }
@@ -71,13 +98,30 @@ impl Default for Mode {
             scope: "plaintext".into(),
             injection_regex: "".into(),
             patterns: vec![],
+            interpreters: vec![],
             comment: None,
+            quotes: vec![],
             indentation: Default::default(),
+            rulers: vec![],
+            language_server: None,
+            formatter: None,
             grammar: Lazy::new(Box::new(|| None)),
         }
     }
 }
 
+/// The name of the interpreter in a `#!` line, skipping `env` and its options,
+/// e.g. `python3` for `#!/usr/bin/env -S python3 -u`
+fn shebang_interpreter(first_line: &str) -> Option<&str> {
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        words.find(|word| !word.starts_with('-') && !word.contains('='))
+    } else {
+        Some(program)
+    }
+}
+
 #[derive(Debug)]
 pub struct Grammar {
     pub id: String,
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use include_dir::{include_dir, Dir};
 use serde_derive::Deserialize;
-use std::fs::File;
+use std::{collections::HashMap, fs::File};
 use zee_grammar::config::ModeConfig;
 
 static DEFAULT_CONFIG_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/config");
@@ -11,9 +11,71 @@ static DEFAULT_CONFIG_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/config");
 pub struct EditorConfig {
     #[serde(default)]
     pub theme: String,
+    #[serde(default)]
+    pub mode_themes: HashMap<String, String>,
     pub modes: Vec<ModeConfig>,
     #[serde(default)]
     pub trim_trailing_whitespace_on_save: bool,
+    #[serde(default = "default_auto_indent")]
+    pub auto_indent: bool,
+    #[serde(default)]
+    pub auto_pairs: bool,
+    #[serde(default)]
+    pub line_numbers: bool,
+    #[serde(default)]
+    pub relative_line_numbers: bool,
+    #[serde(default)]
+    pub highlight_current_line: bool,
+    #[serde(default)]
+    pub scrolloff: usize,
+    #[serde(default)]
+    pub scrollbar: bool,
+    #[serde(default)]
+    pub status_bar_height: usize,
+    #[serde(default)]
+    pub cursor_shape: CursorShape,
+    #[serde(default = "default_insert_cursor_shape")]
+    pub insert_cursor_shape: CursorShape,
+    #[serde(default)]
+    pub restore_layout: bool,
+    #[serde(default)]
+    pub modal_editing: bool,
+    #[serde(default)]
+    pub editorconfig: bool,
+    #[serde(default)]
+    pub language_servers: bool,
+    #[serde(default)]
+    pub format_on_save: bool,
+    #[serde(default)]
+    pub large_file_threshold: u64,
+    #[serde(default)]
+    pub monochrome: bool,
+    #[serde(default)]
+    pub autosave_interval: u64,
+    #[serde(default)]
+    pub related_files: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub key_bindings: HashMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum CursorShape {
+    Block,
+    Underline,
+}
+
+impl Default for CursorShape {
+    fn default() -> Self {
+        Self::Block
+    }
+}
+
+fn default_auto_indent() -> bool {
+    true
+}
+
+fn default_insert_cursor_shape() -> CursorShape {
+    CursorShape::Underline
 }
 
 fn main() -> Result<()> {
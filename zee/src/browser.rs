@@ -0,0 +1,47 @@
+use std::{
+    env, io,
+    process::{Command, Stdio},
+    thread,
+};
+use url::Url;
+
+/// Parses a URL to open in a web browser, e.g. found in a buffer. Other
+/// schemes are left alone, as `src/main.rs:12` is a valid URL too.
+pub fn parse_url(text: &str) -> Option<Url> {
+    Url::parse(text)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https" | "ftp" | "mailto"))
+}
+
+/// Opens a URL with the browser set in `$BROWSER`, or the system's default
+/// handler otherwise. The browser runs in the background and its output is
+/// discarded, as it would be drawn over the editor.
+pub fn open(url: &Url) -> io::Result<()> {
+    let mut command = match env::var("BROWSER") {
+        Ok(browser) if !browser.is_empty() => Command::new(browser),
+        _ => default_command(),
+    };
+    let mut child = command
+        .arg(url.as_str())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reap the process once it exits
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn default_command() -> Command {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "macos")] {
+            Command::new("open")
+        } else if #[cfg(windows)] {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        } else {
+            Command::new("xdg-open")
+        }
+    }
+}
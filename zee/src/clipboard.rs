@@ -5,12 +5,28 @@ use crate::error::Result;
 pub trait Clipboard {
     fn get_contents(&self) -> Result<String>;
     fn set_contents(&self, contents: String) -> Result<()>;
+
+    /// Writes out what couldn't be written when the contents were set, e.g.
+    /// escape sequences which must not end up in the middle of a frame
+    fn flush_pending(&self) {}
 }
 
 pub fn create() -> Result<Arc<dyn Clipboard>> {
+    // Over SSH the system clipboard belongs to the remote machine, so copy to
+    // the clipboard of the user's terminal instead
+    if osc52::is_remote_session() {
+        return osc52::create();
+    }
+
     cfg_if::cfg_if! {
         if #[cfg(feature = "system-clipboard")] {
-            system::create()
+            system::create().or_else(|error| {
+                log::warn!(
+                    "Cannot access the system clipboard, falling back to OSC 52: {:#}",
+                    error
+                );
+                osc52::create()
+            })
         } else {
             local::create()
         }
@@ -88,3 +104,114 @@ mod local {
         }
     }
 }
+
+/// A clipboard which sets the contents of the terminal's clipboard using OSC 52
+/// escape sequences. This works in headless environments and over SSH, as long
+/// as the terminal emulator supports it. Terminals don't generally allow
+/// reading their clipboard, so pasting uses the last copied contents.
+///
+/// The escape sequences are queued and written by the editor between frames,
+/// as the terminal backend owns stdout while it draws.
+mod osc52 {
+    use parking_lot::RwLock;
+    use std::{
+        env,
+        io::{self, Write},
+        sync::Arc,
+    };
+
+    use super::Clipboard;
+    use crate::error::Result;
+
+    pub(crate) fn create() -> Result<Arc<dyn Clipboard>> {
+        Ok(Arc::new(Osc52Clipboard {
+            contents: RwLock::new(String::new()),
+            pending: RwLock::new(Vec::new()),
+        }))
+    }
+
+    pub(crate) fn is_remote_session() -> bool {
+        env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some()
+    }
+
+    struct Osc52Clipboard {
+        contents: RwLock<String>,
+        // The escape sequences not written to the terminal yet
+        pending: RwLock<Vec<String>>,
+    }
+
+    impl Clipboard for Osc52Clipboard {
+        fn get_contents(&self) -> Result<String> {
+            Ok(self.contents.read().clone())
+        }
+
+        fn set_contents(&self, contents: String) -> Result<()> {
+            self.pending.write().push(format!(
+                "\x1b]52;c;{}\x07",
+                encode_base64(contents.as_bytes())
+            ));
+            *self.contents.write() = contents;
+            Ok(())
+        }
+
+        fn flush_pending(&self) {
+            let pending = std::mem::take(&mut *self.pending.write());
+            if pending.is_empty() {
+                return;
+            }
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            let result = pending
+                .iter()
+                .try_for_each(|sequence| stdout.write_all(sequence.as_bytes()))
+                .and_then(|()| stdout.flush());
+            if let Err(error) = result {
+                log::warn!("Could not copy to the terminal's clipboard: {}", error);
+            }
+        }
+    }
+
+    fn encode_base64(input: &[u8]) -> String {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let mut output = String::with_capacity((input.len() + 2) / 3 * 4);
+        for chunk in input.chunks(3) {
+            let bytes = [
+                chunk[0],
+                chunk.get(1).copied().unwrap_or(0),
+                chunk.get(2).copied().unwrap_or(0),
+            ];
+            let group = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+            for index in 0..4 {
+                if index <= chunk.len() {
+                    output.push(ALPHABET[((group >> (18 - 6 * index)) & 0x3f) as usize] as char);
+                } else {
+                    output.push('=');
+                }
+            }
+        }
+        output
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn encode_base64_test_vectors() {
+            // From RFC 4648
+            for (input, expected) in [
+                ("", ""),
+                ("f", "Zg=="),
+                ("fo", "Zm8="),
+                ("foo", "Zm9v"),
+                ("foob", "Zm9vYg=="),
+                ("fooba", "Zm9vYmE="),
+                ("foobar", "Zm9vYmFy"),
+            ] {
+                assert_eq!(encode_base64(input.as_bytes()), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,563 @@
+use zi::Key::{self, *};
+
+use zee_edit::Direction;
+
+use super::{modal::EditingMode, Buffer, Message};
+use crate::editor::buffer::{BufferMessage, CursorMessage};
+
+/// A command of a buffer, run by its key sequences or from the command palette
+pub struct Command {
+    pub name: &'static str,
+    pub default_keys: &'static [&'static [Key]],
+    pub run: fn(&Buffer) -> Option<Message>,
+}
+
+/// The commands of the focused buffer. Typing characters is bound separately,
+/// as it depends on the key pressed.
+pub const COMMANDS: &[Command] = &[
+    // Cursor movement
+    //
+    // Up
+    Command {
+        name: "move-backward-line",
+        default_keys: &[&[Ctrl('p')], &[Up]],
+        run: |this| {
+            this.move_up();
+            None
+        },
+    },
+    // Down
+    Command {
+        name: "move-forward-line",
+        default_keys: &[&[Ctrl('n')], &[Down]],
+        run: |this| {
+            this.move_down();
+            None
+        },
+    },
+    // Left
+    Command {
+        name: "move-backward",
+        default_keys: &[&[Ctrl('b')], &[Left]],
+        run: |this| {
+            this.move_left();
+            None
+        },
+    },
+    // Right
+    Command {
+        name: "move-forward",
+        default_keys: &[&[Ctrl('f')], &[Right]],
+        run: |this| {
+            this.move_right();
+            None
+        },
+    },
+    // Move by word
+    //
+    // TODO: Add Alt + Left / Right / Up / Down alternative key bindings
+    //       For this to be possible, zi should support Alt + a key, not just char
+    Command {
+        name: "move-backward-word",
+        default_keys: &[&[Alt('b')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::MoveWord(Direction::Backward, 1));
+            None
+        },
+    },
+    Command {
+        name: "move-forward-word",
+        default_keys: &[&[Alt('f')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::MoveWord(Direction::Forward, 1));
+            None
+        },
+    },
+    // Move by paragraph
+    Command {
+        name: "move-backward-paragraph",
+        default_keys: &[&[Alt('p')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::MoveParagraph(Direction::Backward, 1));
+            None
+        },
+    },
+    Command {
+        name: "move-forward-paragraph",
+        default_keys: &[&[Alt('n')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::MoveParagraph(Direction::Forward, 1));
+            None
+        },
+    },
+    // Jump to the bracket matching the one under the cursor
+    Command {
+        name: "move-to-matching-bracket",
+        default_keys: &[&[Ctrl('x'), Char('%')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::MatchingBracket);
+            None
+        },
+    },
+    // Jump to the next or previous lines changed compared to git
+    Command {
+        name: "next-git-change",
+        default_keys: &[&[Ctrl('x'), Char('v'), Char(']')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::GitChange(Direction::Forward));
+            None
+        },
+    },
+    Command {
+        name: "previous-git-change",
+        default_keys: &[&[Ctrl('x'), Char('v'), Char('[')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::GitChange(Direction::Backward));
+            None
+        },
+    },
+    // Page down
+    Command {
+        name: "move-page-down",
+        default_keys: &[&[Ctrl('v')], &[PageDown]],
+        run: |this| {
+            this.move_page_down();
+            None
+        },
+    },
+    // Page up
+    Command {
+        name: "move-page-up",
+        default_keys: &[&[Alt('v')], &[PageUp]],
+        run: |this| {
+            this.move_page_up();
+            None
+        },
+    },
+    // Start/end of line
+    Command {
+        name: "move-start-of-line",
+        default_keys: &[&[Ctrl('a')], &[Home]],
+        run: |this| {
+            this.move_start_of_line();
+            None
+        },
+    },
+    Command {
+        name: "move-end-of-line",
+        default_keys: &[&[Ctrl('e')], &[End]],
+        run: |this| {
+            this.move_end_of_line();
+            None
+        },
+    },
+    // Start/end of buffer
+    Command {
+        name: "move-start-of-buffer",
+        default_keys: &[&[Alt('<')]],
+        run: |this| {
+            this.move_start_of_buffer();
+            None
+        },
+    },
+    Command {
+        name: "move-end-of-buffer",
+        default_keys: &[&[Alt('>')]],
+        run: |this| {
+            this.move_end_of_buffer();
+            None
+        },
+    },
+    // Editing
+    //
+    // Delete forward
+    Command {
+        name: "delete-forward",
+        default_keys: &[&[Ctrl('d')], &[Delete]],
+        run: |this| {
+            this.delete_forward();
+            None
+        },
+    },
+    // Delete backward, or move backward in normal mode
+    Command {
+        name: "delete-backward",
+        default_keys: &[&[Backspace]],
+        run: |this| {
+            if this.in_command_mode() {
+                this.move_left()
+            } else {
+                this.delete_backward()
+            }
+            None
+        },
+    },
+    // Delete line
+    Command {
+        name: "delete-line",
+        default_keys: &[&[Ctrl('k')]],
+        run: |this| {
+            this.delete_line();
+            None
+        },
+    },
+    // Duplicate and move lines
+    Command {
+        name: "duplicate-lines",
+        default_keys: &[&[Ctrl('x'), Ctrl('d')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::DuplicateLines);
+            None
+        },
+    },
+    Command {
+        name: "move-lines-up",
+        default_keys: &[&[Ctrl('x'), Ctrl('p')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::MoveLines(Direction::Backward));
+            None
+        },
+    },
+    Command {
+        name: "move-lines-down",
+        default_keys: &[&[Ctrl('x'), Ctrl('n')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::MoveLines(Direction::Forward));
+            None
+        },
+    },
+    // Comment out or uncomment lines
+    Command {
+        name: "toggle-comment",
+        default_keys: &[&[Alt(';')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::ToggleComment);
+            None
+        },
+    },
+    // Insert new line
+    Command {
+        name: "insert-new-line",
+        default_keys: &[&[Char('\n')]],
+        run: |this| {
+            if this.in_command_mode() {
+                this.move_down()
+            } else {
+                this.insert_new_line()
+            }
+            None
+        },
+    },
+    Command {
+        name: "insert-new-line-after",
+        default_keys: &[&[Ctrl('o')]],
+        run: |this| {
+            this.properties.cursor.insert_char('\n', false);
+            None
+        },
+    },
+    // Insert tab, or indent the selected lines
+    Command {
+        name: "insert-tab",
+        default_keys: &[&[Char('\t')]],
+        run: |this| {
+            if !this.in_command_mode() {
+                this.properties.cursor.insert_tab()
+            }
+            None
+        },
+    },
+    // Remove one level of indentation from the selected lines
+    Command {
+        name: "dedent-lines",
+        default_keys: &[&[BackTab]],
+        run: |this| {
+            if !this.in_command_mode() {
+                this.properties.cursor.dedent_lines()
+            }
+            None
+        },
+    },
+    // Selections
+    //
+    // Begin selection
+    Command {
+        name: "begin-selection",
+        default_keys: &[&[Null], &[Ctrl(' ')]],
+        run: |this| {
+            this.properties.cursor.begin_selection();
+            None
+        },
+    },
+    // Select all
+    Command {
+        name: "select-all",
+        default_keys: &[&[Ctrl('x'), Char('h')]],
+        run: |this| {
+            this.properties.cursor.select_all();
+            None
+        },
+    },
+    // Copy selection to clipboard
+    Command {
+        name: "copy-selection",
+        default_keys: &[&[Alt('w')]],
+        run: |this| {
+            this.properties.cursor.copy_selection_to_clipboard();
+            None
+        },
+    },
+    // Cut selection to clipboard
+    Command {
+        name: "cut-selection",
+        default_keys: &[&[Ctrl('w')]],
+        run: |this| {
+            this.properties.cursor.cut_selection_to_clipboard();
+            None
+        },
+    },
+    // Paste from clipboard
+    Command {
+        name: "paste-clipboard",
+        default_keys: &[&[Ctrl('y')]],
+        run: |this| {
+            this.properties.cursor.paste_from_clipboard();
+            None
+        },
+    },
+    // Undo / Redo
+    //
+    // Undo
+    Command {
+        name: "undo",
+        default_keys: &[&[Ctrl('_')], &[Ctrl('z')], &[Ctrl('/')]],
+        run: |this| {
+            this.properties.cursor.undo();
+            None
+        },
+    },
+    // Redo
+    Command {
+        name: "redo",
+        default_keys: &[&[Ctrl('q')]],
+        run: |this| {
+            this.properties.cursor.redo();
+            None
+        },
+    },
+    // Multiple cursors
+    //
+    // Add a cursor at the next occurrence of the word under the cursor
+    Command {
+        name: "add-cursor-at-next-match",
+        default_keys: &[&[Alt('d')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::AddCursorAtNextMatch);
+            None
+        },
+    },
+    // Add a cursor on the line above or below
+    Command {
+        name: "add-cursor-above",
+        default_keys: &[&[Alt('P')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::AddCursorAbove);
+            None
+        },
+    },
+    Command {
+        name: "add-cursor-below",
+        default_keys: &[&[Alt('N')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::AddCursorBelow);
+            None
+        },
+    },
+    Command {
+        name: "select-rectangle",
+        default_keys: &[&[Ctrl('x'), Char(' ')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_cursor(CursorMessage::SelectRectangle);
+            None
+        },
+    },
+    // Go back to a single cursor and clear the search highlight, or to
+    // normal mode if modal editing is enabled
+    Command {
+        name: "collapse-cursors",
+        default_keys: &[&[Esc]],
+        run: |this| {
+            if this
+                .editing_mode
+                .map_or(false, |mode| mode != EditingMode::Normal)
+                || this.pending_operator.is_some()
+            {
+                Some(Message::EnterNormalMode)
+            } else {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::CollapseCursors);
+                None
+            }
+        },
+    },
+    // Save buffer
+    Command {
+        name: "save-buffer",
+        default_keys: &[&[Ctrl('x'), Ctrl('s')], &[Ctrl('x'), Char('s')]],
+        run: |this| {
+            this.properties.cursor.save();
+            None
+        },
+    },
+    // Convert the buffer's line endings between LF and CRLF
+    Command {
+        name: "toggle-line-ending",
+        default_keys: &[&[Ctrl('x'), Char('\n'), Char('l')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_message(BufferMessage::SetLineEnding(
+                    this.properties.line_ending.toggle(),
+                ));
+            None
+        },
+    },
+    // Whether to remove trailing whitespace when saving the buffer,
+    // overriding `trim_trailing_whitespace_on_save`
+    Command {
+        name: "toggle-trim-trailing-whitespace",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('w')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_message(BufferMessage::ToggleTrimTrailingWhitespace);
+            None
+        },
+    },
+    // Whether the buffer can be edited
+    Command {
+        name: "toggle-read-only",
+        default_keys: &[&[Ctrl('x'), Ctrl('q')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_message(BufferMessage::ToggleReadOnly);
+            None
+        },
+    },
+    // Whether new lines are indented automatically
+    Command {
+        name: "toggle-auto-indent",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('i')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_message(BufferMessage::ToggleAutoIndent);
+            None
+        },
+    },
+    // Whether brackets and quotes are closed automatically
+    Command {
+        name: "toggle-auto-pairs",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('p')]],
+        run: |this| {
+            this.properties
+                .cursor
+                .send_message(BufferMessage::ToggleAutoPairs);
+            None
+        },
+    },
+    // Centre cursor visually
+    Command {
+        name: "center-cursor-visually",
+        default_keys: &[&[Ctrl('l')]],
+        run: |_| Some(Message::CenterCursorVisually),
+    },
+    // View edit tree
+    //
+    // Toggle
+    Command {
+        name: "toggle-edit-tree",
+        default_keys: &[&[Ctrl('x'), Char('u')]],
+        run: |_| Some(Message::ToggleEditTree),
+    },
+    // Line numbers
+    Command {
+        name: "toggle-line-numbers",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('l')]],
+        run: |_| Some(Message::ToggleLineNumbers),
+    },
+    Command {
+        name: "toggle-relative-line-numbers",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('r')]],
+        run: |_| Some(Message::ToggleRelativeLineNumbers),
+    },
+    // Highlight the line under the cursor
+    Command {
+        name: "toggle-highlight-current-line",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('h')]],
+        run: |_| Some(Message::ToggleHighlightCurrentLine),
+    },
+    // Wrap long lines instead of cutting them off at the edge of the window
+    Command {
+        name: "toggle-soft-wrap",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('v')]],
+        run: |_| Some(Message::ToggleSoftWrap),
+    },
+    // Scrollbar
+    Command {
+        name: "toggle-scrollbar",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('b')]],
+        run: |_| Some(Message::ToggleScrollbar),
+    },
+    // Show spaces, tabs and trailing whitespace
+    Command {
+        name: "toggle-show-whitespace",
+        default_keys: &[&[Ctrl('x'), Char('t'), Char('s')]],
+        run: |_| Some(Message::ToggleShowWhitespace),
+    },
+    // Close
+    Command {
+        name: "clear-selection",
+        default_keys: &[&[Ctrl('g')]],
+        run: |this| {
+            if this.viewing_edit_tree || this.editing_mode == Some(EditingMode::Visual) {
+                Some(Message::ClearSelection)
+            } else {
+                this.properties.cursor.clear_selection();
+                None
+            }
+        },
+    },
+];
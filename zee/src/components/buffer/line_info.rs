@@ -1,10 +1,77 @@
 use zi::{Canvas, Component, ComponentLink, Layout, Rect, ShouldRender, Style};
 
+use zee_edit::LineIndex;
+
+use crate::{editor::git_diff::LineChange, lsp::Severity};
+
+/// How line numbers are shown in the gutter to the left of a buffer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineNumbers {
+    Hidden,
+    Absolute,
+    /// Show the distance from the line under the cursor, except for the
+    /// current line which shows its absolute number
+    Relative,
+}
+
+impl LineNumbers {
+    pub fn new(show: bool, relative: bool) -> Self {
+        match (show, relative) {
+            (false, _) => Self::Hidden,
+            (true, false) => Self::Absolute,
+            (true, true) => Self::Relative,
+        }
+    }
+}
+
+/// The styles of the marks next to lines with diagnostics or changed since
+/// the git index
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub error: Style,
+    pub warning: Style,
+    /// Also used for hints
+    pub info: Style,
+    pub added: Style,
+    pub modified: Style,
+    pub deleted: Style,
+}
+
 #[derive(Clone, PartialEq)]
 pub struct Properties {
     pub style: Style,
+    pub theme: Theme,
+    /// The lines in view with diagnostics and the most severe one of each,
+    /// sorted by line
+    pub diagnostic_lines: Vec<(LineIndex, Severity)>,
+    /// The changed lines in view, sorted by line. If the file is tracked by
+    /// git, the gutter has an extra column on the left to mark them.
+    pub git_lines: Option<Vec<(LineIndex, LineChange)>>,
     pub line_offset: usize,
+    /// The rows of the line at `line_offset` scrolled past, if it's soft
+    /// wrapped and taller than the view
+    pub row_offset: usize,
     pub num_lines: usize,
+    /// The number of visual rows in view of each line, starting at
+    /// `line_offset`, if lines are soft wrapped
+    pub wrapped_rows: Vec<usize>,
+    pub current_line_index: usize,
+    pub line_numbers: LineNumbers,
+}
+
+/// The number of columns needed by the gutter
+pub fn width(line_numbers: LineNumbers, num_lines: usize, git_gutter: bool) -> usize {
+    usize::from(git_gutter) + line_numbers_width(line_numbers, num_lines)
+}
+
+fn line_numbers_width(line_numbers: LineNumbers, num_lines: usize) -> usize {
+    match line_numbers {
+        LineNumbers::Hidden => 1,
+        LineNumbers::Absolute | LineNumbers::Relative => {
+            // Leave a space between the numbers and the text
+            num_digits(num_lines).max(MIN_LINE_NUMBER_DIGITS) + 1
+        }
+    }
 }
 
 pub struct LineInfo {
@@ -39,25 +106,119 @@ impl Component for LineInfo {
             properties:
                 Properties {
                     style,
+                    ref theme,
+                    ref diagnostic_lines,
+                    ref git_lines,
                     line_offset,
+                    row_offset,
                     num_lines,
+                    ref wrapped_rows,
+                    current_line_index,
+                    line_numbers,
                 },
             frame,
         } = *self;
 
         let mut canvas = Canvas::new(frame.size);
-        for line_index in 0..frame.size.height {
-            canvas.draw_str(
-                0,
-                line_index as usize,
-                style,
-                if line_offset + line_index < num_lines {
-                    " "
-                } else {
-                    "╶"
-                },
-            );
+        canvas.clear(style);
+        // The line numbers start after the column of git changes
+        let x = usize::from(git_lines.is_some());
+        let num_columns = line_numbers_width(line_numbers, num_lines).saturating_sub(1);
+        let mut row = 0;
+        let mut buffer_line_index = line_offset;
+        while row < frame.size.height {
+            let content = if buffer_line_index >= num_lines {
+                format!("{:>width$}", "╶", width = num_columns.max(1))
+            } else if buffer_line_index == line_offset && row_offset > 0 {
+                // The top row continues a line scrolled partly out of view
+                format!("{:>width$}", WRAP_MARKER, width = num_columns.max(1))
+            } else {
+                match line_numbers {
+                    LineNumbers::Hidden => " ".into(),
+                    LineNumbers::Relative if buffer_line_index != current_line_index => format!(
+                        "{:>width$} ",
+                        abs_difference(buffer_line_index, current_line_index),
+                        width = num_columns
+                    ),
+                    LineNumbers::Absolute | LineNumbers::Relative => {
+                        format!("{:>width$} ", buffer_line_index + 1, width = num_columns)
+                    }
+                }
+            };
+            canvas.draw_str(x, row, style, &content);
+
+            // Mark lines with diagnostics in the last column
+            if let Ok(index) =
+                diagnostic_lines.binary_search_by_key(&buffer_line_index, |&(line, _)| line)
+            {
+                let mark_style = match diagnostic_lines[index].1 {
+                    Severity::Error => theme.error,
+                    Severity::Warning => theme.warning,
+                    Severity::Information | Severity::Hint => theme.info,
+                };
+                canvas.draw_str(x + num_columns, row, mark_style, DIAGNOSTIC_MARKER);
+            }
+
+            // Mark lines changed since the git index in the first column
+            let change = git_lines.as_deref().and_then(|git_lines| {
+                git_lines
+                    .binary_search_by_key(&buffer_line_index, |&(line, _)| line)
+                    .ok()
+                    .map(|index| git_lines[index].1)
+            });
+            if let Some(change) = change {
+                let (mark_style, marker) = match change {
+                    LineChange::Added => (theme.added, GIT_CHANGE_MARKER),
+                    LineChange::Modified => (theme.modified, GIT_CHANGE_MARKER),
+                    LineChange::Deleted => (theme.deleted, GIT_DELETE_MARKER),
+                };
+                canvas.draw_str(0, row, mark_style, marker);
+            }
+
+            // Mark the rows which continue a soft wrapped line
+            let line_rows = wrapped_rows
+                .get(buffer_line_index - line_offset)
+                .copied()
+                .unwrap_or(1);
+            let marker = format!("{:>width$}", WRAP_MARKER, width = num_columns.max(1));
+            for continuation_row in row + 1..(row + line_rows).min(frame.size.height) {
+                canvas.draw_str(x, continuation_row, style, &marker);
+            }
+
+            row += line_rows.max(1);
+            buffer_line_index += 1;
         }
         canvas.into()
     }
 }
+
+#[inline]
+fn num_digits(number: usize) -> usize {
+    let mut digits = 1;
+    let mut number = number / 10;
+    while number > 0 {
+        digits += 1;
+        number /= 10;
+    }
+    digits
+}
+
+#[inline]
+fn abs_difference(lhs: usize, rhs: usize) -> usize {
+    if lhs > rhs {
+        lhs - rhs
+    } else {
+        rhs - lhs
+    }
+}
+
+const MIN_LINE_NUMBER_DIGITS: usize = 3;
+
+const WRAP_MARKER: &str = "↪";
+
+const DIAGNOSTIC_MARKER: &str = "●";
+
+const GIT_CHANGE_MARKER: &str = "▎";
+
+// Deleted lines were between this line and the next one
+const GIT_DELETE_MARKER: &str = "▁";
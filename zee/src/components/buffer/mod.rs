@@ -1,18 +1,38 @@
+pub mod bindings;
 pub mod line_info;
+pub mod modal;
+pub mod scrollbar;
 pub mod status_bar;
 pub mod textarea;
 
-use std::{borrow::Cow, iter, path::PathBuf};
+use ropey::Rope;
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    cmp, iter,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 use zi::{
     components::text::{Text, TextAlign, TextProperties},
     prelude::*,
 };
 
-use zee_edit::{tree::EditTree, Direction};
+use zee_edit::{
+    line_ending::{self, LineEnding},
+    movement,
+    search::Query,
+    tree::EditTree,
+    wrap, CharIndex, Direction, LineIndex,
+};
 use zee_grammar::Mode;
 
 use self::{
-    line_info::{LineInfo, Properties as LineInfoProperties},
+    bindings::COMMANDS,
+    line_info::{LineInfo, LineNumbers, Properties as LineInfoProperties, Theme as LineInfoTheme},
+    modal::{EditingMode, Operator, OperatorAction},
+    scrollbar::{Properties as ScrollbarProperties, Scrollbar},
     status_bar::{Properties as StatusBarProperties, StatusBar, Theme as StatusBarTheme},
     textarea::{Properties as TextAreaProperties, TextArea},
 };
@@ -22,8 +42,11 @@ use super::edit_tree_viewer::{
 use crate::{
     editor::{
         buffer::{BufferCursor, CursorMessage, ModifiedStatus, RepositoryRc},
+        git_diff::{LineChange, LineChanges},
         ContextHandle,
     },
+    encoding::FileEncoding,
+    lsp::{Diagnostic, Severity},
     syntax::{highlight::Theme as SyntaxTheme, parse::ParseTree},
     versioned::WeakHandle,
 };
@@ -31,23 +54,47 @@ use crate::{
 #[derive(Clone, Debug, PartialEq)]
 pub struct Theme {
     pub border: Style,
+    pub scrollbar_thumb: Style,
     pub edit_tree_viewer: EditTreeViewerTheme,
     pub status_bar: StatusBarTheme,
+    pub line_info: LineInfoTheme,
     pub syntax: SyntaxTheme,
 }
 
 pub struct Properties {
     pub context: ContextHandle,
     pub theme: Cow<'static, Theme>,
+    /// The name of the editor's theme, shown in the status bar
+    pub theme_name: &'static str,
     pub focused: bool,
     pub frame_id: usize,
     pub mode: &'static Mode,
+    /// The width of a tab character, which can differ from the mode's in
+    /// files covered by an `.editorconfig`
+    pub tab_width: usize,
     pub repo: Option<RepositoryRc>,
     pub content: WeakHandle<EditTree>,
     pub file_path: Option<PathBuf>,
+    /// The file `file_path` points to if it's a symbolic link
+    pub real_path: Option<PathBuf>,
+    /// The editor's working directory, shown in the status bar
+    pub working_dir: PathBuf,
     pub cursor: BufferCursor,
     pub parse_tree: Option<ParseTree>,
     pub modified_status: ModifiedStatus,
+    pub read_only: bool,
+    pub line_ending: LineEnding,
+    pub encoding: FileEncoding,
+    pub search_query: Option<Query>,
+    /// The diagnostics from the language server, underlined in the text and
+    /// marked in the gutter
+    pub diagnostics: Rc<Vec<Diagnostic>>,
+    /// The lines changed compared to the git index, `None` if the file isn't
+    /// tracked
+    pub git_changes: Option<Rc<LineChanges>>,
+    /// The index in `COMMANDS` of a command picked in the command palette,
+    /// taken and run by the focused buffer
+    pub palette_command: Rc<Cell<Option<usize>>>,
 }
 
 impl PartialEq for Properties {
@@ -57,12 +104,23 @@ impl PartialEq for Properties {
             && self.parse_tree.as_ref().map(|tree| tree.version)
                 == other.parse_tree.as_ref().map(|tree| tree.version)
             && self.modified_status == other.modified_status
+            && self.read_only == other.read_only
+            && self.line_ending == other.line_ending
+            && self.encoding == other.encoding
             && self.focused == other.focused
             && self.frame_id == other.frame_id
             && *self.theme == *other.theme
+            && self.theme_name == other.theme_name
             && self.mode == other.mode
+            && self.tab_width == other.tab_width
             && self.repo == other.repo
             && self.file_path == other.file_path
+            && self.working_dir == other.working_dir
+            && self.search_query == other.search_query
+            && Rc::ptr_eq(&self.diagnostics, &other.diagnostics)
+            && self.git_changes.as_ref().map(Rc::as_ptr)
+                == other.git_changes.as_ref().map(Rc::as_ptr)
+            && Rc::ptr_eq(&self.palette_command, &other.palette_command)
     }
 }
 
@@ -71,32 +129,187 @@ pub enum Message {
     CenterCursorVisually,
     ClearSelection,
     ToggleEditTree,
+    ToggleLineNumbers,
+    ToggleRelativeLineNumbers,
+    ToggleHighlightCurrentLine,
+    ToggleShowWhitespace,
+    ToggleSoftWrap,
+    ToggleScrollbar,
+    /// A character typed in normal or visual mode
+    ModalCommand(char),
+    EnterNormalMode,
 }
 
 pub struct Buffer {
     properties: Properties,
     frame: Rect,
     line_offset: usize,
+    // The rows of the first line in view which are scrolled past, when it's
+    // soft wrapped and taller than the view
+    row_offset: usize,
     viewing_edit_tree: bool,
+    line_numbers: LineNumbers,
+    highlight_current_line: bool,
+    show_whitespace: bool,
+    soft_wrap: bool,
+    scrollbar: bool,
+    // `None` unless `modal_editing` is enabled
+    editing_mode: Option<EditingMode>,
+    pending_operator: Option<Operator>,
+    // When the last character was typed, to tell pasted text from typing
+    last_typed: Cell<Option<Instant>>,
 }
 
 impl Buffer {
     fn ensure_cursor_in_view(&mut self) -> ShouldRender {
         let content = self.properties.content.upgrade();
-        let current_line = content.char_to_line(self.properties.cursor.inner().range().start);
-        let num_lines = self.frame.size.height.saturating_sub(1);
-        if current_line < self.line_offset {
-            self.line_offset = current_line;
-            ShouldRender::Yes
-        } else if current_line - self.line_offset > num_lines.saturating_sub(1) {
-            self.line_offset = current_line + 1 - num_lines;
-            ShouldRender::Yes
+        let cursor_index = self.properties.cursor.inner().range().start;
+        let current_line = content.char_to_line(cursor_index);
+        let num_lines = self
+            .frame
+            .size
+            .height
+            .saturating_sub(self.properties.context.config.status_bar_height);
+        // Keep `scrolloff` lines visible around the cursor, except at the end
+        // of the buffer and in windows too short to fit them
+        let margin = cmp::min(
+            self.properties.context.config.scrolloff,
+            num_lines.saturating_sub(1) / 2,
+        );
+        let bottom_margin = cmp::min(margin, content.len_lines().saturating_sub(current_line + 1));
+        let initial_offsets = (self.line_offset, self.row_offset);
+        if current_line < self.line_offset + margin {
+            self.line_offset = current_line.saturating_sub(margin);
+        } else if self.soft_wrap {
+            // Every line takes up at least one row, so only the lines right
+            // above the cursor can share the view with it
+            let last_line = current_line + bottom_margin;
+            self.line_offset = self
+                .line_offset
+                .max((last_line + 1).saturating_sub(num_lines));
+
+            let text = content.staged();
+            let cursor_row_starts = self.row_starts(text, current_line);
+            let mut num_rows = (self.line_offset..current_line)
+                .chain(current_line + 1..=last_line)
+                .map(|line_index| self.row_starts(text, line_index).len())
+                .sum::<usize>()
+                + wrap::row_index(
+                    &cursor_row_starts,
+                    cursor_index - text.line_to_char(current_line),
+                )
+                + 1;
+            while num_rows > num_lines && self.line_offset < current_line {
+                num_rows -= self.row_starts(text, self.line_offset).len();
+                self.line_offset += 1;
+            }
+        } else if current_line + bottom_margin - self.line_offset > num_lines.saturating_sub(1) {
+            self.line_offset = current_line + bottom_margin + 1 - num_lines;
+        }
+
+        // A line taller than the view is scrolled through up to the row of
+        // the cursor
+        self.row_offset = if self.soft_wrap && self.line_offset == current_line {
+            let text = content.staged();
+            let cursor_row = wrap::row_index(
+                &self.row_starts(text, current_line),
+                cursor_index - text.line_to_char(current_line),
+            );
+            if cursor_row < self.row_offset {
+                cursor_row
+            } else if cursor_row >= self.row_offset + num_lines {
+                cursor_row + 1 - num_lines
+            } else {
+                self.row_offset
+            }
         } else {
-            ShouldRender::No
+            0
+        };
+        ((self.line_offset, self.row_offset) != initial_offsets).into()
+    }
+
+    /// The number of columns available for text, next to the gutter, the
+    /// edit tree viewer and the scrollbar
+    fn text_width(&self, text: &Rope) -> usize {
+        let edit_tree_width = if self.viewing_edit_tree {
+            EDIT_TREE_WIDTH
+        } else {
+            0
+        };
+        let scrollbar_width = if self.scrollbar { 1 } else { 0 };
+        self.frame
+            .size
+            .width
+            .saturating_sub(
+                line_info::width(
+                    self.line_numbers,
+                    num_lines(text),
+                    self.properties.git_changes.is_some(),
+                ) + edit_tree_width
+                    + scrollbar_width,
+            )
+            .max(1)
+    }
+
+    /// The offsets at which the visual rows of a line start when soft wrapped
+    fn row_starts(&self, text: &Rope, line_index: LineIndex) -> Vec<CharIndex> {
+        wrap::row_starts(
+            &text.line(line_index),
+            self.properties.tab_width,
+            self.text_width(text),
+        )
+    }
+
+    /// The number of visual rows in view of each line when soft wrapping, or
+    /// nothing if lines aren't wrapped
+    fn wrapped_rows_in_view(&self, text: &Rope) -> Vec<usize> {
+        let mut wrapped_rows = Vec::new();
+        if !self.soft_wrap {
+            return wrapped_rows;
+        }
+        let mut num_rows = 0;
+        let mut line_index = self.line_offset;
+        while num_rows < self.frame.size.height && line_index < text.len_lines() {
+            let mut line_rows = self.row_starts(text, line_index).len();
+            if line_index == self.line_offset {
+                line_rows = line_rows.saturating_sub(self.row_offset);
+            }
+            wrapped_rows.push(line_rows);
+            num_rows += line_rows;
+            line_index += 1;
         }
+        wrapped_rows
+    }
+
+    /// The changed lines in view, if the file is tracked by git
+    fn git_lines(&self) -> Option<Vec<(LineIndex, LineChange)>> {
+        let lines_in_view = self.line_offset..self.line_offset + self.frame.size.height;
+        self.properties.git_changes.as_ref().map(|changes| {
+            changes
+                .iter()
+                .copied()
+                .filter(|(line_index, _)| lines_in_view.contains(line_index))
+                .collect()
+        })
+    }
+
+    /// The lines in view where diagnostics start, with the most severe one
+    fn diagnostic_lines(&self) -> Vec<(LineIndex, Severity)> {
+        let lines_in_view = self.line_offset..self.line_offset + self.frame.size.height;
+        let mut lines: Vec<_> = self
+            .properties
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.severity))
+            .filter(|(line_index, _)| lines_in_view.contains(line_index))
+            .collect();
+        lines.sort_unstable();
+        lines.dedup_by_key(|(line_index, _)| *line_index);
+        lines
     }
 
     fn center_visual_cursor(&mut self) {
+        self.row_offset = 0;
         let content = self.properties.content.upgrade();
         let line_index = content.char_to_line(self.properties.cursor.inner().range().start);
         if line_index >= self.frame.size.height / 2
@@ -113,6 +326,11 @@ impl Buffer {
     fn move_up(&self) {
         if self.viewing_edit_tree {
             self.properties.cursor.undo();
+        } else if self.soft_wrap {
+            let content = self.properties.content.upgrade();
+            self.properties
+                .cursor
+                .move_up_rows(1, self.text_width(content.staged()));
         } else {
             self.properties.cursor.move_up();
         }
@@ -121,6 +339,11 @@ impl Buffer {
     fn move_down(&self) {
         if self.viewing_edit_tree {
             self.properties.cursor.redo();
+        } else if self.soft_wrap {
+            let content = self.properties.content.upgrade();
+            self.properties
+                .cursor
+                .move_down_rows(1, self.text_width(content.staged()));
         } else {
             self.properties.cursor.move_down();
         }
@@ -183,7 +406,167 @@ impl Buffer {
     }
 
     fn insert_new_line(&self) {
-        self.properties.cursor.insert_new_line()
+        // Pasted text has its own indentation
+        if self.record_typing() {
+            self.properties.cursor.insert_char('\n', true)
+        } else {
+            self.properties.cursor.insert_new_line()
+        }
+    }
+
+    /// Records that a character was typed, returning whether it's part of
+    /// pasted text. The terminal sends pasted text as key presses, all at
+    /// once, so a character typed right after another one is taken to be
+    /// pasted.
+    fn record_typing(&self) -> bool {
+        let now = Instant::now();
+        is_pasted(self.last_typed.replace(Some(now)), now)
+    }
+
+    /// Runs the command picked in the command palette, as if its keys were
+    /// pressed
+    fn run_palette_command(&mut self) -> ShouldRender {
+        if !self.properties.focused {
+            return ShouldRender::No;
+        }
+        let command = match self.properties.palette_command.take() {
+            Some(index) => &COMMANDS[index],
+            None => return ShouldRender::No,
+        };
+        match (command.run)(self) {
+            Some(message) => self.update(message),
+            None => ShouldRender::Yes,
+        }
+    }
+
+    /// Whether typed characters are commands rather than text
+    fn in_command_mode(&self) -> bool {
+        matches!(
+            self.editing_mode,
+            Some(EditingMode::Normal | EditingMode::Visual)
+        )
+    }
+
+    /// Runs a command typed in normal or visual mode
+    fn modal_command(&mut self, character: char) {
+        let cursor = &self.properties.cursor;
+        let visual = self.editing_mode == Some(EditingMode::Visual);
+
+        match OperatorAction::new(self.pending_operator.take(), visual, character) {
+            Some(OperatorAction::ApplyToLine(operator)) => {
+                cursor.move_start_of_line();
+                cursor.begin_selection();
+                cursor.move_down();
+                self.apply_operator(operator);
+                return;
+            }
+            // The movement after an operator selects the text it applies to
+            Some(OperatorAction::ApplyToMovement(operator)) => {
+                cursor.begin_selection();
+                if self.modal_movement(character) {
+                    self.apply_operator(operator);
+                } else {
+                    self.properties.cursor.clear_selection();
+                }
+                return;
+            }
+            Some(OperatorAction::ApplyToSelection(operator)) => {
+                self.apply_operator(operator);
+                return;
+            }
+            Some(OperatorAction::Await(operator)) => {
+                self.pending_operator = Some(operator);
+                return;
+            }
+            None => {}
+        }
+
+        match character {
+            'i' => self.editing_mode = Some(EditingMode::Insert),
+            'a' => {
+                cursor.move_right();
+                self.editing_mode = Some(EditingMode::Insert);
+            }
+            'I' => {
+                cursor.move_start_of_line();
+                self.editing_mode = Some(EditingMode::Insert);
+            }
+            'A' => {
+                cursor.move_end_of_line();
+                self.editing_mode = Some(EditingMode::Insert);
+            }
+            'o' => {
+                cursor.move_end_of_line();
+                cursor.insert_new_line();
+                self.editing_mode = Some(EditingMode::Insert);
+            }
+            'O' => {
+                cursor.move_start_of_line();
+                cursor.insert_char('\n', false);
+                self.editing_mode = Some(EditingMode::Insert);
+            }
+            'v' if visual => {
+                cursor.clear_selection();
+                self.editing_mode = Some(EditingMode::Normal);
+            }
+            'v' => {
+                cursor.begin_selection();
+                self.editing_mode = Some(EditingMode::Visual);
+            }
+            'x' => cursor.delete_forward(),
+            'D' => cursor.delete_line(),
+            'p' => cursor.paste_from_clipboard(),
+            'u' => cursor.undo(),
+            'U' => cursor.redo(),
+            _ => {
+                self.modal_movement(character);
+            }
+        }
+    }
+
+    /// Moves the cursor for a movement key typed in normal or visual mode.
+    /// Returns `false` if the key isn't a movement.
+    fn modal_movement(&self, character: char) -> bool {
+        let cursor = &self.properties.cursor;
+        match character {
+            'h' => self.move_left(),
+            'j' => self.move_down(),
+            'k' => self.move_up(),
+            'l' => self.move_right(),
+            'w' => cursor.send_cursor(CursorMessage::MoveWord(Direction::Forward, 1)),
+            'b' => cursor.send_cursor(CursorMessage::MoveWord(Direction::Backward, 1)),
+            '}' => cursor.send_cursor(CursorMessage::MoveParagraph(Direction::Forward, 1)),
+            '{' => cursor.send_cursor(CursorMessage::MoveParagraph(Direction::Backward, 1)),
+            '%' => cursor.send_cursor(CursorMessage::MatchingBracket),
+            'n' => cursor.send_cursor(CursorMessage::SearchMatch(Direction::Forward)),
+            'N' => cursor.send_cursor(CursorMessage::SearchMatch(Direction::Backward)),
+            '0' => cursor.move_start_of_line(),
+            '$' => cursor.move_end_of_line(),
+            'g' => cursor.move_start_of_buffer(),
+            'G' => cursor.move_end_of_buffer(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Applies an operator to the selection and leaves visual mode
+    fn apply_operator(&mut self, operator: Operator) {
+        let cursor = &self.properties.cursor;
+        self.editing_mode = Some(match operator {
+            Operator::Delete => {
+                cursor.cut_selection_to_clipboard();
+                EditingMode::Normal
+            }
+            Operator::Change => {
+                cursor.cut_selection_to_clipboard();
+                EditingMode::Insert
+            }
+            Operator::Yank => {
+                cursor.copy_selection_to_clipboard();
+                cursor.clear_selection();
+                EditingMode::Normal
+            }
+        });
     }
 }
 
@@ -194,18 +577,40 @@ impl Component for Buffer {
     fn create(properties: Self::Properties, frame: Rect, _link: ComponentLink<Self>) -> Self {
         let mut buffer = Self {
             line_offset: 0,
+            row_offset: 0,
             viewing_edit_tree: false,
+            line_numbers: LineNumbers::new(
+                properties.context.config.line_numbers,
+                properties.context.config.relative_line_numbers,
+            ),
+            highlight_current_line: properties.context.config.highlight_current_line,
+            show_whitespace: false,
+            soft_wrap: false,
+            scrollbar: properties.context.config.scrollbar,
+            editing_mode: properties
+                .context
+                .config
+                .modal_editing
+                .then(|| EditingMode::Normal),
+            pending_operator: None,
+            last_typed: Cell::new(None),
             properties,
             frame,
         };
         buffer.ensure_cursor_in_view();
+        // Show the lines around a cursor which starts further down, e.g. at
+        // the position restored when reopening a file
+        if buffer.line_offset > 0 {
+            buffer.center_visual_cursor();
+        }
         buffer
     }
 
     fn change(&mut self, properties: Self::Properties) -> ShouldRender {
         let changed_properties = self.properties != properties;
         self.properties = properties;
-        self.ensure_cursor_in_view() | changed_properties.into()
+        let ran_command = self.run_palette_command();
+        self.ensure_cursor_in_view() | changed_properties.into() | ran_command
     }
 
     fn resize(&mut self, frame: Rect) -> ShouldRender {
@@ -224,11 +629,57 @@ impl Component for Buffer {
                 self.viewing_edit_tree = false;
                 ShouldRender::Yes
             }
-            Message::ClearSelection => ShouldRender::No,
+            Message::ClearSelection | Message::EnterNormalMode => {
+                if self.editing_mode == Some(EditingMode::Visual) {
+                    self.properties.cursor.clear_selection();
+                }
+                if self.editing_mode.is_some() {
+                    self.editing_mode = Some(EditingMode::Normal);
+                }
+                self.pending_operator = None;
+                ShouldRender::Yes
+            }
+            Message::ModalCommand(character) => {
+                self.modal_command(character);
+                ShouldRender::Yes
+            }
             Message::ToggleEditTree => {
                 self.viewing_edit_tree = !self.viewing_edit_tree;
                 ShouldRender::Yes
             }
+            Message::ToggleLineNumbers => {
+                self.line_numbers = match self.line_numbers {
+                    LineNumbers::Hidden => {
+                        LineNumbers::new(true, self.properties.context.config.relative_line_numbers)
+                    }
+                    LineNumbers::Absolute | LineNumbers::Relative => LineNumbers::Hidden,
+                };
+                ShouldRender::Yes
+            }
+            Message::ToggleRelativeLineNumbers => {
+                self.line_numbers = match self.line_numbers {
+                    LineNumbers::Relative => LineNumbers::Absolute,
+                    LineNumbers::Hidden | LineNumbers::Absolute => LineNumbers::Relative,
+                };
+                ShouldRender::Yes
+            }
+            Message::ToggleHighlightCurrentLine => {
+                self.highlight_current_line = !self.highlight_current_line;
+                ShouldRender::Yes
+            }
+            Message::ToggleShowWhitespace => {
+                self.show_whitespace = !self.show_whitespace;
+                ShouldRender::Yes
+            }
+            Message::ToggleScrollbar => {
+                self.scrollbar = !self.scrollbar;
+                ShouldRender::Yes
+            }
+            Message::ToggleSoftWrap => {
+                self.soft_wrap = !self.soft_wrap;
+                self.ensure_cursor_in_view();
+                ShouldRender::Yes
+            }
         }
     }
 
@@ -239,42 +690,86 @@ impl Component for Buffer {
         let textarea = TextArea::with(TextAreaProperties {
             theme: self.properties.theme.syntax.clone(),
             focused: self.properties.focused,
+            highlight_current_line: self.highlight_current_line,
+            show_whitespace: self.show_whitespace,
+            soft_wrap: self.soft_wrap,
             text: content.staged().clone(),
             cursor: self.properties.cursor.inner().clone(),
+            cursor_shape: match self.editing_mode {
+                Some(EditingMode::Insert) => self.properties.context.config.insert_cursor_shape,
+                _ => self.properties.context.config.cursor_shape,
+            },
+            secondary_cursors: self.properties.cursor.secondary().to_vec(),
             mode: self.properties.mode,
+            tab_width: self.properties.tab_width,
             line_offset: self.line_offset,
+            row_offset: self.row_offset,
             parse_tree: self.properties.parse_tree.clone(),
+            search_query: self.properties.search_query.clone(),
+            diagnostics: self.properties.diagnostics.clone(),
         });
 
-        // Vertical info bar which shows line specific diagnostics
+        // Vertical info bar which shows line numbers and line specific
+        // diagnostics
+        let current_line_index = content.char_to_line(self.properties.cursor.inner().range().start);
+        let num_lines = num_lines(content.staged());
+        let line_info_width = line_info::width(
+            self.line_numbers,
+            num_lines,
+            self.properties.git_changes.is_some(),
+        );
         let line_info = LineInfo::with(LineInfoProperties {
             style: self.properties.theme.border,
+            theme: self.properties.theme.line_info.clone(),
+            diagnostic_lines: self.diagnostic_lines(),
+            git_lines: self.git_lines(),
             line_offset: self.line_offset,
-            num_lines: content.len_lines()
-                - if content.line(content.len_lines() - 1).len_chars() > 0 {
-                    0
-                } else {
-                    1
-                },
+            row_offset: self.row_offset,
+            num_lines,
+            wrapped_rows: self.wrapped_rows_in_view(content.staged()),
+            current_line_index,
+            line_numbers: self.line_numbers,
         });
 
+        // Vertical bar which shows the part of the buffer in view
+        let scrollbar = if self.scrollbar {
+            Some(Item::fixed(1)(Scrollbar::with(ScrollbarProperties {
+                track_style: self.properties.theme.border,
+                thumb_style: self.properties.theme.scrollbar_thumb,
+                line_offset: self.line_offset,
+                num_lines,
+            })))
+        } else {
+            None
+        };
+
         // The "status bar" which shows information about the file etc.
         let status_bar = StatusBar::with(StatusBarProperties {
-            current_line_index: content.char_to_line(self.properties.cursor.inner().range().start),
-            column_offset: self
-                .properties
-                .cursor
-                .inner()
-                .column_offset(self.properties.mode.indentation.tab_width(), &content),
+            current_line_index,
+            column_offset: movement::display_column(
+                content.staged(),
+                self.properties.cursor.inner(),
+                self.properties.tab_width,
+                self.soft_wrap.then(|| self.text_width(content.staged())),
+            ),
+            char_column: movement::char_column(content.staged(), self.properties.cursor.inner()),
             file_path: self.properties.file_path.clone(),
+            real_path: self.properties.real_path.clone(),
+            working_dir: self.properties.working_dir.clone(),
             focused: self.properties.focused,
             frame_id: self.properties.frame_id,
             modified_status: self.properties.modified_status,
+            read_only: self.properties.read_only,
+            editing_mode: self.editing_mode,
             mode: self.properties.mode.into(),
-            num_lines: content.len_lines(),
+            line_ending: self.properties.line_ending,
+            lacks_final_newline: line_ending::lacks_final_line_break(content.staged()),
+            encoding: self.properties.encoding,
+            num_lines,
             repository: self.properties.repo.clone(),
             size_bytes: content.len_bytes() as u64,
             theme: self.properties.theme.status_bar.clone(),
+            theme_name: self.properties.theme_name,
         });
 
         // Edit-tree viewer (aka. undo/redo tree)
@@ -303,11 +798,12 @@ impl Component for Buffer {
         Layout::column([
             Item::auto(Layout::row(
                 iter::once(edit_tree_viewer)
-                    .chain(iter::once(Some(Item::fixed(1)(line_info))))
+                    .chain(iter::once(Some(Item::fixed(line_info_width)(line_info))))
                     .chain(iter::once(Some(Item::auto(textarea))))
+                    .chain(iter::once(scrollbar))
                     .flatten(),
             )),
-            Item::fixed(1)(status_bar),
+            Item::fixed(self.properties.context.config.status_bar_height)(status_bar),
         ])
     }
 
@@ -319,207 +815,64 @@ impl Component for Buffer {
             return;
         }
 
-        // Cursor movement
-        //
-        // Up
-        bindings
-            .command("move-backward-line", Self::move_up)
-            .with([Ctrl('p')])
-            .with([Up]);
-
-        // Down
-        bindings
-            .command("move-forward-line", Self::move_down)
-            .with([Ctrl('n')])
-            .with([Down]);
-
-        // Left
-        bindings
-            .command("move-backward", Self::move_left)
-            .with([Ctrl('b')])
-            .with([Left]);
-
-        // Right
-        bindings
-            .command("move-forward", Self::move_right)
-            .with([Ctrl('f')])
-            .with([Right]);
-
-        // Move by word
-        //
-        // TODO: Add Alt + Left / Right / Up / Down alternative key bindings
-        //       For this to be possible, zi should support Alt + a key, not just char
-        bindings
-            .command("move-backward-word", |this: &Self| {
-                this.properties
-                    .cursor
-                    .send_cursor(CursorMessage::MoveWord(Direction::Backward, 1))
-            })
-            .with([Alt('b')]);
-        bindings
-            .command("move-forward-word", |this: &Self| {
-                this.properties
-                    .cursor
-                    .send_cursor(CursorMessage::MoveWord(Direction::Forward, 1))
-            })
-            .with([Alt('f')]);
-
-        // Move by paragraph
-        bindings
-            .command("move-backward-paragraph", |this: &Self| {
-                this.properties
-                    .cursor
-                    .send_cursor(CursorMessage::MoveParagraph(Direction::Backward, 1))
-            })
-            .with([Alt('p')]);
-        bindings
-            .command("move-forward-paragraph", |this: &Self| {
-                this.properties
-                    .cursor
-                    .send_cursor(CursorMessage::MoveParagraph(Direction::Forward, 1))
-            })
-            .with([Alt('n')]);
-
-        // Page down
-        bindings
-            .command("move-page-down", Self::move_page_down)
-            .with([Ctrl('v')])
-            .with([PageDown]);
-
-        // Page up
-        bindings
-            .command("move-page-up", Self::move_page_up)
-            .with([Alt('v')])
-            .with([PageUp]);
-
-        // Start/end of line
-        bindings
-            .command("move-start-of-line", Self::move_start_of_line)
-            .with([Ctrl('a')])
-            .with([Home]);
-        bindings
-            .command("move-end-of-line", Self::move_end_of_line)
-            .with([Ctrl('e')])
-            .with([End]);
-
-        // Start/end of buffer
-        bindings.add(
-            "move-start-of-buffer",
-            [Alt('<')],
-            Self::move_start_of_buffer,
-        );
-        bindings.add("move-end-of-buffer", [Alt('>')], Self::move_end_of_buffer);
-
-        // Editing
-        //
-        // Delete forward
-        bindings
-            .command("delete-forward", Self::delete_forward)
-            .with([Ctrl('d')])
-            .with([Delete]);
-
-        // Delete backward
-        bindings.add("delete-backward", [Backspace], Self::delete_backward);
-
-        // Delete line
-        bindings.add("delete-line", [Ctrl('k')], Self::delete_line);
-
-        // Insert new line
-        bindings.add("insert-new-line", [Char('\n')], Self::insert_new_line);
-        bindings.add("insert-new-line-after", [Ctrl('o')], |this: &Self| {
-            this.properties.cursor.insert_char('\n', false)
-        });
-
-        // Insert tab
-        bindings.add("insert-tab", [Char('\t')], |this: &Self| {
-            this.properties.cursor.insert_tab()
-        });
+        for command in COMMANDS {
+            let mut binding = bindings.command(command.name, command.run);
+            for keys in command.default_keys {
+                binding = binding.with(keys.iter().copied());
+            }
+        }
 
-        // Insert character
+        // Insert character, or run a command in normal and visual mode
         bindings.add(
             "insert-character",
             AnyCharacter,
-            |this: &Self, keys: &[Key]| match keys {
-                &[Char(character)] if character != '\n' => {
-                    this.properties.cursor.insert_char(character, true)
+            |this: &Self, keys: &[Key]| match *keys {
+                [Char(character)] if character != '\n' && this.in_command_mode() => {
+                    Some(Message::ModalCommand(character))
                 }
-                _ => {}
+                [Char(character)] if character != '\n' => {
+                    this.record_typing();
+                    this.properties.cursor.insert_char(character, true);
+                    None
+                }
+                _ => None,
             },
         );
+    }
+}
 
-        // Selections
-        //
-        // Begin selection
-        bindings
-            .command("begin-selection", |this: &Self| {
-                this.properties.cursor.begin_selection();
-            })
-            .with([Null])
-            .with([Ctrl(' ')]);
-
-        // Select all
-        bindings.add("select-all", [Ctrl('x'), Char('h')], |this: &Self| {
-            this.properties.cursor.select_all();
-        });
-        // Copy selection to clipboard
-        bindings.add("copy-selection", [Alt('w')], |this: &Self| {
-            this.properties.cursor.copy_selection_to_clipboard();
-        });
-        // Cut selection to clipboard
-        bindings.add("cut-selection", [Ctrl('w')], |this: &Self| {
-            this.properties.cursor.cut_selection_to_clipboard();
-        });
-        // Paste from clipboard
-        bindings.add("paste-clipboard", [Ctrl('y')], |this: &Self| {
-            this.properties.cursor.paste_from_clipboard();
-        });
-
-        // Undo / Redo
-        //
-        // Undo
-        bindings
-            .command("undo", |this: &Self| {
-                this.properties.cursor.undo();
-            })
-            .with([Ctrl('_')])
-            .with([Ctrl('z')])
-            .with([Ctrl('/')]);
-
-        // Redo
-        bindings.add("redo", [Ctrl('q')], |this: &Self| {
-            this.properties.cursor.redo();
-        });
-
-        // Save buffer
-        bindings
-            .command("save-buffer", |this: &Self| {
-                this.properties.cursor.save();
-            })
-            .with([Ctrl('x'), Ctrl('s')])
-            .with([Ctrl('x'), Char('s')]);
-
-        // Centre cursor visually
-        bindings.add("center-cursor-visually", [Ctrl('l')], || {
-            Message::CenterCursorVisually
-        });
-
-        // View edit tree
-        //
-        // Toggle
-        bindings.add("toggle-edit-tree", [Ctrl('x'), Char('u')], || {
-            Message::ToggleEditTree
-        });
+/// The number of lines, not counting the empty line after a trailing newline
+fn num_lines(text: &Rope) -> usize {
+    text.len_lines()
+        - if text.line(text.len_lines() - 1).len_chars() > 0 {
+            0
+        } else {
+            1
+        }
+}
 
-        // Close
-        bindings.add("clear-selection", [Ctrl('g')], |this: &Self| {
-            if this.viewing_edit_tree {
-                Some(Message::ClearSelection)
-            } else {
-                this.properties.cursor.clear_selection();
-                None
-            }
-        });
-    }
+/// Whether a character typed at `now` is part of pasted text, given when the
+/// previous character was typed
+fn is_pasted(last_typed: Option<Instant>, now: Instant) -> bool {
+    last_typed.map_or(false, |last_typed| {
+        now.saturating_duration_since(last_typed) < PASTE_INTERVAL
+    })
 }
 
 const EDIT_TREE_WIDTH: usize = 36;
+// Nobody types this fast, while pasted characters arrive all at once
+const PASTE_INTERVAL: Duration = Duration::from_millis(5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_typed_at_once_are_pasted() {
+        let now = Instant::now();
+        assert!(!is_pasted(None, now));
+        assert!(is_pasted(Some(now), now));
+        assert!(is_pasted(Some(now), now + Duration::from_millis(1)));
+        assert!(!is_pasted(Some(now), now + Duration::from_millis(100)));
+    }
+}
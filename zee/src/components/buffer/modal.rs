@@ -0,0 +1,137 @@
+/// The editing modes of a buffer when `modal_editing` is enabled. In normal
+/// and visual mode, typing a character runs a command instead of inserting it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditingMode {
+    Normal,
+    Insert,
+    /// Like normal mode, but movements extend the selection
+    Visual,
+}
+
+impl EditingMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+            Self::Visual => "VISUAL",
+        }
+    }
+}
+
+/// An operator typed in normal mode, applied to the text covered by the
+/// movement which follows it, e.g. `dw` deletes a word
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    /// Deletes the text and switches to insert mode
+    Change,
+    /// Copies the text to the clipboard
+    Yank,
+}
+
+impl Operator {
+    pub fn from_char(character: char) -> Option<Self> {
+        match character {
+            'd' => Some(Self::Delete),
+            'c' => Some(Self::Change),
+            'y' => Some(Self::Yank),
+            _ => None,
+        }
+    }
+}
+
+/// How an operator is applied after typing a character in normal or visual
+/// mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperatorAction {
+    /// Repeating an operator applies it to the whole line, e.g. `dd`
+    ApplyToLine(Operator),
+    /// Applies the pending operator to the text covered by the movement
+    /// typed, if it's one
+    ApplyToMovement(Operator),
+    /// In visual mode, operators apply to the selection right away
+    ApplyToSelection(Operator),
+    /// Waits for the movement to apply the operator to
+    Await(Operator),
+}
+
+impl OperatorAction {
+    /// The action for `character` given the operator waiting for a movement,
+    /// or `None` if the character is an unrelated command
+    pub fn new(pending: Option<Operator>, visual: bool, character: char) -> Option<Self> {
+        let operator = Operator::from_char(character);
+        if let Some(pending) = pending {
+            return Some(if operator == Some(pending) {
+                Self::ApplyToLine(pending)
+            } else {
+                Self::ApplyToMovement(pending)
+            });
+        }
+        if visual && character == 'x' {
+            return Some(Self::ApplyToSelection(Operator::Delete));
+        }
+        operator.map(|operator| {
+            if visual {
+                Self::ApplyToSelection(operator)
+            } else {
+                Self::Await(operator)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_wait_for_a_movement() {
+        assert_eq!(
+            OperatorAction::new(None, false, 'd'),
+            Some(OperatorAction::Await(Operator::Delete))
+        );
+        assert_eq!(
+            OperatorAction::new(Some(Operator::Delete), false, 'w'),
+            Some(OperatorAction::ApplyToMovement(Operator::Delete))
+        );
+        // Keys which aren't movements are handled by the caller
+        assert_eq!(
+            OperatorAction::new(Some(Operator::Change), false, 'i'),
+            Some(OperatorAction::ApplyToMovement(Operator::Change))
+        );
+        assert_eq!(OperatorAction::new(None, false, 'w'), None);
+        assert_eq!(OperatorAction::new(None, false, 'x'), None);
+    }
+
+    #[test]
+    fn repeated_operators_apply_to_the_line() {
+        for (character, operator) in [
+            ('d', Operator::Delete),
+            ('c', Operator::Change),
+            ('y', Operator::Yank),
+        ] {
+            assert_eq!(
+                OperatorAction::new(Some(operator), false, character),
+                Some(OperatorAction::ApplyToLine(operator))
+            );
+        }
+        // A different operator is a movement which doesn't exist
+        assert_eq!(
+            OperatorAction::new(Some(Operator::Delete), false, 'y'),
+            Some(OperatorAction::ApplyToMovement(Operator::Delete))
+        );
+    }
+
+    #[test]
+    fn operators_apply_to_the_selection_in_visual_mode() {
+        assert_eq!(
+            OperatorAction::new(None, true, 'y'),
+            Some(OperatorAction::ApplyToSelection(Operator::Yank))
+        );
+        assert_eq!(
+            OperatorAction::new(None, true, 'x'),
+            Some(OperatorAction::ApplyToSelection(Operator::Delete))
+        );
+        assert_eq!(OperatorAction::new(None, true, 'j'), None);
+    }
+}
@@ -0,0 +1,65 @@
+use zi::{Canvas, Component, ComponentLink, Layout, Rect, ShouldRender, Style};
+
+#[derive(Clone, PartialEq)]
+pub struct Properties {
+    pub track_style: Style,
+    pub thumb_style: Style,
+    pub line_offset: usize,
+    pub num_lines: usize,
+}
+
+/// A vertical bar showing which part of the buffer is in view
+pub struct Scrollbar {
+    properties: Properties,
+    frame: Rect,
+}
+
+impl Component for Scrollbar {
+    type Message = ();
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, frame: Rect, _link: ComponentLink<Self>) -> Self {
+        Scrollbar { properties, frame }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        if self.properties != properties {
+            self.properties = properties;
+            ShouldRender::Yes
+        } else {
+            ShouldRender::No
+        }
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let Self {
+            properties:
+                Properties {
+                    track_style,
+                    thumb_style,
+                    line_offset,
+                    num_lines,
+                },
+            frame,
+        } = *self;
+
+        let mut canvas = Canvas::new(frame.size);
+        canvas.clear(track_style);
+        let height = frame.size.height;
+        if height == 0 || num_lines <= height {
+            return canvas.into();
+        }
+
+        let thumb_size = (height * height / num_lines).clamp(1, height);
+        let thumb_start = (height * line_offset / num_lines).min(height - thumb_size);
+        for row in thumb_start..thumb_start + thumb_size {
+            canvas.draw_str(0, row, thumb_style, " ");
+        }
+        canvas.into()
+    }
+}
@@ -5,10 +5,13 @@ use zi::{
     Size, Style,
 };
 
+use zee_edit::line_ending::LineEnding;
 use zee_grammar::Mode;
 
+use super::modal::EditingMode;
 use crate::{
     editor::buffer::{ModifiedStatus, RepositoryRc},
+    encoding::FileEncoding,
     utils::StaticRefEq,
 };
 
@@ -28,13 +31,30 @@ pub struct Theme {
 #[derive(Clone, PartialEq)]
 pub struct Properties {
     pub theme: Theme,
+    pub theme_name: &'static str,
     pub current_line_index: usize,
+    /// The visual column of the cursor, in its row when soft wrapping
     pub column_offset: usize,
+    /// The column of the cursor counted in characters
+    pub char_column: usize,
     pub file_path: Option<PathBuf>,
+    /// Shown after the file name if the file is a symbolic link
+    pub real_path: Option<PathBuf>,
+    pub working_dir: PathBuf,
     pub focused: bool,
     pub frame_id: usize,
     pub modified_status: ModifiedStatus,
+    pub read_only: bool,
+    /// Shown only when modal editing is enabled
+    pub editing_mode: Option<EditingMode>,
     pub mode: StaticRefEq<Mode>,
+    pub line_ending: LineEnding,
+    /// Whether the last line doesn't end with a line break, which saving
+    /// preserves
+    pub lacks_final_newline: bool,
+    pub encoding: FileEncoding,
+    /// The number of lines, not counting the empty line after a trailing
+    /// newline
     pub num_lines: usize,
     pub repository: Option<RepositoryRc>,
     pub size_bytes: u64,
@@ -72,16 +92,25 @@ impl Component for StatusBar {
             properties:
                 Properties {
                     ref file_path,
+                    ref real_path,
+                    ref working_dir,
                     ref modified_status,
+                    read_only,
+                    editing_mode,
                     ref mode,
                     ref repository,
                     ref theme,
+                    theme_name,
+                    line_ending,
+                    lacks_final_newline,
+                    encoding,
                     current_line_index,
                     focused,
                     frame_id,
                     num_lines,
                     size_bytes,
                     column_offset,
+                    char_column,
                 },
             frame,
         } = *self;
@@ -99,7 +128,7 @@ impl Component for StatusBar {
                     &format!(" {} ", frame_id),
                 )
             })
-            // Has unsaved changes
+            // Has unsaved changes or is read-only
             .and_then(|canvas| {
                 canvas.append_start(
                     match modified_status {
@@ -107,11 +136,19 @@ impl Component for StatusBar {
                         _ => theme.is_modified,
                     },
                     match modified_status {
+                        ModifiedStatus::Unchanged if read_only => " % ",
                         ModifiedStatus::Unchanged => " - ",
                         ModifiedStatus::Changed | ModifiedStatus::Saving => " + ",
                     },
                 )
             })
+            // Normal, insert or visual mode
+            .and_then(|canvas| match editing_mode {
+                Some(editing_mode) => {
+                    canvas.append_start(theme.mode, &format!(" {} ", editing_mode.name()))
+                }
+                None => Some(canvas),
+            })
             // Visual indicator for current position in the file, right-aligned
             .and_then(|canvas| {
                 if focused {
@@ -147,6 +184,10 @@ impl Component for StatusBar {
                                 None => format!(" {}", path.display()),
                             }
                         })
+                        .map(|file_name| match real_path {
+                            Some(real_path) => format!("{} → {}", file_name, real_path.display()),
+                            None => file_name,
+                        })
                         .unwrap_or_else(String::new),
                 )
             })
@@ -156,7 +197,7 @@ impl Component for StatusBar {
                     theme.position_in_file,
                     &if current_line_index == 0 {
                         " Top ".into()
-                    } else if current_line_index >= num_lines.saturating_sub(2) {
+                    } else if current_line_index + 1 >= num_lines {
                         " End ".into()
                     } else {
                         format!(
@@ -170,16 +211,51 @@ impl Component for StatusBar {
                     },
                 )
             })
-            // The row:column in the file, right-aligned
+            // The row:column in the file, right-aligned. The column is the
+            // visual one, followed by the column in characters where tabs or
+            // wide characters make them differ.
             .and_then(|canvas| {
-                let line_status = format!(
+                let mut line_status = format!(
                     " {one_based_line_index:>3}:{column_offset:>2} ",
                     one_based_line_index = current_line_index + 1
                 );
+                if char_column != column_offset {
+                    line_status.push_str(&format!("(char {}) ", char_column));
+                }
                 canvas.append_end(theme.is_not_modified, &line_status)
             })
+            // Total number of lines, right-aligned
+            .and_then(|canvas| {
+                canvas.append_end(
+                    theme.file_size,
+                    &format!(
+                        " {} {} ",
+                        num_lines,
+                        if num_lines == 1 { "line" } else { "lines" }
+                    ),
+                )
+            })
             // Name of the current mode
             .and_then(|canvas| canvas.append_start(theme.mode, &format!("  {}", mode.name)))
+            // Line ending style of the buffer, and whether the last line lacks one
+            .and_then(|canvas| {
+                canvas.append_start(
+                    theme.file_size,
+                    &format!(
+                        "  {}{}",
+                        line_ending.name(),
+                        if lacks_final_newline { " noeol" } else { "" }
+                    ),
+                )
+            })
+            // Encoding of the file, unless it's plain UTF-8
+            .and_then(|canvas| {
+                if encoding == FileEncoding::UTF8 {
+                    Some(canvas)
+                } else {
+                    canvas.append_start(theme.file_size, &format!("  {}", encoding))
+                }
+            })
             // Name of the repo right aligned
             .and_then(|canvas| {
                 canvas.append_end(
@@ -194,6 +270,19 @@ impl Component for StatusBar {
                         None => String::new(),
                     },
                 )
+            })
+            // Name of the editor's theme, right aligned
+            .and_then(|canvas| canvas.append_end(theme.file_size, &format!("{}  ", theme_name)))
+            // The working directory, left out first when there's no room
+            .and_then(|canvas| {
+                let working_dir = match dirs::home_dir()
+                    .and_then(|home_dir| working_dir.strip_prefix(home_dir).ok())
+                {
+                    Some(relative) if relative.as_os_str().is_empty() => "~".into(),
+                    Some(relative) => format!("~/{}", relative.display()),
+                    None => working_dir.display().to_string(),
+                };
+                canvas.append_end(theme.file_size, &format!("{}  ", working_dir))
             });
         canvas.into()
     }
@@ -206,7 +295,6 @@ struct StatusCanvas {
 
 impl StatusCanvas {
     fn new(size: Size, base: Style) -> Self {
-        debug_assert!(size.height <= 1);
         let mut canvas = Canvas::new(size);
         canvas.clear(base);
         Self {
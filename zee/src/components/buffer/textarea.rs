@@ -1,29 +1,43 @@
 use euclid::default::SideOffsets2D;
 use ropey::{Rope, RopeSlice};
-use std::{iter, ops::Range};
+use std::{iter, ops::Range, rc::Rc};
 use tree_sitter::{Node, Query, QueryCursor, TextProvider};
 use zi::{
     terminal::GraphemeCluster, Canvas, Component, ComponentLink, Layout, Position, Rect,
-    ShouldRender, Size,
+    ShouldRender, Size, Style,
 };
 
-use zee_edit::{ByteIndex, Cursor, LineIndex, RopeGraphemes};
+use zee_edit::{brackets, search, wrap, ByteIndex, CharIndex, Cursor, LineIndex, RopeGraphemes};
 use zee_grammar::Mode;
 
-use crate::syntax::{
-    highlight::{text_style_at_char, Theme as SyntaxTheme},
-    parse::ParseTree,
+use crate::{
+    lsp::{Diagnostic, Severity},
+    syntax::{
+        highlight::{text_style_at_char, CursorShape, Theme as SyntaxTheme},
+        parse::ParseTree,
+    },
 };
 
 #[derive(Clone)]
 pub struct Properties {
     pub theme: SyntaxTheme,
     pub focused: bool,
+    pub highlight_current_line: bool,
+    pub show_whitespace: bool,
+    pub soft_wrap: bool,
     pub text: Rope,
     pub cursor: Cursor,
+    pub cursor_shape: CursorShape,
+    pub secondary_cursors: Vec<Cursor>,
     pub mode: &'static Mode,
+    pub tab_width: usize,
     pub line_offset: usize,
+    /// The rows of the line at `line_offset` scrolled past, if it's soft
+    /// wrapped and taller than the view
+    pub row_offset: usize,
     pub parse_tree: Option<ParseTree>,
+    pub search_query: Option<search::Query>,
+    pub diagnostics: Rc<Vec<Diagnostic>>,
 }
 
 pub struct TextArea {
@@ -61,6 +75,16 @@ impl TextArea {
     #[inline]
     fn draw_text(&self, canvas: &mut Canvas) {
         let expanse = self.text_expanse_in_view(canvas);
+        let search_matches = self.search_matches_in_view(&expanse);
+        let matching_bracket = if self.properties.focused {
+            brackets::matching_bracket(
+                &self.properties.text,
+                self.properties.cursor.range().start,
+                brackets::SEARCH_LIMIT,
+            )
+        } else {
+            None
+        };
 
         let parse_tree = self
             .properties
@@ -100,9 +124,21 @@ impl TextArea {
                 }
             };
 
-            self.draw_expanse(expanse, canvas, &mut get_scope);
+            self.draw_expanse(
+                expanse,
+                canvas,
+                &search_matches,
+                matching_bracket,
+                &mut get_scope,
+            );
         } else {
-            self.draw_expanse(expanse, canvas, &mut |_| None)
+            self.draw_expanse(
+                expanse,
+                canvas,
+                &search_matches,
+                matching_bracket,
+                &mut |_| None,
+            )
         }
     }
 
@@ -111,63 +147,150 @@ impl TextArea {
         &self,
         expanse: TextExpanse,
         canvas: &mut Canvas,
+        search_matches: &[Range<CharIndex>],
+        matching_bracket: Option<CharIndex>,
         get_scope: &mut impl FnMut(ByteIndex) -> Option<&'a str>,
     ) {
+        let mut row = 0;
         for line_index in expanse.line_range {
-            self.draw_line(
+            if row >= canvas.size().height {
+                break;
+            }
+            let first_row = if line_index == self.properties.line_offset {
+                self.properties.row_offset
+            } else {
+                0
+            };
+            row += self.draw_line(
                 canvas,
-                Rect::from_size(canvas.size()).inner_rect(SideOffsets2D::new(
-                    line_index - self.properties.line_offset,
-                    0,
-                    0,
-                    0,
-                )),
+                Rect::from_size(canvas.size()).inner_rect(SideOffsets2D::new(row, 0, 0, 0)),
                 line_index,
+                first_row,
+                search_matches,
+                matching_bracket,
                 get_scope,
             );
         }
     }
 
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     fn draw_line<'a>(
         &self,
         canvas: &mut Canvas,
         frame: Rect,
         line_index: LineIndex,
+        first_row: usize,
+        search_matches: &[Range<CharIndex>],
+        matching_bracket: Option<CharIndex>,
         get_scope: &mut impl FnMut(ByteIndex) -> Option<&'a str>,
-    ) {
+    ) -> usize {
         // Get references to the relevant bits of context
         let Self {
             properties:
                 Properties {
                     ref theme,
                     focused,
+                    highlight_current_line,
+                    show_whitespace,
+                    soft_wrap,
                     ref text,
                     ref cursor,
+                    cursor_shape,
+                    mode,
+                    ref secondary_cursors,
+                    tab_width,
+                    ref diagnostics,
                     ..
                 },
             ..
         } = *self;
+        let block_cursor_style = if focused {
+            theme.cursor_focused
+        } else {
+            theme.cursor_unfocused
+        };
+        let is_secondary_cursor = |char_index: CharIndex| {
+            secondary_cursors
+                .iter()
+                .any(|cursor| cursor.range().start == char_index)
+        };
+
+        // Split long lines into multiple visual rows if soft wrapping. The
+        // rows before `first_row` are scrolled past and aren't drawn.
+        let line = text.line(line_index);
+        let row_starts = if soft_wrap {
+            wrap::row_starts(&line, tab_width, frame.size.width)
+        } else {
+            vec![0]
+        };
+        let num_rows = row_starts
+            .len()
+            .saturating_sub(first_row)
+            .min(frame.size.height);
 
         // Highlight the currently selected line
-        let line = text.line(line_index);
-        let line_under_cursor = text.char_to_line(cursor.range().start) == line_index;
+        let line_under_cursor =
+            highlight_current_line && text.char_to_line(cursor.range().start) == line_index;
         if line_under_cursor && focused {
             canvas.clear_region(
                 Rect::new(
                     Position::new(frame.origin.x, frame.origin.y),
-                    Size::new(frame.size.width, 1),
+                    Size::new(frame.size.width, num_rows),
                 ),
                 theme.text_current_line,
             );
         }
 
         let mut visual_x = frame.origin.x;
-        let mut char_index = text.line_to_char(line_index);
+        let mut visual_y = frame.origin.y;
+        let mut row = 0;
+        let line_start = text.line_to_char(line_index);
+        let mut char_index = line_start;
         let line_start_byte = text.char_to_byte(char_index);
+        let line_diagnostics: Vec<_> = diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                (diagnostic.range.start.line..=diagnostic.range.end.line).contains(&line_index)
+            })
+            .map(|diagnostic| (diagnostic.char_range(text), diagnostic.severity))
+            .collect();
+        let trailing_whitespace_start = if show_whitespace {
+            char_index + trailing_whitespace_start(line)
+        } else {
+            CharIndex::MAX
+        };
 
         for grapheme in RopeGraphemes::new(&line.slice(..)) {
-            let is_error = false;
+            if row + 1 < row_starts.len() && char_index - line_start == row_starts[row + 1] {
+                row += 1;
+                if row >= first_row + num_rows {
+                    break;
+                }
+                visual_x = frame.origin.x;
+                visual_y = frame.origin.y + row.saturating_sub(first_row);
+            }
+            if row < first_row {
+                char_index += grapheme.len_chars();
+                continue;
+            }
+
+            // Errors use the theme's style for invalid code, other
+            // diagnostics are underlined
+            let severity = line_diagnostics
+                .iter()
+                .filter(|(range, _)| range.contains(&char_index))
+                .map(|&(_, severity)| severity)
+                .min();
+            let is_error = severity == Some(Severity::Error);
+            let is_search_match = {
+                // Matches are sorted and don't overlap
+                let index = search_matches.partition_point(|range| range.end <= char_index);
+                search_matches
+                    .get(index)
+                    .map(|range| range.contains(&char_index))
+                    .unwrap_or(false)
+            };
 
             let scope = get_scope(line_start_byte + grapheme.byte_start).unwrap_or("");
             let style = text_style_at_char(
@@ -178,9 +301,69 @@ impl TextArea {
                 line_under_cursor,
                 scope,
                 is_error,
+                is_search_match,
+                matching_bracket == Some(char_index),
+                cursor_shape,
             );
-            let grapheme_width =
-                zee_edit::graphemes::width(self.properties.mode.indentation.tab_width(), &grapheme);
+            let style = if severity.is_some() && !is_error {
+                Style {
+                    underline: true,
+                    ..style
+                }
+            } else {
+                style
+            };
+            let style = if is_secondary_cursor(char_index) {
+                match cursor_shape {
+                    CursorShape::Block => Style {
+                        background: block_cursor_style.background,
+                        foreground: block_cursor_style.foreground,
+                        ..style
+                    },
+                    CursorShape::Underline => Style {
+                        underline: true,
+                        ..style
+                    },
+                }
+            } else if secondary_cursors
+                .iter()
+                .any(|cursor| cursor.selection().contains(&char_index))
+            {
+                Style {
+                    background: theme.selection_background,
+                    ..style
+                }
+            } else {
+                style
+            };
+            let grapheme_width = zee_edit::graphemes::width(tab_width, &grapheme);
+            let style = if self.covers_ruler(visual_x - frame.origin.x, grapheme_width.max(1))
+                && (style.background == theme.text.background
+                    || style.background == theme.text_current_line.background)
+            {
+                Style {
+                    background: theme.ruler_background,
+                    ..style
+                }
+            } else {
+                style
+            };
+            let is_whitespace = grapheme.slice == " " || grapheme.slice == "\t";
+            let is_under_cursor = char_index == cursor.range().start
+                || cursor.range().contains(&char_index)
+                || is_secondary_cursor(char_index);
+            let style = if show_whitespace && is_whitespace && !is_under_cursor {
+                if char_index >= trailing_whitespace_start {
+                    theme.trailing_whitespace
+                } else {
+                    Style {
+                        foreground: theme.whitespace.foreground,
+                        ..style
+                    }
+                }
+            } else {
+                style
+            };
             let horizontal_bounds_inclusive = frame.min_x()..=frame.max_x();
             if !horizontal_bounds_inclusive.contains(&(visual_x + grapheme_width)) {
                 break;
@@ -188,14 +371,22 @@ impl TextArea {
 
             if grapheme.slice == "\t" {
                 for offset in 0..grapheme_width {
-                    canvas.draw_str(visual_x + offset, frame.origin.y, style, " ");
+                    let symbol = if show_whitespace && offset == 0 {
+                        TAB_SYMBOL
+                    } else {
+                        " "
+                    };
+                    canvas.draw_str(visual_x + offset, visual_y, style, symbol);
                 }
-            } else if grapheme_width == 0 {
-                canvas.draw_str(visual_x, frame.origin.y, style, " ");
+            } else if show_whitespace && grapheme.slice == " " {
+                canvas.draw_str(visual_x, visual_y, style, SPACE_SYMBOL);
+            } else if !zee_edit::graphemes::has_glyph(&grapheme) {
+                // Line endings and control characters
+                canvas.draw_str(visual_x, visual_y, style, " ");
             } else {
                 canvas.draw_graphemes(
                     visual_x,
-                    frame.origin.y,
+                    visual_y,
                     style,
                     iter::once(grapheme.chars().collect::<GraphemeCluster>()),
                 );
@@ -205,20 +396,50 @@ impl TextArea {
             visual_x += grapheme_width.max(1);
         }
 
+        // A line filling its last row has an extra row for the cursor
+        if row + 1 < first_row + num_rows && char_index - line_start == row_starts[row + 1] {
+            visual_x = frame.origin.x;
+            visual_y += 1;
+        }
         if line.get_char(line.len_chars().saturating_sub(1)) != Some('\n')
-            && cursor.range().start == char_index
+            && (cursor.range().start == char_index || is_secondary_cursor(char_index))
         {
-            canvas.draw_str(
-                visual_x,
-                frame.origin.y,
-                if focused {
-                    theme.cursor_focused
-                } else {
-                    theme.cursor_unfocused
+            let cursor_style = match cursor_shape {
+                CursorShape::Block => block_cursor_style,
+                CursorShape::Underline if line_under_cursor && focused => Style {
+                    underline: true,
+                    ..theme.text_current_line
                 },
-                " ",
-            );
+                CursorShape::Underline => Style {
+                    underline: true,
+                    ..theme.text
+                },
+            };
+            canvas.draw_str(visual_x, visual_y, cursor_style, " ");
+            visual_x += 1;
         }
+
+        // Draw the rulers past the end of the line
+        let ruler_style = Style::normal(theme.ruler_background, theme.text.foreground);
+        for &column in mode.rulers.iter() {
+            let ruler_x = frame.origin.x + column;
+            if ruler_x >= visual_x && ruler_x < frame.max_x() {
+                canvas.draw_str(ruler_x, visual_y, ruler_style, " ");
+            }
+        }
+
+        num_rows
+    }
+
+    /// Whether a grapheme drawn at `column` and `width` cells wide overlaps
+    /// one of the rulers of the current mode
+    #[inline]
+    fn covers_ruler(&self, column: usize, width: usize) -> bool {
+        self.properties
+            .mode
+            .rulers
+            .iter()
+            .any(|&ruler| (column..column + width).contains(&ruler))
     }
 
     #[inline]
@@ -244,6 +465,23 @@ impl TextArea {
         }
     }
 
+    #[inline]
+    fn search_matches_in_view(&self, expanse: &TextExpanse) -> Vec<Range<CharIndex>> {
+        let query = match self.properties.search_query.as_ref() {
+            Some(query) if !query.is_empty() => query,
+            _ => return Vec::new(),
+        };
+        let text = &self.properties.text;
+
+        // Include matches which straddle the edges of the view
+        let margin = query.len_chars() - 1;
+        let start = text
+            .byte_to_char(expanse.byte_range.start)
+            .saturating_sub(margin);
+        let end = (text.byte_to_char(expanse.byte_range.end) + margin).min(text.len_chars());
+        query.find_all(text, start..end)
+    }
+
     #[inline]
     fn get_highlights_query(&self) -> Option<&Query> {
         self.properties
@@ -255,6 +493,23 @@ impl TextArea {
     }
 }
 
+/// Returns the offset of the whitespace at the end of a line, excluding the
+/// line ending
+fn trailing_whitespace_start(line: RopeSlice) -> CharIndex {
+    let mut end = line.len_chars();
+    while end > 0 && matches!(line.char(end - 1), '\n' | '\r') {
+        end -= 1;
+    }
+    let mut start = end;
+    while start > 0 && matches!(line.char(start - 1), ' ' | '\t') {
+        start -= 1;
+    }
+    start
+}
+
+const SPACE_SYMBOL: &str = "·";
+const TAB_SYMBOL: &str = "→";
+
 struct TextExpanse {
     byte_range: Range<ByteIndex>,
     line_range: Range<LineIndex>,
@@ -2,4 +2,5 @@ pub mod buffer;
 pub mod edit_tree_viewer;
 pub mod prompt;
 pub mod splash;
+pub mod tab_bar;
 pub mod theme;
@@ -1,20 +1,20 @@
-use ropey::Rope;
 use size_format::SizeFormatterBinary;
 use std::{borrow::Cow, convert::TryInto, path::PathBuf};
 use zi::{
     components::{
-        input::{Cursor, Input, InputChange, InputProperties, InputStyle},
         select::{Select, SelectProperties},
         text::{Text, TextAlign, TextProperties},
     },
     unicode_width::UnicodeWidthStr,
-    Bindings, Callback, Colour, Component, ComponentExt, ComponentLink, Container, FlexBasis,
+    Bindings, Callback, Component, ComponentExt, ComponentLink, Container, FlexBasis,
     FlexDirection, Item, Key, Layout, Rect, ShouldRender, Style,
 };
 
+use zee_edit::line_editor::LineEditor;
 use zee_grammar::Mode;
 
 use super::{
+    line_input::{LineInput, Properties as LineInputProperties},
     matcher::Matcher,
     status::{Status, StatusProperties},
     Theme,
@@ -62,7 +62,7 @@ impl BufferEntry {
 #[derive(Debug)]
 pub enum Message {
     Select,
-    UpdateInput(InputChange),
+    UpdateInput(LineEditor),
     UpdateSelected(usize),
 }
 
@@ -79,8 +79,7 @@ pub struct Properties {
 pub struct BufferPicker {
     properties: Properties,
     link: ComponentLink<Self>,
-    input: Rope,
-    cursor: Cursor,
+    editor: LineEditor,
     selected_index: usize,
     current_task_id: Option<TaskId>,
     matcher: Matcher,
@@ -99,8 +98,7 @@ impl Component for BufferPicker {
         Self {
             properties,
             link,
-            input: "\n".into(),
-            cursor: Cursor::new(),
+            editor: LineEditor::default(),
             selected_index: 0,
             current_task_id: None,
             matcher,
@@ -108,10 +106,9 @@ impl Component for BufferPicker {
     }
 
     fn change(&mut self, properties: Self::Properties) -> ShouldRender {
-        let filter_str: Cow<str> = self.input.slice(..).into();
         self.matcher.set_filter(
             properties.entries.iter().map(|entry| entry.name.as_str()),
-            &filter_str,
+            self.editor.text(),
         );
         self.properties = properties;
         ShouldRender::Yes
@@ -125,15 +122,13 @@ impl Component for BufferPicker {
                     .emit(self.properties.entries[self.matcher[self.selected_index]].id);
                 false
             }
-            Message::UpdateInput(InputChange { content, cursor }) => {
-                self.selected_index = 0;
-                self.cursor = cursor;
-                if let Some(content) = content {
-                    self.input = content;
-                    true
-                } else {
-                    false
+            Message::UpdateInput(editor) => {
+                let input_changed = editor.text() != self.editor.text();
+                self.editor = editor;
+                if input_changed {
+                    self.selected_index = 0;
                 }
+                input_changed
             }
             Message::UpdateSelected(index) => {
                 self.selected_index = index;
@@ -143,13 +138,12 @@ impl Component for BufferPicker {
         };
 
         if input_changed {
-            let filter_str: Cow<str> = self.input.slice(..).into();
             self.matcher.set_filter(
                 self.properties
                     .entries
                     .iter()
                     .map(|entry| entry.name.as_str()),
-                &filter_str,
+                self.editor.text(),
             );
             self.properties.on_filter.emit(self.matcher.num_ranked());
         }
@@ -158,15 +152,11 @@ impl Component for BufferPicker {
     }
 
     fn view(&self) -> Layout {
-        let input = Input::with(InputProperties {
-            style: InputStyle {
-                content: self.properties.theme.input,
-                cursor: self.properties.theme.cursor,
-            },
-            content: self.input.clone(),
-            cursor: self.cursor.clone(),
-            on_change: Some(self.link.callback(Message::UpdateInput)),
-            focused: true,
+        let input = LineInput::with(LineInputProperties {
+            style: self.properties.theme.input,
+            cursor_style: self.properties.theme.cursor,
+            editor: self.editor.clone(),
+            on_change: self.link.callback(Message::UpdateInput),
         });
 
         let entries = self.properties.entries.clone();
@@ -175,18 +165,14 @@ impl Component for BufferPicker {
         let theme = self.properties.theme.clone();
         let item_at = move |index| {
             let entry = &entries[matcher[index]];
-            let background = if index == selected_index {
-                theme.item_focused_background
-            } else {
-                theme.item_unfocused_background
-            };
+            let focused = index == selected_index;
             Item::fixed(1)(Container::row([
                 Text::item_with_key(
                     FlexBasis::Fixed(20),
                     format!("{}name", entry.id).as_str(),
                     TextProperties::new()
                         .content(entry.name.clone())
-                        .style(Style::normal(background, theme.item_file_foreground)),
+                        .style(theme.item_style(focused, theme.item_file_foreground)),
                 ),
                 Text::item_with_key(
                     FlexBasis::Fixed(16),
@@ -196,7 +182,7 @@ impl Component for BufferPicker {
                             " {} ",
                             SizeFormatterBinary::new(entry.len_bytes.try_into().unwrap())
                         ))
-                        .style(Style::normal(background, theme.file_size))
+                        .style(theme.item_style(focused, theme.file_size))
                         .align(TextAlign::Right),
                 ),
                 Text::item_with_key(
@@ -204,7 +190,7 @@ impl Component for BufferPicker {
                     format!("{}mode", entry.id).as_str(),
                     TextProperties::new()
                         .content(entry.mode.name.clone())
-                        .style(Style::normal(background, theme.mode))
+                        .style(theme.item_style(focused, theme.mode))
                         .align(TextAlign::Right),
                 ),
                 Text::item_with_key(
@@ -218,7 +204,7 @@ impl Component for BufferPicker {
                                 .map(|entry| format!("    {}", entry.display()))
                                 .unwrap_or_else(String::new),
                         )
-                        .style(Style::normal(background, theme.file_size)),
+                        .style(theme.item_style(focused, theme.file_size)),
                 ),
             ]))
         };
@@ -234,8 +220,7 @@ impl Component for BufferPicker {
                         })
                         .style(Style::normal(
                             self.properties.theme.item_unfocused_background,
-                            Colour::rgb(251, 73, 52),
-                            // self.properties.theme.action.background,
+                            self.properties.theme.log_error.foreground,
                         )),
                 )
             } else {
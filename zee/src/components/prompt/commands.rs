@@ -0,0 +1,225 @@
+use std::borrow::Cow;
+use zi::{
+    components::{
+        select::{Select, SelectProperties},
+        text::{Text, TextAlign, TextProperties},
+    },
+    unicode_width::UnicodeWidthStr,
+    Bindings, Callback, Component, ComponentExt, ComponentLink, Container, FlexBasis,
+    FlexDirection, Item, Key, Layout, Rect, ShouldRender, Style,
+};
+
+use zee_edit::line_editor::LineEditor;
+
+use super::{
+    line_input::{LineInput, Properties as LineInputProperties},
+    matcher::Matcher,
+    status::{Status, StatusProperties},
+    Theme,
+};
+
+/// An entry of the command palette
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandEntry {
+    pub name: Cow<'static, str>,
+    /// The key sequences bound to the command, if any
+    pub keys: String,
+}
+
+#[derive(Debug)]
+pub enum Message {
+    Select,
+    UpdateInput(LineEditor),
+    UpdateSelected(usize),
+}
+
+#[derive(Clone)]
+pub struct Properties {
+    pub theme: Cow<'static, Theme>,
+    pub message: Cow<'static, str>,
+    pub entries: Vec<CommandEntry>,
+    /// Called with the index of the selected entry
+    pub on_select: Callback<usize>,
+    /// Called with the index of the entry under the cursor as it moves, e.g.
+    /// to preview it
+    pub on_highlight: Option<Callback<usize>>,
+    pub on_filter: Callback<usize>,
+}
+
+/// Fuzzy finds an entry by name, e.g. a command or a recently opened file
+pub struct CommandPicker {
+    properties: Properties,
+    link: ComponentLink<Self>,
+    editor: LineEditor,
+    selected_index: usize,
+    matcher: Matcher,
+}
+
+impl CommandPicker {
+    fn update_filter(&mut self) {
+        self.matcher.set_filter(
+            self.properties
+                .entries
+                .iter()
+                .map(|entry| entry.name.as_ref()),
+            self.editor.text(),
+        );
+    }
+
+    fn highlight_selected(&self) {
+        if let Some(on_highlight) = self.properties.on_highlight.as_ref() {
+            if self.selected_index < self.matcher.num_ranked() {
+                on_highlight.emit(self.matcher[self.selected_index]);
+            }
+        }
+    }
+}
+
+impl Component for CommandPicker {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
+        let mut picker = Self {
+            properties,
+            link,
+            editor: LineEditor::default(),
+            selected_index: 0,
+            matcher: Matcher::new(),
+        };
+        picker.update_filter();
+        picker
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        self.properties = properties;
+        self.update_filter();
+        ShouldRender::Yes
+    }
+
+    fn update(&mut self, message: Message) -> ShouldRender {
+        match message {
+            Message::Select if self.matcher.num_ranked() > 0 => {
+                self.properties
+                    .on_select
+                    .emit(self.matcher[self.selected_index]);
+            }
+            Message::UpdateInput(editor) => {
+                let input_changed = editor.text() != self.editor.text();
+                self.editor = editor;
+                if input_changed {
+                    self.selected_index = 0;
+                    self.update_filter();
+                    self.properties.on_filter.emit(self.matcher.num_ranked());
+                    self.highlight_selected();
+                }
+            }
+            Message::UpdateSelected(index) => {
+                self.selected_index = index;
+                self.highlight_selected();
+            }
+            _ => {}
+        }
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let input = LineInput::with(LineInputProperties {
+            style: self.properties.theme.input,
+            cursor_style: self.properties.theme.cursor,
+            editor: self.editor.clone(),
+            on_change: self.link.callback(Message::UpdateInput),
+        });
+
+        let entries = self.properties.entries.clone();
+        let matcher = self.matcher.clone();
+        let selected_index = self.selected_index;
+        let theme = self.properties.theme.clone();
+        let item_at = move |index| {
+            let entry = &entries[matcher[index]];
+            let focused = index == selected_index;
+            Item::fixed(1)(Container::row([
+                Text::item_with_key(
+                    FlexBasis::Auto,
+                    format!("{}name", entry.name).as_str(),
+                    TextProperties::new()
+                        .content(entry.name.clone())
+                        .style(theme.item_style(focused, theme.item_file_foreground)),
+                ),
+                Text::item_with_key(
+                    FlexBasis::Fixed(entry.keys.width() + 2),
+                    format!("{}keys", entry.name).as_str(),
+                    TextProperties::new()
+                        .content(format!(" {} ", entry.keys))
+                        .style(theme.item_style(focused, theme.mode))
+                        .align(TextAlign::Right),
+                ),
+            ]))
+        };
+        Layout::column([
+            if self.matcher.num_ranked() == 0 {
+                Text::item_with(
+                    FlexBasis::Fixed(1),
+                    TextProperties::new()
+                        .content("No matches")
+                        .style(Style::normal(
+                            self.properties.theme.item_unfocused_background,
+                            self.properties.theme.log_error.foreground,
+                        )),
+                )
+            } else {
+                Item::auto(Select::with(SelectProperties {
+                    background: Style::normal(
+                        self.properties.theme.item_unfocused_background,
+                        self.properties.theme.item_file_foreground,
+                    ),
+                    direction: FlexDirection::ColumnReverse,
+                    item_at: item_at.into(),
+                    focused: true,
+                    num_items: self.matcher.num_ranked(),
+                    selected: self.selected_index,
+                    on_change: self.link.callback(Message::UpdateSelected).into(),
+                    item_size: 1,
+                }))
+            },
+            Item::fixed(1)(Container::row([
+                Status::item_with_key(
+                    FlexBasis::Fixed(self.properties.message.width()),
+                    "status",
+                    StatusProperties {
+                        action_name: self.properties.message.clone(),
+                        pending: false,
+                        style: self.properties.theme.action,
+                    },
+                ),
+                Text::item_with_key(
+                    FlexBasis::Fixed(1),
+                    "spacer",
+                    TextProperties::new().style(self.properties.theme.input),
+                ),
+                Item::auto(input),
+                Text::item_with_key(
+                    FlexBasis::Fixed(12),
+                    "num-results",
+                    TextProperties::new()
+                        .content(format!(
+                            "{} of {} ",
+                            self.matcher.num_ranked(),
+                            self.properties.entries.len()
+                        ))
+                        .style(self.properties.theme.action.invert())
+                        .align(TextAlign::Right),
+                ),
+            ])),
+        ])
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        if !bindings.is_empty() {
+            return;
+        }
+
+        bindings.set_focus(true);
+        bindings.add("select-command", [Key::Char('\n')], || Message::Select);
+    }
+}
@@ -0,0 +1,344 @@
+use ignore::WalkBuilder;
+use ropey::Rope;
+use std::{
+    borrow::Cow,
+    cmp,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use zi::{
+    components::{
+        select::{Select, SelectProperties},
+        text::{Text, TextAlign, TextProperties},
+    },
+    prelude::*,
+    unicode_width::UnicodeWidthStr,
+    Callback, FlexBasis,
+};
+
+use zee_edit::{graphemes::RopeGraphemes, line_editor::LineEditor, search::Query, LineIndex};
+
+use super::{
+    line_input::{LineInput, Properties as LineInputProperties},
+    status::{Status, StatusProperties},
+    Theme, PROMPT_MAX_HEIGHT,
+};
+use crate::{
+    editor::ContextHandle,
+    encoding,
+    task::{CancelToken, Progress, TaskHandle, TaskId},
+};
+
+/// A line of a file containing the query. Only the first match on each line
+/// is listed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrepMatch {
+    pub file_path: PathBuf,
+    pub line_index: LineIndex,
+    /// The column of the match, in grapheme clusters
+    pub column: usize,
+    pub line: String,
+}
+
+#[derive(Debug)]
+pub enum Message {
+    ChangeQuery(LineEditor),
+    ChangeSelected(usize),
+    SearchDone {
+        task_id: TaskId,
+        matches: Vec<GrepMatch>,
+    },
+    Open,
+}
+
+#[derive(Clone)]
+pub struct Properties {
+    pub context: ContextHandle,
+    pub theme: Cow<'static, Theme>,
+    pub root: PathBuf,
+    pub on_open: Callback<GrepMatch>,
+    pub on_change_height: Callback<usize>,
+}
+
+/// Searches the contents of the files under a directory as the query is
+/// typed, skipping ignored, binary and large files
+pub struct Grep {
+    properties: Properties,
+    link: ComponentLink<Self>,
+    editor: LineEditor,
+    matches: Rc<Vec<GrepMatch>>,
+    selected_index: usize,
+    current_task: Option<TaskHandle>,
+}
+
+impl Grep {
+    fn search(&mut self) {
+        // The search for the previous query isn't needed anymore
+        if let Some(task) = self.current_task.take() {
+            task.cancel();
+        }
+        let query = self.editor.text();
+        if query.is_empty() {
+            self.matches = Rc::new(Vec::new());
+            return;
+        }
+
+        let link = self.link.clone();
+        let query = Query::new(query);
+        let root = self.properties.root.clone();
+        let max_file_size = self.properties.context.config.large_file_threshold;
+        self.current_task = Some(self.properties.context.task_pool.spawn_cancelable(
+            move |task_id, token, progress| {
+                let matches = search_files(&root, &query, max_file_size, &token, &progress);
+                if !token.is_cancelled() {
+                    link.send(Message::SearchDone { task_id, matches });
+                }
+            },
+        ));
+    }
+
+    fn height(&self) -> usize {
+        1 + cmp::min(self.matches.len(), PROMPT_MAX_HEIGHT)
+    }
+}
+
+// Stop searching once the prompt is closed
+impl Drop for Grep {
+    fn drop(&mut self) {
+        if let Some(task) = self.current_task.take() {
+            task.cancel();
+        }
+    }
+}
+
+impl Component for Grep {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
+        let editor = properties.context.input_history.editor(HISTORY);
+        Self {
+            properties,
+            link,
+            editor,
+            matches: Rc::new(Vec::new()),
+            selected_index: 0,
+            current_task: None,
+        }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        let should_render = (self.properties.theme != properties.theme).into();
+        self.properties = properties;
+        should_render
+    }
+
+    fn update(&mut self, message: Message) -> ShouldRender {
+        let initial_height = self.height();
+        match message {
+            Message::ChangeQuery(editor) => {
+                let query_changed = editor.text() != self.editor.text();
+                self.editor = editor;
+                if query_changed {
+                    self.search();
+                }
+            }
+            Message::ChangeSelected(index) => {
+                self.selected_index = index;
+            }
+            Message::SearchDone { task_id, matches }
+                if self
+                    .current_task
+                    .as_ref()
+                    .map_or(false, |task| task.id() == task_id) =>
+            {
+                self.matches = Rc::new(matches);
+                self.current_task = None;
+                self.selected_index = 0;
+            }
+            Message::Open => {
+                if let Some(grep_match) = self.matches.get(self.selected_index) {
+                    self.properties
+                        .context
+                        .input_history
+                        .add(HISTORY, self.editor.text());
+                    self.properties.on_open.emit(grep_match.clone());
+                }
+                return ShouldRender::No;
+            }
+            _ => return ShouldRender::No,
+        }
+
+        if initial_height != self.height() {
+            self.properties.on_change_height.emit(self.height());
+        }
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let input = LineInput::with(LineInputProperties {
+            style: self.properties.theme.input,
+            cursor_style: self.properties.theme.cursor,
+            editor: self.editor.clone(),
+            on_change: self.link.callback(Message::ChangeQuery),
+        });
+
+        let matches = self.matches.clone();
+        let root = self.properties.root.clone();
+        let selected_index = self.selected_index;
+        let theme = self.properties.theme.clone();
+        let item_at = move |index| {
+            let grep_match: &GrepMatch = &matches[index];
+            let focused = index == selected_index;
+            let location = format!(
+                "{}:{}: ",
+                grep_match
+                    .file_path
+                    .strip_prefix(&root)
+                    .unwrap_or(&grep_match.file_path)
+                    .display(),
+                grep_match.line_index + 1
+            );
+            Item::fixed(1)(Container::row([
+                Text::item_with_key(
+                    FlexBasis::Fixed(location.width()),
+                    format!("{}location", index).as_str(),
+                    TextProperties::new()
+                        .content(location)
+                        .style(theme.item_style(focused, theme.item_directory_foreground)),
+                ),
+                Text::item_with_key(
+                    FlexBasis::Auto,
+                    format!("{}line", index).as_str(),
+                    TextProperties::new()
+                        .content(grep_match.line.clone())
+                        .style(theme.item_style(focused, theme.item_file_foreground)),
+                ),
+            ]))
+        };
+        Layout::column([
+            Item::auto(Select::with(SelectProperties {
+                background: Style::normal(
+                    self.properties.theme.item_unfocused_background,
+                    self.properties.theme.item_file_foreground,
+                ),
+                direction: FlexDirection::ColumnReverse,
+                item_at: item_at.into(),
+                focused: true,
+                num_items: self.matches.len(),
+                selected: self.selected_index,
+                on_change: self.link.callback(Message::ChangeSelected).into(),
+                item_size: 1,
+            })),
+            Item::fixed(1)(Container::row([
+                Item::fixed(4)(Status::with(StatusProperties {
+                    action_name: "grep".into(),
+                    pending: self.current_task.is_some(),
+                    style: self.properties.theme.action,
+                })),
+                Item::fixed(1)(Text::with(
+                    TextProperties::new().style(self.properties.theme.input),
+                )),
+                Item::auto(input),
+                Text::item_with_key(
+                    FlexBasis::Fixed(16),
+                    "num-matches",
+                    TextProperties::new()
+                        .content(match self.matches.len() {
+                            MAX_MATCHES => format!("{}+ matches ", MAX_MATCHES),
+                            1 => "1 match ".into(),
+                            num_matches => format!("{} matches ", num_matches),
+                        })
+                        .style(self.properties.theme.action.invert())
+                        .align(TextAlign::Right),
+                ),
+            ])),
+        ])
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        if !bindings.is_empty() {
+            return;
+        }
+
+        bindings.set_focus(true);
+        bindings.add("open-match", [Key::Char('\n')], || Message::Open);
+    }
+}
+
+/// Finds the lines containing the query in the files under `root`, honouring
+/// `.gitignore` files. Files larger than `max_file_size` are skipped, like
+/// binary ones. Stops early when cancelled.
+fn search_files(
+    root: &Path,
+    query: &Query,
+    max_file_size: u64,
+    token: &CancelToken,
+    progress: &Progress,
+) -> Vec<GrepMatch> {
+    let mut matches = Vec::new();
+    let files = WalkBuilder::new(root)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_type()
+                .map_or(false, |file_type| file_type.is_file())
+        });
+    for (index, entry) in files.enumerate() {
+        if token.is_cancelled() || matches.len() >= MAX_MATCHES {
+            break;
+        }
+        if index > 0 && index % FILES_PER_PROGRESS_REPORT == 0 {
+            progress.report(format!("searching files ({})", index));
+        }
+        let text = match read_text_file(entry.path(), max_file_size) {
+            Some(text) => Rope::from(text),
+            None => continue,
+        };
+        let mut last_line_index = None;
+        for range in query.find_all(&text, 0..text.len_chars()) {
+            let line_index = text.char_to_line(range.start);
+            if last_line_index == Some(line_index) {
+                continue;
+            }
+            last_line_index = Some(line_index);
+            let line_start = text.line_to_char(line_index);
+            matches.push(GrepMatch {
+                file_path: entry.path().to_path_buf(),
+                line_index,
+                column: RopeGraphemes::new(&text.slice(line_start..range.start)).count(),
+                line: text.line(line_index).to_string().trim().to_owned(),
+            });
+            if matches.len() >= MAX_MATCHES {
+                break;
+            }
+        }
+    }
+    matches
+}
+
+/// Reads a UTF-8 file, unless it's larger than `max_size` or binary. Only the
+/// start of a binary file is read.
+fn read_text_file(file_path: &Path, max_size: u64) -> Option<String> {
+    let mut file = File::open(file_path).ok()?;
+    if file.metadata().ok()?.len() > max_size {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    (&mut file)
+        .take(encoding::BINARY_DETECTION_LENGTH as u64)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    if encoding::is_binary(&bytes) {
+        return None;
+    }
+    file.read_to_end(&mut bytes).ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+const HISTORY: &str = "grep";
+const MAX_MATCHES: usize = 10000;
+const FILES_PER_PROGRESS_REPORT: usize = 256;
@@ -0,0 +1,123 @@
+use std::borrow::Cow;
+use zi::{
+    components::text::{Text, TextProperties},
+    prelude::*,
+    unicode_width::UnicodeWidthStr,
+    Callback, FlexBasis,
+};
+
+use zee_edit::line_editor::LineEditor;
+
+use super::{
+    line_input::{LineInput, Properties as LineInputProperties},
+    status::{Status, StatusProperties},
+    Theme,
+};
+use crate::editor::ContextHandle;
+
+#[derive(Debug)]
+pub enum Message {
+    ChangeInput(LineEditor),
+    Submit,
+    Cancel,
+}
+
+#[derive(Clone)]
+pub struct Properties {
+    pub context: ContextHandle,
+    pub theme: Cow<'static, Theme>,
+    pub message: Cow<'static, str>,
+    /// The kind of input, the inputs of the same kind share a history
+    pub history: &'static str,
+    pub on_submit: Callback<String>,
+    pub on_cancel: Callback<()>,
+}
+
+/// A single line of text input, e.g. the replacement string for
+/// `query-replace`
+pub struct TextInput {
+    properties: Properties,
+    link: ComponentLink<Self>,
+    editor: LineEditor,
+}
+
+impl Component for TextInput {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
+        let editor = properties.context.input_history.editor(properties.history);
+        Self {
+            properties,
+            link,
+            editor,
+        }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        let should_render = (self.properties.theme != properties.theme
+            || self.properties.message != properties.message)
+            .into();
+        self.properties = properties;
+        should_render
+    }
+
+    fn update(&mut self, message: Message) -> ShouldRender {
+        match message {
+            Message::ChangeInput(editor) => {
+                self.editor = editor;
+                ShouldRender::Yes
+            }
+            Message::Submit => {
+                let input = self.editor.text();
+                self.properties
+                    .context
+                    .input_history
+                    .add(self.properties.history, input);
+                self.properties.on_submit.emit(input.to_owned());
+                ShouldRender::No
+            }
+            Message::Cancel => {
+                self.properties.on_cancel.emit(());
+                ShouldRender::No
+            }
+        }
+    }
+
+    fn view(&self) -> Layout {
+        let input = LineInput::with(LineInputProperties {
+            style: self.properties.theme.input,
+            cursor_style: self.properties.theme.cursor,
+            editor: self.editor.clone(),
+            on_change: self.link.callback(Message::ChangeInput),
+        });
+
+        Layout::row([
+            Status::item_with_key(
+                FlexBasis::Fixed(self.properties.message.width()),
+                "status",
+                StatusProperties {
+                    action_name: self.properties.message.clone(),
+                    pending: false,
+                    style: self.properties.theme.action,
+                },
+            ),
+            Text::item_with_key(
+                FlexBasis::Fixed(1),
+                "spacer",
+                TextProperties::new().style(self.properties.theme.input),
+            ),
+            Item::auto(input),
+        ])
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        if !bindings.is_empty() {
+            return;
+        }
+
+        bindings.set_focus(true);
+        bindings.add("submit-input", [Key::Char('\n')], || Message::Submit);
+        bindings.add("cancel-input", [Key::Esc], || Message::Cancel);
+    }
+}
@@ -8,16 +8,19 @@ use zi::{
 
 use super::Theme;
 
-// Message type handled by the `InteractiveMessage` component
-#[derive(Clone, Copy, PartialEq)]
-pub enum Message {
-    Accept,
-    Decline,
+/// The answer to a question asked by the `InteractiveMessage` component
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Yes,
+    No,
+    Cancel,
 }
 
 pub struct Properties {
     pub theme: Cow<'static, Theme>,
-    pub on_input: Callback<bool>,
+    pub on_answer: Callback<Answer>,
+    /// Whether the question can be cancelled rather than only declined
+    pub cancellable: bool,
     pub message: String,
 }
 
@@ -26,7 +29,7 @@ pub struct InteractiveMessage {
 }
 
 impl Component for InteractiveMessage {
-    type Message = Message;
+    type Message = Answer;
 
     type Properties = Properties;
 
@@ -35,7 +38,11 @@ impl Component for InteractiveMessage {
     }
 
     fn view(&self) -> Layout {
-        let message = format!("{} (y/n)", self.properties.message);
+        let message = if self.properties.cancellable {
+            format!("{} (y/n/c)", self.properties.message)
+        } else {
+            format!("{} (y/n)", self.properties.message)
+        };
         Text::with(
             TextProperties::new()
                 .style(self.properties.theme.input)
@@ -43,8 +50,12 @@ impl Component for InteractiveMessage {
         )
     }
 
-    fn update(&mut self, message: Self::Message) -> ShouldRender {
-        self.properties.on_input.emit(message == Message::Accept);
+    fn update(&mut self, answer: Self::Message) -> ShouldRender {
+        let answer = match answer {
+            Answer::Cancel if !self.properties.cancellable => Answer::No,
+            answer => answer,
+        };
+        self.properties.on_answer.emit(answer);
         ShouldRender::No
     }
 
@@ -57,12 +68,17 @@ impl Component for InteractiveMessage {
         bindings.set_focus(true);
 
         bindings
-            .command("accept", || Message::Accept)
+            .command("accept", || Answer::Yes)
             .with([Key::Char('y')]);
 
         bindings
-            .command("decline", || Message::Decline)
-            .with([Key::Esc])
+            .command("decline", || Answer::No)
             .with([Key::Char('n')]);
+
+        // If the question isn't cancellable, cancelling is the same as declining
+        bindings
+            .command("cancel", || Answer::Cancel)
+            .with([Key::Esc])
+            .with([Key::Char('c')]);
     }
 }
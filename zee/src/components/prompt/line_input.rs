@@ -0,0 +1,207 @@
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use zi::{prelude::*, unicode_width::UnicodeWidthStr, Callback, Canvas};
+
+use zee_edit::line_editor::{self, LineEditor};
+
+#[derive(Debug)]
+pub enum Message {
+    Edit(fn(&mut LineEditor)),
+    InsertChar(char),
+}
+
+#[derive(Clone)]
+pub struct Properties {
+    pub style: Style,
+    pub cursor_style: Style,
+    pub editor: LineEditor,
+    pub on_change: Callback<LineEditor>,
+}
+
+/// A single line text input, which edits a [`LineEditor`] owned by its parent
+/// with the same keys in every prompt. The text is scrolled horizontally to
+/// keep the cursor visible.
+pub struct LineInput {
+    properties: Properties,
+    frame: Rect,
+}
+
+impl Component for LineInput {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, frame: Rect, _link: ComponentLink<Self>) -> Self {
+        Self { properties, frame }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        let should_render = (self.properties.style != properties.style
+            || self.properties.cursor_style != properties.cursor_style
+            || self.properties.editor != properties.editor)
+            .into();
+        self.properties = properties;
+        should_render
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+        ShouldRender::Yes
+    }
+
+    fn update(&mut self, message: Message) -> ShouldRender {
+        let mut editor = self.properties.editor.clone();
+        match message {
+            Message::Edit(edit) => edit(&mut editor),
+            Message::InsertChar(character) => editor.insert_char(character),
+        }
+        if editor != self.properties.editor {
+            self.properties.on_change.emit(editor);
+        }
+        ShouldRender::No
+    }
+
+    fn view(&self) -> Layout {
+        let Properties {
+            style,
+            cursor_style,
+            ref editor,
+            ..
+        } = self.properties;
+        let width = self.frame.size.width;
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(style);
+        if width == 0 {
+            return canvas.into();
+        }
+
+        // Scroll just enough to show the cursor in the last column
+        let offset = (editor.cursor_column() + 1).saturating_sub(width);
+        let mut column = 0;
+        for (byte_index, grapheme) in editor.graphemes() {
+            let grapheme_width = line_editor::display_width(grapheme);
+            if column >= offset + width {
+                break;
+            }
+            if column >= offset {
+                let style = if byte_index == editor.cursor() {
+                    cursor_style
+                } else {
+                    style
+                };
+                let has_glyph = grapheme.width() > 0 && !grapheme.chars().all(char::is_control);
+                canvas.draw_str(
+                    column - offset,
+                    0,
+                    style,
+                    if has_glyph { grapheme } else { " " },
+                );
+            }
+            column += grapheme_width;
+        }
+        if editor.cursor() == editor.text().len() {
+            canvas.draw_str(column - offset, 0, cursor_style, " ");
+        }
+        canvas.into()
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        use Key::*;
+
+        bindings.set_focus(true);
+        if !bindings.is_empty() {
+            return;
+        }
+
+        // Cursor movement
+        bindings
+            .command("move-backward", || Message::Edit(LineEditor::move_left))
+            .with([Ctrl('b')])
+            .with([Left]);
+        bindings
+            .command("move-forward", || Message::Edit(LineEditor::move_right))
+            .with([Ctrl('f')])
+            .with([Right]);
+        bindings.add("move-backward-word", [Alt('b')], || {
+            Message::Edit(LineEditor::move_backward_word)
+        });
+        bindings.add("move-forward-word", [Alt('f')], || {
+            Message::Edit(LineEditor::move_forward_word)
+        });
+        bindings
+            .command("move-start-of-line", || {
+                Message::Edit(LineEditor::move_to_start)
+            })
+            .with([Ctrl('a')])
+            .with([Home]);
+        bindings
+            .command("move-end-of-line", || {
+                Message::Edit(LineEditor::move_to_end)
+            })
+            .with([Ctrl('e')])
+            .with([End]);
+
+        // Deletion
+        bindings.add("delete-backward", [Backspace], || {
+            Message::Edit(LineEditor::delete_backward)
+        });
+        bindings
+            .command("delete-forward", || {
+                Message::Edit(LineEditor::delete_forward)
+            })
+            .with([Ctrl('d')])
+            .with([Delete]);
+        bindings.add("delete-backward-word", [Ctrl('w')], || {
+            Message::Edit(LineEditor::delete_backward_word)
+        });
+        bindings.add("delete-forward-word", [Alt('d')], || {
+            Message::Edit(LineEditor::delete_forward_word)
+        });
+        bindings.add("delete-start-of-line", [Ctrl('u')], || {
+            Message::Edit(LineEditor::delete_to_start)
+        });
+        bindings.add("delete-end-of-line", [Ctrl('k')], || {
+            Message::Edit(LineEditor::delete_to_end)
+        });
+
+        // History
+        bindings.add("previous-input", [Alt('p')], || {
+            Message::Edit(LineEditor::previous_history)
+        });
+        bindings.add("next-input", [Alt('n')], || {
+            Message::Edit(LineEditor::next_history)
+        });
+
+        // Enter and Tab are left to the prompts, e.g. to submit the input
+        bindings.add(
+            "insert-character",
+            AnyCharacter,
+            |_this: &Self, keys: &[Key]| match keys {
+                &[Char(character)] if character != '\n' && character != '\t' => {
+                    Some(Message::InsertChar(character))
+                }
+                _ => None,
+            },
+        );
+    }
+}
+
+/// The lines entered in the prompts during a session, by the kind of input,
+/// e.g. search queries or shell commands
+#[derive(Debug, Default)]
+pub struct InputHistory(RwLock<HashMap<&'static str, Vec<String>>>);
+
+impl InputHistory {
+    /// An editor for a new input of a kind, which can go back to the previous
+    /// inputs of that kind
+    pub fn editor(&self, kind: &str) -> LineEditor {
+        let history = self.0.read().get(kind).cloned().unwrap_or_default();
+        LineEditor::default().with_history(history)
+    }
+
+    pub fn add(&self, kind: &'static str, entry: &str) {
+        let mut history = self.0.write();
+        line_editor::add_to_history(history.entry(kind).or_default(), entry, MAX_ENTRIES);
+    }
+}
+
+const MAX_ENTRIES: usize = 100;
@@ -1,30 +1,49 @@
 pub mod buffers;
+pub mod commands;
+pub mod grep;
 pub mod picker;
+pub mod replace;
 
 mod matcher;
 mod status;
 
+mod input;
 mod interactive;
+mod line_input;
+mod search;
 
 use std::{borrow::Cow, path::PathBuf};
 use zi::{
     components::text::{Text, TextProperties},
-    Background, Callback, Component, ComponentExt, ComponentLink, Foreground, Layout, Rect,
-    ShouldRender, Style,
+    unicode_width::UnicodeWidthStr,
+    Background, Callback, Component, ComponentExt, ComponentLink, FlexBasis, Foreground, Item,
+    Layout, Rect, ShouldRender, Style,
 };
 
-use crate::editor::{BufferId, ContextHandle};
+use crate::{
+    editor::{BufferId, ContextHandle, LogLevel},
+    task::TaskPoolStatus,
+};
+
+pub use self::{interactive::Answer, line_input::InputHistory};
 
 use self::{
     buffers::{BufferEntry, BufferPicker, Properties as BufferPickerProperties},
+    commands::{CommandEntry, CommandPicker, Properties as CommandPickerProperties},
+    grep::{Grep, GrepMatch, Properties as GrepProperties},
+    input::{Properties as TextInputProperties, TextInput},
     interactive::{InteractiveMessage, Properties as InteractiveMessageProperties},
     picker::{FilePicker, FileSource, Properties as FilePickerProperties},
+    replace::{Properties as QueryReplaceProperties, QueryReplace, ReplaceChoice},
+    search::{Properties as SearchProperties, Search},
 };
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Theme {
     pub action: Style,
     pub input: Style,
+    pub log_warning: Style,
+    pub log_error: Style,
     pub cursor: Style,
     pub mode: Foreground,
     pub file_size: Foreground,
@@ -34,10 +53,25 @@ pub struct Theme {
     pub item_directory_foreground: Foreground,
 }
 
+impl Theme {
+    /// The style of an item in a list. Text the colour of the focused item's
+    /// background is reversed in it, as in the monochrome theme.
+    pub fn item_style(&self, focused: bool, foreground: Foreground) -> Style {
+        match focused {
+            true if foreground == self.item_focused_background => {
+                Style::normal(foreground, self.item_unfocused_background)
+            }
+            true => Style::normal(self.item_focused_background, foreground),
+            false => Style::normal(self.item_unfocused_background, foreground),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Action {
     None,
     Log {
+        level: LogLevel,
         message: String,
     },
     PickBuffer {
@@ -46,14 +80,61 @@ pub enum Action {
         on_select: Callback<BufferId>,
         on_change_height: Callback<usize>,
     },
+    PickCommand {
+        message: Cow<'static, str>,
+        entries: Vec<CommandEntry>,
+        on_select: Callback<usize>,
+        /// Called with the index of the entry under the cursor as it moves
+        on_highlight: Option<Callback<usize>>,
+        on_change_height: Callback<usize>,
+    },
     OpenFile {
         source: FileSource,
+        initial_path: Option<PathBuf>,
         on_open: Callback<PathBuf>,
         on_change_height: Callback<usize>,
     },
+    SaveFileAs {
+        initial_path: Option<PathBuf>,
+        on_save: Callback<PathBuf>,
+        on_change_height: Callback<usize>,
+    },
+    /// Picks a new working directory
+    ChangeDirectory {
+        initial_path: Option<PathBuf>,
+        on_select: Callback<PathBuf>,
+        on_change_height: Callback<usize>,
+    },
+    /// Searches the contents of the files under `root`
+    Grep {
+        root: PathBuf,
+        on_open: Callback<GrepMatch>,
+        on_change_height: Callback<usize>,
+    },
+    /// Asks a yes or no question
     InteractiveMessage {
         message: Cow<'static, str>,
-        on_input: Callback<bool>,
+        cancellable: bool,
+        on_answer: Callback<Answer>,
+    },
+    Search {
+        message: Cow<'static, str>,
+        note: Option<Cow<'static, str>>,
+        on_change: Callback<String>,
+        on_confirm: Callback<()>,
+        on_cancel: Callback<()>,
+    },
+    Input {
+        message: Cow<'static, str>,
+        /// The kind of input, e.g. `shell command`, inputs of the same kind
+        /// share a history
+        history: &'static str,
+        on_submit: Callback<String>,
+        on_cancel: Callback<()>,
+    },
+    QueryReplace {
+        message: Cow<'static, str>,
+        on_choice: Callback<ReplaceChoice>,
     },
 }
 
@@ -70,11 +151,22 @@ impl Action {
         matches!(self, Self::Log { .. })
     }
 
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Self::Log {
+                level: LogLevel::Error,
+                ..
+            }
+        )
+    }
+
     pub fn initial_height(&self) -> usize {
         match self {
             Self::PickBuffer { ref entries, .. } => {
                 1 + std::cmp::min(std::cmp::max(entries.len(), 1), PROMPT_MAX_HEIGHT)
             }
+            Self::PickCommand { ref entries, .. } => 1 + entries.len().clamp(1, PROMPT_MAX_HEIGHT),
             _ => 1,
         }
     }
@@ -89,32 +181,23 @@ pub struct Properties {
 
 pub struct Prompt {
     properties: Properties,
+    // Long running tasks, shown with a spinner on the right of the prompt
+    tasks: Option<TaskPoolStatus>,
+    spinner_frame: usize,
 }
 
-impl Component for Prompt {
-    type Message = ();
-    type Properties = Properties;
-
-    fn create(properties: Self::Properties, _frame: Rect, _link: ComponentLink<Self>) -> Self {
-        Self { properties }
-    }
-
-    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
-        let should_render = (self.properties.action != properties.action
-            || self.properties.theme != properties.theme)
-            .into();
-        self.properties = properties;
-
-        should_render
-    }
-
-    fn view(&self) -> Layout {
+impl Prompt {
+    fn action_view(&self) -> Layout {
         match &self.properties.action {
             Action::None => Text::with(TextProperties::new().style(self.properties.theme.input)),
-            Action::Log { message } => Text::with(
+            Action::Log { level, message } => Text::with(
                 TextProperties::new()
                     .content(message.clone())
-                    .style(self.properties.theme.input),
+                    .style(match level {
+                        LogLevel::Info => self.properties.theme.input,
+                        LogLevel::Warning => self.properties.theme.log_warning,
+                        LogLevel::Error => self.properties.theme.log_error,
+                    }),
             ),
             Action::PickBuffer {
                 message,
@@ -137,27 +220,195 @@ impl Component for Prompt {
                     on_filter,
                 })
             }
+            Action::PickCommand {
+                message,
+                entries,
+                on_select,
+                on_highlight,
+                on_change_height,
+            } => {
+                let on_change_height = on_change_height.clone();
+                let on_filter = (move |size: usize| {
+                    on_change_height.emit(1 + size.clamp(1, 15));
+                })
+                .into();
+
+                CommandPicker::with(CommandPickerProperties {
+                    message: message.clone(),
+                    theme: self.properties.theme.clone(),
+                    entries: entries.clone(),
+                    on_select: on_select.clone(),
+                    on_highlight: on_highlight.clone(),
+                    on_filter,
+                })
+            }
             Action::OpenFile {
                 source,
+                initial_path,
                 on_change_height,
                 on_open,
             } => FilePicker::with(FilePickerProperties {
                 context: self.properties.context.clone(),
                 theme: self.properties.theme.clone(),
                 source: *source,
+                action_name: source.status_name(),
+                initial_path: initial_path.clone(),
                 on_open: on_open.clone(),
                 on_change_height: on_change_height.clone(),
             }),
-            Action::InteractiveMessage { on_input, message } => {
-                InteractiveMessage::with(InteractiveMessageProperties {
+            Action::SaveFileAs {
+                initial_path,
+                on_save,
+                on_change_height,
+            } => FilePicker::with(FilePickerProperties {
+                context: self.properties.context.clone(),
+                theme: self.properties.theme.clone(),
+                source: FileSource::Directory,
+                action_name: "save".into(),
+                initial_path: initial_path.clone(),
+                on_open: on_save.clone(),
+                on_change_height: on_change_height.clone(),
+            }),
+            Action::ChangeDirectory {
+                initial_path,
+                on_select,
+                on_change_height,
+            } => FilePicker::with(FilePickerProperties {
+                context: self.properties.context.clone(),
+                theme: self.properties.theme.clone(),
+                source: FileSource::Directory,
+                action_name: "cd".into(),
+                initial_path: initial_path.clone(),
+                on_open: on_select.clone(),
+                on_change_height: on_change_height.clone(),
+            }),
+            Action::Grep {
+                root,
+                on_open,
+                on_change_height,
+            } => Grep::with(GrepProperties {
+                context: self.properties.context.clone(),
+                theme: self.properties.theme.clone(),
+                root: root.clone(),
+                on_open: on_open.clone(),
+                on_change_height: on_change_height.clone(),
+            }),
+            Action::InteractiveMessage {
+                message,
+                cancellable,
+                on_answer,
+            } => InteractiveMessage::with(InteractiveMessageProperties {
+                theme: self.properties.theme.clone(),
+                on_answer: on_answer.clone(),
+                cancellable: *cancellable,
+                message: message.to_string(),
+            }),
+            Action::Search {
+                message,
+                note,
+                on_change,
+                on_confirm,
+                on_cancel,
+            } => Search::with(SearchProperties {
+                context: self.properties.context.clone(),
+                theme: self.properties.theme.clone(),
+                message: message.clone(),
+                note: note.clone(),
+                on_change: on_change.clone(),
+                on_confirm: on_confirm.clone(),
+                on_cancel: on_cancel.clone(),
+            }),
+            Action::Input {
+                message,
+                history,
+                on_submit,
+                on_cancel,
+            } => TextInput::with_key(
+                // Keyed by the message, so consecutive inputs start out empty
+                message.as_ref(),
+                TextInputProperties {
+                    context: self.properties.context.clone(),
+                    theme: self.properties.theme.clone(),
+                    message: message.clone(),
+                    history,
+                    on_submit: on_submit.clone(),
+                    on_cancel: on_cancel.clone(),
+                },
+            ),
+            Action::QueryReplace { message, on_choice } => {
+                QueryReplace::with(QueryReplaceProperties {
                     theme: self.properties.theme.clone(),
-                    on_input: on_input.clone(),
                     message: message.to_string(),
+                    on_choice: on_choice.clone(),
                 })
             }
         }
     }
 }
 
+impl Component for Prompt {
+    type Message = ();
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, _link: ComponentLink<Self>) -> Self {
+        Self {
+            properties,
+            tasks: None,
+            spinner_frame: 0,
+        }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        let should_render = (self.properties.action != properties.action
+            || self.properties.theme != properties.theme)
+            .into();
+        self.properties = properties;
+
+        should_render
+    }
+
+    fn update(&mut self, _message: Self::Message) -> ShouldRender {
+        self.tasks = self.properties.context.task_pool.status();
+        self.spinner_frame = (self.spinner_frame + 1) % SPINNER_FRAMES.len();
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let progress = self.tasks.as_ref().map(|tasks| {
+            let description = match tasks.progress {
+                Some(ref progress) => progress.clone(),
+                None if tasks.num_active == 1 => "1 task running".into(),
+                None => format!("{} tasks running", tasks.num_active),
+            };
+            format!(" {} {} ", SPINNER_FRAMES[self.spinner_frame], description)
+        });
+
+        // The spinner is always laid out, even when empty, so the action's
+        // component isn't recreated when tasks start or finish
+        Layout::row([
+            Item::auto(self.action_view()),
+            Text::item_with_key(
+                FlexBasis::Fixed(progress.as_ref().map_or(0, |progress| progress.width())),
+                "progress",
+                TextProperties::new()
+                    .content(progress.unwrap_or_default())
+                    .style(self.properties.theme.action),
+            ),
+        ])
+    }
+
+    // Polls the task pool while tasks are running, and once more after they're
+    // done to clear the spinner. Tasks are polled as soon as they start, the
+    // spinner is only shown once they've been running for a while.
+    fn tick(&self) -> Option<Self::Message> {
+        if self.tasks.is_some() || self.properties.context.task_pool.has_active_tasks() {
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
 pub const PROMPT_INACTIVE_HEIGHT: usize = 1;
 const PROMPT_MAX_HEIGHT: usize = 15;
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
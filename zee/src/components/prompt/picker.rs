@@ -1,6 +1,5 @@
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use ignore::WalkBuilder;
-use ropey::Rope;
 use std::{
     borrow::Cow,
     cmp, fmt, fs,
@@ -9,7 +8,6 @@ use std::{
 };
 use zi::{
     components::{
-        input::{Cursor, Input, InputChange, InputProperties, InputStyle},
         select::{Select, SelectProperties},
         text::{Text, TextProperties},
     },
@@ -17,15 +15,17 @@ use zi::{
     Callback,
 };
 
+use zee_edit::line_editor::LineEditor;
+
 use super::{
+    line_input::{LineInput, Properties as LineInputProperties},
     status::{Status, StatusProperties},
     Theme, PROMPT_MAX_HEIGHT,
 };
 use crate::{
     editor::ContextHandle,
     error::{Context as _Context, Result},
-    task::TaskId,
-    utils::ensure_trailing_newline_with_content,
+    task::{CancelToken, Progress, TaskHandle, TaskId},
 };
 
 #[derive(Debug)]
@@ -41,7 +41,7 @@ pub enum FileSource {
 }
 
 impl FileSource {
-    fn status_name(&self) -> Cow<'static, str> {
+    pub fn status_name(&self) -> Cow<'static, str> {
         match self {
             Self::Directory => "open",
             Self::Repository => "repo",
@@ -57,7 +57,7 @@ pub enum Message {
 
     // Path navigation
     AutocompletePath,
-    ChangePath(InputChange),
+    ChangePath(LineEditor),
     ChangeSelectedFile(usize),
     SelectParentDirectory,
 }
@@ -67,6 +67,8 @@ pub struct Properties {
     pub context: ContextHandle,
     pub theme: Cow<'static, Theme>,
     pub source: FileSource,
+    pub action_name: Cow<'static, str>,
+    pub initial_path: Option<PathBuf>,
     pub on_open: Callback<PathBuf>,
     pub on_change_height: Callback<usize>,
 }
@@ -74,32 +76,129 @@ pub struct Properties {
 pub struct FilePicker {
     properties: Properties,
     link: ComponentLink<Self>,
-    input: Rope,
-    cursor: Cursor,
+    editor: LineEditor,
     listing: Rc<FileListing>,
     selected_index: usize,
-    current_task_id: Option<TaskId>,
+    current_task: Option<TaskHandle>,
+    // Candidates being cycled through by pressing TAB repeatedly
+    completions: Option<(Vec<PathBuf>, usize)>,
 }
 
 impl FilePicker {
     fn list_files(&mut self, source: FileSource) {
         let link = self.link.clone();
-        let input = self.input.clone();
+        let path_str = self.listed_path();
         let mut listing = (*self.listing).clone();
-        self.current_task_id = Some(self.properties.context.task_pool.spawn(move |task_id| {
-            let path_str = input.to_string();
-            link.send(Message::FileListingDone(match source {
-                FileSource::Directory => pick_from_directory(&mut listing, path_str)
-                    .map(|_| FileListingDone { task_id, listing }),
-                FileSource::Repository => pick_from_repository(&mut listing, path_str)
-                    .map(|_| FileListingDone { task_id, listing }),
-            }))
-        }))
+        // The listing for the previous input isn't needed anymore
+        if let Some(task) = self.current_task.take() {
+            task.cancel();
+        }
+        self.current_task = Some(self.properties.context.task_pool.spawn_cancelable(
+            move |task_id, token, progress| {
+                let result = match source {
+                    FileSource::Directory => {
+                        pick_from_directory(&mut listing, path_str, &token, &progress)
+                    }
+                    FileSource::Repository => {
+                        pick_from_repository(&mut listing, path_str, &token, &progress)
+                    }
+                };
+                if !token.is_cancelled() {
+                    link.send(Message::FileListingDone(
+                        result.map(|_| FileListingDone { task_id, listing }),
+                    ))
+                }
+            },
+        ))
     }
 
     fn height(&self) -> usize {
         1 + cmp::min(self.listing.num_filtered(), PROMPT_MAX_HEIGHT)
     }
+
+    // The input followed by a newline, so that the directory listed for
+    // `dir/` is `dir` rather than its parent, as `Path::parent` ignores the
+    // trailing `/`
+    fn listed_path(&self) -> String {
+        format!("{}\n", self.editor.text())
+    }
+
+    fn set_input_path(&mut self, path: &Path, trailing_slash: bool) {
+        let mut path_str = path.to_string_lossy().into_owned();
+        if trailing_slash {
+            path_str.push('/');
+        }
+        self.editor.set_text(path_str);
+        self.selected_index = 0;
+    }
+
+    /// Completes the file name being typed to the longest prefix shared by
+    /// the entries of the directory which start with it. If there's nothing
+    /// left to complete, pressing TAB again cycles through the entries.
+    /// Returns `true` if the input has changed.
+    fn complete_path(&mut self) -> bool {
+        if let Some((candidates, index)) = self.completions.as_mut() {
+            *index = (*index + 1) % candidates.len();
+            let candidate = candidates[*index].clone();
+            self.set_input_path(&candidate, false);
+            return true;
+        }
+
+        let input_str = self.listed_path();
+        let path_str = self.editor.text();
+        let file_name = match path_str.rsplit_once('/') {
+            Some((_, file_name)) => file_name,
+            None => path_str,
+        };
+        let mut candidates: Vec<PathBuf> = match directory_files_iter(&input_str) {
+            Ok(files) => files
+                .filter_map(|path| path.ok())
+                .filter(|path| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().starts_with(file_name))
+                        .unwrap_or(false)
+                })
+                .collect(),
+            Err(_) => return false,
+        };
+        candidates.sort();
+
+        let common_prefix = longest_common_prefix(
+            candidates
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy()),
+        );
+        match candidates.len() {
+            0 => false,
+            1 => {
+                let candidate = candidates.pop().unwrap();
+                let is_dir = candidate.is_dir();
+                self.set_input_path(&candidate, is_dir);
+                true
+            }
+            _ if common_prefix.len() > file_name.len() => {
+                let mut completed = path_str.to_owned();
+                completed.push_str(&common_prefix[file_name.len()..]);
+                self.set_input_path(Path::new(&completed), false);
+                true
+            }
+            _ => {
+                self.set_input_path(&candidates[0], false);
+                self.completions = Some((candidates, 0));
+                true
+            }
+        }
+    }
+}
+
+// Stop listing files once the picker is closed
+impl Drop for FilePicker {
+    fn drop(&mut self) {
+        if let Some(task) = self.current_task.take() {
+            task.cancel();
+        }
+    }
 }
 
 impl Component for FilePicker {
@@ -107,25 +206,27 @@ impl Component for FilePicker {
     type Properties = Properties;
 
     fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
-        let mut cursor = Cursor::new();
-        let mut current_working_dir: String = properties
-            .context
-            .current_working_dir
-            .to_string_lossy()
-            .into();
-        current_working_dir.push('/');
-        current_working_dir.push('\n');
-        let input = current_working_dir.into();
-        cursor.move_to_end_of_line(&input);
+        let initial_input: String = match properties.initial_path.as_ref() {
+            Some(initial_path) => initial_path.to_string_lossy().into(),
+            None => {
+                let mut current_working_dir: String = properties
+                    .context
+                    .current_working_dir()
+                    .to_string_lossy()
+                    .into();
+                current_working_dir.push('/');
+                current_working_dir
+            }
+        };
 
         let mut picker = Self {
             properties,
             link,
-            input,
-            cursor,
+            editor: LineEditor::new(initial_input),
             listing: Rc::new(FileListing::new()),
             selected_index: 0,
-            current_task_id: None,
+            current_task: None,
+            completions: None,
         };
         picker.list_files(picker.properties.source);
         picker.properties.on_change_height.emit(picker.height());
@@ -145,47 +246,47 @@ impl Component for FilePicker {
         let initial_height = self.height();
         let input_changed = match message {
             Message::OpenFile => {
-                let path_str: Cow<str> = self.input.slice(..).into();
-                let path = PathBuf::from(path_str.trim());
+                let path = PathBuf::from(self.editor.text().trim());
                 self.properties.on_open.emit(path);
                 false
             }
             Message::SelectParentDirectory => {
-                let path_str: String = self.input.slice(..).into();
-                self.input = Path::new(&path_str.trim())
+                let parent = Path::new(self.editor.text().trim())
                     .parent()
-                    .map(|parent| parent.to_string_lossy())
-                    .unwrap_or_else(|| "".into())
-                    .into();
-                ensure_trailing_newline_with_content(&mut self.input);
-                self.cursor.move_to_end_of_line(&self.input);
-                self.cursor.insert_char(&mut self.input, '/');
-                self.cursor.move_right(&self.input);
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                self.set_input_path(&parent, true);
                 true
             }
+            Message::AutocompletePath if self.properties.source == FileSource::Directory => {
+                self.complete_path()
+            }
             Message::AutocompletePath => {
                 if let Some(path) = self.listing.selected(self.selected_index) {
-                    self.input = path.to_string_lossy().into();
-                    ensure_trailing_newline_with_content(&mut self.input);
-                    self.cursor.move_to_end_of_line(&self.input);
-                    if path.is_dir() {
-                        self.cursor.insert_char(&mut self.input, '/');
-                        self.cursor.move_right(&self.input);
-                    }
-                    self.selected_index = 0;
+                    let path = path.to_path_buf();
+                    let is_dir = path.is_dir();
+                    self.set_input_path(&path, is_dir);
                     true
                 } else {
                     false
                 }
             }
-            Message::ChangePath(InputChange { content, cursor }) => {
-                self.cursor = cursor;
-                if let Some(content) = content {
-                    self.input = content;
-                    true
-                } else {
-                    false
+            Message::ChangePath(editor) => {
+                let input_changed = editor.text() != self.editor.text();
+                self.editor = editor;
+                if input_changed {
+                    self.completions = None;
+
+                    // Expand a leading `~/` to the home directory
+                    if let (Some(rest), Some(home_dir)) =
+                        (self.editor.text().strip_prefix("~/"), dirs::home_dir())
+                    {
+                        // Joining an empty path keeps the trailing `/`
+                        let path = home_dir.join(rest);
+                        self.set_input_path(&path, false);
+                    }
                 }
+                input_changed
             }
             Message::ChangeSelectedFile(index) => {
                 self.selected_index = index;
@@ -193,13 +294,13 @@ impl Component for FilePicker {
             }
             Message::FileListingDone(Ok(FileListingDone { task_id, listing }))
                 if self
-                    .current_task_id
+                    .current_task
                     .as_ref()
-                    .map(|&expected_task_id| expected_task_id == task_id)
+                    .map(|task| task.id() == task_id)
                     .unwrap_or(false) =>
             {
                 self.listing = Rc::new(listing);
-                self.current_task_id = None;
+                self.current_task = None;
                 self.selected_index = 0;
 
                 false
@@ -221,15 +322,11 @@ impl Component for FilePicker {
     }
 
     fn view(&self) -> Layout {
-        let input = Input::with(InputProperties {
-            style: InputStyle {
-                content: self.properties.theme.input,
-                cursor: self.properties.theme.cursor,
-            },
-            content: self.input.clone(),
-            cursor: self.cursor.clone(),
-            on_change: Some(self.link.callback(Message::ChangePath)),
-            focused: true,
+        let input = LineInput::with(LineInputProperties {
+            style: self.properties.theme.input,
+            cursor_style: self.properties.theme.cursor,
+            editor: self.editor.clone(),
+            on_change: self.link.callback(Message::ChangePath),
         });
 
         let listing = self.listing.clone();
@@ -237,15 +334,14 @@ impl Component for FilePicker {
         let theme = self.properties.theme.clone();
         let item_at = move |index| {
             let path = listing.selected(index).unwrap();
-            let background = if index == selected_index {
-                theme.item_focused_background
-            } else {
-                theme.item_unfocused_background
-            };
+            let focused = index == selected_index;
             let style = if path.is_dir() {
-                Style::bold(background, theme.item_directory_foreground)
+                Style {
+                    bold: true,
+                    ..theme.item_style(focused, theme.item_directory_foreground)
+                }
             } else {
-                Style::normal(background, theme.item_file_foreground)
+                theme.item_style(focused, theme.item_file_foreground)
             };
             let content = &path.to_string_lossy()[listing
                 .prefix()
@@ -273,8 +369,8 @@ impl Component for FilePicker {
             })),
             Item::fixed(1)(Container::row([
                 Item::fixed(4)(Status::with(StatusProperties {
-                    action_name: self.properties.source.status_name(),
-                    pending: self.current_task_id.is_some(),
+                    action_name: self.properties.action_name.clone(),
+                    pending: self.current_task.is_some(),
                     style: self.properties.theme.action,
                 })),
                 Item::fixed(1)(Text::with(
@@ -394,6 +490,8 @@ impl FileListing {
 fn update_listing<FilesIterT>(
     listing: &mut FileListing,
     path_str: String,
+    token: &CancelToken,
+    progress: &Progress,
     files_iter: impl FnOnce(String) -> Result<FilesIterT>,
 ) -> Result<()>
 where
@@ -401,8 +499,19 @@ where
 {
     let prefix = Path::new(&path_str).parent().unwrap();
     if listing.prefix() != prefix {
+        // Walking a large repository can take a while, stop early if the
+        // listing was cancelled
         listing.reset(
-            files_iter(path_str.clone())?.take(MAX_FILES_IN_PICKER),
+            files_iter(path_str.clone())?
+                .take_while(|_| !token.is_cancelled())
+                .take(MAX_FILES_IN_PICKER)
+                .enumerate()
+                .map(|(index, path)| {
+                    if index > 0 && index % FILES_PER_PROGRESS_REPORT == 0 {
+                        progress.report(format!("listing files ({})", index));
+                    }
+                    path
+                }),
             &path_str,
             &prefix,
         );
@@ -412,14 +521,24 @@ where
     Ok(())
 }
 
-fn pick_from_directory(listing: &mut FileListing, path_str: String) -> Result<()> {
-    update_listing(listing, path_str, |path| {
+fn pick_from_directory(
+    listing: &mut FileListing,
+    path_str: String,
+    token: &CancelToken,
+    progress: &Progress,
+) -> Result<()> {
+    update_listing(listing, path_str, token, progress, |path| {
         Ok(directory_files_iter(path)?.filter_map(|result_path| result_path.ok()))
     })
 }
 
-fn pick_from_repository(listing: &mut FileListing, path_str: String) -> Result<()> {
-    update_listing(listing, path_str, |path| {
+fn pick_from_repository(
+    listing: &mut FileListing,
+    path_str: String,
+    token: &CancelToken,
+    progress: &Progress,
+) -> Result<()> {
+    update_listing(listing, path_str, token, progress, |path| {
         Ok(repository_files_iter(path).filter_map(|result_path| result_path.ok()))
     })
 }
@@ -456,4 +575,23 @@ fn repository_files_iter(path: impl AsRef<Path>) -> impl Iterator<Item = Result<
         })
 }
 
+/// Returns the longest prefix shared by all `names`
+fn longest_common_prefix<'a>(mut names: impl Iterator<Item = Cow<'a, str>>) -> String {
+    let mut prefix = match names.next() {
+        Some(name) => name.into_owned(),
+        None => return String::new(),
+    };
+    for name in names {
+        let common_length = prefix
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, lhs), rhs)| lhs != rhs)
+            .map(|((index, _), _)| index)
+            .unwrap_or_else(|| prefix.len().min(name.len()));
+        prefix.truncate(common_length);
+    }
+    prefix
+}
+
 const MAX_FILES_IN_PICKER: usize = 16384;
+const FILES_PER_PROGRESS_REPORT: usize = 1024;
@@ -0,0 +1,88 @@
+use std::borrow::Cow;
+
+use zi::{
+    components::text::{Text, TextProperties},
+    prelude::*,
+    Callback,
+};
+
+use super::Theme;
+
+/// What to do with a match of `query-replace`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplaceChoice {
+    Replace,
+    Skip,
+    ReplaceAll,
+    Quit,
+}
+
+pub struct Properties {
+    pub theme: Cow<'static, Theme>,
+    pub message: String,
+    pub on_choice: Callback<ReplaceChoice>,
+}
+
+/// Asks whether to replace the current match of `query-replace`
+pub struct QueryReplace {
+    properties: Properties,
+}
+
+impl Component for QueryReplace {
+    type Message = ReplaceChoice;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, _link: ComponentLink<Self>) -> Self {
+        Self { properties }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        let should_render = (self.properties.message != properties.message
+            || self.properties.theme != properties.theme)
+            .into();
+        self.properties = properties;
+        should_render
+    }
+
+    fn view(&self) -> Layout {
+        Text::with(
+            TextProperties::new()
+                .style(self.properties.theme.input)
+                .content(format!("{} (y/n/a/q)", self.properties.message)),
+        )
+    }
+
+    fn update(&mut self, choice: Self::Message) -> ShouldRender {
+        self.properties.on_choice.emit(choice);
+        ShouldRender::No
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        if !bindings.is_empty() {
+            return;
+        }
+
+        bindings.set_focus(true);
+
+        bindings
+            .command("replace-match", || ReplaceChoice::Replace)
+            .with([Key::Char('y')])
+            .with([Key::Char(' ')]);
+
+        bindings
+            .command("skip-match", || ReplaceChoice::Skip)
+            .with([Key::Char('n')])
+            .with([Key::Backspace]);
+
+        bindings
+            .command("replace-all-matches", || ReplaceChoice::ReplaceAll)
+            .with([Key::Char('a')])
+            .with([Key::Char('!')]);
+
+        bindings
+            .command("quit-replace", || ReplaceChoice::Quit)
+            .with([Key::Char('q')])
+            .with([Key::Char('\n')])
+            .with([Key::Esc]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+use zi::{
+    components::text::{Text, TextAlign, TextProperties},
+    prelude::*,
+    unicode_width::UnicodeWidthStr,
+    Callback, FlexBasis,
+};
+
+use zee_edit::line_editor::LineEditor;
+
+use super::{
+    line_input::{LineInput, Properties as LineInputProperties},
+    status::{Status, StatusProperties},
+    Theme,
+};
+use crate::editor::ContextHandle;
+
+#[derive(Debug)]
+pub enum Message {
+    ChangeQuery(LineEditor),
+    Confirm,
+    Cancel,
+}
+
+#[derive(Clone)]
+pub struct Properties {
+    pub context: ContextHandle,
+    pub theme: Cow<'static, Theme>,
+    pub message: Cow<'static, str>,
+    pub note: Option<Cow<'static, str>>,
+    pub on_change: Callback<String>,
+    pub on_confirm: Callback<()>,
+    pub on_cancel: Callback<()>,
+}
+
+pub struct Search {
+    properties: Properties,
+    link: ComponentLink<Self>,
+    editor: LineEditor,
+}
+
+impl Component for Search {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
+        let editor = properties.context.input_history.editor(HISTORY);
+        Self {
+            properties,
+            link,
+            editor,
+        }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        let should_render = (self.properties.theme != properties.theme
+            || self.properties.message != properties.message
+            || self.properties.note != properties.note)
+            .into();
+        self.properties = properties;
+        should_render
+    }
+
+    fn update(&mut self, message: Message) -> ShouldRender {
+        match message {
+            Message::ChangeQuery(editor) => {
+                if editor.text() != self.editor.text() {
+                    self.properties.on_change.emit(editor.text().to_owned());
+                }
+                self.editor = editor;
+                ShouldRender::Yes
+            }
+            Message::Confirm => {
+                self.properties
+                    .context
+                    .input_history
+                    .add(HISTORY, self.editor.text());
+                self.properties.on_confirm.emit(());
+                ShouldRender::No
+            }
+            Message::Cancel => {
+                self.properties.on_cancel.emit(());
+                ShouldRender::No
+            }
+        }
+    }
+
+    fn view(&self) -> Layout {
+        let input = LineInput::with(LineInputProperties {
+            style: self.properties.theme.input,
+            cursor_style: self.properties.theme.cursor,
+            editor: self.editor.clone(),
+            on_change: self.link.callback(Message::ChangeQuery),
+        });
+        let note = self
+            .properties
+            .note
+            .as_ref()
+            .map(|note| format!("{} ", note))
+            .unwrap_or_default();
+
+        Layout::row([
+            Status::item_with_key(
+                FlexBasis::Fixed(self.properties.message.width()),
+                "status",
+                StatusProperties {
+                    action_name: self.properties.message.clone(),
+                    pending: false,
+                    style: self.properties.theme.action,
+                },
+            ),
+            Text::item_with_key(
+                FlexBasis::Fixed(1),
+                "spacer",
+                TextProperties::new().style(self.properties.theme.input),
+            ),
+            Item::auto(input),
+            Text::item_with_key(
+                FlexBasis::Fixed(note.width()),
+                "note",
+                TextProperties::new()
+                    .content(note)
+                    .style(self.properties.theme.action.invert())
+                    .align(TextAlign::Right),
+            ),
+        ])
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        if !bindings.is_empty() {
+            return;
+        }
+
+        bindings.set_focus(true);
+        bindings.add("confirm-search", [Key::Char('\n')], || Message::Confirm);
+        bindings.add("cancel-search", [Key::Esc], || Message::Cancel);
+    }
+}
+
+// Searches in both directions share a history
+const HISTORY: &str = "search";
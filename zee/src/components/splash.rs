@@ -13,9 +13,19 @@ pub struct Theme {
     pub credits: Style,
 }
 
+/// A command listed on the splash screen along with its keybinding
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyHint {
+    pub keys: String,
+    pub description: &'static str,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Properties {
     pub theme: Cow<'static, Theme>,
+    pub hints: Vec<KeyHint>,
+    /// Recently opened files, the most recent first
+    pub recent_files: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -49,22 +59,55 @@ impl Component for Splash {
     #[inline]
     fn view(&self) -> Layout {
         let Self {
-            properties: Properties { ref theme },
+            properties:
+                Properties {
+                    ref theme,
+                    ref hints,
+                    ref recent_files,
+                },
             frame,
         } = *self;
         let logo_size = text_block_size(LOGO);
         let tagline_size = text_block_size(TAGLINE);
         let credits_size = text_block_size(&CREDITS);
+        let hints = hints_block(hints);
+        let hints_size = text_block_size(&hints);
+        let recent_files = recent_files_block(recent_files, frame.size.width);
+        let recent_files_size = text_block_size(&recent_files);
 
         let mut canvas = Canvas::new(frame.size);
         canvas.clear(theme.logo);
 
+        // The logo is left out when the terminal is too short to fit it
+        // along with the hints and recent files
+        let text_height = 2
+            + tagline_size.height
+            + 1
+            + credits_size.height
+            + 2
+            + hints_size.height
+            + 2
+            + recent_files_size.height;
+        let show_logo = frame.size.height >= logo_size.height + text_height;
+
         // Draw logo
-        let middle_x = (frame.size.width / 2).saturating_sub(logo_size.width / 2);
-        let mut middle_y = cmp::min(8, frame.size.height.saturating_sub(logo_size.height));
-        for line in LOGO.lines() {
-            canvas.draw_str(middle_x, middle_y, theme.logo, line);
-            middle_y += 1;
+        let mut middle_y = if show_logo {
+            cmp::min(
+                8,
+                frame
+                    .size
+                    .height
+                    .saturating_sub(logo_size.height + text_height),
+            )
+        } else {
+            frame.size.height.saturating_sub(text_height) / 2
+        };
+        if show_logo {
+            let middle_x = (frame.size.width / 2).saturating_sub(logo_size.width / 2);
+            for line in LOGO.lines() {
+                canvas.draw_str(middle_x, middle_y, theme.logo, line);
+                middle_y += 1;
+            }
         }
 
         // Draw tagline
@@ -83,10 +126,74 @@ impl Component for Splash {
             middle_y += 1;
         }
 
+        // Draw key hints
+        middle_y += 2;
+        let middle_x = (frame.size.width / 2).saturating_sub(hints_size.width / 2);
+        for line in hints.lines() {
+            canvas.draw_str(middle_x, middle_y, theme.tagline, line);
+            middle_y += 1;
+        }
+
+        // Draw recent files
+        middle_y += 2;
+        let middle_x = (frame.size.width / 2).saturating_sub(recent_files_size.width / 2);
+        for line in recent_files.lines() {
+            canvas.draw_str(middle_x, middle_y, theme.credits, line);
+            middle_y += 1;
+        }
+
         canvas.into()
     }
 }
 
+/// The key hints as two aligned columns, keys on the left
+fn hints_block(hints: &[KeyHint]) -> String {
+    let keys_width = hints
+        .iter()
+        .map(|hint| hint.keys.width())
+        .max()
+        .unwrap_or(0);
+    hints
+        .iter()
+        .map(|hint| {
+            format!(
+                "{}{}   {}",
+                hint.keys,
+                " ".repeat(keys_width - hint.keys.width()),
+                hint.description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The most recent files, with long paths truncated from the left to fit
+/// the width of the splash screen
+fn recent_files_block(recent_files: &[String], width: usize) -> String {
+    if recent_files.is_empty() {
+        return String::new();
+    }
+    let max_width = cmp::max(width.saturating_sub(4), 1);
+    let mut block = String::from("Recent files");
+    for path in recent_files.iter().take(MAX_RECENT_FILES) {
+        block.push('\n');
+        if path.width() <= max_width {
+            block.push_str(path);
+        } else {
+            let mut truncated = String::new();
+            for character in path.chars().rev() {
+                if truncated.width() + 2 > max_width {
+                    break;
+                }
+                truncated.insert(0, character);
+            }
+            block.push('…');
+            block.push_str(&truncated);
+        }
+    }
+    block
+}
+
 fn text_block_size(text: &str) -> Size {
     let width = text.lines().map(UnicodeWidthStr::width).max().unwrap_or(0);
     let height = text.lines().count();
@@ -107,6 +214,7 @@ zzzzzzzz::::::z   e::::::e     e:::::e e::::::e     e:::::e
 z:::::::::::::::z   ee:::::::::::::e     ee:::::::::::::e
 zzzzzzzzzzzzzzzzz     eeeeeeeeeeeeee       eeeeeeeeeeeeee
 "#;
+const MAX_RECENT_FILES: usize = 5;
 const TAGLINE: &str = "a modern editor for the terminal";
 
 static CREDITS: Lazy<String> = Lazy::new(|| {
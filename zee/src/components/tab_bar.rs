@@ -0,0 +1,116 @@
+use std::borrow::Cow;
+use zi::{
+    unicode_width::UnicodeWidthStr, Canvas, Component, ComponentLink, Layout, Rect, ShouldRender,
+    Style,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub base: Style,
+    pub tab_focused: Style,
+    pub tab_unfocused: Style,
+}
+
+/// A buffer listed in the tab bar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tab {
+    pub name: String,
+    pub modified: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Properties {
+    pub theme: Cow<'static, Theme>,
+    pub tabs: Vec<Tab>,
+    /// The index of the tab of the visible buffer
+    pub selected: usize,
+}
+
+/// Lists the open buffers when only the focused window is shown
+#[derive(Debug)]
+pub struct TabBar {
+    properties: Properties,
+    frame: Rect,
+}
+
+impl Component for TabBar {
+    type Message = ();
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, frame: Rect, _link: ComponentLink<Self>) -> Self {
+        Self { properties, frame }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        if self.properties != properties {
+            self.properties = properties;
+            ShouldRender::Yes
+        } else {
+            ShouldRender::No
+        }
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let Properties {
+            ref theme,
+            ref tabs,
+            selected,
+        } = self.properties;
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(theme.base);
+
+        let labels: Vec<_> = tabs.iter().map(tab_label).collect();
+
+        // Skip tabs on the left until the selected one fits
+        let mut first = 0;
+        while first < selected
+            && labels[first..=selected]
+                .iter()
+                .map(|label| label.width())
+                .sum::<usize>()
+                > self.frame.size.width
+        {
+            first += 1;
+        }
+
+        let mut x = 0;
+        for (index, label) in labels.iter().enumerate().skip(first) {
+            if x >= self.frame.size.width {
+                break;
+            }
+            let style = if index == selected {
+                theme.tab_focused
+            } else {
+                theme.tab_unfocused
+            };
+            x += canvas.draw_str(x, 0, style, label);
+        }
+        canvas.into()
+    }
+}
+
+/// The file name of a buffer, truncated to `MAX_TAB_NAME_WIDTH` columns and
+/// followed by `+` if the buffer has unsaved changes
+fn tab_label(tab: &Tab) -> String {
+    let name = if tab.name.width() > MAX_TAB_NAME_WIDTH {
+        let mut truncated = String::new();
+        for character in tab.name.chars() {
+            if truncated.width() + 1 >= MAX_TAB_NAME_WIDTH {
+                break;
+            }
+            truncated.push(character);
+        }
+        truncated.push('…');
+        truncated
+    } else {
+        tab.name.clone()
+    };
+    format!(" {}{} ", name, if tab.modified { "+" } else { " " })
+}
+
+const MAX_TAB_NAME_WIDTH: usize = 24;
@@ -4,15 +4,20 @@ pub use self::base16::Base16Theme;
 use zi::terminal::{Colour, Style};
 
 use super::{
-    buffer::{status_bar::Theme as StatusBarTheme, Theme as BufferTheme},
+    buffer::{
+        line_info::Theme as LineInfoTheme, status_bar::Theme as StatusBarTheme,
+        Theme as BufferTheme,
+    },
     edit_tree_viewer::Theme as EditTreeViewerTheme,
     prompt::Theme as PromptTheme,
     splash::Theme as SplashTheme,
+    tab_bar::Theme as TabBarTheme,
 };
 use crate::syntax::highlight::Theme as SyntaxTheme;
 
-pub const THEMES: [(Theme, &str); 31] = [
+pub const THEMES: [(Theme, &str); 32] = [
     (Theme::gruvbox(), "zee-gruvbox"),
+    (Theme::monochrome(), MONOCHROME_THEME),
     (
         Theme::from_base16(&base16::SOLARIZED_DARK),
         "base16-solarized-dark",
@@ -87,11 +92,16 @@ pub const THEMES: [(Theme, &str); 31] = [
     ),
 ];
 
+/// The name of the theme without colours, used when `monochrome` is enabled
+/// or the `NO_COLOR` environment variable is set
+pub const MONOCHROME_THEME: &str = "zee-monochrome";
+
 #[derive(Clone, Debug)]
 pub struct Theme {
     pub buffer: BufferTheme,
     pub splash: SplashTheme,
     pub prompt: PromptTheme,
+    pub tab_bar: TabBarTheme,
 }
 
 impl Theme {
@@ -125,6 +135,11 @@ impl Theme {
                     selection_background: DARK0_HARD,
                     text: normal(DARK0, LIGHT1),
                     text_current_line: normal(DARK0_HARD, LIGHT1),
+                    search_match: normal(NEUTRAL_YELLOW, DARK0_HARD),
+                    matching_bracket: bold(DARK2, BRIGHT_YELLOW),
+                    ruler_background: DARK0_SOFT,
+                    whitespace: normal(DARK0, DARK3),
+                    trailing_whitespace: normal(NEUTRAL_RED, LIGHT1),
                     code_char: normal(DARK0_SOFT, BRIGHT_GREEN),
                     code_comment: normal(DARK0_SOFT, DARK4),
                     code_comment_doc: normal(DARK0_SOFT, LIGHT4),
@@ -148,6 +163,7 @@ impl Theme {
                     alternate_connector: normal(DARK0, DARK4),
                 },
                 border: normal(DARK0_HARD, GRAY_245),
+                scrollbar_thumb: normal(DARK2, GRAY_245),
                 status_bar: StatusBarTheme {
                     base: normal(DARK0_SOFT, DARK0),
                     frame_id_focused: normal(BRIGHT_BLUE, DARK0_HARD),
@@ -159,6 +175,14 @@ impl Theme {
                     position_in_file: normal(DARK0_SOFT, GRAY_245),
                     mode: bold(DARK0_SOFT, BRIGHT_AQUA),
                 },
+                line_info: LineInfoTheme {
+                    error: normal(DARK0_HARD, BRIGHT_RED),
+                    warning: normal(DARK0_HARD, BRIGHT_YELLOW),
+                    info: normal(DARK0_HARD, BRIGHT_BLUE),
+                    added: normal(DARK0_HARD, BRIGHT_GREEN),
+                    modified: normal(DARK0_HARD, BRIGHT_YELLOW),
+                    deleted: normal(DARK0_HARD, BRIGHT_RED),
+                },
             },
             splash: SplashTheme {
                 logo: normal(DARK0_SOFT, LIGHT2),
@@ -167,6 +191,8 @@ impl Theme {
             },
             prompt: PromptTheme {
                 input: normal(DARK0_HARD, NEUTRAL_YELLOW),
+                log_warning: normal(DARK0_HARD, BRIGHT_ORANGE),
+                log_error: bold(DARK0_HARD, BRIGHT_RED),
                 action: normal(BRIGHT_BLUE, DARK0_HARD),
                 cursor: normal(LIGHT0, DARK0),
                 file_size: GRAY_245,
@@ -176,6 +202,99 @@ impl Theme {
                 item_file_foreground: LIGHT1,
                 item_directory_foreground: BRIGHT_RED,
             },
+            tab_bar: TabBarTheme {
+                base: normal(DARK0_HARD, GRAY_245),
+                tab_focused: bold(DARK0, LIGHT1),
+                tab_unfocused: normal(DARK0_HARD, GRAY_245),
+            },
+        }
+    }
+
+    /// A theme with only black and white, which sets text apart with bold,
+    /// underlined and reversed text instead of colours
+    pub const fn monochrome() -> Self {
+        use monochrome::*;
+        Self {
+            buffer: BufferTheme {
+                syntax: SyntaxTheme {
+                    cursor_focused: normal(WHITE, BLACK),
+                    cursor_unfocused: underline(BLACK, WHITE),
+                    // Text in the selection is reversed, see `text_style_at_char`
+                    selection_background: WHITE,
+                    text: normal(BLACK, WHITE),
+                    text_current_line: normal(BLACK, WHITE),
+                    search_match: normal(WHITE, BLACK),
+                    matching_bracket: bold_underline(BLACK, WHITE),
+                    ruler_background: BLACK,
+                    whitespace: normal(BLACK, WHITE),
+                    trailing_whitespace: normal(WHITE, BLACK),
+                    code_char: normal(BLACK, WHITE),
+                    code_comment: normal(BLACK, WHITE),
+                    code_comment_doc: normal(BLACK, WHITE),
+                    code_constant: normal(BLACK, WHITE),
+                    code_function_call: normal(BLACK, WHITE),
+                    code_invalid: bold_underline(BLACK, WHITE),
+                    code_keyword: bold(BLACK, WHITE),
+                    code_keyword_light: bold(BLACK, WHITE),
+                    code_link: underline(BLACK, WHITE),
+                    code_macro_call: bold(BLACK, WHITE),
+                    code_operator: normal(BLACK, WHITE),
+                    code_string: normal(BLACK, WHITE),
+                    code_type: underline(BLACK, WHITE),
+                    code_variant: underline(BLACK, WHITE),
+                },
+                edit_tree_viewer: EditTreeViewerTheme {
+                    current_revision: normal(WHITE, BLACK),
+                    master_revision: bold(BLACK, WHITE),
+                    master_connector: bold(BLACK, WHITE),
+                    alternate_revision: normal(BLACK, WHITE),
+                    alternate_connector: normal(BLACK, WHITE),
+                },
+                border: normal(BLACK, WHITE),
+                scrollbar_thumb: normal(WHITE, BLACK),
+                status_bar: StatusBarTheme {
+                    base: normal(WHITE, BLACK),
+                    frame_id_focused: bold(BLACK, WHITE),
+                    frame_id_unfocused: normal(BLACK, WHITE),
+                    is_modified: bold(WHITE, BLACK),
+                    is_not_modified: normal(WHITE, BLACK),
+                    file_name: bold(WHITE, BLACK),
+                    file_size: normal(WHITE, BLACK),
+                    position_in_file: normal(WHITE, BLACK),
+                    mode: normal(WHITE, BLACK),
+                },
+                line_info: LineInfoTheme {
+                    error: bold(BLACK, WHITE),
+                    warning: normal(BLACK, WHITE),
+                    info: normal(BLACK, WHITE),
+                    added: bold(BLACK, WHITE),
+                    modified: normal(BLACK, WHITE),
+                    deleted: bold(BLACK, WHITE),
+                },
+            },
+            splash: SplashTheme {
+                logo: bold(BLACK, WHITE),
+                tagline: normal(BLACK, WHITE),
+                credits: normal(BLACK, WHITE),
+            },
+            prompt: PromptTheme {
+                input: normal(BLACK, WHITE),
+                log_warning: normal(BLACK, WHITE),
+                log_error: bold(BLACK, WHITE),
+                action: normal(WHITE, BLACK),
+                cursor: normal(WHITE, BLACK),
+                file_size: WHITE,
+                mode: WHITE,
+                item_focused_background: WHITE,
+                item_unfocused_background: BLACK,
+                item_file_foreground: WHITE,
+                item_directory_foreground: WHITE,
+            },
+            tab_bar: TabBarTheme {
+                base: normal(BLACK, WHITE),
+                tab_focused: normal(WHITE, BLACK),
+                tab_unfocused: normal(BLACK, WHITE),
+            },
         }
     }
 
@@ -223,6 +342,11 @@ impl Theme {
                     selection_background,
                     text: normal(default_background, default_foreground),
                     text_current_line: normal(lighter_background, default_foreground),
+                    search_match: normal(classes, default_background),
+                    matching_bracket: bold(selection_background, support),
+                    ruler_background: lighter_background,
+                    whitespace: normal(default_background, selection_background),
+                    trailing_whitespace: normal(variables, default_background),
                     code_char: normal(default_background, support),
                     code_comment: normal(default_background, comments),
                     code_comment_doc: bold(default_background, comments),
@@ -246,6 +370,7 @@ impl Theme {
                     alternate_connector: normal(default_background, comments),
                 },
                 border: normal(lighter_background, dark_foreground),
+                scrollbar_thumb: normal(selection_background, dark_foreground),
                 status_bar: StatusBarTheme {
                     base: normal(lighter_background, default_background),
                     frame_id_focused: normal(functions, default_background),
@@ -257,6 +382,14 @@ impl Theme {
                     position_in_file: normal(lighter_background, dark_foreground),
                     mode: normal(lighter_background, strings),
                 },
+                line_info: LineInfoTheme {
+                    error: normal(lighter_background, variables),
+                    warning: normal(lighter_background, constants),
+                    info: normal(lighter_background, functions),
+                    added: normal(lighter_background, strings),
+                    modified: normal(lighter_background, classes),
+                    deleted: normal(lighter_background, variables),
+                },
             },
             splash: SplashTheme {
                 logo: normal(lighter_background, dark_foreground),
@@ -265,6 +398,8 @@ impl Theme {
             },
             prompt: PromptTheme {
                 input: normal(default_background, classes),
+                log_warning: normal(default_background, constants),
+                log_error: bold(default_background, variables),
                 action: normal(functions, default_background),
                 cursor: normal(light_foreground, default_background),
                 file_size: dark_foreground,
@@ -274,10 +409,22 @@ impl Theme {
                 item_file_foreground: default_foreground,
                 item_directory_foreground: keywords,
             },
+            tab_bar: TabBarTheme {
+                base: normal(lighter_background, dark_foreground),
+                tab_focused: bold(default_background, default_foreground),
+                tab_unfocused: normal(lighter_background, dark_foreground),
+            },
         }
     }
 }
 
+pub mod monochrome {
+    use zi::Colour;
+
+    pub const BLACK: Colour = Colour::rgb(0, 0, 0);
+    pub const WHITE: Colour = Colour::rgb(255, 255, 255);
+}
+
 #[allow(dead_code)]
 pub mod gruvbox {
     use zi::Colour;
@@ -337,7 +484,42 @@ const fn bold(background: Colour, foreground: Colour) -> Style {
     Style::bold(background, foreground)
 }
 
+#[inline]
+const fn bold_underline(background: Colour, foreground: Colour) -> Style {
+    Style {
+        background,
+        foreground,
+        bold: true,
+        underline: true,
+    }
+}
+
 #[inline]
 const fn underline(background: Colour, foreground: Colour) -> Style {
     Style::underline(background, foreground)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focused_items_stand_out() {
+        for (theme, name) in THEMES.iter() {
+            let prompt = &theme.prompt;
+            for foreground in [
+                prompt.item_file_foreground,
+                prompt.item_directory_foreground,
+                prompt.file_size,
+                prompt.mode,
+            ] {
+                assert_ne!(
+                    prompt.item_style(true, foreground),
+                    prompt.item_style(false, foreground),
+                    "focused items look unfocused in {}",
+                    name
+                );
+            }
+        }
+    }
+}
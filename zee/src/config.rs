@@ -1,22 +1,142 @@
 use include_dir::{include_dir, Dir};
 use once_cell::sync::Lazy;
 use serde_derive::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use zi::Colour;
 
 use zee_grammar::{config::ModeConfig, Mode};
 
-use crate::error::{Context, Result};
+use crate::{
+    components::theme::Base16Theme,
+    error::{Context, Result},
+    syntax::highlight::CursorShape,
+};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename = "Zee")]
 pub struct EditorConfig {
     #[serde(default)]
     pub theme: String,
+    /// Themes used to highlight the syntax of some modes, keyed by the name of
+    /// the mode, e.g. `{"Markdown": "base16-solarized-light"}`. Default: `{}`.
+    #[serde(default)]
+    pub mode_themes: HashMap<String, String>,
+    /// Draw without colours, setting text apart with bold, underlined and
+    /// reversed text instead. Also enabled by setting the `NO_COLOR`
+    /// environment variable. Default: `false`.
+    #[serde(default)]
+    pub monochrome: bool,
     pub modes: Vec<ModeConfig>,
     /// Remove whitespace from the end of lines when saving. On large files this could negatively
     /// impact performance. Default: `true`.
     #[serde(default)]
     pub trim_trailing_whitespace_on_save: bool,
+    /// Indent new lines like the previous one, and one level more after the
+    /// mode's `indent_after` characters. Default: `true`.
+    #[serde(default = "default_auto_indent")]
+    pub auto_indent: bool,
+    /// Insert the closing bracket or quote after typing an opening one, and
+    /// surround the selection with the pair instead of typing over it. Quotes
+    /// are only closed in modes which list them in `quotes`. Default: `false`.
+    #[serde(default)]
+    pub auto_pairs: bool,
+    /// Show line numbers in the gutter to the left of buffers. Default: `false`.
+    #[serde(default)]
+    pub line_numbers: bool,
+    /// Show line numbers relative to the line under the cursor. Default: `false`.
+    #[serde(default)]
+    pub relative_line_numbers: bool,
+    /// Highlight the background of the line under the cursor. Default: `true`.
+    #[serde(default = "default_highlight_current_line")]
+    pub highlight_current_line: bool,
+    /// The number of lines kept visible above and below the cursor when
+    /// scrolling. Default: `0`.
+    #[serde(default)]
+    pub scrolloff: usize,
+    /// Show a scrollbar to the right of buffers. Default: `false`.
+    #[serde(default)]
+    pub scrollbar: bool,
+    /// The number of rows taken by the status bar below each window, `0`
+    /// hides it. Default: `1`.
+    #[serde(default = "default_status_bar_height")]
+    pub status_bar_height: usize,
+    /// How the cursor is drawn, `Block` or `Underline`. With modal editing,
+    /// this is the cursor of normal and visual mode. Default: `Block`.
+    #[serde(default)]
+    pub cursor_shape: CursorShape,
+    /// How the cursor is drawn in insert mode with modal editing.
+    /// Default: `Underline`.
+    #[serde(default = "default_insert_cursor_shape")]
+    pub insert_cursor_shape: CursorShape,
+    /// Save the windows and the files they show when quitting, and restore
+    /// them when zee is started in the same directory without any files.
+    /// Default: `false`.
+    #[serde(default)]
+    pub restore_layout: bool,
+    /// Vim-like modal editing. Buffers start in normal mode, where typed
+    /// characters are commands, `i` switches to insert mode and `Esc` back.
+    /// Default: `false`.
+    #[serde(default)]
+    pub modal_editing: bool,
+    /// Apply the indentation, line ending, charset and whitespace settings of
+    /// the nearest `.editorconfig` file to opened files, taking precedence
+    /// over the mode and the options above. Default: `true`.
+    #[serde(default = "default_editorconfig")]
+    pub editorconfig: bool,
+    /// Start the language server configured for a mode when a file of the
+    /// mode is opened, to show its diagnostics and hover information.
+    /// Default: `false`.
+    #[serde(default)]
+    pub language_servers: bool,
+    /// Run the mode's `formatter` command on a buffer before saving it, and
+    /// don't save if it fails. Default: `false`.
+    #[serde(default)]
+    pub format_on_save: bool,
+    /// Files larger than this many bytes are opened read-only and read in
+    /// chunks as the cursor moves through them. Default: `67108864` (64 MiB).
+    #[serde(default = "default_large_file_threshold")]
+    pub large_file_threshold: u64,
+    /// Write modified buffers to recovery files in the config directory every
+    /// this many seconds, to restore unsaved changes after a crash. `0`
+    /// disables it. Default: `0`.
+    #[serde(default)]
+    pub autosave_interval: u64,
+    /// File name suffixes mapped to the suffixes of related files, which
+    /// `find-related-file` switches between, e.g. `{".h": [".c", ".cpp"]}`.
+    #[serde(default)]
+    pub related_files: HashMap<String, Vec<String>>,
+    /// Key sequences for editor commands, overriding the default ones, e.g.
+    /// `{"quit": ["C-x q"]}`.
+    #[serde(default)]
+    pub key_bindings: HashMap<String, Vec<String>>,
+}
+
+fn default_auto_indent() -> bool {
+    true
+}
+
+fn default_highlight_current_line() -> bool {
+    true
+}
+
+fn default_status_bar_height() -> usize {
+    1
+}
+
+fn default_insert_cursor_shape() -> CursorShape {
+    CursorShape::Underline
+}
+
+fn default_editorconfig() -> bool {
+    true
+}
+
+fn default_large_file_threshold() -> u64 {
+    64 * 1024 * 1024
 }
 
 impl Default for EditorConfig {
@@ -25,15 +145,135 @@ impl Default for EditorConfig {
     }
 }
 
+impl EditorConfig {
+    /// Whether to draw without colours, following the `NO_COLOR` convention
+    /// (https://no-color.org) of ignoring the variable when it's empty
+    pub fn is_monochrome(&self) -> bool {
+        self.monochrome || std::env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty())
+    }
+}
+
+/// A base16 theme defined by the user in a file inside the `themes`
+/// directory. Colours are specified as hex strings, e.g. `"#282828"`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename = "Base16")]
+pub struct Base16ThemeConfig {
+    pub base00: String,
+    pub base01: String,
+    pub base02: String,
+    pub base03: String,
+    pub base04: String,
+    pub base05: String,
+    pub base06: String,
+    pub base07: String,
+    pub base08: String,
+    pub base09: String,
+    pub base0a: String,
+    pub base0b: String,
+    pub base0c: String,
+    pub base0d: String,
+    pub base0e: String,
+    pub base0f: String,
+}
+
+impl Base16ThemeConfig {
+    pub fn to_theme(&self) -> Result<Base16Theme> {
+        Ok(Base16Theme {
+            base00: parse_hex_colour(&self.base00)?,
+            base01: parse_hex_colour(&self.base01)?,
+            base02: parse_hex_colour(&self.base02)?,
+            base03: parse_hex_colour(&self.base03)?,
+            base04: parse_hex_colour(&self.base04)?,
+            base05: parse_hex_colour(&self.base05)?,
+            base06: parse_hex_colour(&self.base06)?,
+            base07: parse_hex_colour(&self.base07)?,
+            base08: parse_hex_colour(&self.base08)?,
+            base09: parse_hex_colour(&self.base09)?,
+            base0a: parse_hex_colour(&self.base0a)?,
+            base0b: parse_hex_colour(&self.base0b)?,
+            base0c: parse_hex_colour(&self.base0c)?,
+            base0d: parse_hex_colour(&self.base0d)?,
+            base0e: parse_hex_colour(&self.base0e)?,
+            base0f: parse_hex_colour(&self.base0f)?,
+        })
+    }
+}
+
+/// Returns the configuration directory, either the one passed in explicitly or
+/// the system specific default.
+pub fn find_config_dir(config_dir: Option<PathBuf>) -> Option<PathBuf> {
+    config_dir.or_else(|| zee_grammar::config::config_dir().ok())
+}
+
 /// Finds the editor configuration. If we cannot for any reason, we'll use the
 /// default configuration to ensure the editor opens in any environment.
-pub fn find_editor_config(config_dir: Option<PathBuf>) -> EditorConfig {
+pub fn find_editor_config(config_dir: Option<&Path>) -> EditorConfig {
     config_dir
-        .or_else(|| zee_grammar::config::config_dir().ok())
         .map(|config_dir| config_dir.join("config.ron"))
         .map_or_else(Default::default, |path| read_config_file(&path))
 }
 
+/// Reads the user defined themes from the `themes` directory inside the
+/// configuration directory. Each `.ron` file contains one theme, named after
+/// the file. Themes which cannot be read are returned as errors, so they can
+/// be reported and skipped.
+pub fn find_user_themes(config_dir: Option<&Path>) -> Vec<Result<(String, Base16Theme)>> {
+    let themes_dir = match config_dir.map(|config_dir| config_dir.join("themes")) {
+        Some(themes_dir) if themes_dir.is_dir() => themes_dir,
+        _ => return Vec::new(),
+    };
+    let mut theme_paths: Vec<_> = match std::fs::read_dir(&themes_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |extension| extension == "ron")
+            })
+            .collect(),
+        Err(error) => {
+            return vec![Err(error).with_context(|| {
+                format!("Could not read themes directory `{}`", themes_dir.display())
+            })]
+        }
+    };
+    theme_paths.sort();
+
+    theme_paths
+        .into_iter()
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("Could not read theme file `{}`", path.display()))
+                .and_then(|contents| {
+                    ron::de::from_str::<Base16ThemeConfig>(&contents)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|theme| theme.to_theme())
+                        .with_context(|| format!("Could not parse theme file `{}`", path.display()))
+                })
+                .map(|theme| (name, theme))
+        })
+        .collect()
+}
+
+fn parse_hex_colour(hex: &str) -> Result<Colour> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let component = |index: usize| {
+        digits
+            .get(index..index + 2)
+            .and_then(|component| u8::from_str_radix(component, 16).ok())
+    };
+    match (digits.len(), component(0), component(2), component(4)) {
+        (6, Some(red), Some(green), Some(blue)) => Ok(Colour::rgb(red, green, blue)),
+        _ => Err(anyhow::anyhow!(
+            "Invalid colour `{}`, expected e.g. `#282828`",
+            hex
+        )),
+    }
+}
+
 fn read_config_file(path: &Path) -> EditorConfig {
     if path.exists() {
         std::fs::read_to_string(path)
@@ -1,6 +1,11 @@
+use anyhow::anyhow;
+use std::collections::HashMap;
 use zi::{terminal::Key, Bindings, EndsWith, FlexDirection};
 
-use super::{Editor, FileSource, Message};
+use zee_edit::Direction;
+
+use super::{Context, Editor, FileSource, Message};
+use crate::error::Result;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct KeySequenceSlice<'a> {
@@ -38,76 +43,497 @@ impl<'a> std::fmt::Display for KeySequenceSlice<'a> {
     }
 }
 
-pub(super) fn initialize(bindings: &mut Bindings<Editor>) {
+/// A command of the editor which can be bound to key sequences
+pub(super) struct Command {
+    pub name: &'static str,
+    pub default_keys: &'static [&'static [Key]],
+    pub message: fn() -> Message,
+}
+
+// Columns or lines added or removed when resizing a window
+const WINDOW_RESIZE_STEP: isize = 2;
+
+/// The commands of the editor which can be rebound in the configuration file.
+/// Commands handled by buffers and prompts are not included.
+pub(super) const COMMANDS: &[Command] = &[
+    // Open a file
+    Command {
+        name: "find-file",
+        default_keys: &[&[Key::Ctrl('x'), Key::Ctrl('f')]],
+        message: || Message::OpenFilePicker(FileSource::Directory),
+    },
+    Command {
+        name: "find-recent-file",
+        default_keys: &[&[Key::Ctrl('x'), Key::Ctrl('r')]],
+        message: || Message::RecentFilesPicker,
+    },
+    Command {
+        name: "find-file-in-repo",
+        default_keys: &[&[Key::Ctrl('x'), Key::Ctrl('v')]],
+        message: || Message::OpenFilePicker(FileSource::Repository),
+    },
+    // Open a file in a new window next to the focused one
+    Command {
+        name: "find-file-split-below",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('4'), Key::Char('2')]],
+        message: || Message::OpenFileSplitPicker(FlexDirection::Column),
+    },
+    Command {
+        name: "find-file-split-right",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('4'), Key::Char('3')]],
+        message: || Message::OpenFileSplitPicker(FlexDirection::Row),
+    },
+    // Open the file paired with the focused buffer's file, e.g. a C header
+    // and its source file, according to `related_files`
+    Command {
+        name: "find-related-file",
+        default_keys: &[&[Key::Ctrl('x'), Key::Ctrl('a')]],
+        message: || Message::FindRelatedFile(None),
+    },
+    Command {
+        name: "find-related-file-split-right",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('4'), Key::Ctrl('a')]],
+        message: || Message::FindRelatedFile(Some(FlexDirection::Row)),
+    },
+    // Open the file path or URL under the cursor, e.g. in a compiler's output
+    Command {
+        name: "find-file-at-point",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('g')]],
+        message: || Message::FindFileAtPoint,
+    },
+    // Save the focused buffer to a different file
+    Command {
+        name: "write-file",
+        default_keys: &[&[Key::Ctrl('x'), Key::Ctrl('w')]],
+        message: || Message::SaveFileAsPicker,
+    },
+    // Buffer management
+    Command {
+        name: "switch-buffer",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('b')]],
+        message: || Message::SelectBufferPicker,
+    },
+    Command {
+        name: "kill-buffer",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('k')]],
+        message: || Message::KillBufferPicker,
+    },
+    Command {
+        name: "reopen-closed-buffer",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('K')]],
+        message: || Message::ReopenClosedBuffer,
+    },
+    // Discard the unsaved changes of the focused buffer
+    Command {
+        name: "revert-buffer",
+        default_keys: &[],
+        message: || Message::RevertBuffer,
+    },
+    // Window management
+    //
+    // Change focus
+    Command {
+        name: "focus-next-window",
+        default_keys: &[
+            &[Key::Ctrl('x'), Key::Char('o')],
+            &[Key::Ctrl('x'), Key::Ctrl('o')],
+        ],
+        message: || Message::FocusNextWindow,
+    },
+    Command {
+        name: "focus-previous-window",
+        // Terminals send `C-i` as `TAB`, hence the additional `A-o`
+        default_keys: &[
+            &[Key::Ctrl('x'), Key::Char('i')],
+            &[Key::Ctrl('x'), Key::Ctrl('i')],
+            &[Key::Alt('o')],
+        ],
+        message: || Message::FocusPreviousWindow,
+    },
+    // Make current window fullscreen
+    Command {
+        name: "fullscreen-window",
+        default_keys: &[
+            &[Key::Ctrl('x'), Key::Char('1')],
+            &[Key::Ctrl('x'), Key::Ctrl('1')],
+        ],
+        message: || Message::FullscreenWindow,
+    },
+    // Show only the current window until pressed again
+    Command {
+        name: "toggle-zoom-window",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('z')]],
+        message: || Message::ToggleZoomWindow,
+    },
+    // Split window below (column)
+    Command {
+        name: "split-window-below",
+        default_keys: &[
+            &[Key::Ctrl('x'), Key::Char('2')],
+            &[Key::Ctrl('x'), Key::Ctrl('2')],
+        ],
+        message: || Message::SplitWindow(FlexDirection::Column),
+    },
+    // Split window right (row)
+    Command {
+        name: "split-window-right",
+        default_keys: &[
+            &[Key::Ctrl('x'), Key::Char('3')],
+            &[Key::Ctrl('x'), Key::Ctrl('3')],
+        ],
+        message: || Message::SplitWindow(FlexDirection::Row),
+    },
+    // Delete window
+    Command {
+        name: "delete-window",
+        default_keys: &[
+            &[Key::Ctrl('x'), Key::Char('0')],
+            &[Key::Ctrl('x'), Key::Ctrl('0')],
+        ],
+        message: || Message::DeleteWindow,
+    },
+    // Resize the focused window along the direction it was split in
+    Command {
+        name: "enlarge-window",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('}')]],
+        message: || Message::ResizeWindow(WINDOW_RESIZE_STEP),
+    },
+    Command {
+        name: "shrink-window",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('{')]],
+        message: || Message::ResizeWindow(-WINDOW_RESIZE_STEP),
+    },
+    Command {
+        name: "balance-windows",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('+')]],
+        message: || Message::BalanceWindows,
+    },
+    // Show only the focused window, with a tab bar listing the open buffers
+    Command {
+        name: "toggle-tabs",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('t'), Key::Char('t')]],
+        message: || Message::ToggleTabs,
+    },
+    Command {
+        name: "next-buffer",
+        default_keys: &[&[Key::Ctrl('x'), Key::Right]],
+        message: || Message::CycleBuffer(Direction::Forward),
+    },
+    Command {
+        name: "previous-buffer",
+        default_keys: &[&[Key::Ctrl('x'), Key::Left]],
+        message: || Message::CycleBuffer(Direction::Backward),
+    },
+    // Move the cursor to a line in the focused buffer
+    Command {
+        name: "goto-line",
+        default_keys: &[
+            &[Key::Alt('g'), Key::Char('g')],
+            &[Key::Alt('g'), Key::Alt('g')],
+        ],
+        message: || Message::GoToLinePicker,
+    },
+    // Decode the focused buffer's file again with a different encoding
+    Command {
+        name: "reopen-with-encoding",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('\n'), Key::Char('r')]],
+        message: || Message::ReopenWithEncodingPicker,
+    },
+    // Recent messages shown in the prompt
+    Command {
+        name: "show-messages",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('l')]],
+        message: || Message::ShowMessages,
+    },
+    Command {
+        name: "set-mode",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('\n'), Key::Char('m')]],
+        message: || Message::SetModePicker,
+    },
+    // Incremental search in the focused buffer
+    Command {
+        name: "search-forward",
+        default_keys: &[&[Key::Ctrl('s')]],
+        message: || Message::Search(Direction::Forward),
+    },
+    Command {
+        name: "search-backward",
+        default_keys: &[&[Key::Ctrl('r')]],
+        message: || Message::Search(Direction::Backward),
+    },
+    Command {
+        name: "clear-search-highlight",
+        default_keys: &[],
+        message: || Message::ClearSearchHighlight,
+    },
+    // Change the working directory, e.g. to the focused buffer's
+    Command {
+        name: "change-directory",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('d')]],
+        message: || Message::ChangeDirectoryPicker,
+    },
+    Command {
+        name: "change-directory-to-repository",
+        default_keys: &[],
+        message: || Message::ChangeDirectoryToRepository,
+    },
+    // Search the files under the working directory
+    Command {
+        name: "grep",
+        default_keys: &[&[Key::Alt('s'), Key::Char('g')]],
+        message: || Message::GrepPicker,
+    },
+    // Return to positions before large jumps, e.g. going to a line
+    Command {
+        name: "jump-back",
+        default_keys: &[&[Key::Alt(',')]],
+        message: || Message::Jump(Direction::Backward),
+    },
+    Command {
+        name: "jump-forward",
+        default_keys: &[&[Key::Alt('.')]],
+        message: || Message::Jump(Direction::Forward),
+    },
+    // Replace matches in the focused buffer, asking about each one
+    Command {
+        name: "query-replace",
+        default_keys: &[&[Key::Alt('%')]],
+        message: || Message::QueryReplacePicker,
+    },
+    // Replace the selection, or the whole buffer, with the output of a shell
+    // command it's piped through
+    Command {
+        name: "filter-through-command",
+        default_keys: &[&[Key::Alt('|')]],
+        message: || Message::FilterPicker,
+    },
+    // Show the language server's information about the symbol under the
+    // cursor
+    Command {
+        name: "show-hover",
+        default_keys: &[&[Key::Alt('k')]],
+        message: || Message::ShowHover,
+    },
+    // Theme
+    Command {
+        name: "change-theme",
+        default_keys: &[&[Key::Ctrl('x'), Key::Ctrl('t')]],
+        message: || Message::ChangeTheme(Direction::Forward),
+    },
+    Command {
+        name: "change-theme-backward",
+        default_keys: &[&[Key::Ctrl('x'), Key::Char('T')]],
+        message: || Message::ChangeTheme(Direction::Backward),
+    },
+    // Pick a theme by name, previewing the one under the cursor
+    Command {
+        name: "select-theme",
+        default_keys: &[&[Key::Ctrl('x'), Key::Alt('t')]],
+        message: || Message::ThemePicker,
+    },
+    // Fuzzy find and run any command
+    Command {
+        name: "execute-command",
+        default_keys: &[&[Key::Alt('x')]],
+        message: || Message::CommandPalette,
+    },
+    // Quit
+    Command {
+        name: "quit",
+        default_keys: &[&[Key::Ctrl('x'), Key::Ctrl('c')]],
+        message: || Message::Quit,
+    },
+];
+
+pub(super) fn initialize(
+    bindings: &mut Bindings<Editor>,
+    key_bindings: &HashMap<String, Vec<String>>,
+    context: &Context,
+) {
     bindings.set_focus(true);
     bindings.set_notify(true);
 
     // Cancel
     bindings.add("cancel", EndsWith(Key::Ctrl('g')), || Message::Cancel);
 
-    // Open a file
-    bindings.add("find-file", [Key::Ctrl('x'), Key::Ctrl('f')], || {
-        Message::OpenFilePicker(FileSource::Directory)
-    });
-    bindings.add(
-        "find-file-in-repo",
-        [Key::Ctrl('x'), Key::Ctrl('v')],
-        || Message::OpenFilePicker(FileSource::Repository),
-    );
+    // Check the configured key bindings, ignoring the invalid ones
+    let mut custom_keys: HashMap<&str, Vec<Vec<Key>>> = HashMap::new();
+    for (name, sequences) in key_bindings.iter() {
+        if !COMMANDS.iter().any(|command| command.name == name) {
+            context.log(format!("Cannot bind keys to unknown command `{}`", name));
+            continue;
+        }
+        let parsed = sequences
+            .iter()
+            .map(|sequence| parse_key_sequence(sequence))
+            .collect::<Result<Vec<_>>>();
+        match parsed {
+            Ok(parsed) => {
+                custom_keys.insert(name, parsed);
+            }
+            Err(error) => {
+                context.log(format!("Invalid key binding for `{}`: {}", name, error));
+            }
+        }
+    }
 
-    // Buffer management
-    bindings.add("switch-buffer", [Key::Ctrl('x'), Key::Char('b')], || {
-        Message::SelectBufferPicker
-    });
-    bindings.add("kill-buffer", [Key::Ctrl('x'), Key::Char('k')], || {
-        Message::KillBufferPicker
+    for command in COMMANDS {
+        let message = command.message;
+        match custom_keys.get(command.name) {
+            Some(sequences) => {
+                for keys in sequences {
+                    add_binding(bindings, command.name, keys, message);
+                }
+            }
+            None => {
+                for keys in command.default_keys {
+                    add_binding(bindings, command.name, keys, message);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the key sequences bound to a command, i.e. the ones in the
+/// configuration if they are valid, otherwise the default ones
+pub(super) fn command_keys(
+    command: &Command,
+    key_bindings: &HashMap<String, Vec<String>>,
+) -> Vec<Vec<Key>> {
+    let custom_keys = key_bindings.get(command.name).map(|sequences| {
+        sequences
+            .iter()
+            .map(|sequence| parse_key_sequence(sequence))
+            .collect::<Result<Vec<_>>>()
     });
+    match custom_keys {
+        Some(Ok(sequences)) => sequences,
+        _ => command
+            .default_keys
+            .iter()
+            .map(|keys| keys.to_vec())
+            .collect(),
+    }
+}
 
-    // Window management
-    //
-    // Change focus
-    bindings
-        .command("focus-next-window", || Message::FocusNextWindow)
-        .with([Key::Ctrl('x'), Key::Char('o')])
-        .with([Key::Ctrl('x'), Key::Ctrl('o')]);
-    bindings
-        .command("focus-previous-window", || Message::FocusPreviousWindow)
-        .with([Key::Ctrl('x'), Key::Char('i')])
-        .with([Key::Ctrl('x'), Key::Ctrl('i')]);
+fn add_binding(
+    bindings: &mut Bindings<Editor>,
+    name: &'static str,
+    keys: &[Key],
+    message: fn() -> Message,
+) {
+    match *keys {
+        [first] => bindings.add(name, [first], message),
+        [first, second] => bindings.add(name, [first, second], message),
+        [first, second, third] => bindings.add(name, [first, second, third], message),
+        _ => unreachable!("key sequences are validated when parsed"),
+    }
+}
 
-    // Make current window fullscreen
-    bindings
-        .command("fullscreen-window", || Message::FullscreenWindow)
-        .with([Key::Ctrl('x'), Key::Char('1')])
-        .with([Key::Ctrl('x'), Key::Ctrl('1')]);
+/// Parses a sequence of keys separated by spaces, e.g. `C-x C-s`, using the
+/// same notation as the one used when displaying keys in the prompt.
+fn parse_key_sequence(sequence: &str) -> Result<Vec<Key>> {
+    let keys = sequence
+        .split_whitespace()
+        .map(|key| parse_key(key).ok_or_else(|| anyhow!("unknown key `{}`", key)))
+        .collect::<Result<Vec<_>>>()?;
+    if keys.is_empty() || keys.len() > MAX_KEY_SEQUENCE_LENGTH {
+        return Err(anyhow!(
+            "`{}` should have between 1 and {} keys",
+            sequence,
+            MAX_KEY_SEQUENCE_LENGTH
+        ));
+    }
+    Ok(keys)
+}
 
-    // Split window below (column)
-    bindings
-        .command("split-window-below", || {
-            Message::SplitWindow(FlexDirection::Column)
-        })
-        .with([Key::Ctrl('x'), Key::Char('2')])
-        .with([Key::Ctrl('x'), Key::Ctrl('2')]);
+fn parse_key(key: &str) -> Option<Key> {
+    let single_char = |text: &str| {
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(character), None) => Some(character),
+            _ => None,
+        }
+    };
 
-    // Split window right (row)
-    bindings
-        .command("split-window-right", || {
-            Message::SplitWindow(FlexDirection::Row)
-        })
-        .with([Key::Ctrl('x'), Key::Char('3')])
-        .with([Key::Ctrl('x'), Key::Ctrl('3')]);
+    let parsed = match key {
+        "SPC" => Key::Char(' '),
+        "RET" => Key::Char('\n'),
+        "TAB" => Key::Char('\t'),
+        "ESC" => Key::Esc,
+        "Backspace" => Key::Backspace,
+        "Delete" => Key::Delete,
+        "Insert" => Key::Insert,
+        "Up" => Key::Up,
+        "Down" => Key::Down,
+        "Left" => Key::Left,
+        "Right" => Key::Right,
+        "Home" => Key::Home,
+        "End" => Key::End,
+        "PageUp" => Key::PageUp,
+        "PageDown" => Key::PageDown,
+        "BackTab" => Key::BackTab,
+        _ => {
+            if let Some(character) = key.strip_prefix("C-").and_then(single_char) {
+                Key::Ctrl(character)
+            } else if let Some(character) = key.strip_prefix("A-").and_then(single_char) {
+                Key::Alt(character)
+            } else if let Some(number) = key
+                .strip_prefix('F')
+                .and_then(|number| number.parse::<u8>().ok())
+            {
+                Key::F(number)
+            } else {
+                Key::Char(single_char(key)?)
+            }
+        }
+    };
+    Some(parsed)
+}
 
-    // Delete window
-    bindings
-        .command("delete-window", || Message::DeleteWindow)
-        .with([Key::Ctrl('x'), Key::Char('0')])
-        .with([Key::Ctrl('x'), Key::Ctrl('0')]);
+const MAX_KEY_SEQUENCE_LENGTH: usize = 3;
 
-    // Theme
-    bindings.add("change-theme", [Key::Ctrl('x'), Key::Ctrl('t')], || {
-        Message::ChangeTheme
-    });
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Quit
-    bindings.add("quit", [Key::Ctrl('x'), Key::Ctrl('c')], || Message::Quit);
+    #[test]
+    fn parse_modified_keys() {
+        assert_eq!(parse_key("C-x"), Some(Key::Ctrl('x')));
+        assert_eq!(parse_key("A-x"), Some(Key::Alt('x')));
+        assert_eq!(parse_key("A-%"), Some(Key::Alt('%')));
+        assert_eq!(parse_key("x"), Some(Key::Char('x')));
+        assert_eq!(parse_key("F"), Some(Key::Char('F')));
+        // Terminals don't report Control and Alt pressed together as such
+        assert_eq!(parse_key("C-A-x"), None);
+    }
+
+    #[test]
+    fn parse_named_keys() {
+        assert_eq!(parse_key("SPC"), Some(Key::Char(' ')));
+        assert_eq!(parse_key("RET"), Some(Key::Char('\n')));
+        assert_eq!(parse_key("TAB"), Some(Key::Char('\t')));
+        assert_eq!(parse_key("ESC"), Some(Key::Esc));
+        assert_eq!(parse_key("PageDown"), Some(Key::PageDown));
+        assert_eq!(parse_key("F5"), Some(Key::F(5)));
+    }
+
+    #[test]
+    fn reject_unknown_keys() {
+        assert_eq!(parse_key(""), None);
+        assert_eq!(parse_key("C-"), None);
+        assert_eq!(parse_key("C-xy"), None);
+        assert_eq!(parse_key("Enter"), None);
+        assert!(parse_key_sequence("").is_err());
+        assert!(parse_key_sequence("C-x C-x C-x C-x").is_err());
+        assert!(parse_key_sequence("C-x Foo").is_err());
+    }
+
+    #[test]
+    fn parse_sequences() {
+        assert_eq!(
+            parse_key_sequence("C-x  4 C-a").unwrap(),
+            vec![Key::Ctrl('x'), Key::Char('4'), Key::Ctrl('a')]
+        );
+    }
 }
@@ -1,29 +1,49 @@
 use git2::Repository;
+use parking_lot::Mutex;
 use ropey::Rope;
 use std::{
+    cmp,
     fmt::Display,
-    fs::File,
-    io::{self, BufWriter},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    ops::Range,
     path::{Path, PathBuf},
     rc::Rc,
+    sync::Arc,
+    time::Instant,
 };
-use zi::ComponentLink;
+use zi::{ComponentLink, ShouldRender};
 
 use zee_edit::{
-    graphemes::strip_trailing_whitespace, movement, tree::EditTree, Cursor, Direction, OpaqueDiff,
+    brackets::{self, AutoPair},
+    graphemes::{self, strip_trailing_whitespace},
+    indent,
+    line_ending::{self, LineEnding},
+    movement,
+    search::{self, Query, SearchMatch},
+    tree::EditTree,
+    CharIndex, Cursor, Direction, LineIndex, OpaqueDiff,
 };
-use zee_grammar::Mode;
+use zee_grammar::{config::IndentationConfig, Mode};
 
-use super::{ContextHandle, Editor};
+use super::{
+    git_diff::{self, GitDiff, LineChanges},
+    large_file::{self, LargeFile},
+    recovery, ContextHandle, Editor, Message,
+};
 use crate::{
-    config::PLAIN_TEXT_MODE,
+    editorconfig,
+    encoding::{self, FileEncoding},
     error::Result,
+    filter,
+    lsp::Diagnostic,
     syntax::parse::{ParseTree, ParserPool, ParserStatus},
+    utils::symlink_target,
     versioned::{Versioned, WeakHandle},
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct BufferId(usize);
+pub struct BufferId(pub(super) usize);
 
 impl Display for BufferId {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -47,12 +67,45 @@ pub struct BuffersMessage {
 }
 
 impl BuffersMessage {
-    fn new(buffer_id: BufferId, message: BufferMessage) -> Self {
+    pub fn new(buffer_id: BufferId, message: BufferMessage) -> Self {
         Self {
             buffer_id,
             inner: message,
         }
     }
+
+    pub fn buffer_id(&self) -> BufferId {
+        self.buffer_id
+    }
+
+    /// The cursor moved by the message if it's a large jump worth remembering
+    /// in the jump list, e.g. to the matching bracket
+    pub fn jumping_cursor(&self) -> Option<CursorId> {
+        match self.inner {
+            BufferMessage::CursorMessage {
+                cursor_id,
+                message:
+                    CursorMessage::StartOfBuffer
+                    | CursorMessage::EndOfBuffer
+                    | CursorMessage::GoToLine { .. }
+                    | CursorMessage::GoToChar(_)
+                    | CursorMessage::MatchingBracket
+                    | CursorMessage::GitChange(_)
+                    | CursorMessage::SearchMatch(_),
+            } => Some(cursor_id),
+            _ => None,
+        }
+    }
+
+    /// Whether the message asks to write the buffer to its file
+    pub fn is_save_start(&self) -> bool {
+        matches!(self.inner, BufferMessage::SaveBufferStart)
+    }
+
+    /// Whether the message reports that the buffer was written to its file
+    pub fn is_saved(&self) -> bool {
+        matches!(self.inner, BufferMessage::SaveBufferEnd(Ok(_)))
+    }
 }
 
 pub struct Buffers {
@@ -73,6 +126,7 @@ impl Buffers {
     pub fn add(
         &mut self,
         text: Rope,
+        encoding: FileEncoding,
         file_path: Option<PathBuf>,
         repo: Option<RepositoryRc>,
     ) -> BufferId {
@@ -83,6 +137,7 @@ impl Buffers {
             self.context.clone(),
             buffer_id,
             text,
+            encoding,
             file_path,
             repo,
         ));
@@ -93,7 +148,7 @@ impl Buffers {
         self.buffers
             .iter()
             .position(|buffer| buffer.id == id)
-            .map(|buffer_index| self.buffers.swap_remove(buffer_index))
+            .map(|buffer_index| self.buffers.remove(buffer_index))
     }
 
     pub fn get(&self, id: BufferId) -> Option<&Buffer> {
@@ -129,17 +184,16 @@ impl Buffers {
         self.buffers.is_empty()
     }
 
-    pub fn handle_message(&mut self, message: BuffersMessage) {
+    pub fn handle_message(&mut self, message: BuffersMessage) -> ShouldRender {
         match self.get_mut(message.buffer_id) {
-            Some(buffer) => {
-                buffer.handle_message(message.inner);
-            }
+            Some(buffer) => buffer.handle_message(message.inner),
             None => {
                 log::warn!(
                     "Received message for unknown buffer_id={} message={:?}",
                     message.buffer_id,
                     message
-                )
+                );
+                ShouldRender::No
             }
         }
     }
@@ -156,12 +210,47 @@ pub struct Buffer {
     context: ContextHandle,
     id: BufferId,
     mode: &'static Mode,
+    // The mode's indentation, unless overridden by an `.editorconfig`
+    indentation: IndentationConfig,
+    editorconfig: editorconfig::Settings,
     repo: Option<RepositoryRc>,
     content: Versioned<EditTree>,
     file_path: Option<PathBuf>,
+    // The file `file_path` points to if it's a symbolic link, which is
+    // written to when saving
+    real_path: Option<PathBuf>,
     modified_status: ModifiedStatus,
     cursors: Vec<Cursor>,
+    // Additional cursors of each window, edited together with its cursor
+    secondary_cursors: Vec<Vec<Cursor>>,
     parser: Option<ParserPool>,
+    last_edit: Option<(CursorId, EditKind)>,
+    line_ending: LineEnding,
+    encoding: FileEncoding,
+    trim_trailing_whitespace: bool,
+    insert_final_newline: bool,
+    auto_indent: bool,
+    auto_pairs: bool,
+    read_only: bool,
+    // Binary files are shown as a read-only hex dump, which can't be saved
+    // over the file
+    hex_view: bool,
+    // Files above the `large_file_threshold` are read in chunks, they're
+    // read-only and can't be saved
+    large_file: Option<LargeFile>,
+    // The latest diagnostics from the mode's language server
+    diagnostics: Rc<Vec<Diagnostic>>,
+    git_diff: GitDiff,
+    // The version written to the recovery file, `None` if there's no
+    // recovery file
+    autosaved_version: Option<usize>,
+    // The newest version written to or removed from the recovery file. It's
+    // shared with the background writes, so a write finishing after the file
+    // was saved or closed doesn't leave a stale recovery file behind.
+    recovery_version: Arc<Mutex<usize>>,
+    // The query of the last search, whose matches stay highlighted until
+    // they're cleared
+    search_highlight: Option<Query>,
 }
 
 impl Buffer {
@@ -169,18 +258,25 @@ impl Buffer {
         context: ContextHandle,
         id: BufferId,
         text: Rope,
+        encoding: FileEncoding,
         file_path: Option<PathBuf>,
         repo: Option<RepositoryRc>,
     ) -> Self {
-        let mode = file_path
-            .as_ref()
-            .map(|path| context.0.mode_by_filename(path))
-            .unwrap_or(&PLAIN_TEXT_MODE);
+        let mode = context.0.detect_mode(file_path.as_deref(), &text);
+        let real_path = file_path.as_deref().and_then(symlink_target);
+
+        let detected = line_ending::detect(&text);
+        if detected.mixed {
+            context.log_warning(format!(
+                "Mixed line endings, using {}",
+                detected.line_ending.name()
+            ));
+        }
 
-        let mut parser = mode
-            .language()
-            .and_then(|result| result.ok())
-            .map(ParserPool::new);
+        let trim_trailing_whitespace = context.config.trim_trailing_whitespace_on_save;
+        let auto_indent = context.config.auto_indent;
+        let auto_pairs = context.config.auto_pairs;
+        let mut parser = create_parser(&context, mode);
         if let Some(parser) = parser.as_mut() {
             let link = context.link.clone();
             parser.ensure_tree(
@@ -195,17 +291,38 @@ impl Buffer {
             );
         };
 
-        Self {
+        let mut buffer = Self {
             context,
             id,
             mode,
+            indentation: mode.indentation.clone(),
+            editorconfig: editorconfig::Settings::default(),
             repo,
             content: Versioned::new(EditTree::new(text)),
             file_path,
+            real_path,
             modified_status: ModifiedStatus::Unchanged,
             cursors: vec![Cursor::new()],
+            secondary_cursors: vec![Vec::new()],
             parser,
-        }
+            last_edit: None,
+            line_ending: detected.line_ending,
+            encoding,
+            trim_trailing_whitespace,
+            insert_final_newline: false,
+            auto_indent,
+            auto_pairs,
+            read_only: false,
+            hex_view: false,
+            large_file: None,
+            diagnostics: Rc::new(Vec::new()),
+            git_diff: GitDiff::default(),
+            autosaved_version: None,
+            recovery_version: Arc::new(Mutex::new(0)),
+            search_highlight: None,
+        };
+        buffer.reload_git_index();
+        buffer
     }
 
     #[inline]
@@ -218,6 +335,12 @@ impl Buffer {
         self.file_path.as_ref()
     }
 
+    /// The file the buffer's file path points to, if it's a symbolic link
+    #[inline]
+    pub fn real_path(&self) -> Option<&PathBuf> {
+        self.real_path.as_ref()
+    }
+
     #[inline]
     pub fn mode(&self) -> &'static Mode {
         self.mode
@@ -228,11 +351,70 @@ impl Buffer {
         self.repo.as_ref()
     }
 
+    #[inline]
+    pub fn indentation(&self) -> &IndentationConfig {
+        &self.indentation
+    }
+
+    #[inline]
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    #[inline]
+    pub fn encoding(&self) -> FileEncoding {
+        self.encoding
+    }
+
+    /// Replaces the content with the file's, e.g. after it changed on disk or
+    /// to decode it using a different encoding. The new content is marked as
+    /// saved, the previous content can still be restored with undo.
+    pub fn reload(&mut self, text: Rope, encoding: FileEncoding) {
+        self.sync_cursors(&text);
+        self.line_ending = line_ending::detect(&text).line_ending;
+        self.encoding = encoding;
+        *self.content.staged_mut() = text;
+        self.content
+            .create_revision(OpaqueDiff::empty(), self.cursors[0].clone());
+        self.content.mark_saved();
+        self.modified_status = ModifiedStatus::Unchanged;
+        self.last_edit = None;
+        self.update_parse_tree(&OpaqueDiff::empty(), true);
+        self.reload_git_index();
+    }
+
+    /// Replaces the content with unsaved text, e.g. restored from a recovery
+    /// file. Unlike `reload`, the text is a regular edit on top of the saved
+    /// content, which undo goes back to.
+    pub fn restore(&mut self, text: Rope) {
+        let diff = OpaqueDiff::new(
+            0,
+            self.content.len_bytes(),
+            text.len_bytes(),
+            0,
+            self.content.len_chars(),
+            text.len_chars(),
+        );
+        self.sync_cursors(&text);
+        *self.content.staged_mut() = text;
+        self.content
+            .create_revision(diff.clone(), self.cursors[0].clone());
+        self.update_parse_tree(&diff, false);
+        self.update_modified_status();
+        self.last_edit = None;
+    }
+
     #[inline]
     pub fn edit_tree(&self) -> &EditTree {
         &self.content
     }
 
+    /// The version of the content, which changes with every edit
+    #[inline]
+    pub fn version(&self) -> usize {
+        self.content.version()
+    }
+
     #[inline]
     pub fn edit_tree_handle(&self) -> WeakHandle<EditTree> {
         self.content.weak()
@@ -248,11 +430,182 @@ impl Buffer {
         self.modified_status
     }
 
+    #[inline]
+    pub fn is_modified(&self) -> bool {
+        self.modified_status != ModifiedStatus::Unchanged
+    }
+
+    /// Marks the content as not saved, e.g. when it was restored from memory
+    #[inline]
+    pub fn mark_modified(&mut self) {
+        self.modified_status = ModifiedStatus::Changed;
+    }
+
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Prevents or allows editing the buffer, e.g. when the file isn't
+    /// writable. Moving around, searching and copying are always allowed.
+    #[inline]
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Applies the settings of the `.editorconfig` files covering the buffer's
+    /// file. The line ending is only used if the file doesn't have any line
+    /// breaks yet, existing ones aren't converted.
+    pub fn apply_editorconfig(&mut self, editorconfig: editorconfig::Settings) {
+        self.indentation = editorconfig.indentation(&self.mode.indentation);
+        if let Some(line_ending) = editorconfig.end_of_line {
+            if self.content.staged().len_lines() <= 1 {
+                self.line_ending = line_ending;
+            }
+        }
+        if let Some(trim_trailing_whitespace) = editorconfig.trim_trailing_whitespace {
+            self.trim_trailing_whitespace = trim_trailing_whitespace;
+        }
+        if let Some(insert_final_newline) = editorconfig.insert_final_newline {
+            self.insert_final_newline = insert_final_newline;
+        }
+        self.editorconfig = editorconfig;
+    }
+
+    #[inline]
+    pub fn diagnostics(&self) -> &Rc<Vec<Diagnostic>> {
+        &self.diagnostics
+    }
+
+    /// Replaces the diagnostics, published by the language server for the
+    /// current content
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = Rc::new(diagnostics);
+    }
+
+    /// The lines changed compared to the git index, `None` if the file isn't
+    /// tracked
+    #[inline]
+    pub fn git_changes(&self) -> Option<&Rc<LineChanges>> {
+        self.git_diff
+            .original
+            .as_ref()
+            .map(|_| &self.git_diff.changes)
+    }
+
+    #[inline]
+    pub fn is_git_diff_outdated(&self) -> bool {
+        self.git_diff.is_outdated(self.version())
+    }
+
+    /// Recomputes the lines changed compared to the git index once the buffer
+    /// hasn't been edited for a moment
+    pub fn update_git_diff(&mut self) {
+        if !self.is_git_diff_outdated() {
+            return;
+        }
+        let outdated_since = *self
+            .git_diff
+            .outdated_since
+            .get_or_insert_with(Instant::now);
+        if outdated_since.elapsed() >= git_diff::DIFF_DELAY {
+            self.spawn_git_diff();
+        }
+    }
+
+    /// Whether the recovery file is out of date: the buffer was edited since
+    /// it was written, or the buffer isn't modified anymore and it's stale
+    pub fn needs_autosave(&self) -> bool {
+        self.file_path.is_some()
+            && self.autosaved_version != self.is_modified().then(|| self.version())
+    }
+
+    /// Writes the content of a modified buffer to its recovery file in the
+    /// background, or removes the recovery file of an unmodified one
+    pub fn autosave(&mut self) {
+        let recovery_path = match self.recovery_file_path() {
+            Some(recovery_path) => recovery_path,
+            None => return,
+        };
+        if !self.is_modified() {
+            self.remove_recovery_file();
+            return;
+        }
+        let version = self.version();
+        self.autosaved_version = Some(version);
+        let text = self.content.staged().clone();
+        let recovery_version = self.recovery_version.clone();
+        self.context.task_pool.spawn(move |_| {
+            // Skip writes overtaken by a newer one or by removing the file
+            let mut recovery_version = recovery_version.lock();
+            if version <= *recovery_version {
+                return;
+            }
+            *recovery_version = version;
+            if let Err(error) = recovery::write(&recovery_path, &text) {
+                log::warn!(
+                    "Could not write recovery file `{}`: {}",
+                    recovery_path.display(),
+                    error
+                );
+            }
+        });
+    }
+
+    /// Removes the recovery file, e.g. once the buffer is saved or closed
+    pub fn remove_recovery_file(&mut self) {
+        let mut recovery_version = self.recovery_version.lock();
+        *recovery_version = cmp::max(*recovery_version, self.version());
+        if let Some(recovery_path) = self.recovery_file_path() {
+            recovery::remove(&recovery_path);
+        }
+        drop(recovery_version);
+        self.autosaved_version = None;
+    }
+
+    fn recovery_file_path(&self) -> Option<PathBuf> {
+        Some(recovery::file_path(
+            self.context.config_dir.as_deref()?,
+            self.file_path.as_deref()?,
+        ))
+    }
+
+    #[inline]
+    pub fn is_hex_view(&self) -> bool {
+        self.hex_view
+    }
+
+    /// Marks the buffer as the hex dump of a binary file, which is read-only
+    pub fn set_hex_view(&mut self) {
+        self.hex_view = true;
+        self.read_only = true;
+    }
+
+    #[inline]
+    pub fn is_large_file(&self) -> bool {
+        self.large_file.is_some()
+    }
+
+    /// Marks the buffer as the first chunk of a large file, which is
+    /// read-only and isn't highlighted
+    pub fn set_large_file(&mut self, large_file: LargeFile) {
+        self.large_file = Some(large_file);
+        self.read_only = true;
+        self.parser = None;
+    }
+
+    /// The additional cursors of a window with multiple cursors
+    #[inline]
+    pub fn secondary_cursors(&self, cursor_id: CursorId) -> &[Cursor] {
+        &self.secondary_cursors[cursor_id.0]
+    }
+
     #[inline]
     pub fn new_cursor(&mut self) -> CursorId {
         let new_cursor_id = CursorId(self.cursors.len());
         self.cursors
             .push(self.cursors.get(0).cloned().unwrap_or_else(Cursor::new));
+        self.secondary_cursors.push(Vec::new());
         new_cursor_id
     }
 
@@ -260,6 +613,7 @@ impl Buffer {
     pub fn duplicate_cursor(&mut self, cursor_id: CursorId) -> CursorId {
         let new_cursor_id = CursorId(self.cursors.len());
         self.cursors.push(self.cursors[cursor_id.0].clone());
+        self.secondary_cursors.push(Vec::new());
         new_cursor_id
     }
 
@@ -268,28 +622,187 @@ impl Buffer {
         self.parser.as_ref().and_then(|parser| parser.tree.as_ref())
     }
 
+    /// Moves the cursor to the start of the closest match of `query`, searching
+    /// from `position` in the given direction. The cursor is left unchanged if
+    /// nothing matches.
+    pub fn search(
+        &mut self,
+        cursor_id: CursorId,
+        query: &Query,
+        position: CharIndex,
+        direction: Direction,
+    ) -> Option<SearchMatch> {
+        let found = query.find(&self.content, position, direction)?;
+        movement::move_to_char(
+            &self.content,
+            &mut self.cursors[cursor_id.0],
+            found.range.start,
+        );
+        self.last_edit = None;
+        Some(found)
+    }
+
+    /// The query whose matches stay highlighted after a search
+    #[inline]
+    pub fn search_highlight(&self) -> Option<&Query> {
+        self.search_highlight.as_ref()
+    }
+
+    /// Highlights the matches of a query until they're cleared with `None`.
+    /// `CursorMessage::SearchMatch` moves between them.
+    pub fn set_search_highlight(&mut self, query: Option<Query>) {
+        self.search_highlight = query.filter(|query| !query.is_empty());
+    }
+
+    /// Replaces the text in `range`, e.g. a search match, as a new revision.
+    /// The cursor is moved after the replacement, whose end is returned.
+    pub fn replace_match(
+        &mut self,
+        cursor_id: CursorId,
+        range: Range<CharIndex>,
+        replacement: &str,
+    ) -> CharIndex {
+        let diff = search::replace(&mut self.content, range, replacement);
+        let end = diff.char_index + diff.new_char_length;
+        self.record_edit(cursor_id, end, diff);
+        end
+    }
+
+    /// Pipes the selection, or the whole buffer if nothing is selected,
+    /// through a shell command in the background. The text is replaced with
+    /// the command's output if it succeeds and the buffer wasn't edited in the
+    /// meantime.
+    pub fn filter_through_command(&mut self, cursor_id: CursorId, command: String) {
+        if self.read_only {
+            self.context.log("Buffer is read-only");
+            return;
+        }
+        let cursor = &self.cursors[cursor_id.0];
+        let range = if cursor.has_selection() {
+            cursor.selection()
+        } else {
+            0..self.content.len_chars()
+        };
+        let input = self.content.slice(range.clone()).to_string();
+        let version = self.version();
+
+        let buffer_id = self.id;
+        let link = self.context.link.clone();
+        let current_dir = self.context.current_working_dir();
+        self.context.task_pool.spawn(move |_| {
+            let result = filter::run(&command, input, &current_dir);
+            let buffer_message = BufferMessage::FilterEnd {
+                cursor_id,
+                range,
+                version,
+                command,
+                result,
+            };
+            link.send(BuffersMessage::new(buffer_id, buffer_message).into())
+        });
+    }
+
+    /// Replaces all matches of `query` starting at or after `position`. All
+    /// replacements are undone as a single revision. Returns the number of
+    /// replaced matches.
+    pub fn replace_all(
+        &mut self,
+        cursor_id: CursorId,
+        query: &Query,
+        replacement: &str,
+        position: CharIndex,
+    ) -> usize {
+        let (count, diff) = search::replace_all(&mut self.content, query, replacement, position);
+        if count > 0 {
+            let end = diff.char_index + diff.new_char_length;
+            self.record_edit(cursor_id, end, diff);
+        }
+        count
+    }
+
+    /// Replaces a cursor, e.g. to return to where a search started
+    pub fn set_cursor(&mut self, cursor_id: CursorId, cursor: Cursor) {
+        self.cursors[cursor_id.0] = cursor;
+        self.last_edit = None;
+    }
+
+    /// Changes the mode of the buffer, reparsing it with the mode's grammar
+    pub fn set_mode(&mut self, mode: &'static Mode) {
+        if !std::ptr::eq(mode, self.mode) {
+            self.mode = mode;
+            self.indentation = self.editorconfig.indentation(&mode.indentation);
+            self.parser = create_parser(&self.context, mode);
+            self.update_parse_tree(&OpaqueDiff::empty(), true);
+        }
+    }
+
+    /// Changes the file the buffer is visiting and writes its content there.
+    /// The mode is redetected from the new file name.
+    pub fn save_as(&mut self, file_path: PathBuf) {
+        if self.large_file.is_some() {
+            self.context
+                .log_warning("Cannot save a large file, only part of it is loaded");
+            return;
+        }
+        let mode = self.context.0.detect_mode(Some(&file_path), &self.content);
+        self.set_mode(mode);
+        self.repo = Repository::discover(&file_path).ok().map(RepositoryRc::new);
+        self.real_path = symlink_target(&file_path);
+        // The changes are about to be saved to the new file
+        self.remove_recovery_file();
+        self.file_path = Some(file_path);
+        // Once written to a different file, the dump is just text
+        self.hex_view = false;
+        self.spawn_save_file();
+    }
+
+    /// Handles a message for the buffer. Returns whether the visible state of
+    /// the buffer changed, e.g. a stale syntax tree doesn't require redrawing.
     #[inline]
-    pub fn handle_message(&mut self, message: BufferMessage) {
+    pub fn handle_message(&mut self, message: BufferMessage) -> ShouldRender {
+        if self.read_only && message.is_edit() {
+            self.context.log("Buffer is read-only");
+            return ShouldRender::No;
+        }
+
         match message {
             // Start writing the buffer to disk asynchronously
+            BufferMessage::SaveBufferStart if self.hex_view => {
+                self.context
+                    .log_warning("Cannot save the hex dump over the binary file");
+            }
+            BufferMessage::SaveBufferStart if self.large_file.is_some() => {
+                self.context
+                    .log_warning("Cannot save a large file, only part of it is loaded");
+            }
             BufferMessage::SaveBufferStart => {
                 self.spawn_save_file();
             }
             // Saved the buffer successfully
             BufferMessage::SaveBufferEnd(Ok(new_content)) => {
                 self.modified_status = ModifiedStatus::Unchanged;
+                if let Some(file_path) = self.file_path.as_ref() {
+                    self.context.log(format!(
+                        "Wrote {} ({} bytes)",
+                        file_path.display(),
+                        new_content.len_bytes()
+                    ));
+                }
 
                 // For now, we just assume the content may have changed
                 //
                 // Sync the cursors
-                for cursor in self.cursors.iter_mut() {
-                    cursor.sync(&self.content, &new_content);
-                }
+                self.sync_cursors(&new_content);
 
-                // Create a new revision, update the content.
+                // Update the content, create a new revision and remember it
+                // matches the file on disk
+                *self.content.staged_mut() = new_content;
                 self.content
                     .create_revision(OpaqueDiff::empty(), self.cursors[0].clone());
-                *self.content.staged_mut() = new_content;
+                self.content.mark_saved();
+                self.last_edit = None;
+                self.reload_git_index();
+                self.remove_recovery_file();
 
                 // We don't know the diff, so we just use OpaqueDiff::Empty.
                 // This is ok as we pass in fresh=true, so the previous parser
@@ -298,25 +811,431 @@ impl Buffer {
             }
             // Failed to save the buffer
             BufferMessage::SaveBufferEnd(Err(error)) => {
-                self.context.log(error.to_string());
+                self.modified_status = ModifiedStatus::Changed;
+                self.context
+                    .log_error(format!("Could not save buffer: {}", error));
             }
             // The syntax parser finished parsing the code (tree-sitter)
             BufferMessage::ParseSyntax { version, status } => {
                 let parsed = status.unwrap();
-                if let Some(parser) = self.parser.as_mut() {
-                    parser.handle_parse_syntax_done(version, parsed);
+                return self
+                    .parser
+                    .as_mut()
+                    .map(|parser| parser.handle_parse_syntax_done(version, parsed))
+                    .unwrap_or(false)
+                    .into();
+            }
+            // A shell command filtering the text finished
+            BufferMessage::FilterEnd {
+                command,
+                result: Err(error),
+                ..
+            } => {
+                self.context
+                    .log_error(format!("`{}` failed: {}", command, error));
+            }
+            BufferMessage::FilterEnd {
+                version, command, ..
+            } if version != self.version() => {
+                self.context.log_warning(format!(
+                    "The buffer changed while `{}` was running, its output was discarded",
+                    command
+                ));
+            }
+            BufferMessage::FilterEnd {
+                cursor_id,
+                range,
+                result: Ok(output),
+                ..
+            } => {
+                self.cursors[cursor_id.0].clear_selection();
+                self.replace_match(cursor_id, range, &output);
+            }
+            // The lines changed compared to the git index were computed
+            BufferMessage::GitDiff { version, changes } => {
+                if self.git_diff.version != Some(version) {
+                    return ShouldRender::No;
+                }
+                self.git_diff.changes = Rc::new(changes);
+            }
+            // The file was read from the git index. The changes are computed
+            // again if the buffer was edited in the meantime.
+            BufferMessage::GitIndex {
+                version,
+                original,
+                changes,
+            } => {
+                self.git_diff.original = original;
+                if self.git_diff.version != Some(version) {
+                    return ShouldRender::No;
+                }
+                self.git_diff.changes = Rc::new(changes);
+            }
+            // The next chunk of a large file was read
+            BufferMessage::LargeFileChunk(Ok(chunk)) => self.append_large_file_chunk(chunk),
+            BufferMessage::LargeFileChunk(Err(error)) => {
+                if let Some(large_file) = self.large_file.as_mut() {
+                    large_file.loading = false;
+                    self.context.log_error(format!(
+                        "Could not read {}: {}",
+                        large_file.file_path.display(),
+                        error
+                    ));
                 }
             }
             BufferMessage::CursorMessage { cursor_id, message } => {
-                self.handle_cursor_message(cursor_id, message)
+                let should_render = self.handle_cursor_message(cursor_id, message);
+                self.load_large_file_chunk(cursor_id);
+                return should_render;
+            }
+            BufferMessage::SetLineEnding(line_ending) => self.set_line_ending(line_ending),
+            BufferMessage::ToggleAutoIndent => {
+                self.auto_indent = !self.auto_indent;
+                self.context.log(if self.auto_indent {
+                    "Auto indent enabled for this buffer"
+                } else {
+                    "Auto indent disabled for this buffer"
+                });
+            }
+            BufferMessage::ToggleAutoPairs => {
+                self.auto_pairs = !self.auto_pairs;
+                self.context.log(if self.auto_pairs {
+                    "Brackets and quotes are closed automatically in this buffer"
+                } else {
+                    "Brackets and quotes are not closed automatically in this buffer"
+                });
+            }
+            BufferMessage::ToggleTrimTrailingWhitespace => {
+                self.trim_trailing_whitespace = !self.trim_trailing_whitespace;
+                self.context.log(if self.trim_trailing_whitespace {
+                    "Trailing whitespace is removed when saving this buffer"
+                } else {
+                    "Trailing whitespace is kept when saving this buffer"
+                });
+            }
+            BufferMessage::ToggleReadOnly if self.hex_view => {
+                self.context
+                    .log("The hex dump of a binary file is read-only");
+            }
+            BufferMessage::ToggleReadOnly if self.large_file.is_some() => {
+                self.context.log("Large files are read-only");
+            }
+            BufferMessage::ToggleReadOnly => {
+                self.read_only = !self.read_only;
+                self.context.log(if self.read_only {
+                    "Buffer is read-only"
+                } else {
+                    "Buffer is writable"
+                });
             }
             BufferMessage::PreviousChildRevision => self.content.previous_child(),
             BufferMessage::NextChildRevision => self.content.next_child(),
         };
+        ShouldRender::Yes
     }
 
+    /// Returns `ShouldRender::No` if the message didn't change the content or
+    /// the cursor, e.g. moving left at the beginning of the buffer.
     #[inline]
-    fn handle_cursor_message(&mut self, cursor_id: CursorId, message: CursorMessage) {
+    fn handle_cursor_message(
+        &mut self,
+        cursor_id: CursorId,
+        message: CursorMessage,
+    ) -> ShouldRender {
+        match message {
+            CursorMessage::AddCursorAtNextMatch => return self.add_cursor_at_next_match(cursor_id),
+            CursorMessage::AddCursorAbove => {
+                return self.add_cursor_vertically(cursor_id, Direction::Backward)
+            }
+            CursorMessage::AddCursorBelow => {
+                return self.add_cursor_vertically(cursor_id, Direction::Forward)
+            }
+            CursorMessage::SelectRectangle => return self.select_rectangle(cursor_id),
+            CursorMessage::CollapseCursors => {
+                let secondary_cursors = &mut self.secondary_cursors[cursor_id.0];
+                let collapsed = !secondary_cursors.is_empty();
+                secondary_cursors.clear();
+                // Like `Esc` in Vim after a search, the matches aren't
+                // highlighted anymore
+                let cleared = self.search_highlight.take().is_some();
+                return (collapsed || cleared).into();
+            }
+            _ => {}
+        }
+        if !self.secondary_cursors[cursor_id.0].is_empty() {
+            if message.applies_to_each_cursor() {
+                return self.handle_multiple_cursors_message(cursor_id, message);
+            } else if matches!(message, CursorMessage::Undo | CursorMessage::Redo) {
+                // Revisions only remember a single cursor
+                self.secondary_cursors[cursor_id.0].clear();
+            }
+        }
+
+        let initial_cursor = self.cursors[cursor_id.0].clone();
+        let edit_kind = match message {
+            CursorMessage::InsertChar { character, .. } if character.is_whitespace() => {
+                Some(EditKind::InsertWhitespace)
+            }
+            CursorMessage::InsertChar { .. } => Some(EditKind::InsertWord),
+            CursorMessage::DeleteForward => Some(EditKind::DeleteForward),
+            CursorMessage::DeleteBackward => Some(EditKind::DeleteBackward),
+            _ => None,
+        };
+        let undoing = matches!(message, CursorMessage::Undo | CursorMessage::Redo);
+        let diff = self.apply_cursor_message(cursor_id, message);
+
+        let last_edit = std::mem::replace(
+            &mut self.last_edit,
+            edit_kind.map(|edit_kind| (cursor_id, edit_kind)),
+        );
+        if !diff.is_empty() {
+            self.reconcile_other_cursors(cursor_id, &diff);
+            if !undoing {
+                // Consecutive edits of the same kind, e.g. typing a word, are
+                // merged into a single revision so they are undone together
+                let cursor = self.cursors[cursor_id.0].clone();
+                let amended = edit_kind.is_some()
+                    && last_edit == self.last_edit
+                    && self.content.amend_revision(diff.clone(), cursor.clone());
+                if !amended {
+                    self.content.create_revision(diff.clone(), cursor);
+                }
+                self.update_parse_tree(&diff, false);
+            }
+            self.update_modified_status();
+            ShouldRender::Yes
+        } else {
+            (self.cursors[cursor_id.0] != initial_cursor).into()
+        }
+    }
+
+    /// Applies a message to each of a window's cursors, e.g. to type the same
+    /// text at several places. The edits are undone as a single revision.
+    fn handle_multiple_cursors_message(
+        &mut self,
+        cursor_id: CursorId,
+        message: CursorMessage,
+    ) -> ShouldRender {
+        let mut secondary_cursors = std::mem::take(&mut self.secondary_cursors[cursor_id.0]);
+        let initial_cursors = (self.cursors[cursor_id.0].clone(), secondary_cursors.clone());
+
+        // Typing or deleting replaces the selections, e.g. of a rectangle,
+        // unless typing an opening bracket wraps them in the pair
+        let replaces_selections = match message {
+            CursorMessage::DeleteForward | CursorMessage::DeleteBackward => true,
+            CursorMessage::InsertChar {
+                character,
+                move_forward,
+            } => {
+                !(move_forward
+                    && self.auto_pairs
+                    && brackets::closing_pair(character, &self.mode.quotes).is_some())
+            }
+            _ => false,
+        } && std::iter::once(&self.cursors[cursor_id.0])
+            .chain(secondary_cursors.iter())
+            .any(Cursor::has_selection);
+        let mut combined_diff = OpaqueDiff::empty();
+        if replaces_selections {
+            combined_diff = self.apply_to_each_cursor(
+                cursor_id,
+                &mut secondary_cursors,
+                CursorMessage::DeleteSelection,
+            );
+        }
+        if !(replaces_selections
+            && matches!(
+                message,
+                CursorMessage::DeleteForward | CursorMessage::DeleteBackward
+            ))
+        {
+            let diff = self.apply_to_each_cursor(cursor_id, &mut secondary_cursors, message);
+            combined_diff = combined_diff.compose(&diff);
+        }
+
+        // Cursors which ended up at the same position are merged
+        let primary_start = self.cursors[cursor_id.0].range().start;
+        secondary_cursors.retain(|cursor| cursor.range().start != primary_start);
+        secondary_cursors.sort_by_key(|cursor| cursor.range().start);
+        secondary_cursors.dedup_by_key(|cursor| cursor.range().start);
+        self.secondary_cursors[cursor_id.0] = secondary_cursors;
+
+        self.last_edit = None;
+        if !combined_diff.is_empty() {
+            self.content
+                .create_revision(combined_diff.clone(), self.cursors[cursor_id.0].clone());
+            self.update_parse_tree(&combined_diff, false);
+            self.update_modified_status();
+            ShouldRender::Yes
+        } else {
+            (initial_cursors
+                != (
+                    self.cursors[cursor_id.0].clone(),
+                    self.secondary_cursors[cursor_id.0].clone(),
+                ))
+                .into()
+        }
+    }
+
+    /// Applies a message at the secondary cursors of a window and then at its
+    /// cursor, updating the other cursors after each edit. Returns the
+    /// combined diff of the edits.
+    fn apply_to_each_cursor(
+        &mut self,
+        cursor_id: CursorId,
+        secondary_cursors: &mut [Cursor],
+        message: CursorMessage,
+    ) -> OpaqueDiff {
+        let mut combined_diff = OpaqueDiff::empty();
+        for index in 0..secondary_cursors.len() {
+            // Temporarily make the secondary cursor the window's cursor
+            std::mem::swap(
+                &mut self.cursors[cursor_id.0],
+                &mut secondary_cursors[index],
+            );
+            let diff = self.apply_cursor_message(cursor_id, message);
+            std::mem::swap(
+                &mut self.cursors[cursor_id.0],
+                &mut secondary_cursors[index],
+            );
+            if !diff.is_empty() {
+                self.reconcile_other_cursors(cursor_id, &diff);
+                self.cursors[cursor_id.0].reconcile(&self.content, &diff);
+                for (other_index, cursor) in secondary_cursors.iter_mut().enumerate() {
+                    if other_index != index {
+                        cursor.reconcile(&self.content, &diff);
+                    }
+                }
+                combined_diff = combined_diff.compose(&diff);
+            }
+        }
+        let diff = self.apply_cursor_message(cursor_id, message);
+        if !diff.is_empty() {
+            self.reconcile_other_cursors(cursor_id, &diff);
+            for cursor in secondary_cursors.iter_mut() {
+                cursor.reconcile(&self.content, &diff);
+            }
+            combined_diff = combined_diff.compose(&diff);
+        }
+        combined_diff
+    }
+
+    /// Turns the selection into a rectangle, with a cursor on each line it
+    /// spans selecting the columns between the selection's start and the
+    /// cursor. Typing then inserts on every line and deleting removes the
+    /// selected columns. Lines too short to reach the columns get a cursor at
+    /// their end.
+    fn select_rectangle(&mut self, cursor_id: CursorId) -> ShouldRender {
+        let cursor = &self.cursors[cursor_id.0];
+        if !cursor.has_selection() {
+            self.context
+                .log("Select up to the opposite corner of the rectangle first");
+            return ShouldRender::No;
+        }
+        let position = cursor.range().start;
+        let selection = cursor.selection();
+        let anchor = if selection.start == position {
+            selection.end
+        } else {
+            selection.start
+        };
+
+        let text = &self.content;
+        let tab_width = self.indentation.tab_width();
+        let column = |char_index: CharIndex| {
+            let line_start = text.line_to_char(text.char_to_line(char_index));
+            graphemes::width(tab_width, &text.slice(line_start..char_index))
+        };
+        let (anchor_line, anchor_column) = (text.char_to_line(anchor), column(anchor));
+        let (cursor_line, cursor_column) = (text.char_to_line(position), column(position));
+        let first_line = cmp::min(anchor_line, cursor_line);
+        let mut cursors: Vec<_> = (first_line..=cmp::max(anchor_line, cursor_line))
+            .map(|line_index| {
+                let mut cursor = Cursor::new();
+                let start = movement::char_at_column(text, line_index, tab_width, anchor_column);
+                movement::move_to_char(text, &mut cursor, start);
+                cursor.begin_selection();
+                let end = movement::char_at_column(text, line_index, tab_width, cursor_column);
+                movement::move_to_char(text, &mut cursor, end);
+                cursor
+            })
+            .collect();
+        self.cursors[cursor_id.0] = cursors.remove(cursor_line - first_line);
+        self.secondary_cursors[cursor_id.0] = cursors;
+        ShouldRender::Yes
+    }
+
+    /// Adds a cursor at the next occurrence of the selection or of the word
+    /// under the cursor, at the same offset within it
+    fn add_cursor_at_next_match(&mut self, cursor_id: CursorId) -> ShouldRender {
+        let cursor = &self.cursors[cursor_id.0];
+        let pattern_range = if cursor.selection().is_empty() {
+            match movement::word_at(&self.content, cursor.range().start) {
+                Some(word_range) => word_range,
+                None => {
+                    self.context.log("No word under the cursor");
+                    return ShouldRender::No;
+                }
+            }
+        } else {
+            cursor.selection()
+        };
+        let offset = cursor.range().start.saturating_sub(pattern_range.start);
+        let query = Query::new(&self.content.slice(pattern_range).to_string());
+
+        // Continue searching after the most recently added cursor
+        let last_cursor = self.secondary_cursors[cursor_id.0]
+            .last()
+            .unwrap_or(&self.cursors[cursor_id.0]);
+        let position = last_cursor.range().start.saturating_sub(offset) + 1;
+        let found = match query.find(&self.content, position, Direction::Forward) {
+            Some(found) => found,
+            None => return ShouldRender::No,
+        };
+        let char_index = found.range.start + offset;
+        let is_taken = |cursor: &Cursor| cursor.range().start == char_index;
+        if is_taken(&self.cursors[cursor_id.0])
+            || self.secondary_cursors[cursor_id.0].iter().any(is_taken)
+        {
+            self.context.log("No more matches");
+            return ShouldRender::No;
+        }
+
+        let mut new_cursor = Cursor::new();
+        movement::move_to_char(&self.content, &mut new_cursor, char_index);
+        self.secondary_cursors[cursor_id.0].push(new_cursor);
+        ShouldRender::Yes
+    }
+
+    /// Adds a cursor on the line above the topmost cursor or below the
+    /// bottommost one
+    fn add_cursor_vertically(&mut self, cursor_id: CursorId, direction: Direction) -> ShouldRender {
+        let cursors = std::iter::once(&self.cursors[cursor_id.0])
+            .chain(self.secondary_cursors[cursor_id.0].iter());
+        let outermost = match direction {
+            Direction::Backward => cursors.min_by_key(|cursor| cursor.range().start),
+            Direction::Forward => cursors.max_by_key(|cursor| cursor.range().start),
+        };
+        let mut new_cursor = outermost.unwrap().clone();
+        new_cursor.clear_selection();
+        let line_index = self.content.char_to_line(new_cursor.range().start);
+        movement::move_vertically(
+            &self.content,
+            &mut new_cursor,
+            self.indentation.tab_width(),
+            direction,
+            1,
+        );
+        if self.content.char_to_line(new_cursor.range().start) == line_index {
+            return ShouldRender::No;
+        }
+        self.secondary_cursors[cursor_id.0].push(new_cursor);
+        ShouldRender::Yes
+    }
+
+    /// Moves the cursor or edits the text at the cursor. Returns the diff of
+    /// the edit, which is empty if the text didn't change.
+    fn apply_cursor_message(&mut self, cursor_id: CursorId, message: CursorMessage) -> OpaqueDiff {
         {
             let content = &self.content;
             let cursor = &mut self.cursors[cursor_id.0];
@@ -325,17 +1244,33 @@ impl Buffer {
                 CursorMessage::Up(n) => movement::move_vertically(
                     content,
                     cursor,
-                    self.mode.indentation.tab_width(),
+                    self.indentation.tab_width(),
                     Direction::Backward,
                     n,
                 ),
                 CursorMessage::Down(n) => movement::move_vertically(
                     content,
                     cursor,
-                    self.mode.indentation.tab_width(),
+                    self.indentation.tab_width(),
                     Direction::Forward,
                     n,
                 ),
+                CursorMessage::UpRows { count, width } => movement::move_vertically_wrapped(
+                    content,
+                    cursor,
+                    self.indentation.tab_width(),
+                    width,
+                    Direction::Backward,
+                    count,
+                ),
+                CursorMessage::DownRows { count, width } => movement::move_vertically_wrapped(
+                    content,
+                    cursor,
+                    self.indentation.tab_width(),
+                    width,
+                    Direction::Forward,
+                    count,
+                ),
                 CursorMessage::Left => {
                     movement::move_horizontally(content, cursor, Direction::Backward, 1)
                 }
@@ -346,12 +1281,55 @@ impl Buffer {
                 CursorMessage::EndOfLine => movement::move_to_end_of_line(content, cursor),
                 CursorMessage::StartOfBuffer => movement::move_to_start_of_buffer(content, cursor),
                 CursorMessage::EndOfBuffer => movement::move_to_end_of_buffer(content, cursor),
+                CursorMessage::GoToLine { line, column } => {
+                    movement::move_to_line(content, cursor, line, column)
+                }
+                CursorMessage::GoToChar(char_index) => {
+                    movement::move_to_char(content, cursor, char_index)
+                }
                 CursorMessage::MoveWord(direction, count) => {
                     movement::move_word(content, cursor, direction, count)
                 }
                 CursorMessage::MoveParagraph(direction, count) => {
                     movement::move_paragraph(content, cursor, direction, count)
                 }
+                CursorMessage::MatchingBracket => {
+                    match brackets::matching_bracket(
+                        content,
+                        cursor.range().start,
+                        brackets::SEARCH_LIMIT,
+                    ) {
+                        Some(char_index) => movement::move_to_char(content, cursor, char_index),
+                        None => self.context.log("No matching bracket"),
+                    }
+                }
+                CursorMessage::GitChange(direction) => {
+                    let line_index = content.char_to_line(cursor.range().start);
+                    match git_diff::change_start(&self.git_diff.changes, line_index, direction) {
+                        Some(line_index) => movement::move_to_line(content, cursor, line_index, 0),
+                        None => self.context.log("No more changes"),
+                    }
+                }
+                CursorMessage::SearchMatch(direction) => {
+                    let position = match direction {
+                        Direction::Forward => cursor.range().start + 1,
+                        Direction::Backward => cursor.range().start,
+                    };
+                    let found = self
+                        .search_highlight
+                        .as_ref()
+                        .map(|query| query.find(content, position, direction));
+                    match found {
+                        Some(Some(found)) => {
+                            if found.wrapped {
+                                self.context.log("Search wrapped");
+                            }
+                            movement::move_to_char(content, cursor, found.range.start)
+                        }
+                        Some(None) => self.context.log("No match"),
+                        None => self.context.log("No search to repeat"),
+                    }
+                }
 
                 CursorMessage::BeginSelection => cursor.begin_selection(),
                 CursorMessage::ClearSelection => {
@@ -363,106 +1341,246 @@ impl Buffer {
             }
         }
 
-        let mut undoing = false;
-        let diff = {
-            match message {
-                CursorMessage::DeleteForward => {
-                    let operation = self.cursors[cursor_id.0].delete_forward(&mut self.content);
-                    if operation.diff.is_empty() {
-                        self.context.log("End of buffer");
-                    }
-                    operation.diff
+        match message {
+            CursorMessage::DeleteForward => {
+                let operation = self.cursors[cursor_id.0].delete_forward(&mut self.content);
+                if operation.diff.is_empty() {
+                    self.context.log("End of buffer");
                 }
-                CursorMessage::DeleteBackward => {
-                    let operation = self.cursors[cursor_id.0].delete_backward(&mut self.content);
-                    if operation.diff.is_empty() {
-                        self.context.log("Beginning of buffer");
-                    }
+                operation.diff
+            }
+            CursorMessage::DeleteBackward => {
+                let operation = self.cursors[cursor_id.0].delete_backward(&mut self.content);
+                if operation.diff.is_empty() {
+                    self.context.log("Beginning of buffer");
+                }
+                operation.diff
+            }
+            CursorMessage::DeleteLine => {
+                let diff = self.delete_line(cursor_id);
+                if diff.is_empty() {
+                    self.context.log("End of buffer");
+                }
+                diff
+            }
+            CursorMessage::DuplicateLines => self.cursors[cursor_id.0]
+                .duplicate_lines(&mut self.content, self.line_ending.as_str()),
+            CursorMessage::MoveLines(direction) => {
+                let diff = self.cursors[cursor_id.0].move_lines(&mut self.content, direction);
+                if diff.is_empty() {
+                    self.context.log(match direction {
+                        Direction::Backward => "Beginning of buffer",
+                        Direction::Forward => "End of buffer",
+                    });
+                }
+                diff
+            }
+            CursorMessage::ToggleComment => match self.mode.comment {
+                Some(ref comment) => {
+                    self.cursors[cursor_id.0].toggle_comment(&mut self.content, &comment.token)
+                }
+                None => {
+                    self.context
+                        .log(format!("No comment syntax for {}", self.mode.name));
+                    OpaqueDiff::empty()
+                }
+            },
+            CursorMessage::Yank => self.paste_from_clipboard(cursor_id),
+            CursorMessage::CopySelection => self.copy_selection_to_clipboard(cursor_id),
+            CursorMessage::CutSelection => self.cut_selection_to_clipboard(cursor_id),
+            CursorMessage::DeleteSelection => {
+                let cursor = &mut self.cursors[cursor_id.0];
+                if cursor.has_selection() {
+                    let start = cursor.selection().start;
+                    let operation = cursor.delete_selection(&mut self.content);
+                    movement::move_to_char(&self.content, cursor, start);
                     operation.diff
+                } else {
+                    OpaqueDiff::empty()
                 }
-                CursorMessage::DeleteLine => {
-                    let diff = self.delete_line(cursor_id);
-                    if diff.is_empty() {
-                        self.context.log("End of buffer");
+            }
+            CursorMessage::InsertTab if self.selects_multiple_lines(cursor_id) => {
+                let unit = self.indentation.unit_str();
+                self.cursors[cursor_id.0].indent_lines(&mut self.content, &unit)
+            }
+            CursorMessage::DedentLines => self.cursors[cursor_id.0]
+                .dedent_lines(&mut self.content, self.indentation.tab_width()),
+            CursorMessage::InsertTab => {
+                let (indentation_unit, indentation_count) =
+                    (self.indentation.to_char(), self.indentation.char_count());
+                let diff = self.cursors[cursor_id.0].insert_chars(
+                    &mut self.content,
+                    std::iter::repeat(indentation_unit).take(indentation_count),
+                );
+                movement::move_horizontally(
+                    &self.content,
+                    &mut self.cursors[cursor_id.0],
+                    Direction::Forward,
+                    indentation_count,
+                );
+                diff
+            }
+            CursorMessage::InsertNewLine => {
+                let indentation = if self.auto_indent {
+                    indent::new_line_indentation(
+                        &self.content,
+                        self.cursors[cursor_id.0].range().start,
+                        &self.indentation.unit_str(),
+                        &self.indentation.indent_after,
+                    )
+                } else {
+                    String::new()
+                };
+                let diff = self.cursors[cursor_id.0].insert_chars(
+                    &mut self.content,
+                    self.line_ending.as_str().chars().chain(indentation.chars()),
+                );
+                let cursor = &mut self.cursors[cursor_id.0];
+                movement::move_vertically(
+                    &self.content,
+                    cursor,
+                    self.indentation.tab_width(),
+                    Direction::Forward,
+                    1,
+                );
+                movement::move_to_start_of_line(&self.content, cursor);
+                movement::move_horizontally(
+                    &self.content,
+                    cursor,
+                    Direction::Forward,
+                    indentation.chars().count(),
+                );
+                diff
+            }
+            CursorMessage::InsertChar {
+                character,
+                move_forward,
+            } => {
+                if move_forward && self.auto_pairs {
+                    if let Some(diff) = self.insert_pair(cursor_id, character) {
+                        return diff;
                     }
-                    diff
                 }
-                CursorMessage::Yank => self.paste_from_clipboard(cursor_id),
-                CursorMessage::CopySelection => self.copy_selection_to_clipboard(cursor_id),
-                CursorMessage::CutSelection => self.cut_selection_to_clipboard(cursor_id),
-                CursorMessage::InsertTab => {
-                    let (indentation_unit, indentation_count) = (
-                        self.mode.indentation.to_char(),
-                        self.mode.indentation.char_count(),
-                    );
-                    let diff = self.cursors[cursor_id.0].insert_chars(
-                        &mut self.content,
-                        std::iter::repeat(indentation_unit).take(indentation_count),
-                    );
+                let diff = if character == '\n' {
+                    self.cursors[cursor_id.0]
+                        .insert_chars(&mut self.content, self.line_ending.as_str().chars())
+                } else {
+                    self.cursors[cursor_id.0].insert_char(&mut self.content, character)
+                };
+                if move_forward {
                     movement::move_horizontally(
                         &self.content,
                         &mut self.cursors[cursor_id.0],
                         Direction::Forward,
-                        indentation_count,
-                    );
-                    diff
-                }
-                CursorMessage::InsertNewLine => {
-                    let diff = self.cursors[cursor_id.0].insert_char(&mut self.content, '\n');
-                    let cursor = &mut self.cursors[cursor_id.0];
-                    movement::move_vertically(
-                        &self.content,
-                        cursor,
-                        self.mode.indentation.tab_width(),
-                        Direction::Forward,
                         1,
                     );
-                    movement::move_to_start_of_line(&self.content, cursor);
-                    diff
-                }
-                CursorMessage::InsertChar {
-                    character,
-                    move_forward,
-                } => {
-                    let diff = self.cursors[cursor_id.0].insert_char(&mut self.content, character);
-                    if move_forward {
-                        movement::move_horizontally(
-                            &self.content,
-                            &mut self.cursors[cursor_id.0],
-                            Direction::Forward,
-                            1,
-                        );
-                    }
-                    diff
-                }
-                CursorMessage::Undo => {
-                    undoing = true;
-                    self.undo(cursor_id)
                 }
-                CursorMessage::Redo => {
-                    undoing = true;
-                    self.redo(cursor_id)
-                }
-
-                _ => OpaqueDiff::empty(),
+                diff
             }
-        };
+            CursorMessage::Undo => self.undo(cursor_id),
+            CursorMessage::Redo => self.redo(cursor_id),
 
-        if !diff.is_empty() {
-            self.modified_status = ModifiedStatus::Changed;
-            for (id, cursor) in self.cursors.iter_mut().enumerate() {
-                if id != cursor_id.0 {
-                    cursor.reconcile(&self.content, &diff);
-                }
+            _ => OpaqueDiff::empty(),
+        }
+    }
+
+    /// Whether the selection of the cursor spans more than one line, in which
+    /// case `TAB` indents the lines rather than inserting a tab
+    fn selects_multiple_lines(&self, cursor_id: CursorId) -> bool {
+        let cursor = &self.cursors[cursor_id.0];
+        let selection = cursor.selection();
+        cursor.has_selection()
+            && self.content.char_to_line(selection.start)
+                != self.content.char_to_line(selection.end - 1)
+    }
+
+    /// Closes brackets and quotes when typing them, or surrounds the selection
+    /// with them. The pair is inserted as a single edit. Returns `None` if
+    /// `character` should be inserted as usual.
+    fn insert_pair(&mut self, cursor_id: CursorId, character: char) -> Option<OpaqueDiff> {
+        let quotes = &self.mode.quotes;
+        let cursor = &mut self.cursors[cursor_id.0];
+        if cursor.has_selection() {
+            let close = brackets::closing_pair(character, quotes)?;
+            return Some(cursor.wrap_selection(&mut self.content, character, close));
+        }
+        match brackets::auto_pair(&self.content, cursor.range().start, character, quotes)? {
+            AutoPair::Close(close) => {
+                let diff = cursor.insert_chars(&mut self.content, [character, close]);
+                movement::move_horizontally(&self.content, cursor, Direction::Forward, 1);
+                Some(diff)
             }
-            if !undoing {
-                self.content
-                    .create_revision(diff.clone(), self.cursors[cursor_id.0].clone());
-                self.update_parse_tree(&diff, false);
+            AutoPair::Skip => {
+                movement::move_horizontally(&self.content, cursor, Direction::Forward, 1);
+                Some(OpaqueDiff::empty())
             }
         }
     }
 
+    /// Updates the cursors other than `cursor_id` after an edit
+    fn reconcile_other_cursors(&mut self, cursor_id: CursorId, diff: &OpaqueDiff) {
+        for (id, cursor) in self.cursors.iter_mut().enumerate() {
+            if id != cursor_id.0 {
+                cursor.reconcile(&self.content, diff);
+            }
+        }
+        for cursor in self.secondary_cursors.iter_mut().flatten() {
+            cursor.reconcile(&self.content, diff);
+        }
+    }
+
+    /// Updates all cursors before the content is replaced by `new_text`
+    fn sync_cursors(&mut self, new_text: &Rope) {
+        for cursor in self
+            .cursors
+            .iter_mut()
+            .chain(self.secondary_cursors.iter_mut().flatten())
+        {
+            cursor.sync(&self.content, new_text);
+        }
+    }
+
+    fn update_modified_status(&mut self) {
+        self.modified_status = if self.content.is_saved() {
+            ModifiedStatus::Unchanged
+        } else {
+            ModifiedStatus::Changed
+        };
+    }
+
+    /// Creates a revision for an edit which didn't originate from a cursor
+    /// message, moving the cursor to `char_index`
+    fn record_edit(&mut self, cursor_id: CursorId, char_index: CharIndex, diff: OpaqueDiff) {
+        movement::move_to_char(&self.content, &mut self.cursors[cursor_id.0], char_index);
+        self.reconcile_other_cursors(cursor_id, &diff);
+        self.content
+            .create_revision(diff.clone(), self.cursors[cursor_id.0].clone());
+        self.update_parse_tree(&diff, false);
+        self.update_modified_status();
+        self.last_edit = None;
+    }
+
+    /// Converts all line breaks to `line_ending`, which is also used for new
+    /// lines from now on
+    fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+        self.context
+            .log(format!("Line endings set to {}", line_ending.name()));
+
+        let mut text = self.content.staged().clone();
+        let diff = line_ending::convert(&mut text, line_ending);
+        if diff.is_empty() {
+            return;
+        }
+        self.sync_cursors(&text);
+        *self.content.staged_mut() = text;
+        self.content
+            .create_revision(diff.clone(), self.cursors[0].clone());
+        self.update_parse_tree(&diff, false);
+        self.update_modified_status();
+        self.last_edit = None;
+    }
+
     fn delete_line(&mut self, cursor_id: CursorId) -> OpaqueDiff {
         self.cursors[cursor_id.0]
             .delete_line(&mut self.content)
@@ -471,25 +1589,40 @@ impl Buffer {
 
     fn copy_selection_to_clipboard(&mut self, cursor_id: CursorId) -> OpaqueDiff {
         let selection = self.cursors[cursor_id.0].selection();
-        self.context
+        if let Err(error) = self
+            .context
             .clipboard
             .set_contents(self.content.slice(selection.start..selection.end).into())
-            .unwrap();
+        {
+            self.context
+                .log(format!("Could not copy to the clipboard: {}", error));
+        }
         self.cursors[cursor_id.0].clear_selection();
         OpaqueDiff::empty()
     }
 
     fn cut_selection_to_clipboard(&mut self, cursor_id: CursorId) -> OpaqueDiff {
         let operation = self.cursors[cursor_id.0].delete_selection(&mut self.content);
-        self.context
+        if let Err(error) = self
+            .context
             .clipboard
             .set_contents(operation.deleted.into())
-            .unwrap();
+        {
+            self.context
+                .log(format!("Could not copy to the clipboard: {}", error));
+        }
         operation.diff
     }
 
     fn paste_from_clipboard(&mut self, cursor_id: CursorId) -> OpaqueDiff {
-        let clipboard_str = self.context.clipboard.get_contents().unwrap();
+        let clipboard_str = match self.context.clipboard.get_contents() {
+            Ok(contents) => contents,
+            Err(error) => {
+                self.context
+                    .log(format!("Could not paste from the clipboard: {}", error));
+                return OpaqueDiff::empty();
+            }
+        };
         if !clipboard_str.is_empty() {
             self.cursors[cursor_id.0].insert_chars(&mut self.content, clipboard_str.chars())
         } else {
@@ -502,7 +1635,7 @@ impl Buffer {
             .undo()
             .map(|(diff, cursor)| {
                 self.cursors[cursor_id.0] = cursor;
-                self.update_parse_tree(&diff, true);
+                self.update_parse_tree(&diff, diff.is_empty());
                 diff
             })
             .unwrap_or_else(OpaqueDiff::empty)
@@ -513,12 +1646,17 @@ impl Buffer {
             .redo()
             .map(|(diff, cursor)| {
                 self.cursors[cursor_id.0] = cursor;
-                self.update_parse_tree(&diff, true);
+                self.update_parse_tree(&diff, diff.is_empty());
                 diff
             })
             .unwrap_or_else(OpaqueDiff::empty)
     }
 
+    /// Reparses the buffer in the background after an edit described by
+    /// `diff`. Unless `fresh` is set, the previous syntax tree is edited and
+    /// reused so that only the changed region is reparsed. An empty diff
+    /// can't describe a change of the text, e.g. undoing a reload from disk,
+    /// so callers should pass `fresh` in that case.
     fn update_parse_tree(&mut self, diff: &OpaqueDiff, fresh: bool) {
         if let Some(parser) = self.parser.as_mut() {
             if fresh {
@@ -543,31 +1681,267 @@ impl Buffer {
     fn spawn_save_file(&mut self) {
         let file_path = match self.file_path.clone() {
             Some(file_path) => file_path,
-            None => return,
+            None => {
+                self.context.link.send(Message::SaveFileAsPicker);
+                return;
+            }
         };
 
         self.modified_status = ModifiedStatus::Saving;
         let buffer_id = self.id;
         let text = self.content.staged().clone();
         let link = self.context.link.clone();
-        let trim_trailing_whitespace = self.context.config.trim_trailing_whitespace_on_save;
+        let trim_trailing_whitespace = self.trim_trailing_whitespace;
+        let insert_final_newline = self.insert_final_newline;
+        let line_ending = self.line_ending;
+        let encoding = self.encoding;
+        let formatter = self
+            .mode
+            .formatter
+            .clone()
+            .filter(|_| self.context.config.format_on_save);
+        // Formatters look for their configuration next to the file
+        let formatter_dir = self
+            .context
+            .resolve_path(&file_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.context.current_working_dir());
         self.context.task_pool.spawn(move |_| {
-            let text = match trim_trailing_whitespace {
+            let text = match formatter {
+                Some(formatter) => {
+                    match filter::run(&formatter, text.to_string(), &formatter_dir) {
+                        Ok(formatted) => Rope::from(formatted),
+                        Err(error) => {
+                            let error = io::Error::new(
+                                io::ErrorKind::Other,
+                                format!("`{}` failed: {}", formatter, error),
+                            );
+                            let buffer_message = BufferMessage::SaveBufferEnd(Err(error));
+                            link.send(BuffersMessage::new(buffer_id, buffer_message).into());
+                            return;
+                        }
+                    }
+                }
+                None => text,
+            };
+            let mut text = match trim_trailing_whitespace {
                 true => strip_trailing_whitespace(text),
                 false => text,
             };
+            if insert_final_newline {
+                line_ending::insert_final_line_break(&mut text, line_ending);
+            }
 
             let buffer_message = BufferMessage::SaveBufferEnd(
-                File::create(&file_path)
-                    .map(BufWriter::new)
-                    .and_then(|writer| {
-                        text.write_to(writer)?;
-                        Ok(text)
-                    }),
+                write_file_atomically(&file_path, &text, encoding).map(|_| text),
             );
             link.send(BuffersMessage::new(buffer_id, buffer_message).into())
         });
     }
+
+    /// Reads the next chunk of a large file in the background once the cursor
+    /// gets close to the end of the text loaded so far
+    fn load_large_file_chunk(&mut self, cursor_id: CursorId) {
+        let large_file = match self.large_file.as_mut() {
+            Some(large_file) if !large_file.loading && !large_file.is_loaded() => large_file,
+            _ => return,
+        };
+        let line_index = self
+            .content
+            .char_to_line(self.cursors[cursor_id.0].range().start);
+        if line_index + large_file::LOAD_MARGIN_LINES < self.content.len_lines() {
+            return;
+        }
+
+        large_file.loading = true;
+        let file_path = large_file.file_path.clone();
+        let offset = large_file.loaded;
+        let buffer_id = self.id;
+        let link = self.context.link.clone();
+        self.context.task_pool.spawn(move |_| {
+            let chunk = large_file::read_chunk(&file_path, offset);
+            link.send(BuffersMessage::new(buffer_id, BufferMessage::LargeFileChunk(chunk)).into())
+        });
+    }
+
+    /// Appends a chunk of a large file to the buffer as a new revision, which
+    /// matches the file on disk
+    fn append_large_file_chunk(&mut self, chunk: Vec<u8>) {
+        let large_file = match self.large_file.as_mut() {
+            Some(large_file) => large_file,
+            None => return,
+        };
+        large_file.loading = false;
+        if chunk.is_empty() {
+            // The file was truncated since it was opened
+            large_file.size = large_file.loaded;
+            return;
+        }
+        large_file.loaded += chunk.len() as u64;
+
+        let text = String::from_utf8_lossy(&chunk);
+        let char_index = self.content.len_chars();
+        let diff = OpaqueDiff::new(
+            self.content.len_bytes(),
+            0,
+            text.len(),
+            char_index,
+            0,
+            text.chars().count(),
+        );
+        self.content.staged_mut().insert(char_index, &text);
+        self.content.create_revision(diff, self.cursors[0].clone());
+        self.content.mark_saved();
+
+        // The cursor may still be close to the end
+        for cursor_id in 0..self.cursors.len() {
+            self.load_large_file_chunk(CursorId(cursor_id));
+        }
+    }
+
+    /// Reads the file from the git index and compares the buffer with it,
+    /// e.g. after saving as the index may have changed since. It's done in the
+    /// background as reading the index can take a while.
+    fn reload_git_index(&mut self) {
+        let (git_dir, file_path) = match (self.repo.as_ref(), self.file_path.as_ref()) {
+            (Some(repo), Some(file_path)) if !self.hex_view => {
+                (repo.path().to_path_buf(), file_path.clone())
+            }
+            _ => {
+                self.git_diff.original = None;
+                return;
+            }
+        };
+        let version = self.version();
+        self.git_diff.version = Some(version);
+        self.git_diff.outdated_since = None;
+
+        let buffer_id = self.id;
+        let text = self.content.staged().clone();
+        let link = self.context.link.clone();
+        self.context.task_pool.spawn(move |_| {
+            // Repositories can't be shared between threads, so it's opened
+            // again here
+            let original = Repository::open(&git_dir)
+                .ok()
+                .and_then(|repo| git_diff::index_content(&repo, &file_path))
+                .map(Arc::new);
+            let changes = original
+                .as_deref()
+                .map_or_else(Vec::new, |original| git_diff::line_changes(original, &text));
+            link.send(
+                BuffersMessage::new(
+                    buffer_id,
+                    BufferMessage::GitIndex {
+                        version,
+                        original,
+                        changes,
+                    },
+                )
+                .into(),
+            )
+        });
+    }
+
+    fn spawn_git_diff(&mut self) {
+        let original = match self.git_diff.original.clone() {
+            Some(original) => original,
+            None => return,
+        };
+        let version = self.version();
+        self.git_diff.version = Some(version);
+        self.git_diff.outdated_since = None;
+
+        let buffer_id = self.id;
+        let text = self.content.staged().clone();
+        let link = self.context.link.clone();
+        self.context.task_pool.spawn(move |_| {
+            let changes = git_diff::line_changes(&original, &text);
+            link.send(
+                BuffersMessage::new(buffer_id, BufferMessage::GitDiff { version, changes }).into(),
+            )
+        });
+    }
+}
+
+/// Creates a syntax parser for the mode's grammar. If the grammar cannot be
+/// loaded, the error is logged and the buffer falls back to plain text.
+fn create_parser(context: &ContextHandle, mode: &Mode) -> Option<ParserPool> {
+    let parser = mode
+        .language()?
+        .map_err(|error| anyhow::anyhow!("{:#}", error))
+        .and_then(ParserPool::new);
+    match parser {
+        Ok(parser) => Some(parser),
+        Err(error) => {
+            log::error!("Could not load the grammar for {}: {:#}", mode.name, error);
+            context.log(format!(
+                "Could not load the grammar for {}, falling back to plain text \
+                 (run `zee --build` to build the grammars)",
+                mode.name
+            ));
+            None
+        }
+    }
+}
+
+/// Writes `text` in the given encoding to a temporary file next to
+/// `file_path` and then renames it over the original. If the editor crashes
+/// mid-write, the original file is left untouched.
+fn write_file_atomically(file_path: &Path, text: &Rope, encoding: FileEncoding) -> io::Result<()> {
+    // Write through symlinks rather than replacing them with a regular file
+    let file_path = match fs::canonicalize(file_path) {
+        Ok(canonical_path) => canonical_path,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => file_path.to_path_buf(),
+        Err(error) => return Err(error),
+    };
+    let file_name = file_path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` is not a file path", file_path.display()),
+        )
+    })?;
+    let temp_path = file_path.with_file_name(format!(
+        ".{}.zee-save-{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    // The buffer is UTF-8 in memory, other encodings are converted up front
+    let encoded = if encoding == FileEncoding::UTF8 {
+        None
+    } else {
+        Some(encoding::encode(text, encoding)?)
+    };
+
+    let write_temp_file = || -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        match encoded {
+            Some(ref bytes) => writer.write_all(bytes)?,
+            None => text.write_to(&mut writer)?,
+        }
+        writer.flush()?;
+        let file = writer.into_inner().map_err(|error| error.into_error())?;
+        if let Ok(metadata) = fs::metadata(&file_path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp_path, &file_path)
+    };
+
+    write_temp_file().map_err(|error| {
+        let _ = fs::remove_file(&temp_path);
+        error
+    })
+}
+
+/// Kinds of edits which are merged into a single revision when repeated
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EditKind {
+    InsertWord,
+    InsertWhitespace,
+    DeleteForward,
+    DeleteBackward,
 }
 
 #[derive(Clone, PartialEq)]
@@ -575,6 +1949,7 @@ pub struct BufferCursor {
     buffer_id: BufferId,
     cursor_id: CursorId,
     cursor: Cursor,
+    secondary_cursors: Vec<Cursor>,
     link: ComponentLink<Editor>,
 }
 
@@ -583,12 +1958,14 @@ impl BufferCursor {
         buffer_id: BufferId,
         cursor_id: CursorId,
         cursor: Cursor,
+        secondary_cursors: Vec<Cursor>,
         link: ComponentLink<Editor>,
     ) -> Self {
         Self {
             buffer_id,
             cursor_id,
             cursor,
+            secondary_cursors,
             link,
         }
     }
@@ -621,6 +1998,11 @@ impl BufferCursor {
         &self.cursor
     }
 
+    /// The additional cursors when editing with multiple cursors
+    pub fn secondary(&self) -> &[Cursor] {
+        &self.secondary_cursors
+    }
+
     pub fn previous_child_revision(&self) {
         self.send_message(BufferMessage::PreviousChildRevision)
     }
@@ -649,6 +2031,18 @@ impl BufferCursor {
         self.send_cursor(CursorMessage::Down(n));
     }
 
+    /// Moves up by visual rows, with lines soft wrapped to `width` columns
+    #[inline]
+    pub fn move_up_rows(&self, count: usize, width: usize) {
+        self.send_cursor(CursorMessage::UpRows { count, width });
+    }
+
+    /// Moves down by visual rows, with lines soft wrapped to `width` columns
+    #[inline]
+    pub fn move_down_rows(&self, count: usize, width: usize) {
+        self.send_cursor(CursorMessage::DownRows { count, width });
+    }
+
     #[inline]
     pub fn move_left(&self) {
         self.send_cursor(CursorMessage::Left);
@@ -744,6 +2138,11 @@ impl BufferCursor {
         self.send_cursor(CursorMessage::InsertTab);
     }
 
+    #[inline]
+    pub fn dedent_lines(&self) {
+        self.send_cursor(CursorMessage::DedentLines);
+    }
+
     #[inline]
     pub fn insert_char(&self, character: char, move_forward: bool) {
         self.send_cursor(CursorMessage::InsertChar {
@@ -761,6 +2160,28 @@ pub enum BufferMessage {
         version: usize,
         status: Result<ParserStatus>,
     },
+    GitDiff {
+        version: usize,
+        changes: LineChanges,
+    },
+    GitIndex {
+        version: usize,
+        original: Option<Arc<Vec<u8>>>,
+        changes: LineChanges,
+    },
+    LargeFileChunk(io::Result<Vec<u8>>),
+    FilterEnd {
+        cursor_id: CursorId,
+        range: Range<CharIndex>,
+        version: usize,
+        command: String,
+        result: std::result::Result<String, String>,
+    },
+    SetLineEnding(LineEnding),
+    ToggleTrimTrailingWhitespace,
+    ToggleAutoIndent,
+    ToggleAutoPairs,
+    ToggleReadOnly,
     PreviousChildRevision,
     NextChildRevision,
     CursorMessage {
@@ -769,11 +2190,31 @@ pub enum BufferMessage {
     },
 }
 
-#[derive(Debug)]
+impl BufferMessage {
+    /// Whether the message changes the content of the buffer
+    fn is_edit(&self) -> bool {
+        match self {
+            Self::SetLineEnding(_) | Self::PreviousChildRevision | Self::NextChildRevision => true,
+            Self::CursorMessage { message, .. } => message.is_edit(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub enum CursorMessage {
     // Movement
     Up(usize),
     Down(usize),
+    /// Move by visual rows of lines soft wrapped to `width` columns
+    UpRows {
+        count: usize,
+        width: usize,
+    },
+    DownRows {
+        count: usize,
+        width: usize,
+    },
     Left,
     Right,
     StartOfLine,
@@ -782,6 +2223,17 @@ pub enum CursorMessage {
     EndOfBuffer,
     MoveWord(Direction, usize),
     MoveParagraph(Direction, usize),
+    GoToLine {
+        line: LineIndex,
+        column: usize,
+    },
+    GoToChar(CharIndex),
+    MatchingBracket,
+    /// Move to the next or previous block of lines changed compared to the
+    /// git index
+    GitChange(Direction),
+    /// Move to the next or previous match of the highlighted search
+    SearchMatch(Direction),
 
     // Editing
     BeginSelection,
@@ -790,17 +2242,86 @@ pub enum CursorMessage {
     Yank,
     CopySelection,
     CutSelection,
+    /// Delete the selected text without copying it, if there's a selection
+    DeleteSelection,
 
     DeleteForward,
     DeleteBackward,
     DeleteLine,
+    DuplicateLines,
+    MoveLines(Direction),
+    ToggleComment,
     InsertTab,
+    DedentLines,
     InsertNewLine,
-    InsertChar { character: char, move_forward: bool },
+    InsertChar {
+        character: char,
+        move_forward: bool,
+    },
 
     // Undo / Redo
     Undo,
     Redo,
+
+    // Multiple cursors
+    AddCursorAtNextMatch,
+    AddCursorAbove,
+    AddCursorBelow,
+    /// Replace the selection with a rectangle of cursors, one on each line
+    SelectRectangle,
+    /// Remove the secondary cursors and clear the search highlight
+    CollapseCursors,
+}
+
+impl CursorMessage {
+    /// Whether the message changes the content of the buffer
+    fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            Self::Yank
+                | Self::CutSelection
+                | Self::DeleteSelection
+                | Self::DeleteForward
+                | Self::DeleteBackward
+                | Self::DeleteLine
+                | Self::DuplicateLines
+                | Self::MoveLines(_)
+                | Self::ToggleComment
+                | Self::InsertTab
+                | Self::DedentLines
+                | Self::InsertNewLine
+                | Self::InsertChar { .. }
+                | Self::Undo
+                | Self::Redo
+        )
+    }
+
+    /// Whether the message is applied at each cursor of a window with multiple
+    /// cursors, rather than only at its main cursor
+    fn applies_to_each_cursor(&self) -> bool {
+        matches!(
+            self,
+            Self::Up(_)
+                | Self::Down(_)
+                | Self::UpRows { .. }
+                | Self::DownRows { .. }
+                | Self::Left
+                | Self::Right
+                | Self::StartOfLine
+                | Self::EndOfLine
+                | Self::MoveWord(..)
+                | Self::MoveParagraph(..)
+                | Self::BeginSelection
+                | Self::ClearSelection
+                | Self::DeleteForward
+                | Self::DeleteBackward
+                | Self::DeleteLine
+                | Self::InsertTab
+                | Self::DedentLines
+                | Self::InsertNewLine
+                | Self::InsertChar { .. }
+        )
+    }
 }
 
 #[derive(Clone)]
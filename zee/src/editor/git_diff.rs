@@ -0,0 +1,166 @@
+use git2::{DiffOptions, Patch, Repository};
+use ropey::Rope;
+use std::{
+    fs,
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use zee_edit::{Direction, LineIndex};
+
+/// How a line differs from the file in the git index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// Lines were deleted after this one, or before it on the first line
+    Deleted,
+}
+
+/// The changed lines of a buffer, sorted by line
+pub type LineChanges = Vec<(LineIndex, LineChange)>;
+
+/// The changes of a buffer compared to its file in the git index. They're
+/// recomputed in the background a moment after the buffer was edited.
+#[derive(Debug, Default)]
+pub struct GitDiff {
+    // The file's content in the index, `None` if it isn't tracked
+    pub original: Option<Arc<Vec<u8>>>,
+    pub changes: Rc<LineChanges>,
+    // The version of the buffer the changes are for, or are being computed for
+    pub version: Option<usize>,
+    // When the buffer was first seen edited since the changes were computed
+    pub outdated_since: Option<Instant>,
+}
+
+impl GitDiff {
+    /// Whether the changes were computed for an older version of the buffer
+    pub fn is_outdated(&self, version: usize) -> bool {
+        self.original.is_some() && self.version != Some(version)
+    }
+}
+
+/// Reads the content of a file in the repository's index, `None` if the file
+/// isn't tracked
+pub fn index_content(repo: &Repository, file_path: &Path) -> Option<Vec<u8>> {
+    let workdir = fs::canonicalize(repo.workdir()?).ok()?;
+    let file_path = fs::canonicalize(file_path).ok()?;
+    let relative_path = file_path.strip_prefix(workdir).ok()?;
+    let mut index = repo.index().ok()?;
+    // The index may have changed on disk, e.g. after a `git add`
+    index.read(false).ok()?;
+    let entry = index.get_path(relative_path, 0)?;
+    Some(repo.find_blob(entry.id).ok()?.content().to_vec())
+}
+
+/// Compares the text of a buffer with the original content of its file
+pub fn line_changes(original: &[u8], text: &Rope) -> LineChanges {
+    let text = text.to_string();
+    let mut options = DiffOptions::new();
+    options.context_lines(0);
+    let patch = match Patch::from_buffers(original, None, text.as_bytes(), None, Some(&mut options))
+    {
+        Ok(patch) => patch,
+        Err(error) => {
+            log::warn!("Could not diff the buffer with the git index: {}", error);
+            return Vec::new();
+        }
+    };
+
+    let mut changes = Vec::new();
+    for hunk_index in 0..patch.num_hunks() {
+        let hunk = match patch.hunk(hunk_index) {
+            Ok((hunk, _)) => hunk,
+            Err(_) => continue,
+        };
+        // Line numbers start at one. Deleted lines start after the line
+        // before them, which is zero at the start of the file.
+        let start = (hunk.new_start() as usize).saturating_sub(1);
+        let new_lines = hunk.new_lines() as usize;
+        let change = match (hunk.old_lines(), new_lines) {
+            (_, 0) => {
+                changes.push((start, LineChange::Deleted));
+                continue;
+            }
+            (0, _) => LineChange::Added,
+            _ => LineChange::Modified,
+        };
+        changes.extend((start..start + new_lines).map(|line_index| (line_index, change)));
+    }
+    changes.dedup_by_key(|(line_index, _)| *line_index);
+    changes
+}
+
+/// The first line of the next or previous block of changed lines
+pub fn change_start(
+    changes: &[(LineIndex, LineChange)],
+    line_index: LineIndex,
+    direction: Direction,
+) -> Option<LineIndex> {
+    let mut starts = changes
+        .iter()
+        .enumerate()
+        .filter(|&(index, &(line, _))| index == 0 || changes[index - 1].0 + 1 != line)
+        .map(|(_, &(line, _))| line);
+    match direction {
+        Direction::Forward => starts.find(|&start| start > line_index),
+        Direction::Backward => starts.rfind(|&start| start < line_index),
+    }
+}
+
+/// How long after the last edit the changes are recomputed
+pub const DIFF_DELAY: Duration = Duration::from_millis(300);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LineChange::*;
+
+    fn changes(original: &str, text: &str) -> LineChanges {
+        line_changes(original.as_bytes(), &Rope::from(text))
+    }
+
+    #[test]
+    fn unchanged_text() {
+        assert_eq!(changes("a\nb\n", "a\nb\n"), vec![]);
+    }
+
+    #[test]
+    fn added_and_modified_lines() {
+        assert_eq!(
+            changes("a\nb\n", "a\nx\ny\nb\n"),
+            vec![(1, Added), (2, Added)]
+        );
+        assert_eq!(
+            changes("a\nb\nc\n", "a\nx\ny\nc\n"),
+            vec![(1, Modified), (2, Modified)]
+        );
+        assert_eq!(
+            changes("a\nb\nc\nd\n", "x\nb\nc\nd\ny\n"),
+            vec![(0, Modified), (4, Added)]
+        );
+    }
+
+    #[test]
+    fn deleted_lines() {
+        assert_eq!(changes("a\nb\nc\n", "a\nc\n"), vec![(0, Deleted)]);
+        // At the start and at the end of the file
+        assert_eq!(changes("a\nb\nc\n", "b\nc\n"), vec![(0, Deleted)]);
+        assert_eq!(changes("a\nb\nc\n", "a\nb\n"), vec![(1, Deleted)]);
+        assert_eq!(changes("a\nb\nc\n", ""), vec![(0, Deleted)]);
+    }
+
+    #[test]
+    fn find_change_starts() {
+        let changes = vec![(1, Added), (2, Added), (5, Modified), (9, Deleted)];
+        assert_eq!(change_start(&changes, 0, Direction::Forward), Some(1));
+        assert_eq!(change_start(&changes, 1, Direction::Forward), Some(5));
+        assert_eq!(change_start(&changes, 2, Direction::Forward), Some(5));
+        assert_eq!(change_start(&changes, 9, Direction::Forward), None);
+        assert_eq!(change_start(&changes, 9, Direction::Backward), Some(5));
+        assert_eq!(change_start(&changes, 5, Direction::Backward), Some(1));
+        assert_eq!(change_start(&changes, 1, Direction::Backward), None);
+        assert_eq!(change_start(&[], 0, Direction::Forward), None);
+    }
+}
@@ -0,0 +1,124 @@
+use zee_edit::CharIndex;
+
+use super::buffer::BufferId;
+
+/// A cursor position to return to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Jump {
+    pub buffer_id: BufferId,
+    pub char_index: CharIndex,
+}
+
+/// Positions of the cursor before large jumps, e.g. going to a line or
+/// switching buffers, which can be revisited backward and forward like the
+/// history of a web browser
+#[derive(Debug, Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    // The position in `jumps` while going back, `jumps.len()` otherwise
+    index: usize,
+}
+
+impl JumpList {
+    /// Records the position before a jump. Positions that were gone back from
+    /// are forgotten.
+    pub fn push(&mut self, jump: Jump) {
+        self.jumps.truncate(self.index);
+        if self.jumps.last() != Some(&jump) {
+            self.jumps.push(jump);
+        }
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+        self.index = self.jumps.len();
+    }
+
+    /// The position before the current one. The first time back, `current` is
+    /// recorded so that it can be returned to with [`JumpList::forward`].
+    pub fn back(&mut self, current: Jump) -> Option<Jump> {
+        if self.index == self.jumps.len() {
+            if self.jumps.last() != Some(&current) {
+                self.jumps.push(current);
+            }
+            self.index = self.jumps.len() - 1;
+        }
+        if self.index == 0 {
+            return None;
+        }
+        self.index -= 1;
+        Some(self.jumps[self.index])
+    }
+
+    /// The position after the current one, if the list was gone back in
+    pub fn forward(&mut self) -> Option<Jump> {
+        if self.index + 1 >= self.jumps.len() {
+            return None;
+        }
+        self.index += 1;
+        Some(self.jumps[self.index])
+    }
+}
+
+const MAX_JUMPS: usize = 100;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jump(char_index: CharIndex) -> Jump {
+        Jump {
+            buffer_id: BufferId(0),
+            char_index,
+        }
+    }
+
+    #[test]
+    fn go_back_and_forward() {
+        let mut jumps = JumpList::default();
+        assert_eq!(jumps.back(jump(0)), None);
+        assert_eq!(jumps.forward(), None);
+
+        let mut jumps = JumpList::default();
+        jumps.push(jump(1));
+        jumps.push(jump(2));
+        // Pushing the same position twice records it once
+        jumps.push(jump(2));
+        assert_eq!(jumps.back(jump(3)), Some(jump(2)));
+        assert_eq!(jumps.back(jump(2)), Some(jump(1)));
+        assert_eq!(jumps.back(jump(1)), None);
+        assert_eq!(jumps.forward(), Some(jump(2)));
+        // The position before going back is returned to
+        assert_eq!(jumps.forward(), Some(jump(3)));
+        assert_eq!(jumps.forward(), None);
+    }
+
+    #[test]
+    fn push_forgets_the_positions_gone_back_from() {
+        let mut jumps = JumpList::default();
+        jumps.push(jump(1));
+        jumps.push(jump(2));
+        assert_eq!(jumps.back(jump(3)), Some(jump(2)));
+        assert_eq!(jumps.back(jump(2)), Some(jump(1)));
+
+        jumps.push(jump(4));
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(jump(5)), Some(jump(4)));
+        assert_eq!(jumps.back(jump(4)), None);
+    }
+
+    #[test]
+    fn keep_the_most_recent_jumps() {
+        let mut jumps = JumpList::default();
+        for char_index in 0..MAX_JUMPS + 10 {
+            jumps.push(jump(char_index));
+        }
+        let mut current = jump(MAX_JUMPS + 10);
+        let mut num_jumps = 0;
+        while let Some(previous) = jumps.back(current) {
+            current = previous;
+            num_jumps += 1;
+        }
+        assert_eq!(num_jumps, MAX_JUMPS);
+        assert_eq!(current, jump(10));
+    }
+}
@@ -0,0 +1,316 @@
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    buffer::{Buffer, BufferId, Buffers, CursorId},
+    ContextHandle, Message,
+};
+use crate::lsp::{self, Client, Event, RequestId};
+
+/// A buffer's file opened with a language server
+struct Document {
+    // The name of the buffer's mode, which identifies the server
+    server: String,
+    // The buffer's file path, to notice when it's saved to a different file
+    buffer_path: PathBuf,
+    // The canonical path, as reported by the server with diagnostics
+    file_path: PathBuf,
+    uri: String,
+    version: usize,
+}
+
+/// The language servers of the modes of open files, started when the first
+/// file of a mode is opened. The content of the buffers is sent to the
+/// servers as it changes, see [`LanguageServers::sync`].
+pub struct LanguageServers {
+    context: ContextHandle,
+    clients: HashMap<String, Client>,
+    // Servers which could not be started or exited, which are not restarted
+    failed: HashSet<String>,
+    documents: HashMap<BufferId, Document>,
+    // The latest hover request, responses to earlier ones are ignored
+    hover: Option<HoverRequest>,
+}
+
+struct HoverRequest {
+    server: String,
+    id: RequestId,
+    // Diagnostics under the cursor, shown before the server's information
+    diagnostics: Vec<String>,
+}
+
+impl LanguageServers {
+    pub fn new(context: ContextHandle) -> Self {
+        Self {
+            context,
+            clients: HashMap::new(),
+            failed: HashSet::new(),
+            documents: HashMap::new(),
+            hover: None,
+        }
+    }
+
+    /// Whether buffers were opened, edited or closed since the last sync
+    pub fn needs_sync(&self, buffers: &Buffers) -> bool {
+        if !self.context.config.language_servers {
+            return false;
+        }
+        self.documents
+            .keys()
+            .any(|&buffer_id| buffers.get(buffer_id).is_none())
+            || buffers.iter().any(|buffer| {
+                match (self.documents.get(&buffer.id()), self.server_name(buffer)) {
+                    (Some(document), Some(server)) => {
+                        document.server != server
+                            || Some(&document.buffer_path) != buffer.file_path()
+                            || document.version != buffer.version()
+                    }
+                    (Some(_), None) | (None, Some(_)) => true,
+                    (None, None) => false,
+                }
+            })
+    }
+
+    /// Opens the files of new buffers with their mode's server, sends the
+    /// content of edited buffers and closes the files of killed buffers
+    pub fn sync(&mut self, buffers: &Buffers) {
+        let stale: Vec<_> = self
+            .documents
+            .iter()
+            .filter(|(&buffer_id, document)| {
+                buffers.get(buffer_id).map_or(true, |buffer| {
+                    self.server_name(buffer) != Some(document.server.as_str())
+                        || Some(&document.buffer_path) != buffer.file_path()
+                })
+            })
+            .map(|(&buffer_id, _)| buffer_id)
+            .collect();
+        for buffer_id in stale {
+            if let Some(document) = self.documents.remove(&buffer_id) {
+                self.close(document);
+            }
+        }
+
+        for buffer in buffers.iter() {
+            match self.documents.get_mut(&buffer.id()) {
+                Some(document) if document.version != buffer.version() => {
+                    document.version = buffer.version();
+                    if let Some(client) = self.clients.get(&document.server) {
+                        client.did_change(
+                            document.uri.clone(),
+                            document.version,
+                            buffer.edit_tree().staged().clone(),
+                        );
+                    }
+                }
+                Some(_) => {}
+                None => self.open(buffer),
+            }
+        }
+    }
+
+    /// Tells the server that a buffer was written to its file. The buffer
+    /// should be synced first, as saving may have changed its content.
+    pub fn did_save(&self, buffer_id: BufferId) {
+        if let Some((client, document)) = self.client(buffer_id) {
+            client.notify(
+                "textDocument/didSave",
+                json!({"textDocument": {"uri": document.uri}}),
+            );
+        }
+    }
+
+    /// Asks the server for information about the symbol under a cursor, which
+    /// is logged once it arrives together with the diagnostics at the cursor.
+    /// Returns false if the buffer has no server.
+    pub fn hover(&mut self, buffer: &Buffer, cursor_id: CursorId) -> bool {
+        let document = match self.documents.get(&buffer.id()) {
+            Some(document) => document,
+            None => return false,
+        };
+        let client = match self.clients.get_mut(&document.server) {
+            Some(client) => client,
+            None => return false,
+        };
+        let text = buffer.edit_tree().staged();
+        let char_index = buffer.cursor(cursor_id).range().start;
+        let diagnostics = buffer
+            .diagnostics()
+            .iter()
+            .filter(|diagnostic| diagnostic.char_range(text).contains(&char_index))
+            .map(|diagnostic| format!("{}: {}", diagnostic.severity.name(), diagnostic.message))
+            .collect();
+        let id = client.request(
+            "textDocument/hover",
+            json!({
+                "textDocument": {"uri": document.uri},
+                "position": lsp::position(text, char_index),
+            }),
+        );
+        self.hover = Some(HoverRequest {
+            server: document.server.clone(),
+            id,
+            diagnostics,
+        });
+        true
+    }
+
+    pub fn handle_event(&mut self, server: String, event: Event, buffers: &mut Buffers) {
+        match event {
+            Event::Diagnostics {
+                file_path,
+                diagnostics,
+            } => {
+                let file_path = fs::canonicalize(&file_path).unwrap_or(file_path);
+                let buffer_id = self
+                    .documents
+                    .iter()
+                    .find(|(_, document)| {
+                        document.server == server && document.file_path == file_path
+                    })
+                    .map(|(&buffer_id, _)| buffer_id);
+                if let Some(buffer) = buffer_id.and_then(|buffer_id| buffers.get_mut(buffer_id)) {
+                    buffer.set_diagnostics(diagnostics);
+                }
+            }
+            Event::Response { id, result } => {
+                let request = match self.hover.take() {
+                    Some(request) if request.server == server && request.id == id => request,
+                    request => {
+                        self.hover = request;
+                        return;
+                    }
+                };
+                let lines: Vec<_> = request
+                    .diagnostics
+                    .into_iter()
+                    .chain(lsp::hover_lines(&result).into_iter().take(MAX_HOVER_LINES))
+                    .collect();
+                if lines.is_empty() {
+                    self.context.log("No information at the cursor");
+                } else {
+                    self.context.log(lines.join("  "));
+                }
+            }
+            Event::Error(message) => {
+                self.context
+                    .log_error(format!("Language server for {}: {}", server, message));
+            }
+            Event::Exited => {
+                if self.clients.remove(&server).is_none() {
+                    return;
+                }
+                self.context
+                    .log_warning(format!("Language server for {} exited", server));
+                self.documents.retain(|&buffer_id, document| {
+                    if document.server == server {
+                        if let Some(buffer) = buffers.get_mut(buffer_id) {
+                            buffer.set_diagnostics(Vec::new());
+                        }
+                    }
+                    document.server != server
+                });
+                self.failed.insert(server);
+            }
+        }
+    }
+
+    fn open(&mut self, buffer: &Buffer) {
+        let (server, buffer_path) = match (self.server_name(buffer), buffer.file_path()) {
+            (Some(server), Some(buffer_path)) => (server.to_owned(), buffer_path.clone()),
+            _ => return,
+        };
+        let config = match buffer.mode().language_server.as_ref() {
+            Some(config) => config,
+            None => return,
+        };
+        let file_path = self.context.resolve_path(&buffer_path);
+        let file_path = fs::canonicalize(&file_path).unwrap_or(file_path);
+        let uri = match lsp::file_uri(&file_path) {
+            Some(uri) => uri,
+            None => return,
+        };
+
+        if !self.clients.contains_key(&server) {
+            let root = self.root(buffer);
+            let link = self.context.link.clone();
+            let name = server.clone();
+            let on_event =
+                move |event: Event| link.send(Message::LanguageServer(name.clone(), event));
+            match Client::start(config, &root, on_event) {
+                Ok(client) => {
+                    self.clients.insert(server.clone(), client);
+                }
+                Err(error) => {
+                    self.context.log_error(format!(
+                        "Could not start the language server `{}`: {}",
+                        config.command, error
+                    ));
+                    self.failed.insert(server);
+                    return;
+                }
+            }
+        }
+
+        let document = Document {
+            server,
+            buffer_path,
+            file_path,
+            uri,
+            version: buffer.version(),
+        };
+        self.clients[&document.server].notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": document.uri,
+                    "languageId": config.language_id,
+                    "version": document.version,
+                    "text": buffer.edit_tree().staged().to_string(),
+                }
+            }),
+        );
+        self.documents.insert(buffer.id(), document);
+    }
+
+    fn close(&self, document: Document) {
+        if let Some(client) = self.clients.get(&document.server) {
+            client.notify(
+                "textDocument/didClose",
+                json!({"textDocument": {"uri": document.uri}}),
+            );
+        }
+    }
+
+    fn client(&self, buffer_id: BufferId) -> Option<(&Client, &Document)> {
+        let document = self.documents.get(&buffer_id)?;
+        Some((self.clients.get(&document.server)?, document))
+    }
+
+    /// The server for a buffer, if its mode has one which didn't fail and it
+    /// has a file
+    fn server_name<'a>(&self, buffer: &'a Buffer) -> Option<&'a str> {
+        let mode = buffer.mode();
+        (mode.language_server.is_some()
+            && buffer.file_path().is_some()
+            && !buffer.is_hex_view()
+            && !buffer.is_large_file()
+            && !self.failed.contains(&mode.name))
+        .then(|| mode.name.as_str())
+    }
+
+    /// The workspace of a server, the repository of the first file it's
+    /// started for or else the current directory
+    fn root(&self, buffer: &Buffer) -> PathBuf {
+        buffer
+            .repository()
+            .and_then(|repo| repo.0.workdir().map(Path::to_path_buf))
+            .unwrap_or_else(|| self.context.current_working_dir())
+    }
+}
+
+const MAX_HOVER_LINES: usize = 3;
@@ -0,0 +1,126 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// A file larger than the `large_file_threshold`, which isn't read at once.
+/// The buffer starts with the first chunk of the file and the next ones are
+/// appended as the cursor gets close to the end of the text read so far.
+#[derive(Debug)]
+pub struct LargeFile {
+    pub file_path: PathBuf,
+    pub size: u64,
+    // The number of bytes read so far
+    pub loaded: u64,
+    // Whether the next chunk is being read in the background
+    pub loading: bool,
+}
+
+impl LargeFile {
+    pub fn new(file_path: PathBuf, size: u64, loaded: u64) -> Self {
+        Self {
+            file_path,
+            size,
+            loaded,
+            loading: false,
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded >= self.size
+    }
+}
+
+/// Reads the chunk of a file starting at `offset`. Unless the chunk is at the
+/// end of the file, it ends after its last line break so lines and characters
+/// aren't split between chunks. Without a line break, it ends before a UTF-8
+/// character which doesn't fit in it.
+pub fn read_chunk(file_path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(file_path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    file.take(CHUNK_SIZE as u64).read_to_end(&mut chunk)?;
+    if chunk.len() == CHUNK_SIZE {
+        match chunk.iter().rposition(|&byte| byte == b'\n') {
+            Some(end) => chunk.truncate(end + 1),
+            None => chunk.truncate(utf8_boundary(&chunk)),
+        }
+    }
+    Ok(chunk)
+}
+
+/// The length of `bytes` without the incomplete UTF-8 character at its end,
+/// if there's one
+fn utf8_boundary(bytes: &[u8]) -> usize {
+    // A character is at most 4 bytes long, so it starts in the last 4 bytes
+    for start in (bytes.len().saturating_sub(4)..bytes.len()).rev() {
+        let length = match bytes[start] {
+            // A continuation byte in the middle of a character
+            0x80..=0xbf => continue,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xff => 4,
+            _ => 1,
+        };
+        return if start + length > bytes.len() {
+            start
+        } else {
+            bytes.len()
+        };
+    }
+    bytes.len()
+}
+
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// How many lines before the end of the loaded text the next chunk is read
+pub const LOAD_MARGIN_LINES: usize = 1000;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn chunks_end_after_line_breaks() {
+        let dir = TempDir::new("large-file-lines");
+        let mut contents = "a".repeat(CHUNK_SIZE - 10);
+        contents.push('\n');
+        contents.push_str(&"b".repeat(20));
+        let file_path = dir.write("large.txt", &contents);
+
+        let chunk = read_chunk(&file_path, 0).unwrap();
+        assert_eq!(chunk.len(), CHUNK_SIZE - 9);
+        assert_eq!(chunk.last(), Some(&b'\n'));
+        assert_eq!(
+            read_chunk(&file_path, chunk.len() as u64).unwrap(),
+            [b'b'; 20]
+        );
+    }
+
+    #[test]
+    fn chunks_without_line_breaks_dont_split_characters() {
+        let dir = TempDir::new("large-file-characters");
+        // The 3 bytes of `€` straddle the end of the first chunk
+        let contents = format!("{}\u{20ac}b", "a".repeat(CHUNK_SIZE - 1));
+        let file_path = dir.write("large.txt", &contents);
+
+        let first = read_chunk(&file_path, 0).unwrap();
+        assert_eq!(first.len(), CHUNK_SIZE - 1);
+        let second = read_chunk(&file_path, first.len() as u64).unwrap();
+        assert_eq!(second, "\u{20ac}b".as_bytes());
+    }
+
+    #[test]
+    fn find_utf8_boundaries() {
+        assert_eq!(utf8_boundary(b""), 0);
+        assert_eq!(utf8_boundary(b"ab"), 2);
+        assert_eq!(utf8_boundary("a\u{e9}".as_bytes()), 3);
+        assert_eq!(utf8_boundary(&"a\u{e9}".as_bytes()[..2]), 1);
+        assert_eq!(utf8_boundary(&"a\u{1f600}".as_bytes()[..4]), 1);
+        assert_eq!(utf8_boundary("a\u{1f600}".as_bytes()), 5);
+        // Bytes which aren't UTF-8 are left alone
+        assert_eq!(utf8_boundary(b"a\x80\x80\x80\x80"), 5);
+    }
+}
@@ -1 +1,123 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
+/// How important a message shown in the prompt is. A message only replaces
+/// the one in the prompt if it's at least as important.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Warning,
+    /// Errors stay in the prompt until they're dismissed with `ESC` or `C-g`,
+    /// other messages are cleared by the next key press
+    Error,
+}
+
+impl LogLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    pub message: String,
+    pub time: Instant,
+}
+
+/// The most recent messages shown in the prompt
+#[derive(Debug, Default)]
+pub struct MessageLog {
+    entries: VecDeque<LogEntry>,
+}
+
+impl MessageLog {
+    pub fn push(&mut self, level: LogLevel, message: String) {
+        if self.entries.len() == MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry {
+            level,
+            message,
+            time: Instant::now(),
+        });
+    }
+
+    /// The logged messages, the most recent first
+    pub fn iter(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter().rev()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Formats how long ago a message was logged, e.g. `5m ago`
+pub fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    if seconds < 60 {
+        format!("{}s ago", seconds)
+    } else if seconds < 60 * 60 {
+        format!("{}m ago", seconds / 60)
+    } else {
+        format!("{}h ago", seconds / (60 * 60))
+    }
+}
+
+const MAX_LOG_ENTRIES: usize = 100;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_keeps_the_most_recent_messages() {
+        let mut log = MessageLog::default();
+        assert!(log.is_empty());
+
+        log.push(LogLevel::Info, "first".into());
+        log.push(LogLevel::Error, "second".into());
+        assert!(!log.is_empty());
+        let entries: Vec<_> = log
+            .iter()
+            .map(|entry| (entry.level, entry.message.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![(LogLevel::Error, "second"), (LogLevel::Info, "first")]
+        );
+
+        for index in 0..MAX_LOG_ENTRIES {
+            log.push(LogLevel::Warning, index.to_string());
+        }
+        assert_eq!(log.iter().count(), MAX_LOG_ENTRIES);
+        assert_eq!(
+            log.iter().next().unwrap().message,
+            (MAX_LOG_ENTRIES - 1).to_string()
+        );
+        assert_eq!(log.iter().last().unwrap().message, "0");
+    }
+
+    #[test]
+    fn format_ages() {
+        assert_eq!(format_age(Duration::from_millis(500)), "0s ago");
+        assert_eq!(format_age(Duration::from_secs(59)), "59s ago");
+        assert_eq!(format_age(Duration::from_secs(60)), "1m ago");
+        assert_eq!(format_age(Duration::from_secs(60 * 60 - 1)), "59m ago");
+        assert_eq!(format_age(Duration::from_secs(60 * 60)), "1h ago");
+        assert_eq!(format_age(Duration::from_secs(50 * 60 * 60)), "50h ago");
+    }
+
+    #[test]
+    fn errors_are_the_most_important() {
+        assert!(LogLevel::Info < LogLevel::Warning);
+        assert!(LogLevel::Warning < LogLevel::Error);
+    }
+}
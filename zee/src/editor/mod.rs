@@ -1,46 +1,89 @@
 mod bindings;
 pub mod buffer;
+pub mod git_diff;
+mod jumps;
+mod language_servers;
+mod large_file;
+mod logger;
+mod recovery;
+mod watcher;
 mod windows;
 
-pub use self::buffer::{BufferId, ModifiedStatus};
+pub use self::{
+    buffer::{BufferId, ModifiedStatus},
+    logger::LogLevel,
+};
 
 use git2::Repository;
+use parking_lot::RwLock;
 use ropey::Rope;
+use size_format::SizeFormatterBinary;
 use std::{
     borrow::Cow,
+    cell::Cell,
+    env,
     fmt::Display,
-    fs::File,
-    io::{self, BufReader},
+    fs::{self, File},
+    io, iter,
+    ops::Range,
     path::{Path, PathBuf},
+    rc::Rc,
     sync::Arc,
+    time::Instant,
 };
 use zi::{
     Bindings, Callback, Component, ComponentExt, ComponentLink, FlexBasis, FlexDirection, Item,
-    Key, Layout, NamedBindingQuery, Rect, ShouldRender,
+    Key, Layout, NamedBindingQuery, Rect, ShouldRender, Size,
 };
 
+use zee_edit::{
+    movement,
+    search::{Query, SearchMatch},
+    CharIndex, Cursor, Direction,
+};
 use zee_grammar::Mode;
 
 use crate::{
+    browser,
     clipboard::Clipboard,
     components::{
-        buffer::{Buffer as BufferView, Properties as BufferViewProperties},
+        buffer::{
+            bindings::COMMANDS as BUFFER_COMMANDS, Buffer as BufferView,
+            Properties as BufferViewProperties, Theme as BufferTheme,
+        },
         prompt::{
-            buffers::BufferEntry, picker::FileSource, Action as PromptAction, Prompt,
+            buffers::BufferEntry, commands::CommandEntry, grep::GrepMatch, picker::FileSource,
+            replace::ReplaceChoice, Action as PromptAction, Answer, InputHistory, Prompt,
             Properties as PromptProperties, PROMPT_INACTIVE_HEIGHT,
         },
-        splash::{Properties as SplashProperties, Splash},
-        theme::{Theme, THEMES},
+        splash::{KeyHint, Properties as SplashProperties, Splash},
+        tab_bar::{Properties as TabBarProperties, Tab, TabBar},
+        theme::{Theme, MONOCHROME_THEME, THEMES},
     },
-    config::{EditorConfig, PLAIN_TEXT_MODE},
+    config::{self, EditorConfig, PLAIN_TEXT_MODE},
+    editorconfig,
+    encoding::{self, FileEncoding},
     error::Result,
+    lsp,
+    state::{EditorState, FilePosition, SavedLayout, SavedNode},
     task::TaskPool,
+    utils::{
+        canonical_path, normalize_path, parse_line_column, related_files, split_path_position,
+    },
 };
 
 use self::{
-    bindings::KeySequenceSlice,
-    buffer::{BufferCursor, Buffers, BuffersMessage, CursorId, RepositoryRc},
-    windows::{CycleFocus, Window, WindowTree},
+    bindings::{command_keys, KeySequenceSlice, COMMANDS},
+    buffer::{
+        Buffer, BufferCursor, BufferMessage, Buffers, BuffersMessage, CursorId, CursorMessage,
+        RepositoryRc,
+    },
+    jumps::{Jump, JumpList},
+    language_servers::LanguageServers,
+    large_file::LargeFile,
+    logger::{format_age, MessageLog},
+    watcher::FileWatcher,
+    windows::{CycleFocus, Node, Window, WindowTree},
 };
 
 #[derive(Debug)]
@@ -51,6 +94,11 @@ pub enum Message {
     FocusPreviousWindow,
     SplitWindow(FlexDirection),
     FullscreenWindow,
+    ToggleZoomWindow,
+    ResizeWindow(isize),
+    BalanceWindows,
+    ToggleTabs,
+    CycleBuffer(Direction),
 
     // Prompt
     SelectBufferPicker,
@@ -59,13 +107,60 @@ pub enum Message {
     KillBuffer(BufferId),
     OpenFilePicker(FileSource),
     OpenFile(PathBuf),
+    OpenFiles(Vec<PathBuf>),
+    RestoreLayout,
+    OpenFileSplitPicker(FlexDirection),
+    OpenFileSplit(PathBuf, FlexDirection),
+    FindRelatedFile(Option<FlexDirection>),
+    FindFileAtPoint,
+    SaveFileAsPicker,
+    ChangeDirectoryPicker,
+    ChangeDirectory(PathBuf),
+    ChangeDirectoryToRepository,
+    SaveFileAs(BufferId, PathBuf),
+    CommandPalette,
+    RunCommand(usize),
+    RecentFilesPicker,
+    ReopenClosedBuffer,
+    RevertBuffer,
+    GoToLinePicker,
+    GoToLine(String),
+    ReopenWithEncodingPicker,
+    ReopenWithEncoding(String),
+    SetModePicker,
+    SetMode(Option<usize>),
+    FileChanged(PathBuf),
+    LanguageServer(String, lsp::Event),
+    SyncLanguageServers,
+    ShowHover,
+    UpdateGitDiffs,
+    Autosave,
+    OfferRecovery(BufferId),
+    Search(Direction),
+    ClearSearchHighlight,
+    SearchQuery(String),
+    SearchConfirm,
+    GrepPicker,
+    OpenGrepMatch(GrepMatch),
+    Jump(Direction),
+    QueryReplacePicker,
+    QueryReplaceWith(String),
+    QueryReplace(String, String),
+    FilterPicker,
+    FilterThroughCommand(String),
+    PostInteractionReplace(ReplaceChoice),
     ChangePromptHeight(usize),
     Buffer(BuffersMessage),
-    Log(Option<String>),
-    PostInteractionQuit(bool),
+    Log(Option<(LogLevel, String)>),
+    ShowMessages,
+    ClosePrompt,
+    Answer(Answer),
 
     // Global
-    ChangeTheme,
+    ChangeTheme(Direction),
+    ThemePicker,
+    PreviewTheme(usize),
+    SetTheme(usize),
     Cancel,
     Quit,
 }
@@ -79,18 +174,46 @@ impl From<BuffersMessage> for Message {
 pub struct Properties {
     pub args_files: Vec<PathBuf>,
     pub current_working_dir: PathBuf,
+    pub config_dir: Option<PathBuf>,
     pub config: EditorConfig,
     pub task_pool: TaskPool,
     pub clipboard: Arc<dyn Clipboard>,
+    /// Set when the editor quits, read once the UI loop returns
+    pub exit_status: Rc<Cell<ExitStatus>>,
+}
+
+/// How the editor exited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Quit with the `quit` command, after asking about unsaved changes
+    Quit,
+    /// The UI loop was stopped with `C-c` without asking about unsaved
+    /// changes, e.g. because the editor stopped responding
+    Interrupted,
+}
+
+impl ExitStatus {
+    /// The exit code of the process, 130 for an interrupt like shells do
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Quit => 0,
+            Self::Interrupted => 130,
+        }
+    }
 }
 
 pub struct Context {
     pub args_files: Vec<PathBuf>,
-    pub current_working_dir: PathBuf,
+    // Relative paths are resolved against it. It starts out as the directory
+    // zee was started in and can be changed while editing.
+    current_working_dir: RwLock<PathBuf>,
+    pub config_dir: Option<PathBuf>,
     pub config: EditorConfig,
     pub modes: Vec<Mode>,
     pub task_pool: TaskPool,
     pub clipboard: Arc<dyn Clipboard>,
+    // What was entered in the prompts, e.g. search queries
+    pub input_history: InputHistory,
     pub link: ComponentLink<Editor>,
 }
 
@@ -101,6 +224,22 @@ impl Context {
             .find(|&mode| mode.matches_by_filename(filename.as_ref()))
             .unwrap_or(&PLAIN_TEXT_MODE)
     }
+
+    /// The mode for a file, by its name or else by the interpreter in a `#!`
+    /// first line
+    pub fn detect_mode(&self, file_path: Option<&Path>, text: &Rope) -> &Mode {
+        let mode = file_path
+            .map(|path| self.mode_by_filename(path))
+            .unwrap_or(&PLAIN_TEXT_MODE);
+        if !std::ptr::eq(mode, &*PLAIN_TEXT_MODE) || text.len_lines() == 0 {
+            return mode;
+        }
+        let first_line = text.line(0).to_string();
+        self.modes
+            .iter()
+            .find(|&mode| mode.matches_by_shebang(first_line.trim_end()))
+            .unwrap_or(&PLAIN_TEXT_MODE)
+    }
 }
 
 #[derive(Clone)]
@@ -115,8 +254,42 @@ impl std::ops::Deref for ContextHandle {
 }
 
 impl Context {
+    pub fn current_working_dir(&self) -> PathBuf {
+        self.current_working_dir.read().clone()
+    }
+
+    /// Changes the working directory, also of the process so that commands
+    /// run from the editor and language servers started later use it
+    pub fn set_current_working_dir(&self, directory: PathBuf) -> Result<()> {
+        env::set_current_dir(&directory)?;
+        *self.current_working_dir.write() = directory;
+        Ok(())
+    }
+
+    /// An absolute path, with relative paths resolved against the working
+    /// directory
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            normalize_path(&self.current_working_dir.read().join(path))
+        }
+    }
+
     pub fn log(&self, message: impl Into<String>) {
-        self.link.send(Message::Log(Some(message.into())));
+        self.link
+            .send(Message::Log(Some((LogLevel::Info, message.into()))));
+    }
+
+    pub fn log_warning(&self, message: impl Into<String>) {
+        self.link
+            .send(Message::Log(Some((LogLevel::Warning, message.into()))));
+    }
+
+    /// Logs an error, which stays in the prompt until it's dismissed
+    pub fn log_error(&self, message: impl Into<String>) {
+        self.link
+            .send(Message::Log(Some((LogLevel::Error, message.into()))));
     }
 }
 
@@ -124,17 +297,67 @@ pub struct Editor {
     context: ContextHandle,
     themes: &'static [(Theme, &'static str)],
     theme_index: usize,
+    // The theme to go back to if the theme picker is cancelled
+    theme_before_preview: Option<usize>,
+    // A buffer command picked in the command palette, run by the focused
+    // buffer when it's drawn next
+    palette_command: Rc<Cell<Option<usize>>>,
+    state: EditorState,
+    exit_status: Rc<Cell<ExitStatus>>,
 
     prompt_action: PromptAction,
     prompt_height: usize,
 
     buffers: Buffers,
     windows: WindowTree<BufferViewId>,
+    watcher: FileWatcher,
+    language_servers: LanguageServers,
+    frame: Rect,
+
+    // Show only the focused window with a tab bar listing the buffers
+    tabbed: bool,
+
+    // When modified buffers were last written to their recovery files
+    last_autosave: Instant,
+
+    // Buffer to kill once it finished saving
+    kill_buffer_after_save: Option<BufferId>,
+
+    // Recently killed buffers, the most recent last
+    closed_buffers: Vec<ClosedBuffer>,
+
+    // Positions before large jumps of the cursor
+    jumps: JumpList,
+
+    // Messages shown in the prompt
+    messages: MessageLog,
+
+    // Action waiting for the answer to a question in the prompt
+    pending_action: Option<PendingAction>,
+
+    // Incremental search in progress
+    search: Option<SearchState>,
+
+    // Query replace in progress
+    replace: Option<ReplaceState>,
 }
 
 impl Editor {
-    #[inline]
+    /// Shows a buffer in the focused window, remembering the previous position
+    /// in the jump list
     fn focus_on_buffer(&mut self, buffer_id: BufferId) {
+        if self
+            .windows
+            .get_focused()
+            .map_or(false, |view| view.buffer_id != buffer_id)
+        {
+            self.record_jump();
+        }
+        self.show_buffer(buffer_id);
+    }
+
+    #[inline]
+    fn show_buffer(&mut self, buffer_id: BufferId) {
         if self.windows.is_empty() {
             self.windows
                 .add(BufferViewId::new(buffer_id, CursorId::default()));
@@ -145,17 +368,89 @@ impl Editor {
     }
 
     fn open_file(&mut self, file_path: PathBuf) -> Result<bool> {
-        // Check if the buffer is already open
-        if let Some(buffer_id) = self.buffers.find_by_path(&file_path) {
+        let file_path = self.context.resolve_path(&file_path);
+
+        // Open `path:line:column` at the position, unless a file with that
+        // name exists
+        if !file_path.exists() {
+            if let Some((file_path, line, column)) = split_path_position(&file_path) {
+                let is_new_file = self.open_file(file_path)?;
+                self.go_to_line(line, column.unwrap_or(0));
+                return Ok(is_new_file);
+            }
+        }
+
+        // A broken link would otherwise be opened as a new file, and saving
+        // it would replace the link
+        if !file_path.exists() {
+            if let Ok(target) = fs::read_link(&file_path) {
+                return Err(anyhow::anyhow!(
+                    "{} is a broken symbolic link to {}",
+                    file_path.display(),
+                    target.display()
+                ));
+            }
+        }
+
+        if let Some(buffer_id) = self.find_open_buffer(&file_path) {
+            self.remember_recent_file(&file_path);
             self.focus_on_buffer(buffer_id);
             return Ok(false);
         }
 
-        let (is_new_file, text) = if file_path.exists() {
-            (
-                false,
-                Rope::from_reader(BufReader::new(File::open(&file_path)?))?,
-            )
+        let editorconfig = self.find_editorconfig(&file_path);
+        let mut read_only = false;
+        let mut hex_view = false;
+        let mut large_file = None;
+        let file_size = fs::metadata(&file_path).map_or(0, |metadata| metadata.len());
+        let (is_new_file, text, encoding) = if file_size > self.context.config.large_file_threshold
+        {
+            let chunk = large_file::read_chunk(&file_path, 0)?;
+            if encoding::is_binary(&chunk) {
+                return Err(anyhow::anyhow!(
+                    "{} is a binary file too large to show ({})",
+                    file_path.display(),
+                    SizeFormatterBinary::new(file_size)
+                ));
+            }
+            self.context.log(format!(
+                "Large file ({}), it's read-only and loaded as you scroll. Searches only cover \
+                 the loaded text",
+                SizeFormatterBinary::new(file_size)
+            ));
+            large_file = Some(LargeFile::new(
+                file_path.clone(),
+                file_size,
+                chunk.len() as u64,
+            ));
+            let text = Rope::from(String::from_utf8_lossy(&chunk).as_ref());
+            (false, text, FileEncoding::UTF8)
+        } else if file_path.exists() {
+            let bytes = fs::read(&file_path)?;
+            // Rendering raw bytes could mess up the terminal
+            hex_view = encoding::is_binary(&bytes);
+            let decoded = if hex_view {
+                self.context
+                    .log("Binary file, showing a read-only hex dump");
+                encoding::hex_dump(&bytes)
+            } else {
+                decode_text(&bytes, editorconfig.charset)
+            };
+            if decoded.had_errors {
+                self.context.log(format!(
+                    "Some bytes are not valid {} and were replaced",
+                    decoded.encoding
+                ));
+            }
+            // Protect files we can't write to from accidental edits
+            if let Err(error) = fs::OpenOptions::new().write(true).open(&file_path) {
+                if error.kind() == io::ErrorKind::PermissionDenied {
+                    self.context
+                        .log("Buffer is read-only, no permission to write the file");
+                    read_only = true;
+                }
+            }
+            (false, decoded.text, decoded.encoding)
         } else {
             // Optimistically check if we can create it
             let is_new_file = File::open(&file_path)
@@ -181,267 +476,2212 @@ impl Editor {
                         Err(error)
                     }
                 })?;
-            (is_new_file, Rope::new())
+            let encoding = editorconfig.charset.unwrap_or(FileEncoding::UTF8);
+            (is_new_file, Rope::new(), encoding)
         };
 
-        let repo = Repository::discover(&file_path).ok().map(RepositoryRc::new);
+        // Diffing a large file with the git index would read all of it
+        let repo = match large_file {
+            Some(_) => None,
+            None => Repository::discover(&file_path).ok().map(RepositoryRc::new),
+        };
+        self.remember_recent_file(&file_path);
+        self.watcher.watch(&file_path);
 
-        // Store the new buffer
-        let buffer_id = self.buffers.add(text, Some(file_path), repo);
+        // Store the new buffer, with the cursor where it was when the file
+        // was last closed
+        let saved_position = self
+            .state
+            .file_position(&canonical_path(&file_path))
+            .map(|position| (position.line, position.column));
+        let buffer_id = self.buffers.add(text, encoding, Some(file_path), repo);
+        if let Some(buffer) = self.buffers.get_mut(buffer_id) {
+            buffer.set_read_only(read_only);
+            buffer.apply_editorconfig(editorconfig);
+            if hex_view {
+                buffer.set_hex_view();
+            }
+            if let Some(large_file) = large_file {
+                buffer.set_large_file(large_file);
+            }
+            if let Some((line, column)) = saved_position {
+                let mut cursor = Cursor::new();
+                movement::move_to_line(buffer.edit_tree(), &mut cursor, line, column);
+                buffer.set_cursor(CursorId::default(), cursor);
+            }
+        }
 
         // Focus on the new buffer
         self.focus_on_buffer(buffer_id);
+        // Asked once the prompt which opened the file has been closed
+        self.context.link.send(Message::OfferRecovery(buffer_id));
 
         Ok(is_new_file)
     }
 
-    fn open_buffer_picker(&mut self, message: Cow<'static, str>, on_select: Callback<BufferId>) {
-        self.prompt_action = PromptAction::PickBuffer {
-            message,
-            entries: self
-                .buffers
-                .iter()
-                .map(|buffer| {
-                    BufferEntry::new(
-                        buffer.id(),
-                        buffer.file_path().cloned(),
-                        false,
-                        buffer.edit_tree().len_bytes(),
-                        buffer.mode(),
-                    )
-                })
-                .collect(),
-            on_select,
-            on_change_height: self.context.link.callback(Message::ChangePromptHeight),
-        };
-        self.prompt_height = self.prompt_action.initial_height();
+    /// The `.editorconfig` settings covering a file, if they're enabled
+    fn find_editorconfig(&self, file_path: &Path) -> editorconfig::Settings {
+        if self.context.config.editorconfig {
+            editorconfig::Settings::find(file_path)
+        } else {
+            editorconfig::Settings::default()
+        }
     }
-}
 
-impl Component for Editor {
-    type Message = Message;
-    type Properties = Properties;
+    /// Asks whether to restore the unsaved changes of a newly opened file, if
+    /// an earlier session autosaved them and exited without saving the file
+    fn offer_recovery(&mut self, buffer_id: BufferId) {
+        let (config_dir, buffer) = match (
+            self.context.config_dir.as_deref(),
+            self.buffers.get(buffer_id),
+        ) {
+            (Some(config_dir), Some(buffer)) if !buffer.is_read_only() => (config_dir, buffer),
+            _ => return,
+        };
+        let file_path = match buffer.file_path() {
+            Some(file_path) => file_path.clone(),
+            None => return,
+        };
+        let recovery_path = recovery::file_path(config_dir, &file_path);
+        match recovery::read(&recovery_path, &file_path) {
+            // Written just before the file was saved
+            Some(text) if text == **buffer.edit_tree() => recovery::remove(&recovery_path),
+            Some(_) if !self.prompt_action.is_interactive() => self.confirm(
+                format!(
+                    "{} has unsaved changes from a previous session. Restore them?",
+                    file_path.display()
+                ),
+                true,
+                PendingAction::Recover(buffer_id, recovery_path),
+            ),
+            _ => {}
+        }
+    }
 
-    fn create(properties: Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
-        for (index, file_path) in properties.args_files.iter().cloned().enumerate() {
-            link.send(Message::OpenFile(file_path));
-            if index < properties.args_files.len().saturating_sub(1) {
-                link.send(Message::SplitWindow(FlexDirection::Row));
+    fn recover_buffer(&mut self, buffer_id: BufferId, recovery_path: &Path) {
+        let buffer = match self.buffers.get_mut(buffer_id) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let file_path = buffer.file_path().cloned().unwrap_or_default();
+        match recovery::read(recovery_path, &file_path) {
+            Some(text) => {
+                // Undo goes back to the content of the file
+                buffer.restore(text);
+                self.context.log(format!(
+                    "Restored the unsaved changes to {}",
+                    file_path.display()
+                ));
             }
+            None => self.context.log_error(format!(
+                "Could not read recovery file {}",
+                recovery_path.display()
+            )),
         }
+    }
 
-        let theme_name = properties.config.theme.clone();
-        let context = ContextHandle(Box::leak(
-            Context {
-                args_files: properties.args_files,
-                current_working_dir: properties.current_working_dir,
-                modes: properties
-                    .config
-                    .modes
-                    .iter()
-                    .cloned()
-                    .map(Mode::new)
-                    .collect(),
-                config: properties.config,
-                task_pool: properties.task_pool,
-                clipboard: properties.clipboard,
-                link,
-            }
-            .into(),
-        ));
+    /// Whether a modified buffer is waiting to be written to its recovery file
+    fn is_autosave_pending(&self) -> bool {
+        recovery::interval(self.context.config.autosave_interval).is_some()
+            && self.buffers.iter().any(Buffer::needs_autosave)
+    }
+
+    fn is_autosave_due(&self) -> bool {
+        self.is_autosave_pending()
+            && recovery::interval(self.context.config.autosave_interval)
+                .map_or(false, |interval| self.last_autosave.elapsed() >= interval)
+    }
+
+    /// Shows the next or previous buffer in the focused window, in the order
+    /// they were opened
+    fn cycle_buffer(&mut self, direction: Direction) {
+        let focused_buffer_id = match self.windows.get_focused() {
+            Some(view) => view.buffer_id,
+            None => return,
+        };
+        let buffer_ids: Vec<_> = self.buffers.iter().map(Buffer::id).collect();
+        let index = match buffer_ids.iter().position(|&id| id == focused_buffer_id) {
+            Some(index) => index,
+            None => return,
+        };
+        let new_index = match direction {
+            Direction::Forward => (index + 1) % buffer_ids.len(),
+            Direction::Backward => (index + buffer_ids.len() - 1) % buffer_ids.len(),
+        };
+        self.focus_on_buffer(buffer_ids[new_index]);
+    }
 
-        let theme_index = {
-            let theme = THEMES.iter().position(|(_, name)| *name == theme_name);
-            if theme.is_none() {
-                context.log(format!("Unknown theme `{}`", theme_name));
+    /// Moves the cursor of the focused window to a zero-based line and column
+    fn go_to_line(&mut self, line: usize, column: usize) {
+        self.record_jump();
+        if let Some(view) = self.windows.get_focused() {
+            if let Some(buffer) = self.buffers.get_mut(view.buffer_id) {
+                buffer.handle_message(BufferMessage::CursorMessage {
+                    cursor_id: view.cursor_id,
+                    message: CursorMessage::GoToLine { line, column },
+                });
             }
-            theme
         }
-        .unwrap_or(0);
+    }
 
-        Self {
-            themes: &THEMES,
-            theme_index,
-            prompt_action: PromptAction::None,
-            prompt_height: PROMPT_INACTIVE_HEIGHT,
-            buffers: Buffers::new(context.clone()),
-            context,
-            windows: WindowTree::new(),
-        }
+    /// Asks a yes or no question in the prompt, running `action` once it's
+    /// answered. A cancellable question can also be dismissed without doing
+    /// anything.
+    fn confirm(
+        &mut self,
+        message: impl Into<Cow<'static, str>>,
+        cancellable: bool,
+        action: PendingAction,
+    ) {
+        self.pending_action = Some(action);
+        self.prompt_action = PromptAction::InteractiveMessage {
+            message: message.into(),
+            cancellable,
+            on_answer: self.context.link.callback(Message::Answer),
+        };
+        self.prompt_height = self.prompt_action.initial_height();
     }
 
-    fn update(&mut self, message: Self::Message) -> ShouldRender {
-        match message {
-            Message::Cancel => {
-                self.prompt_action = PromptAction::None;
-                self.prompt_height = self.prompt_action.initial_height();
-                self.context.log("Cancel");
-            }
-            Message::ChangeTheme => {
-                self.theme_index = (self.theme_index + 1) % self.themes.len();
-                if !self.prompt_action.is_interactive() {
-                    self.context.log(format!(
-                        "Theme changed to {}",
-                        self.themes[self.theme_index].1
-                    ));
+    fn resolve_pending_action(&mut self, answer: Answer) {
+        self.prompt_action = PromptAction::None;
+        self.prompt_height = self.prompt_action.initial_height();
+        let action = match self.pending_action.take() {
+            Some(action) => action,
+            None => return,
+        };
+        match (action, answer) {
+            (PendingAction::CreateParentDirectory(buffer_id, file_path), answer) => {
+                if answer == Answer::Yes {
+                    self.create_parent_directory_and_save(buffer_id, file_path);
+                } else if self.kill_buffer_after_save == Some(buffer_id) {
+                    // The buffer is kept, as it couldn't be saved
+                    self.kill_buffer_after_save = None;
                 }
             }
-            Message::OpenFilePicker(source) if !self.prompt_action.is_interactive() => {
-                self.prompt_action = PromptAction::OpenFile {
-                    source,
-                    on_open: self.context.link.callback(Message::OpenFile),
-                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
-                };
-                self.prompt_height = self.prompt_action.initial_height();
+            (_, Answer::Cancel) => self.context.log("Cancel"),
+            (PendingAction::Quit, Answer::Yes) => self.quit(),
+            (PendingAction::KillBuffer(buffer_id), Answer::Yes) => {
+                // Kill the buffer once it's saved
+                self.kill_buffer_after_save = Some(buffer_id);
+                self.context
+                    .link
+                    .send(BuffersMessage::new(buffer_id, BufferMessage::SaveBufferStart).into());
             }
-            Message::OpenFile(path) => {
-                self.prompt_action = self.open_file(path).map_or_else(
-                    |error| PromptAction::Log {
-                        message: format!("Could not open file: {}", error),
-                    },
-                    |new_file| {
-                        if new_file {
-                            PromptAction::Log {
-                                message: "[New file]".into(),
-                            }
-                        } else {
-                            PromptAction::None
-                        }
-                    },
-                );
-                self.prompt_height = self.prompt_action.initial_height();
+            (PendingAction::KillBuffer(buffer_id), Answer::No) => self.kill_buffer(buffer_id),
+            (PendingAction::SaveFileAs(buffer_id, file_path), Answer::Yes) => {
+                self.save_file_as(buffer_id, file_path)
             }
-            Message::SelectBufferPicker if !self.prompt_action.is_interactive() => {
-                self.open_buffer_picker(
-                    "buffer".into(),
-                    self.context.link.callback(Message::SelectBuffer),
-                );
+            (PendingAction::Reload(buffer_id), Answer::Yes) => self.reload_buffer(buffer_id),
+            (PendingAction::Revert(buffer_id), Answer::Yes) => self.revert_buffer(buffer_id),
+            (PendingAction::CreateRelatedFile(file_path, direction), Answer::Yes) => {
+                self.open_related_file(file_path, direction)
             }
-            Message::SelectBuffer(buffer_id) => {
-                self.prompt_action = PromptAction::None;
-                self.prompt_height = self.prompt_action.initial_height();
-                self.focus_on_buffer(buffer_id);
+            (PendingAction::Recover(buffer_id, recovery_path), Answer::Yes) => {
+                self.recover_buffer(buffer_id, &recovery_path)
             }
-            Message::KillBufferPicker if !self.prompt_action.is_interactive() => {
-                self.open_buffer_picker(
-                    "kill buffer".into(),
-                    self.context.link.callback(Message::KillBuffer),
-                );
+            (PendingAction::Recover(_, recovery_path), Answer::No) => {
+                recovery::remove(&recovery_path)
             }
-            Message::KillBuffer(buffer_id) => {
-                self.prompt_action = PromptAction::None;
-                self.prompt_height = self.prompt_action.initial_height();
-                let removed_buffer = self.buffers.remove(buffer_id);
-                debug_assert!(removed_buffer.is_some());
-                if self.buffers.is_empty() {
-                    self.windows.clear();
-                } else {
-                    let some_buffer = self.buffers.iter_mut().next().unwrap();
-                    self.windows.nodes_mut().for_each(|view_id| {
-                        if view_id.buffer_id == buffer_id {
-                            *view_id =
-                                BufferViewId::new(some_buffer.id(), some_buffer.new_cursor());
-                        }
-                    });
-                }
+            (_, Answer::No) => {}
+        }
+    }
+
+    /// Opens the first existing file related to the focused buffer's file,
+    /// e.g. its header, or offers to create the first candidate if none exist
+    fn find_related_file(&mut self, direction: Option<FlexDirection>) {
+        let file_path = match self
+            .windows
+            .get_focused()
+            .and_then(|view| self.buffers.get(view.buffer_id))
+            .and_then(Buffer::file_path)
+        {
+            Some(file_path) => file_path.clone(),
+            None => {
+                self.context.log("The buffer isn't visiting a file");
+                return;
             }
-            Message::ChangePromptHeight(height) => {
-                self.prompt_height = height;
+        };
+        let candidates = related_files(&file_path, &self.context.config.related_files);
+        if let Some(related_path) = candidates.iter().find(|path| path.exists()) {
+            self.open_related_file(related_path.clone(), direction);
+        } else if let Some(related_path) = candidates.into_iter().next() {
+            self.confirm(
+                format!("{} doesn't exist. Create it?", related_path.display()),
+                true,
+                PendingAction::CreateRelatedFile(related_path, direction),
+            );
+        } else {
+            self.context.log(format!(
+                "No related files configured for {}",
+                file_path.display()
+            ));
+        }
+    }
+
+    /// Opens the file path or URL under the focused window's cursor. Relative
+    /// paths are looked up next to the buffer's file first, then in the
+    /// working directory, and a `:line:column` suffix moves the cursor there.
+    /// URLs are opened in the web browser.
+    fn find_file_at_point(&mut self) {
+        let (token, buffer_dir) = match self.windows.get_focused().and_then(|view| {
+            let buffer = self.buffers.get(view.buffer_id)?;
+            let text = buffer.edit_tree();
+            let range = movement::path_at(text, buffer.cursor(view.cursor_id).range().start)?;
+            let buffer_dir = buffer
+                .file_path()
+                .and_then(|path| path.parent())
+                .map(Path::to_path_buf);
+            Some((text.slice(range).to_string(), buffer_dir))
+        }) {
+            Some(found) => found,
+            None => {
+                self.context.log("No file or URL at the cursor");
+                return;
             }
-            Message::FocusNextWindow => self.windows.cycle_focus(CycleFocus::Next),
-            Message::FocusPreviousWindow => self.windows.cycle_focus(CycleFocus::Previous),
-            Message::SplitWindow(direction) if !self.buffers.is_empty() => {
-                if let Some(view_id) = self.windows.get_focused() {
-                    let buffer = self.buffers.get_mut(view_id.buffer_id).unwrap();
-                    self.windows.insert_at_focused(
-                        BufferViewId::new(
-                            view_id.buffer_id,
-                            buffer.duplicate_cursor(view_id.cursor_id),
-                        ),
-                        direction,
-                    );
-                }
+        };
+
+        if let Some(url) = browser::parse_url(&token) {
+            match browser::open(&url) {
+                Ok(()) => self.context.log(format!("Opening {}", url)),
+                Err(error) => self
+                    .context
+                    .log_error(format!("Could not open {}: {}", url, error)),
             }
-            Message::FullscreenWindow if !self.buffers.is_empty() => {
-                self.windows.delete_all_except_focused();
+            return;
+        }
+
+        let path = match (token.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home_dir)) => home_dir.join(rest),
+            _ => PathBuf::from(&token),
+        };
+        let exists = |path: &Path| {
+            path.exists() || split_path_position(path).map_or(false, |(path, _, _)| path.exists())
+        };
+        let mut candidates = buffer_dir
+            .filter(|_| path.is_relative())
+            .map(|buffer_dir| normalize_path(&buffer_dir.join(&path)))
+            .into_iter()
+            .chain(Some(self.context.resolve_path(&path)));
+        match candidates.find(|candidate| exists(candidate)) {
+            Some(file_path) => {
+                let result = self.open_file(file_path);
+                self.report_open_file(result);
             }
-            Message::DeleteWindow if !self.buffers.is_empty() => {
-                self.windows.delete_focused();
+            None => self.context.log(format!("No such file: {}", token)),
+        }
+    }
+
+    /// Opens a file in a new window split off the focused one, which is
+    /// removed again if the file can't be opened
+    /// The buffer of a file if it's already open, possibly through a different
+    /// path to the same file
+    fn find_open_buffer(&self, file_path: &Path) -> Option<BufferId> {
+        let canonical_file_path = canonical_path(file_path);
+        self.buffers.find_by_path(file_path).or_else(|| {
+            self.buffers
+                .iter()
+                .find(|buffer| {
+                    buffer.file_path().map(|path| canonical_path(path)).as_ref()
+                        == Some(&canonical_file_path)
+                })
+                .map(Buffer::id)
+        })
+    }
+
+    fn open_file_split(&mut self, file_path: PathBuf, direction: FlexDirection) -> Result<bool> {
+        // Move the focus to the new window, which will show the opened file
+        let split = self.split_focused_window(direction);
+        if split {
+            self.windows.cycle_focus(CycleFocus::Next);
+        }
+        let result = self.open_file(file_path);
+        if split && result.is_err() {
+            self.windows.delete_focused();
+        }
+        result
+    }
+
+    /// Opens each file in its own window, e.g. the files given on the command
+    /// line, and focuses the first one. Files which can't be opened are
+    /// logged and skipped.
+    fn open_files(&mut self, file_paths: impl IntoIterator<Item = PathBuf>) {
+        let mut first_window = None;
+        for file_path in file_paths {
+            // Directories are browsed with the file picker
+            if file_path.is_dir() {
+                self.context.link.send(Message::OpenFile(file_path));
+                continue;
             }
-            Message::Log(message) if !self.prompt_action.is_interactive() => {
-                self.prompt_action = message
-                    .map(|message| PromptAction::Log { message })
-                    .unwrap_or(PromptAction::None);
-                self.prompt_height = self.prompt_action.initial_height();
+            let result = if first_window.is_none() {
+                self.open_file(file_path)
+            } else {
+                self.open_file_split(file_path, FlexDirection::Row)
+            };
+            if result.is_ok() && first_window.is_none() {
+                first_window = self.windows.get_focused();
             }
+            self.report_open_file(result);
+        }
+        if let Some(view_id) = first_window {
+            self.windows.set_focused(view_id);
+        }
+    }
 
-            // Quit zee but prompt to save changed buffers first
-            Message::Quit => {
-                if self
-                    .buffers
-                    .iter()
-                    .any(|buffer| buffer.modified_status() != ModifiedStatus::Unchanged)
-                {
-                    let message = "One or more buffers have changed. Exit anyway?";
-                    self.prompt_action = PromptAction::InteractiveMessage {
-                        on_input: self.context.link.callback(Message::PostInteractionQuit),
-                        message: Cow::from(message),
-                    };
-                    self.prompt_height = self.prompt_action.initial_height();
-                } else {
-                    self.context.link.exit();
-                }
-            }
+    fn open_related_file(&mut self, file_path: PathBuf, direction: Option<FlexDirection>) {
+        self.context.link.send(match direction {
+            Some(direction) => Message::OpenFileSplit(file_path, direction),
+            None => Message::OpenFile(file_path),
+        });
+    }
 
-            // Quit regardless of the buffer modify status
-            Message::PostInteractionQuit(quit_anyway) => {
-                if quit_anyway {
-                    self.context.link.exit();
-                } else {
-                    self.prompt_action = PromptAction::None;
-                    self.prompt_height = self.prompt_action.initial_height();
-                }
-            }
-            Message::Buffer(message) => self.buffers.handle_message(message),
-            _ => {}
+    /// Closes the prompt after opening a file, logging whether the file is new
+    /// or why it couldn't be opened
+    fn report_open_file(&mut self, result: Result<bool>) {
+        self.prompt_action = PromptAction::None;
+        self.prompt_height = self.prompt_action.initial_height();
+        match result {
+            Ok(true) => self.context.log("[New file]"),
+            Ok(false) => {}
+            Err(error) => self
+                .context
+                .log_error(format!("Could not open file: {}", error)),
         }
-        ShouldRender::Yes
     }
 
-    fn view(&self) -> Layout {
-        let buffers = if self.windows.is_empty() {
-            Splash::item_with_key(
-                FlexBasis::Auto,
-                "splash",
+    /// The position of the focused window's cursor
+    fn current_position(&self) -> Option<Jump> {
+        let view = self.windows.get_focused()?;
+        let buffer = self.buffers.get(view.buffer_id)?;
+        Some(Jump {
+            buffer_id: view.buffer_id,
+            char_index: buffer.cursor(view.cursor_id).range().start,
+        })
+    }
+
+    /// Remembers the position of the focused window's cursor before it jumps
+    fn record_jump(&mut self) {
+        if let Some(position) = self.current_position() {
+            self.jumps.push(position);
+        }
+    }
+
+    /// Returns to the previous or next position in the jump list, skipping
+    /// positions in buffers that were killed since
+    fn jump(&mut self, direction: Direction) {
+        let current = match self.current_position() {
+            Some(position) => position,
+            None => return,
+        };
+        loop {
+            let jump = match direction {
+                Direction::Backward => self.jumps.back(current),
+                Direction::Forward => self.jumps.forward(),
+            };
+            let jump = match jump {
+                Some(jump) => jump,
+                None => {
+                    self.context.log(match direction {
+                        Direction::Backward => "No earlier position",
+                        Direction::Forward => "No later position",
+                    });
+                    return;
+                }
+            };
+            if self.buffers.get(jump.buffer_id).is_none() {
+                continue;
+            }
+
+            self.show_buffer(jump.buffer_id);
+            if let Some(view) = self.windows.get_focused() {
+                if let Some(buffer) = self.buffers.get_mut(view.buffer_id) {
+                    buffer.handle_message(BufferMessage::CursorMessage {
+                        cursor_id: view.cursor_id,
+                        message: CursorMessage::GoToChar(jump.char_index),
+                    });
+                }
+            }
+            return;
+        }
+    }
+
+    /// The working directory of the focused buffer's repository, falling back
+    /// to the repository containing the current working directory
+    fn repository_root(&self) -> Option<PathBuf> {
+        self.windows
+            .get_focused()
+            .and_then(|view| self.buffers.get(view.buffer_id))
+            .and_then(|buffer| buffer.repository())
+            .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+            .or_else(|| {
+                Repository::discover(self.context.current_working_dir())
+                    .ok()
+                    .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+            })
+    }
+
+    /// Changes the working directory, which relative paths, the file picker
+    /// and grep start from
+    fn change_directory(&mut self, directory: PathBuf) {
+        let directory = self.context.resolve_path(&directory);
+        if !directory.is_dir() {
+            self.context
+                .log_error(format!("{} is not a directory", directory.display()));
+            return;
+        }
+        match self.context.set_current_working_dir(directory) {
+            Ok(()) => self.context.log(format!(
+                "Working directory is {}",
+                self.context.current_working_dir().display()
+            )),
+            Err(error) => self
+                .context
+                .log_error(format!("Could not change the working directory: {}", error)),
+        }
+    }
+
+    /// Decodes the file of the focused buffer again, using the encoding with
+    /// the given label
+    fn reopen_with_encoding(&mut self, label: &str) {
+        let encoding = match encoding::for_label(label) {
+            Some(encoding) => encoding,
+            None => {
+                self.context
+                    .log(format!("Unknown encoding `{}`", label.trim()));
+                return;
+            }
+        };
+        let buffer = match self
+            .windows
+            .get_focused()
+            .and_then(|view| self.buffers.get_mut(view.buffer_id))
+        {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let file_path = match buffer.file_path() {
+            Some(file_path) => file_path.clone(),
+            None => {
+                self.context.log("The buffer is not backed by a file");
+                return;
+            }
+        };
+        if buffer.is_modified() {
+            self.context
+                .log("Save the buffer before reopening it with a different encoding");
+            return;
+        }
+        if buffer.is_hex_view() {
+            self.context.log("Binary files are shown as a hex dump");
+            return;
+        }
+
+        match fs::read(&file_path) {
+            Ok(bytes) => {
+                let decoded = encoding::decode_with(&bytes, encoding);
+                self.context.log(if decoded.had_errors {
+                    format!(
+                        "Reopened as {}, some bytes were not valid and were replaced",
+                        decoded.encoding
+                    )
+                } else {
+                    format!("Reopened as {}", decoded.encoding)
+                });
+                buffer.reload(decoded.text, decoded.encoding);
+            }
+            Err(error) => self.context.log(format!(
+                "Could not open {} ({})",
+                file_path.display(),
+                error
+            )),
+        }
+    }
+
+    /// Shows the diagnostics and the language server's information about the
+    /// symbol under the cursor
+    fn show_hover(&mut self) {
+        let view = match self.windows.get_focused() {
+            Some(view) => view,
+            None => return,
+        };
+        let requested = self.buffers.get(view.buffer_id).map_or(false, |buffer| {
+            self.language_servers.hover(buffer, view.cursor_id)
+        });
+        if !requested {
+            self.context.log("No language server for the buffer");
+        }
+    }
+
+    /// Handles a change to a file made by another program. Unmodified buffers
+    /// are reloaded, otherwise the user is asked whether to discard the changes.
+    fn file_changed(&mut self, path: &Path) -> ShouldRender {
+        let buffer = match self.buffers.iter().find(|buffer| {
+            buffer
+                .file_path()
+                .and_then(|file_path| watcher::canonical_path(file_path))
+                == Some(path.to_path_buf())
+        }) {
+            Some(buffer) => buffer,
+            None => return ShouldRender::No,
+        };
+        // Our own writes are reported too, they're ignored as the content on
+        // disk is the same as the buffer's
+        if buffer.modified_status() == ModifiedStatus::Saving {
+            return ShouldRender::No;
+        }
+        let decoded = match read_buffer_file(buffer, path) {
+            Ok(decoded) => decoded,
+            Err(_) => return ShouldRender::No,
+        };
+        if decoded.text == **buffer.edit_tree() {
+            return ShouldRender::No;
+        }
+
+        let buffer_id = buffer.id();
+        if !buffer.is_modified() {
+            self.reload_buffer(buffer_id);
+        } else if !self.prompt_action.is_interactive() {
+            self.confirm(
+                format!("{} changed on disk. Reload it?", path.display()),
+                true,
+                PendingAction::Reload(buffer_id),
+            );
+        }
+        ShouldRender::Yes
+    }
+
+    fn reload_buffer(&mut self, buffer_id: BufferId) {
+        let buffer = match self.buffers.get_mut(buffer_id) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let file_path = match buffer.file_path() {
+            Some(file_path) => file_path.clone(),
+            None => return,
+        };
+        match read_buffer_file(buffer, &file_path) {
+            Ok(decoded) => {
+                buffer.reload(decoded.text, decoded.encoding);
+                self.context.log(format!(
+                    "Reloaded {}, it changed on disk",
+                    file_path.display()
+                ));
+            }
+            Err(error) => self.context.log(format!(
+                "Could not reload {} ({})",
+                file_path.display(),
+                error
+            )),
+        }
+    }
+
+    /// Asks whether to discard the unsaved changes of the focused buffer and
+    /// read its file again. Unmodified buffers are reverted right away.
+    fn confirm_revert_buffer(&mut self) {
+        let buffer = match self
+            .windows
+            .get_focused()
+            .and_then(|view| self.buffers.get(view.buffer_id))
+        {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let buffer_id = buffer.id();
+        let message = match buffer.file_path() {
+            Some(file_path) if buffer.is_modified() => {
+                format!("Discard the changes to {}?", file_path.display())
+            }
+            Some(_) => return self.revert_buffer(buffer_id),
+            None => return self.context.log("The buffer is not backed by a file"),
+        };
+        self.confirm(message, true, PendingAction::Revert(buffer_id));
+    }
+
+    /// Reads a buffer's file again, discarding its unsaved changes. The
+    /// encoding is detected again like when the file was opened, the previous
+    /// content can still be restored with undo.
+    fn revert_buffer(&mut self, buffer_id: BufferId) {
+        let file_path = match self.buffers.get(buffer_id).and_then(Buffer::file_path) {
+            Some(file_path) => file_path.clone(),
+            None => return,
+        };
+        let charset = self.find_editorconfig(&file_path).charset;
+        let buffer = match self.buffers.get_mut(buffer_id) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let decoded = if buffer.is_large_file() || buffer.is_hex_view() {
+            read_buffer_file(buffer, &file_path)
+        } else {
+            fs::read(&file_path).map(|bytes| decode_text(&bytes, charset))
+        };
+        match decoded {
+            Ok(decoded) => {
+                buffer.reload(decoded.text, decoded.encoding);
+                buffer.remove_recovery_file();
+                self.context.log(if decoded.had_errors {
+                    format!(
+                        "Reverted {}, some bytes were not valid {} and were replaced",
+                        file_path.display(),
+                        decoded.encoding
+                    )
+                } else {
+                    format!("Reverted {}", file_path.display())
+                });
+            }
+            Err(error) => self.context.log_error(format!(
+                "Could not revert {} ({})",
+                file_path.display(),
+                error
+            )),
+        }
+    }
+
+    /// Asks whether to create the directory of a file a buffer is about to be
+    /// saved to, if it doesn't exist. Returns `false` if there's nothing to
+    /// ask and the buffer can be saved right away.
+    fn confirm_missing_parent_directory(&mut self, buffer_id: BufferId, file_path: &Path) -> bool {
+        match file_path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() && !directory.exists() => {
+                self.confirm(
+                    format!("Create directory {}?", directory.display()),
+                    true,
+                    PendingAction::CreateParentDirectory(buffer_id, file_path.to_path_buf()),
+                );
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn create_parent_directory_and_save(&mut self, buffer_id: BufferId, file_path: PathBuf) {
+        if let Some(directory) = file_path.parent() {
+            if let Err(error) = fs::create_dir_all(directory) {
+                self.context.log_error(format!(
+                    "Could not create directory {}: {}",
+                    directory.display(),
+                    error
+                ));
+                return;
+            }
+        }
+        let is_visited_file = self
+            .buffers
+            .get(buffer_id)
+            .and_then(|buffer| buffer.file_path())
+            .map_or(false, |buffer_path| *buffer_path == file_path);
+        if is_visited_file {
+            self.buffers.handle_message(BuffersMessage::new(
+                buffer_id,
+                BufferMessage::SaveBufferStart,
+            ));
+        } else {
+            self.save_file_as(buffer_id, file_path);
+        }
+    }
+
+    fn save_file_as(&mut self, buffer_id: BufferId, file_path: PathBuf) {
+        self.prompt_action = PromptAction::None;
+        self.prompt_height = self.prompt_action.initial_height();
+        if let Some(buffer) = self.buffers.get_mut(buffer_id) {
+            if let Some(old_path) = buffer.file_path() {
+                self.watcher.unwatch(old_path);
+            }
+            self.watcher.watch(&file_path);
+            buffer.save_as(file_path);
+        }
+    }
+
+    /// Splits the focused window, the new window shows the same buffer. Returns
+    /// `false` if there is no window to split.
+    fn split_focused_window(&mut self, direction: FlexDirection) -> bool {
+        match self.windows.get_focused() {
+            Some(view_id) => {
+                let buffer = self.buffers.get_mut(view_id.buffer_id).unwrap();
+                self.windows.insert_at_focused(
+                    BufferViewId::new(
+                        view_id.buffer_id,
+                        buffer.duplicate_cursor(view_id.cursor_id),
+                    ),
+                    direction,
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn kill_buffer(&mut self, buffer_id: BufferId) {
+        self.prompt_action = PromptAction::None;
+        self.prompt_height = self.prompt_action.initial_height();
+        let cursor_id = self
+            .windows
+            .get_focused()
+            .filter(|view| view.buffer_id == buffer_id)
+            .map(|view| view.cursor_id)
+            .unwrap_or_default();
+        let removed_buffer = self.buffers.remove(buffer_id);
+        debug_assert!(removed_buffer.is_some());
+        if let Some(mut buffer) = removed_buffer {
+            if let Some(file_path) = buffer.file_path() {
+                self.watcher.unwatch(file_path);
+            }
+            // Unsaved changes are only kept in memory once the buffer is
+            // closed, for reopening it
+            buffer.remove_recovery_file();
+            self.remember_closed_buffer(&buffer, cursor_id);
+            if let Some(position) = file_position(&buffer, cursor_id) {
+                self.state.set_file_position(position);
+                self.save_state();
+            }
+        }
+        if self.buffers.is_empty() {
+            self.windows.clear();
+        } else {
+            let some_buffer = self.buffers.iter_mut().next().unwrap();
+            self.windows.nodes_mut().for_each(|view_id| {
+                if view_id.buffer_id == buffer_id {
+                    *view_id = BufferViewId::new(some_buffer.id(), some_buffer.new_cursor());
+                }
+            });
+        }
+    }
+
+    fn remember_closed_buffer(&mut self, buffer: &Buffer, cursor_id: CursorId) {
+        // Discarded changes and new files that were never written are kept in
+        // memory, so that reopening the buffer doesn't lose them
+        let text = buffer.edit_tree();
+        let is_unwritten = buffer.file_path().map_or(true, |path| !path.exists());
+        let unsaved_text = if buffer.is_modified() || (is_unwritten && text.len_chars() > 0) {
+            Some(Rope::clone(text))
+        } else {
+            None
+        };
+        if buffer.file_path().is_none() && unsaved_text.is_none() {
+            return;
+        }
+        self.closed_buffers.push(ClosedBuffer {
+            file_path: buffer.file_path().cloned(),
+            encoding: buffer.encoding(),
+            position: buffer.cursor(cursor_id).range().start,
+            unsaved_text,
+        });
+        if self.closed_buffers.len() > MAX_CLOSED_BUFFERS {
+            self.closed_buffers.remove(0);
+        }
+    }
+
+    /// Reopens the most recently killed buffer and restores its cursor
+    /// position. If its file was opened again since, that buffer is focused
+    /// instead, keeping any unsaved changes of the closed one to reopen once
+    /// it's closed too. Returns whether the file is new, like `open_file`.
+    fn reopen_closed_buffer(&mut self) -> Result<bool> {
+        let closed = match self.closed_buffers.pop() {
+            Some(closed) => closed,
+            None => {
+                self.context.log("No closed buffers to reopen");
+                return Ok(false);
+            }
+        };
+
+        let open_buffer = closed
+            .file_path
+            .as_ref()
+            .and_then(|file_path| Some((file_path.clone(), self.find_open_buffer(file_path)?)));
+        if let Some((file_path, buffer_id)) = open_buffer {
+            self.focus_on_buffer(buffer_id);
+            if closed.unsaved_text.is_some() {
+                self.context.log(format!(
+                    "{} is already open, close it to reopen the unsaved changes",
+                    file_path.display()
+                ));
+                self.closed_buffers.push(closed);
+            }
+            return Ok(false);
+        }
+
+        let (buffer_id, is_new_file) = match (closed.unsaved_text, closed.file_path) {
+            (Some(text), file_path) => {
+                let repo = file_path
+                    .as_ref()
+                    .and_then(|path| Repository::discover(path).ok())
+                    .map(RepositoryRc::new);
+                if let Some(file_path) = file_path.as_ref() {
+                    self.watcher.watch(file_path);
+                }
+                let buffer_id = self.buffers.add(text, closed.encoding, file_path, repo);
+                if let Some(buffer) = self.buffers.get_mut(buffer_id) {
+                    buffer.mark_modified();
+                }
+                self.focus_on_buffer(buffer_id);
+                (buffer_id, false)
+            }
+            (None, Some(file_path)) => {
+                let is_new_file = self.open_file(file_path.clone())?;
+                match self.buffers.find_by_path(&file_path) {
+                    Some(buffer_id) => (buffer_id, is_new_file),
+                    None => return Ok(is_new_file),
+                }
+            }
+            (None, None) => return Ok(false),
+        };
+
+        if let Some(buffer) = self.buffers.get_mut(buffer_id) {
+            let mut cursor = Cursor::new();
+            movement::move_to_char(buffer.edit_tree(), &mut cursor, closed.position);
+            buffer.set_cursor(CursorId::default(), cursor);
+        }
+        Ok(is_new_file)
+    }
+
+    /// Starts an incremental search in the focused window
+    fn start_search(&mut self, direction: Direction) {
+        let view = match self.windows.get_focused() {
+            Some(view) => view,
+            None => return,
+        };
+        let origin = match self.buffers.get(view.buffer_id) {
+            Some(buffer) => buffer.cursor(view.cursor_id).clone(),
+            None => return,
+        };
+        self.search = Some(SearchState {
+            view,
+            origin,
+            query: Query::new(""),
+            direction,
+            current: None,
+        });
+        self.prompt_action = self.search_prompt_action();
+        self.prompt_height = self.prompt_action.initial_height();
+    }
+
+    /// Moves the cursor to the closest match of the current search query,
+    /// starting at `position`
+    fn search_from(&mut self, position: CharIndex) {
+        let state = match self.search.as_mut() {
+            Some(state) => state,
+            None => return,
+        };
+        if let Some(buffer) = self.buffers.get_mut(state.view.buffer_id) {
+            if state.query.is_empty() {
+                buffer.set_cursor(state.view.cursor_id, state.origin.clone());
+                state.current = None;
+            } else {
+                state.current = buffer.search(
+                    state.view.cursor_id,
+                    &state.query,
+                    position,
+                    state.direction,
+                );
+            }
+        }
+        self.prompt_action = self.search_prompt_action();
+    }
+
+    fn search_prompt_action(&self) -> PromptAction {
+        let state = match self.search.as_ref() {
+            Some(state) => state,
+            None => return PromptAction::None,
+        };
+        let note = match state.current {
+            _ if state.query.is_empty() => None,
+            None => Some("no match".into()),
+            Some(SearchMatch { wrapped: true, .. }) => Some("wrapped".into()),
+            Some(SearchMatch { wrapped: false, .. }) => None,
+        };
+        PromptAction::Search {
+            message: match state.direction {
+                Direction::Forward => "search".into(),
+                Direction::Backward => "search backward".into(),
+            },
+            note,
+            on_change: self.context.link.callback(Message::SearchQuery),
+            on_confirm: self.context.link.callback(|()| Message::SearchConfirm),
+            on_cancel: self.context.link.callback(|()| Message::Cancel),
+        }
+    }
+
+    /// Moves to the next match of the query replace in progress and asks what
+    /// to do with it. Finishes the query replace if there are no more matches.
+    fn query_replace_next(&mut self) {
+        let state = match self.replace.as_mut() {
+            Some(state) => state,
+            None => return,
+        };
+        let next_match = self
+            .buffers
+            .get_mut(state.view.buffer_id)
+            .and_then(|buffer| {
+                let range = state.query.find_next(
+                    buffer.edit_tree(),
+                    state.position,
+                    Direction::Forward,
+                )?;
+                buffer.search(
+                    state.view.cursor_id,
+                    &state.query,
+                    range.start,
+                    Direction::Forward,
+                );
+                Some(range)
+            });
+        state.current = next_match;
+
+        if state.current.is_some() {
+            let message = format!("Replace `{}` with `{}`?", state.pattern, state.replacement);
+            self.prompt_action = PromptAction::QueryReplace {
+                message: message.into(),
+                on_choice: self.context.link.callback(Message::PostInteractionReplace),
+            };
+            self.prompt_height = self.prompt_action.initial_height();
+        } else {
+            self.finish_query_replace();
+        }
+    }
+
+    fn finish_query_replace(&mut self) {
+        self.prompt_action = PromptAction::None;
+        self.prompt_height = self.prompt_action.initial_height();
+        if let Some(ReplaceState { num_replaced, .. }) = self.replace.take() {
+            self.context.log(format!(
+                "Replaced {} occurrence{}",
+                num_replaced,
+                if num_replaced == 1 { "" } else { "s" }
+            ));
+        }
+    }
+
+    /// The query whose matches are highlighted in a window, the one of the
+    /// search in progress or of the buffer's last search
+    fn highlighted_query(&self, view: BufferViewId) -> Option<Query> {
+        let search = self.search.as_ref().map(|state| (state.view, &state.query));
+        let replace = self
+            .replace
+            .as_ref()
+            .map(|state| (state.view, &state.query));
+        search
+            .or(replace)
+            .filter(|(searched, _)| *searched == view)
+            .map(|(_, query)| query.clone())
+            .or_else(|| {
+                self.buffers
+                    .get(view.buffer_id)
+                    .and_then(|buffer| buffer.search_highlight().cloned())
+            })
+    }
+
+    /// Stops highlighting the matches of the focused buffer's last search
+    fn clear_search_highlight(&mut self) {
+        if let Some(buffer) = self
+            .windows
+            .get_focused()
+            .and_then(|view| self.buffers.get_mut(view.buffer_id))
+        {
+            buffer.set_search_highlight(None);
+        }
+    }
+
+    /// Writes the changes to the state file in the background
+    fn save_state(&self) {
+        self.state
+            .save_in_background(self.context.config_dir.clone(), &self.context.task_pool);
+    }
+
+    fn remember_recent_file(&mut self, file_path: &Path) {
+        self.state.add_recent_file(canonical_path(file_path));
+        self.save_state();
+    }
+
+    fn quit(&mut self) {
+        if self.context.config.restore_layout {
+            self.save_layout();
+        }
+        for buffer in self.buffers.iter_mut() {
+            if let Some(position) = file_position(buffer, CursorId::default()) {
+                self.state.set_file_position(position);
+            }
+            // Quitting discards the unsaved changes
+            buffer.remove_recovery_file();
+        }
+        if let Err(error) = self.state.save(self.context.config_dir.as_deref()) {
+            log::error!("{:#}", error);
+        }
+        self.exit_status.set(ExitStatus::Quit);
+        self.context.link.exit();
+    }
+
+    /// Stores the windows and the files they show in the state file, to
+    /// restore them when zee is started in the same directory
+    fn save_layout(&mut self) {
+        let buffers = &self.buffers;
+        let (nodes, focused) = self.windows.to_nodes(|view| {
+            buffers
+                .get(view.buffer_id)
+                .and_then(|buffer| buffer.file_path().cloned())
+        });
+
+        let mut saved_nodes = Vec::with_capacity(nodes.len());
+        let mut saved_focused = focused;
+        let mut window_index = 0;
+        for node in nodes {
+            match node {
+                Node::Window(Some(file_path), size) => {
+                    saved_nodes.push(SavedNode::Window {
+                        file_path: canonical_path(&file_path),
+                        size,
+                    });
+                    window_index += 1;
+                }
+                // Buffers without a file can't be restored
+                Node::Window(None, _) => {
+                    if window_index < focused {
+                        saved_focused -= 1;
+                    }
+                    window_index += 1;
+                }
+                Node::ContainerStart(direction) => saved_nodes.push(SavedNode::Split {
+                    column: matches!(
+                        direction,
+                        FlexDirection::Column | FlexDirection::ColumnReverse
+                    ),
+                }),
+                Node::ContainerEnd => saved_nodes.push(SavedNode::EndSplit),
+            }
+        }
+
+        self.state.set_layout(SavedLayout {
+            directory: self.context.current_working_dir(),
+            nodes: saved_nodes,
+            focused: saved_focused,
+        });
+    }
+
+    /// Reopens the windows saved when zee last quit in the working directory.
+    /// Windows showing files which don't exist anymore are left out.
+    fn restore_layout(&mut self) {
+        let layout = match self.state.layout(&self.context.current_working_dir()) {
+            Some(layout) => layout.clone(),
+            None => return,
+        };
+
+        let mut nodes = Vec::with_capacity(layout.nodes.len());
+        let mut focused = layout.focused;
+        let mut shown_buffers = Vec::new();
+        let mut num_missing = 0;
+        let mut window_index = 0;
+        for node in layout.nodes {
+            match node {
+                SavedNode::Window { file_path, size } => {
+                    let buffer_id = if file_path.exists() {
+                        self.open_file(file_path.clone())
+                            .ok()
+                            .and_then(|_| self.buffers.find_by_path(&file_path))
+                    } else {
+                        None
+                    };
+                    match buffer_id {
+                        Some(buffer_id) => {
+                            // Windows showing the same buffer need their own cursors
+                            let cursor_id = if shown_buffers.contains(&buffer_id) {
+                                self.buffers.get_mut(buffer_id).unwrap().new_cursor()
+                            } else {
+                                shown_buffers.push(buffer_id);
+                                CursorId::default()
+                            };
+                            nodes.push(Node::Window(BufferViewId::new(buffer_id, cursor_id), size));
+                        }
+                        None => {
+                            log::info!(
+                                "Not restoring a window for missing file `{}`",
+                                file_path.display()
+                            );
+                            num_missing += 1;
+                            if window_index < layout.focused {
+                                focused -= 1;
+                            }
+                        }
+                    }
+                    window_index += 1;
+                }
+                SavedNode::Split { column } => nodes.push(Node::ContainerStart(if column {
+                    FlexDirection::Column
+                } else {
+                    FlexDirection::Row
+                })),
+                SavedNode::EndSplit => nodes.push(Node::ContainerEnd),
+            }
+        }
+
+        self.windows = WindowTree::from_nodes(nodes, focused);
+        if num_missing > 0 {
+            self.context.log(format!(
+                "Restored the windows, {} file{} no longer exist{}",
+                num_missing,
+                if num_missing == 1 { "" } else { "s" },
+                if num_missing == 1 { "s" } else { "" },
+            ));
+        }
+    }
+
+    /// Stores the current theme in the state file, to restore it in the next
+    /// session
+    fn remember_theme(&mut self) {
+        self.state
+            .set_theme(self.themes[self.theme_index].1.to_owned());
+        self.save_state();
+    }
+
+    /// The entries of the command palette: the editor's commands, the
+    /// buffers' commands and a command to switch to each of the themes
+    fn command_entries(&self) -> Vec<CommandEntry> {
+        let format_keys = |sequences: Vec<&[Key]>| {
+            sequences
+                .into_iter()
+                .map(|keys| KeySequenceSlice::new(keys, false).to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let commands = COMMANDS.iter().map(|command| CommandEntry {
+            name: command.name.into(),
+            keys: format_keys(
+                command_keys(command, &self.context.config.key_bindings)
+                    .iter()
+                    .map(Vec::as_slice)
+                    .collect(),
+            ),
+        });
+        let buffer_commands = BUFFER_COMMANDS.iter().map(|command| CommandEntry {
+            name: command.name.into(),
+            keys: format_keys(command.default_keys.to_vec()),
+        });
+        let themes = self.themes.iter().map(|(_, name)| CommandEntry {
+            name: format!("change-theme: {}", name).into(),
+            keys: String::new(),
+        });
+        commands.chain(buffer_commands).chain(themes).collect()
+    }
+
+    /// Commands to get started with, shown on the splash screen with their
+    /// first keybinding
+    fn splash_hints(&self) -> Vec<KeyHint> {
+        SPLASH_HINTS
+            .iter()
+            .filter_map(|&(name, description)| {
+                let command = COMMANDS.iter().find(|command| command.name == name)?;
+                let keys = command_keys(command, &self.context.config.key_bindings);
+                Some(KeyHint {
+                    keys: KeySequenceSlice::new(keys.first()?, false).to_string(),
+                    description,
+                })
+            })
+            .collect()
+    }
+
+    /// The current theme for buffers, with the syntax highlighted by the theme
+    /// configured for the mode in `mode_themes`, if any. The monochrome theme
+    /// isn't mixed with colours.
+    fn buffer_theme(&self, mode: &Mode) -> Cow<'static, BufferTheme> {
+        let themes = self.themes;
+        let (theme, theme_name) = &themes[self.theme_index];
+        let theme = &theme.buffer;
+        if *theme_name == MONOCHROME_THEME {
+            return Cow::Borrowed(theme);
+        }
+        let mode_theme = self
+            .context
+            .config
+            .mode_themes
+            .get(&mode.name)
+            .and_then(|name| {
+                themes
+                    .iter()
+                    .find(|(_, existing)| *existing == name.as_str())
+            });
+        match mode_theme {
+            Some((mode_theme, _)) => Cow::Owned(BufferTheme {
+                syntax: mode_theme.buffer.syntax.clone(),
+                ..theme.clone()
+            }),
+            None => Cow::Borrowed(theme),
+        }
+    }
+
+    fn buffer_view_layout(&self, Window { id, focused, index }: Window<BufferViewId>) -> Layout {
+        let buffer = self.buffers.get(id.buffer_id).unwrap();
+        BufferView::with_key(
+            format!("{}.{}", index, id).as_str(),
+            BufferViewProperties {
+                context: self.context.clone(),
+                theme: self.buffer_theme(buffer.mode()),
+                theme_name: self.themes[self.theme_index].1,
+                focused: focused && !self.prompt_action.is_interactive(),
+                frame_id: index.one_based_index(),
+                mode: buffer.mode(),
+                tab_width: buffer.indentation().tab_width(),
+                repo: buffer.repository().cloned(),
+                content: buffer.edit_tree_handle(),
+                file_path: buffer.file_path().cloned(),
+                real_path: buffer.real_path().cloned(),
+                working_dir: self.context.current_working_dir(),
+                cursor: BufferCursor::new(
+                    id.buffer_id,
+                    id.cursor_id,
+                    buffer.cursor(id.cursor_id).clone(),
+                    buffer.secondary_cursors(id.cursor_id).to_vec(),
+                    self.context.link.clone(),
+                ),
+                parse_tree: buffer.parse_tree().cloned(),
+                modified_status: buffer.modified_status(),
+                read_only: buffer.is_read_only(),
+                line_ending: buffer.line_ending(),
+                encoding: buffer.encoding(),
+                search_query: self.highlighted_query(id),
+                diagnostics: buffer.diagnostics().clone(),
+                git_changes: buffer.git_changes().cloned(),
+                palette_command: self.palette_command.clone(),
+            },
+        )
+    }
+
+    fn tab_bar(&self, visible_buffer_id: BufferId) -> Item {
+        let tabs = self
+            .buffers
+            .iter()
+            .map(|buffer| Tab {
+                name: buffer
+                    .file_path()
+                    .and_then(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "[no file]".into()),
+                modified: buffer.is_modified(),
+            })
+            .collect();
+        let selected = self
+            .buffers
+            .iter()
+            .position(|buffer| buffer.id() == visible_buffer_id)
+            .unwrap_or(0);
+        TabBar::item_with_key(
+            FlexBasis::Fixed(1),
+            "tab-bar",
+            TabBarProperties {
+                theme: Cow::Borrowed(&self.themes[self.theme_index].0.tab_bar),
+                tabs,
+                selected,
+            },
+        )
+    }
+
+    fn open_buffer_picker(&mut self, message: Cow<'static, str>, on_select: Callback<BufferId>) {
+        self.prompt_action = PromptAction::PickBuffer {
+            message,
+            entries: self
+                .buffers
+                .iter()
+                .map(|buffer| {
+                    BufferEntry::new(
+                        buffer.id(),
+                        buffer.file_path().cloned(),
+                        false,
+                        buffer.edit_tree().len_bytes(),
+                        buffer.mode(),
+                    )
+                })
+                .collect(),
+            on_select,
+            on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+        };
+        self.prompt_height = self.prompt_action.initial_height();
+    }
+}
+
+impl Component for Editor {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Properties, frame: Rect, link: ComponentLink<Self>) -> Self {
+        if properties.args_files.is_empty() && properties.config.restore_layout {
+            link.send(Message::RestoreLayout);
+        }
+        if !properties.args_files.is_empty() {
+            link.send(Message::OpenFiles(properties.args_files.clone()));
+        }
+
+        let theme_name = properties.config.theme.clone();
+        let context = ContextHandle(Box::leak(
+            Context {
+                args_files: properties.args_files,
+                current_working_dir: RwLock::new(properties.current_working_dir),
+                config_dir: properties.config_dir,
+                modes: properties
+                    .config
+                    .modes
+                    .iter()
+                    .cloned()
+                    .map(Mode::new)
+                    .collect(),
+                config: properties.config,
+                task_pool: properties.task_pool,
+                clipboard: properties.clipboard,
+                input_history: InputHistory::default(),
+                link,
+            }
+            .into(),
+        ));
+
+        let themes = load_themes(&context);
+        let state = EditorState::load(context.config_dir.as_deref());
+        // Without colours the editor starts with the monochrome theme.
+        // Otherwise the theme selected in the last session takes precedence over the
+        // configured one, unless it doesn't exist anymore.
+        let monochrome_theme = context
+            .config
+            .is_monochrome()
+            .then(|| {
+                themes
+                    .iter()
+                    .position(|(_, name)| *name == MONOCHROME_THEME)
+            })
+            .flatten();
+        let saved_theme = state
+            .theme
+            .as_deref()
+            .and_then(|saved| themes.iter().position(|(_, name)| *name == saved));
+        let theme_index = monochrome_theme
+            .or(saved_theme)
+            .or_else(|| {
+                let theme = themes.iter().position(|(_, name)| *name == theme_name);
+                if theme.is_none() {
+                    context.log_warning(format!("Unknown theme `{}`", theme_name));
+                }
+                theme
+            })
+            .unwrap_or(0);
+        for (mode_name, theme_name) in context.config.mode_themes.iter() {
+            if !themes.iter().any(|(_, name)| *name == theme_name.as_str()) {
+                context.log_warning(format!(
+                    "Unknown theme `{}` for mode `{}`",
+                    theme_name, mode_name
+                ));
+            }
+        }
+
+        Self {
+            themes,
+            theme_index,
+            theme_before_preview: None,
+            palette_command: Rc::new(Cell::new(None)),
+            state,
+            exit_status: properties.exit_status,
+            prompt_action: PromptAction::None,
+            prompt_height: PROMPT_INACTIVE_HEIGHT,
+            buffers: Buffers::new(context.clone()),
+            watcher: FileWatcher::new(context.link.clone()),
+            language_servers: LanguageServers::new(context.clone()),
+            context,
+            windows: WindowTree::new(),
+            frame,
+            last_autosave: Instant::now(),
+            kill_buffer_after_save: None,
+            closed_buffers: Vec::new(),
+            jumps: JumpList::default(),
+            messages: MessageLog::default(),
+            pending_action: None,
+            tabbed: false,
+            search: None,
+            replace: None,
+        }
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Message::Cancel if self.replace.is_some() => self.finish_query_replace(),
+            Message::Cancel => {
+                // Outside of prompts, cancelling clears the search highlight
+                if !self.prompt_action.is_interactive() {
+                    self.clear_search_highlight();
+                }
+                self.pending_action = None;
+                if let Some(theme_index) = self.theme_before_preview.take() {
+                    self.theme_index = theme_index;
+                }
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                self.context.log("Cancel");
+
+                // Return to where the search started
+                if let Some(SearchState { view, origin, .. }) = self.search.take() {
+                    if let Some(buffer) = self.buffers.get_mut(view.buffer_id) {
+                        buffer.set_cursor(view.cursor_id, origin);
+                    }
+                }
+            }
+            Message::ChangeTheme(direction) => {
+                self.theme_index = match direction {
+                    Direction::Forward => (self.theme_index + 1) % self.themes.len(),
+                    Direction::Backward => {
+                        (self.theme_index + self.themes.len() - 1) % self.themes.len()
+                    }
+                };
+                self.remember_theme();
+                if !self.prompt_action.is_interactive() {
+                    self.context.log(format!(
+                        "Theme changed to {}",
+                        self.themes[self.theme_index].1
+                    ));
+                }
+            }
+            Message::ThemePicker if !self.prompt_action.is_interactive() => {
+                self.theme_before_preview = Some(self.theme_index);
+                self.prompt_action = PromptAction::PickCommand {
+                    message: "theme".into(),
+                    entries: self
+                        .themes
+                        .iter()
+                        .map(|(_, name)| CommandEntry {
+                            name: (*name).into(),
+                            keys: String::new(),
+                        })
+                        .collect(),
+                    on_select: self.context.link.callback(Message::SetTheme),
+                    on_highlight: Some(self.context.link.callback(Message::PreviewTheme)),
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            // Shows the theme under the cursor of the theme picker, until one
+            // is picked or the picker is cancelled
+            Message::PreviewTheme(index) if index < self.themes.len() => {
+                self.theme_index = index;
+            }
+            Message::SetTheme(index) if index < self.themes.len() => {
+                self.theme_before_preview = None;
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                self.theme_index = index;
+                self.remember_theme();
+                self.context
+                    .log(format!("Theme changed to {}", self.themes[index].1));
+            }
+            Message::CommandPalette if !self.prompt_action.is_interactive() => {
+                self.prompt_action = PromptAction::PickCommand {
+                    message: "command".into(),
+                    entries: self.command_entries(),
+                    on_select: self.context.link.callback(Message::RunCommand),
+                    on_highlight: None,
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::RunCommand(index) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                // The entries are the editor's commands, followed by the
+                // buffers' commands and the themes
+                let buffer_index = index.saturating_sub(COMMANDS.len());
+                match COMMANDS.get(index) {
+                    Some(command) => self.context.link.send((command.message)()),
+                    None if buffer_index < BUFFER_COMMANDS.len() => {
+                        if self.windows.get_focused().is_some() {
+                            self.palette_command.set(Some(buffer_index));
+                        } else {
+                            self.context.log("No buffer to run the command in");
+                        }
+                    }
+                    None => self
+                        .context
+                        .link
+                        .send(Message::SetTheme(buffer_index - BUFFER_COMMANDS.len())),
+                }
+            }
+            Message::RecentFilesPicker if !self.prompt_action.is_interactive() => {
+                let recent_files: Vec<PathBuf> = self
+                    .state
+                    .recent_files
+                    .iter()
+                    .filter(|path| path.exists())
+                    .cloned()
+                    .collect();
+                if recent_files.is_empty() {
+                    self.context.log("No recent files");
+                    return ShouldRender::Yes;
+                }
+                self.prompt_action = PromptAction::PickCommand {
+                    message: "recent".into(),
+                    entries: recent_files
+                        .iter()
+                        .map(|path| CommandEntry {
+                            name: path.to_string_lossy().into_owned().into(),
+                            keys: String::new(),
+                        })
+                        .collect(),
+                    on_select: self.context.link.callback(move |index: usize| {
+                        Message::OpenFile(recent_files[index].clone())
+                    }),
+                    on_highlight: None,
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::OpenFilePicker(source) if !self.prompt_action.is_interactive() => {
+                self.prompt_action = PromptAction::OpenFile {
+                    source,
+                    initial_path: match source {
+                        FileSource::Directory => None,
+                        FileSource::Repository => self.repository_root(),
+                    },
+                    on_open: self.context.link.callback(Message::OpenFile),
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::ReopenClosedBuffer if !self.prompt_action.is_interactive() => {
+                let result = self.reopen_closed_buffer();
+                self.report_open_file(result);
+            }
+            Message::RevertBuffer if !self.prompt_action.is_interactive() => {
+                self.confirm_revert_buffer()
+            }
+            Message::RestoreLayout => self.restore_layout(),
+            // Browse a directory with the file picker instead of opening it
+            Message::OpenFile(path) if path.is_dir() => {
+                let path = fs::canonicalize(&path).unwrap_or(path);
+                self.prompt_action = PromptAction::OpenFile {
+                    source: FileSource::Directory,
+                    // Joining an empty path adds a trailing `/`, listing the
+                    // directory's entries
+                    initial_path: Some(path.join("")),
+                    on_open: self.context.link.callback(Message::OpenFile),
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::OpenFile(path) => {
+                let result = self.open_file(path);
+                self.report_open_file(result);
+            }
+            Message::OpenFileSplitPicker(direction) if !self.prompt_action.is_interactive() => {
+                self.prompt_action = PromptAction::OpenFile {
+                    source: FileSource::Directory,
+                    initial_path: None,
+                    on_open: self
+                        .context
+                        .link
+                        .callback(move |path| Message::OpenFileSplit(path, direction)),
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::OpenFiles(paths) => self.open_files(paths),
+            Message::OpenFileSplit(path, direction) => {
+                let result = self.open_file_split(path, direction);
+                self.report_open_file(result);
+            }
+            Message::FindRelatedFile(direction) if !self.prompt_action.is_interactive() => {
+                self.find_related_file(direction);
+            }
+            Message::FindFileAtPoint if !self.prompt_action.is_interactive() => {
+                self.find_file_at_point();
+            }
+            Message::SaveFileAsPicker if !self.prompt_action.is_interactive() => {
+                if let Some(BufferViewId { buffer_id, .. }) = self.windows.get_focused() {
+                    self.prompt_action = PromptAction::SaveFileAs {
+                        initial_path: self
+                            .buffers
+                            .get(buffer_id)
+                            .and_then(|buffer| buffer.file_path().cloned()),
+                        on_save: self
+                            .context
+                            .link
+                            .callback(move |file_path| Message::SaveFileAs(buffer_id, file_path)),
+                        on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                    };
+                    self.prompt_height = self.prompt_action.initial_height();
+                }
+            }
+            Message::ChangeDirectoryPicker if !self.prompt_action.is_interactive() => {
+                // Start at the focused buffer's directory, so it's a single
+                // key away
+                let directory = self
+                    .windows
+                    .get_focused()
+                    .and_then(|view| self.buffers.get(view.buffer_id))
+                    .and_then(|buffer| buffer.file_path())
+                    .and_then(|path| path.parent())
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| self.context.current_working_dir());
+                self.prompt_action = PromptAction::ChangeDirectory {
+                    // Joining an empty path adds a trailing `/`
+                    initial_path: Some(directory.join("")),
+                    on_select: self.context.link.callback(Message::ChangeDirectory),
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::ChangeDirectory(directory) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                self.change_directory(directory);
+            }
+            Message::ChangeDirectoryToRepository => match self.repository_root() {
+                Some(directory) => self.change_directory(directory),
+                None => self.context.log("Not in a git repository"),
+            },
+            Message::SaveFileAs(buffer_id, file_path) => {
+                let is_visited_file = self
+                    .buffers
+                    .get(buffer_id)
+                    .and_then(|buffer| buffer.file_path())
+                    .map(|buffer_path| *buffer_path == file_path)
+                    .unwrap_or(false);
+                if file_path.is_dir() {
+                    self.prompt_action = PromptAction::None;
+                    self.prompt_height = self.prompt_action.initial_height();
+                    self.context
+                        .log_error(format!("{} is a directory", file_path.display()));
+                } else if file_path.exists() && !is_visited_file {
+                    self.confirm(
+                        format!("{} already exists. Overwrite?", file_path.display()),
+                        false,
+                        PendingAction::SaveFileAs(buffer_id, file_path),
+                    );
+                } else if !self.confirm_missing_parent_directory(buffer_id, &file_path) {
+                    self.save_file_as(buffer_id, file_path);
+                }
+            }
+            Message::FileChanged(path) => return self.file_changed(&path),
+            Message::LanguageServer(server, event) => {
+                self.language_servers
+                    .handle_event(server, event, &mut self.buffers);
+            }
+            Message::SyncLanguageServers => {
+                self.language_servers.sync(&self.buffers);
+                return ShouldRender::No;
+            }
+            Message::ShowHover => self.show_hover(),
+            Message::UpdateGitDiffs => {
+                for buffer in self.buffers.iter_mut() {
+                    buffer.update_git_diff();
+                }
+                return ShouldRender::No;
+            }
+            Message::OfferRecovery(buffer_id) => self.offer_recovery(buffer_id),
+            // Ticks are only requested again after a redraw, so keep redrawing
+            // until the autosave is due
+            Message::Autosave if !self.is_autosave_due() => {}
+            Message::Autosave => {
+                self.last_autosave = Instant::now();
+                for buffer in self
+                    .buffers
+                    .iter_mut()
+                    .filter(|buffer| buffer.needs_autosave())
+                {
+                    buffer.autosave();
+                }
+                return ShouldRender::No;
+            }
+            // Repeating the command jumps to the next match
+            Message::Search(direction) if self.search.is_some() => {
+                let state = self.search.as_mut().unwrap();
+                let position = match (state.current.as_ref(), direction) {
+                    (Some(current), Direction::Forward) => current.range.start + 1,
+                    (Some(current), Direction::Backward) => current.range.start,
+                    (None, _) => state.origin.range().start,
+                };
+                state.direction = direction;
+                self.search_from(position);
+            }
+            Message::Search(direction) if !self.prompt_action.is_interactive() => {
+                self.start_search(direction);
+            }
+            Message::ClearSearchHighlight => self.clear_search_highlight(),
+            Message::SearchQuery(pattern) => {
+                let state = match self.search.as_mut() {
+                    Some(state) => state,
+                    None => return ShouldRender::No,
+                };
+                state.query = Query::new(&pattern);
+
+                // Stay on the current match while it still matches the query
+                let position = match (state.current.as_ref(), state.direction) {
+                    (Some(current), Direction::Forward) => current.range.start,
+                    (Some(current), Direction::Backward) => current.range.start + 1,
+                    (None, _) => state.origin.range().start,
+                };
+                self.search_from(position);
+            }
+            Message::SearchConfirm => {
+                // Remember where the search started if the cursor moved, and
+                // keep highlighting the matches
+                if let Some(SearchState {
+                    view,
+                    origin,
+                    query,
+                    ..
+                }) = self.search.take()
+                {
+                    let moved = self
+                        .buffers
+                        .get_mut(view.buffer_id)
+                        .map_or(false, |buffer| {
+                            buffer.set_search_highlight(Some(query));
+                            buffer.cursor(view.cursor_id).range() != origin.range()
+                        });
+                    if moved {
+                        self.jumps.push(Jump {
+                            buffer_id: view.buffer_id,
+                            char_index: origin.range().start,
+                        });
+                    }
+                }
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::GoToLinePicker
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                self.prompt_action = PromptAction::Input {
+                    message: "goto line".into(),
+                    history: "goto line",
+                    on_submit: self.context.link.callback(Message::GoToLine),
+                    on_cancel: self.context.link.callback(|()| Message::Cancel),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::GoToLine(input) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+
+                // An empty position, e.g. a bare `:`, does nothing
+                let input = input.trim();
+                if input.is_empty() || input == ":" {
+                    return ShouldRender::Yes;
+                }
+                let (line, column) = match parse_line_column(input) {
+                    Some(position) => position,
+                    None => {
+                        self.context.log(format!("Invalid line number `{}`", input));
+                        return ShouldRender::Yes;
+                    }
+                };
+                self.go_to_line(line, column.unwrap_or(0));
+            }
+            Message::ReopenWithEncodingPicker
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                self.prompt_action = PromptAction::Input {
+                    message: "reopen with encoding".into(),
+                    history: "encoding",
+                    on_submit: self.context.link.callback(Message::ReopenWithEncoding),
+                    on_cancel: self.context.link.callback(|()| Message::Cancel),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::ReopenWithEncoding(label) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                self.reopen_with_encoding(&label);
+            }
+            Message::SetModePicker
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                // Plain text first, followed by the configured modes
+                let entries = iter::once(&*PLAIN_TEXT_MODE)
+                    .chain(self.context.modes.iter())
+                    .map(|mode| CommandEntry {
+                        name: mode.name.clone().into(),
+                        keys: String::new(),
+                    })
+                    .collect();
+                self.prompt_action = PromptAction::PickCommand {
+                    message: "mode".into(),
+                    entries,
+                    on_select: self
+                        .context
+                        .link
+                        .callback(|index: usize| Message::SetMode(index.checked_sub(1))),
+                    on_highlight: None,
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::SetMode(index) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                let mode = match index {
+                    Some(index) => &self.context.0.modes[index],
+                    None => &*PLAIN_TEXT_MODE,
+                };
+                if let Some(buffer) = self
+                    .windows
+                    .get_focused()
+                    .and_then(|view| self.buffers.get_mut(view.buffer_id))
+                {
+                    buffer.set_mode(mode);
+                    self.context.log(format!("Mode set to {}", mode.name));
+                }
+            }
+            Message::GrepPicker if !self.prompt_action.is_interactive() => {
+                self.prompt_action = PromptAction::Grep {
+                    root: self.context.current_working_dir(),
+                    on_open: self.context.link.callback(Message::OpenGrepMatch),
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::OpenGrepMatch(GrepMatch {
+                file_path,
+                line_index,
+                column,
+                ..
+            }) => {
+                let result = self.open_file(file_path);
+                if result.is_ok() {
+                    self.go_to_line(line_index, column);
+                }
+                self.report_open_file(result);
+            }
+            Message::FilterPicker
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                self.prompt_action = PromptAction::Input {
+                    message: "filter through command".into(),
+                    history: "shell command",
+                    on_submit: self.context.link.callback(Message::FilterThroughCommand),
+                    on_cancel: self.context.link.callback(|()| Message::Cancel),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::FilterThroughCommand(command) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                if command.trim().is_empty() {
+                    return ShouldRender::Yes;
+                }
+                if let Some(view) = self.windows.get_focused() {
+                    if let Some(buffer) = self.buffers.get_mut(view.buffer_id) {
+                        buffer.filter_through_command(view.cursor_id, command);
+                    }
+                }
+            }
+            Message::QueryReplacePicker
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                self.prompt_action = PromptAction::Input {
+                    message: "replace".into(),
+                    history: "replace",
+                    on_submit: self.context.link.callback(Message::QueryReplaceWith),
+                    on_cancel: self.context.link.callback(|()| Message::Cancel),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::QueryReplaceWith(pattern) if pattern.is_empty() => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::QueryReplaceWith(pattern) => {
+                self.prompt_action = PromptAction::Input {
+                    message: format!("replace `{}` with", pattern).into(),
+                    history: "replace with",
+                    on_submit: self.context.link.callback(move |replacement| {
+                        Message::QueryReplace(pattern.clone(), replacement)
+                    }),
+                    on_cancel: self.context.link.callback(|()| Message::Cancel),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::QueryReplace(pattern, replacement) => {
+                if let Some(view) = self.windows.get_focused() {
+                    if self
+                        .buffers
+                        .get(view.buffer_id)
+                        .map(Buffer::is_read_only)
+                        .unwrap_or(false)
+                    {
+                        self.context.log("Buffer is read-only");
+                        self.prompt_action = PromptAction::None;
+                        self.prompt_height = self.prompt_action.initial_height();
+                        return ShouldRender::Yes;
+                    }
+                    let position = self
+                        .buffers
+                        .get(view.buffer_id)
+                        .map(|buffer| buffer.cursor(view.cursor_id).range().start)
+                        .unwrap_or(0);
+                    self.replace = Some(ReplaceState {
+                        view,
+                        query: Query::new(&pattern),
+                        pattern,
+                        replacement,
+                        position,
+                        current: None,
+                        num_replaced: 0,
+                    });
+                    self.query_replace_next();
+                }
+            }
+            Message::PostInteractionReplace(choice) => {
+                let state = match self.replace.as_mut() {
+                    Some(state) => state,
+                    None => return ShouldRender::No,
+                };
+                let (buffer, current) = match (
+                    self.buffers.get_mut(state.view.buffer_id),
+                    state.current.clone(),
+                ) {
+                    (Some(buffer), Some(current)) => (buffer, current),
+                    _ => {
+                        self.finish_query_replace();
+                        return ShouldRender::Yes;
+                    }
+                };
+                match choice {
+                    ReplaceChoice::Replace => {
+                        state.position =
+                            buffer.replace_match(state.view.cursor_id, current, &state.replacement);
+                        state.num_replaced += 1;
+                        self.query_replace_next();
+                    }
+                    ReplaceChoice::Skip => {
+                        state.position = current.end;
+                        self.query_replace_next();
+                    }
+                    ReplaceChoice::ReplaceAll => {
+                        state.num_replaced += buffer.replace_all(
+                            state.view.cursor_id,
+                            &state.query,
+                            &state.replacement,
+                            current.start,
+                        );
+                        self.finish_query_replace();
+                    }
+                    ReplaceChoice::Quit => self.finish_query_replace(),
+                }
+            }
+            Message::SelectBufferPicker if !self.prompt_action.is_interactive() => {
+                self.open_buffer_picker(
+                    "buffer".into(),
+                    self.context.link.callback(Message::SelectBuffer),
+                );
+            }
+            Message::SelectBuffer(buffer_id) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                self.focus_on_buffer(buffer_id);
+            }
+            Message::KillBufferPicker if !self.prompt_action.is_interactive() => {
+                self.open_buffer_picker(
+                    "kill buffer".into(),
+                    self.context.link.callback(Message::KillBuffer),
+                );
+            }
+            Message::KillBuffer(buffer_id) => match self.buffers.get(buffer_id) {
+                Some(buffer) if buffer.is_modified() => {
+                    let message = format!(
+                        "Buffer {} has changed. Save changes?",
+                        buffer
+                            .file_path()
+                            .map(|path| path.display().to_string())
+                            .unwrap_or_else(|| "[no file]".into())
+                    );
+                    self.confirm(message, true, PendingAction::KillBuffer(buffer_id));
+                }
+                _ => self.kill_buffer(buffer_id),
+            },
+            Message::ChangePromptHeight(height) => {
+                self.prompt_height = height;
+            }
+            Message::FocusNextWindow => self.windows.cycle_focus(CycleFocus::Next),
+            Message::FocusPreviousWindow => self.windows.cycle_focus(CycleFocus::Previous),
+            Message::SplitWindow(direction) if !self.buffers.is_empty() => {
+                self.split_focused_window(direction);
+            }
+            Message::FullscreenWindow if !self.buffers.is_empty() => {
+                self.windows.delete_all_except_focused();
+            }
+            Message::ToggleZoomWindow => self.windows.toggle_zoom(),
+            Message::ResizeWindow(delta) if !self.buffers.is_empty() => {
+                let mut size = self.frame.size;
+                size.height = size.height.saturating_sub(self.prompt_height);
+                self.windows.resize_focused(delta, size, MIN_WINDOW_SIZE);
+            }
+            Message::BalanceWindows => self.windows.balance(),
+            Message::ToggleTabs => self.tabbed = !self.tabbed,
+            Message::CycleBuffer(direction) => self.cycle_buffer(direction),
+            Message::DeleteWindow if !self.buffers.is_empty() => {
+                self.windows.delete_focused();
+            }
+            Message::Log(entry) => {
+                // Messages logged while a prompt is open, or while a more
+                // important one is shown, are only kept in the message log
+                if let Some((level, ref message)) = entry {
+                    self.messages.push(level, message.clone());
+                }
+                let replaces_current = match (&entry, &self.prompt_action) {
+                    (Some((level, _)), PromptAction::Log { level: current, .. }) => {
+                        level >= current
+                    }
+                    _ => true,
+                };
+                if !self.prompt_action.is_interactive() && replaces_current {
+                    self.prompt_action = entry
+                        .map(|(level, message)| PromptAction::Log { level, message })
+                        .unwrap_or(PromptAction::None);
+                    self.prompt_height = self.prompt_action.initial_height();
+                }
+            }
+            Message::ShowMessages if !self.prompt_action.is_interactive() => {
+                if self.messages.is_empty() {
+                    self.context.log("No messages");
+                    return ShouldRender::Yes;
+                }
+                let now = Instant::now();
+                self.prompt_action = PromptAction::PickCommand {
+                    message: "messages".into(),
+                    entries: self
+                        .messages
+                        .iter()
+                        .map(|entry| CommandEntry {
+                            name: entry.message.clone().into(),
+                            keys: format!(
+                                "{} {}",
+                                entry.level.name(),
+                                format_age(now.duration_since(entry.time))
+                            ),
+                        })
+                        .collect(),
+                    on_select: self.context.link.callback(|_| Message::ClosePrompt),
+                    on_highlight: None,
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::ClosePrompt => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+
+            // Quit zee but prompt to save changed buffers first
+            Message::Quit => {
+                if self.buffers.iter().any(Buffer::is_modified) {
+                    self.confirm(
+                        "One or more buffers have changed. Exit anyway?",
+                        false,
+                        PendingAction::Quit,
+                    );
+                } else {
+                    self.quit();
+                }
+            }
+
+            Message::Answer(answer) => self.resolve_pending_action(answer),
+            Message::Jump(direction) => self.jump(direction),
+            Message::Buffer(message) => {
+                let buffer_id = message.buffer_id();
+                if message.is_save_start() {
+                    let file_path = self
+                        .buffers
+                        .get(buffer_id)
+                        .and_then(|buffer| buffer.file_path())
+                        .cloned();
+                    if let Some(file_path) = file_path {
+                        if self.confirm_missing_parent_directory(buffer_id, &file_path) {
+                            return ShouldRender::Yes;
+                        }
+                    }
+                }
+                if let Some(cursor_id) = message.jumping_cursor() {
+                    if let Some(buffer) = self.buffers.get(buffer_id) {
+                        self.jumps.push(Jump {
+                            buffer_id,
+                            char_index: buffer.cursor(cursor_id).range().start,
+                        });
+                    }
+                }
+                let is_saved = message.is_saved();
+                let should_render = self.buffers.handle_message(message);
+                // Copying may have queued an escape sequence for the terminal,
+                // which is written now rather than while a frame is presented
+                self.context.clipboard.flush_pending();
+                if is_saved && self.context.config.language_servers {
+                    self.language_servers.sync(&self.buffers);
+                    self.language_servers.did_save(buffer_id);
+                }
+
+                // Finish killing a buffer that was saved first
+                if self.kill_buffer_after_save == Some(buffer_id) {
+                    match self.buffers.get(buffer_id).map(Buffer::modified_status) {
+                        Some(ModifiedStatus::Saving) => {}
+                        Some(ModifiedStatus::Unchanged) => {
+                            self.kill_buffer_after_save = None;
+                            self.kill_buffer(buffer_id);
+                            return ShouldRender::Yes;
+                        }
+                        _ => self.kill_buffer_after_save = None,
+                    }
+                }
+
+                // Background tasks, e.g. parsing, only need a redraw if they
+                // changed what's visible
+                return should_render;
+            }
+            // Ignored messages, e.g. opening a picker while another prompt is
+            // active, don't change anything on screen
+            _ => return ShouldRender::No,
+        }
+        ShouldRender::Yes
+    }
+
+    // Keeps the language servers, the git changes and the recovery files up
+    // to date with the buffers
+    fn tick(&self) -> Option<Self::Message> {
+        if self.language_servers.needs_sync(&self.buffers) {
+            Some(Message::SyncLanguageServers)
+        } else if self.buffers.iter().any(Buffer::is_git_diff_outdated) {
+            Some(Message::UpdateGitDiffs)
+        } else if self.is_autosave_pending() {
+            Some(Message::Autosave)
+        } else {
+            None
+        }
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        // Resized windows may not fit anymore, share the new space equally
+        if frame.size != self.frame.size {
+            self.windows.balance();
+        }
+        self.frame = frame;
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let buffers = if self.windows.is_empty() {
+            Splash::item_with_key(
+                FlexBasis::Auto,
+                "splash",
                 SplashProperties {
                     theme: Cow::Borrowed(&self.themes[self.theme_index].0.splash),
+                    hints: self.splash_hints(),
+                    recent_files: self
+                        .state
+                        .recent_files
+                        .iter()
+                        .map(|path| path.to_string_lossy().into_owned())
+                        .collect(),
                 },
             )
+        } else if self.tabbed {
+            // Only the focused window is shown in tabbed mode
+            let window = self.windows.focused_window().unwrap();
+            Item::auto(Layout::column([
+                self.tab_bar(window.id.buffer_id),
+                Item::auto(self.buffer_view_layout(window)),
+            ]))
         } else {
-            Item::auto(self.windows.layout(&mut |Window { id, focused, index }| {
-                let buffer = self.buffers.get(id.buffer_id).unwrap();
-                BufferView::with_key(
-                    format!("{}.{}", index, id).as_str(),
-                    BufferViewProperties {
-                        context: self.context.clone(),
-                        theme: Cow::Borrowed(&self.themes[self.theme_index].0.buffer),
-                        focused: focused && !self.prompt_action.is_interactive(),
-                        frame_id: index.one_based_index(),
-                        mode: buffer.mode(),
-                        repo: buffer.repository().cloned(),
-                        content: buffer.edit_tree_handle(),
-                        file_path: buffer.file_path().cloned(),
-                        cursor: BufferCursor::new(
-                            id.buffer_id,
-                            id.cursor_id,
-                            buffer.cursor(id.cursor_id).clone(),
-                            self.context.link.clone(),
-                        ),
-                        parse_tree: buffer.parse_tree().cloned(),
-                        modified_status: buffer.modified_status(),
-                    },
-                )
-            }))
+            Item::auto(
+                self.windows
+                    .layout(&mut |window| self.buffer_view_layout(window)),
+            )
         };
 
         Layout::column([
@@ -464,7 +2704,7 @@ impl Component for Editor {
 
     fn bindings(&self, bindings: &mut Bindings<Self>) {
         if bindings.is_empty() {
-            bindings::initialize(bindings);
+            bindings::initialize(bindings, &self.context.config.key_bindings, &self.context);
         }
     }
 
@@ -492,8 +2732,12 @@ impl Component for Editor {
 
         match merged_all {
             Some(NamedBindingQuery::Match(_command)) => match merged_without_self {
-                Some(NamedBindingQuery::Match(_command)) if self.prompt_action.is_log() => {
-                    // Clear log message
+                Some(NamedBindingQuery::Match(_command))
+                    if self.prompt_action.is_log()
+                        && (!self.prompt_action.is_error() || keys == [Key::Esc]) =>
+                {
+                    // Clear log message, errors stay until they're dismissed
+                    // with `ESC` or `C-g`
                     self.context.link.send(Message::Log(None));
                 }
                 _ => {}
@@ -515,6 +2759,136 @@ impl Component for Editor {
     }
 }
 
+/// Returns the built-in themes followed by the ones defined by the user. A
+/// user theme with the same name as a built-in one replaces it.
+fn load_themes(context: &Context) -> &'static [(Theme, &'static str)] {
+    let mut themes = THEMES.to_vec();
+    for user_theme in config::find_user_themes(context.config_dir.as_deref()) {
+        match user_theme {
+            Ok((name, base16)) => {
+                let theme = Theme::from_base16(&base16);
+                match themes.iter_mut().find(|(_, existing)| *existing == name) {
+                    Some(existing) => existing.0 = theme,
+                    None => themes.push((theme, Box::leak(name.into_boxed_str()))),
+                }
+            }
+            Err(error) => {
+                log::error!("{:#}", error);
+                context.log_error(format!("{:#}", error));
+            }
+        }
+    }
+    // Themes live for the whole lifetime of the editor
+    Box::leak(themes.into_boxed_slice())
+}
+
+/// Decodes the content of a text file with the charset of its `.editorconfig`
+/// settings, or with the encoding detected from the bytes if it isn't set
+fn decode_text(bytes: &[u8], charset: Option<FileEncoding>) -> encoding::DecodedText {
+    match charset {
+        Some(charset) => encoding::decode_with(bytes, charset.encoding),
+        None => encoding::decode(bytes),
+    }
+}
+
+/// Reads the buffer's file again, e.g. after it changed on disk. Binary files
+/// are shown as a hex dump.
+fn read_buffer_file(buffer: &Buffer, file_path: &Path) -> io::Result<encoding::DecodedText> {
+    if buffer.is_large_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "large files are only read in chunks",
+        ));
+    }
+    let bytes = fs::read(file_path)?;
+    Ok(if buffer.is_hex_view() {
+        encoding::hex_dump(&bytes)
+    } else {
+        encoding::decode_with(&bytes, buffer.encoding().encoding)
+    })
+}
+
+/// The position of a cursor in the buffer's file, to restore it when the file
+/// is opened again. Buffers without a file don't have one.
+fn file_position(buffer: &Buffer, cursor_id: CursorId) -> Option<FilePosition> {
+    let file_path = buffer.file_path()?;
+    let (line, column) = movement::line_and_column(buffer.edit_tree(), buffer.cursor(cursor_id));
+    Some(FilePosition {
+        file_path: canonical_path(file_path),
+        line,
+        column,
+    })
+}
+
+const MAX_CLOSED_BUFFERS: usize = 20;
+
+// Commands shown on the splash screen, by name, with a description
+const SPLASH_HINTS: &[(&str, &str)] = &[
+    ("find-file", "open a file"),
+    ("find-recent-file", "open a recent file"),
+    ("find-file-in-repo", "open a file in the repository"),
+    ("execute-command", "run a command"),
+    ("quit", "quit"),
+];
+
+// Windows can't be shrunk further than this, the height includes the status bar
+const MIN_WINDOW_SIZE: Size = Size::new(10, 3);
+
+struct ClosedBuffer {
+    file_path: Option<PathBuf>,
+    encoding: FileEncoding,
+    // Where the cursor was when the buffer was killed
+    position: CharIndex,
+    // The content if it wasn't saved to the file
+    unsaved_text: Option<Rope>,
+}
+
+/// An action waiting for the answer to a question asked with
+/// [`Editor::confirm`]
+enum PendingAction {
+    /// Quit even though some buffers have unsaved changes
+    Quit,
+    /// Save the buffer before killing it, or kill it without saving it
+    KillBuffer(BufferId),
+    /// Overwrite an existing file
+    SaveFileAs(BufferId, PathBuf),
+    /// Reload a modified buffer whose file changed on disk
+    Reload(BufferId),
+    /// Discard the unsaved changes of a buffer
+    Revert(BufferId),
+    /// Open a related file which doesn't exist yet, in a new window if a
+    /// direction is given
+    CreateRelatedFile(PathBuf, Option<FlexDirection>),
+    /// Create the missing directory of a file before saving a buffer to it
+    CreateParentDirectory(BufferId, PathBuf),
+    /// Restore the unsaved changes of a buffer from a recovery file left
+    /// over from a previous session, or discard them
+    Recover(BufferId, PathBuf),
+}
+
+struct SearchState {
+    // The window being searched
+    view: BufferViewId,
+    // Where the cursor was when the search started
+    origin: Cursor,
+    query: Query,
+    direction: Direction,
+    current: Option<SearchMatch>,
+}
+
+struct ReplaceState {
+    // The window where text is replaced
+    view: BufferViewId,
+    query: Query,
+    pattern: String,
+    replacement: String,
+    // Where to look for the next match
+    position: CharIndex,
+    // The match waiting for the user's choice
+    current: Option<Range<CharIndex>>,
+    num_replaced: usize,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct BufferViewId {
     buffer_id: BufferId,
@@ -539,3 +2913,172 @@ impl Display for BufferViewId {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::{self, Receiver, Sender},
+        thread,
+        time::Duration,
+    };
+    use zi::{
+        app::{App, ComponentMessage, MessageSender},
+        terminal::Event,
+    };
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    // Longer than the pause after which typed characters aren't pasted
+    const PAUSE: Duration = Duration::from_millis(20);
+
+    #[derive(Clone, Debug)]
+    struct ChannelSender(Sender<ComponentMessage>);
+
+    impl MessageSender for ChannelSender {
+        fn send(&self, message: ComponentMessage) {
+            let _ = self.0.send(message);
+        }
+
+        fn clone_box(&self) -> Box<dyn MessageSender> {
+            Box::new(self.clone())
+        }
+    }
+
+    /// Starts an editor with the files given as arguments
+    fn start(config_dir: &Path, args_files: Vec<PathBuf>) -> (App, Receiver<ComponentMessage>) {
+        let (sender, receiver) = mpsc::channel();
+        let editor = Editor::with(Properties {
+            args_files,
+            current_working_dir: config_dir.to_owned(),
+            config_dir: Some(config_dir.to_owned()),
+            config: EditorConfig::default(),
+            task_pool: TaskPool::new().unwrap(),
+            clipboard: crate::clipboard::create().unwrap(),
+            exit_status: Rc::new(Cell::new(ExitStatus::Interrupted)),
+        });
+        let app = App::new(ChannelSender(sender), Size::new(120, 20), editor);
+        (app, receiver)
+    }
+
+    /// Handles the messages sent so far and draws the screen as lines of text
+    fn draw(app: &mut App, receiver: &Receiver<ComponentMessage>) -> Vec<String> {
+        app.draw();
+        // Drawing sends more messages, e.g. when a buffer runs a command
+        while let Ok(message) = receiver.try_recv() {
+            app.handle_message(message);
+            app.draw();
+        }
+        let canvas = app.draw();
+        canvas
+            .buffer()
+            .chunks(canvas.size().width)
+            .map(|row| {
+                row.iter()
+                    .flatten()
+                    .map(|textel| textel.grapheme.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Presses the keys, drawing the screen after each of them
+    fn press(app: &mut App, receiver: &Receiver<ComponentMessage>, keys: &[Key]) -> Vec<String> {
+        let mut screen = draw(app, receiver);
+        for &key in keys {
+            app.handle_input(Event::KeyPress(key));
+            screen = draw(app, receiver);
+        }
+        screen
+    }
+
+    #[test]
+    fn run_buffer_commands_from_the_palette() {
+        let dir = TempDir::new("editor-palette");
+        let file_path = dir.write("a.txt", "text\n");
+        let (mut app, receiver) = start(dir.path(), vec![file_path]);
+
+        let mut keys = vec![Key::Alt('x')];
+        keys.extend("toggle-read-only".chars().map(Key::Char));
+        keys.push(Key::Char('\n'));
+        let screen = press(&mut app, &receiver, &keys);
+        assert!(
+            screen
+                .iter()
+                .any(|line| line.contains("Buffer is read-only")),
+            "{:#?}",
+            screen
+        );
+    }
+
+    #[test]
+    fn pasted_new_lines_are_not_indented() {
+        let dir = TempDir::new("editor-paste");
+        let file_path = dir.write("a.rs", "");
+        let (mut app, receiver) = start(dir.path(), vec![file_path]);
+
+        draw(&mut app, &receiver);
+        // The keys are pressed at once, as if pasted
+        for character in "    a\nb".chars() {
+            app.handle_input(Event::KeyPress(Key::Char(character)));
+        }
+        let screen = draw(&mut app, &receiver);
+        assert_eq!(
+            screen[..2],
+            ["     a", " b"].map(|line| format!("{:120}", line))
+        );
+
+        // Pressing `Enter` after a pause indents the new line
+        for key in [Key::Up, Key::Ctrl('e'), Key::Char('\n'), Key::Char('c')] {
+            thread::sleep(PAUSE);
+            press(&mut app, &receiver, &[key]);
+        }
+        let screen = draw(&mut app, &receiver);
+        assert_eq!(
+            screen[..3],
+            ["     a", "     c", " b"].map(|line| format!("{:120}", line))
+        );
+    }
+
+    #[test]
+    fn reopen_a_buffer_whose_file_is_open_again() {
+        let dir = TempDir::new("editor-reopen");
+        let file_path = dir.write("a.txt", "one\ntwo\nthree\n");
+        let (mut app, receiver) = start(dir.path(), vec![file_path]);
+
+        // Close the file and open it again
+        let mut keys = vec![Key::Down, Key::Down, Key::Ctrl('x'), Key::Char('k')];
+        keys.extend([Key::Char('\n'), Key::Ctrl('x'), Key::Ctrl('f')]);
+        keys.extend("a.txt\n".chars().map(Key::Char));
+        keys.extend([Key::Up, Key::Ctrl('x'), Key::Char('K')]);
+        let screen = press(&mut app, &receiver, &keys);
+        // The open buffer keeps its cursor
+        assert!(
+            screen.iter().any(|line| line.contains("3 lines    2: 0")),
+            "{:#?}",
+            screen
+        );
+    }
+
+    #[test]
+    fn offer_to_recover_opened_files() {
+        let dir = TempDir::new("editor-recovery");
+        let file_path = dir.write("a.txt", "saved\n");
+        let file_path = fs::canonicalize(file_path).unwrap();
+        recovery::write(
+            &recovery::file_path(dir.path(), &file_path),
+            &Rope::from("unsaved\n"),
+        )
+        .unwrap();
+
+        let (mut app, receiver) = start(dir.path(), vec![file_path]);
+        let screen = draw(&mut app, &receiver);
+        assert!(
+            screen
+                .iter()
+                .any(|line| line.contains("has unsaved changes from a previous session")),
+            "{:#?}",
+            screen
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use ropey::Rope;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// The recovery file of a file, which holds the unsaved content of its
+/// buffer. It's kept in the `recovery` directory of the config directory,
+/// named after the file's path with the separators and `%` percent-encoded,
+/// so distinct paths never share a recovery file.
+pub fn file_path(config_dir: &Path, file_path: &Path) -> PathBuf {
+    let mut name = String::new();
+    for character in file_path.to_string_lossy().chars() {
+        match character {
+            '%' | '/' | '\\' | ':' => name.push_str(&format!("%{:02X}", character as u8)),
+            character => name.push(character),
+        }
+    }
+    config_dir.join("recovery").join(name)
+}
+
+/// Writes the content of a buffer to its recovery file. The content is
+/// written to a temporary file first, so a crash mid-write doesn't lose the
+/// previous copy.
+pub fn write(recovery_path: &Path, text: &Rope) -> io::Result<()> {
+    if let Some(parent) = recovery_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_path = recovery_path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    text.write_to(&mut writer)?;
+    writer.into_inner()?.sync_all()?;
+    fs::rename(&temp_path, recovery_path)
+}
+
+/// Reads a recovery file left over from a previous session, unless the file
+/// was saved after it was written
+pub fn read(recovery_path: &Path, file_path: &Path) -> Option<Rope> {
+    let written = fs::metadata(recovery_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    let saved = fs::metadata(file_path).and_then(|metadata| metadata.modified());
+    if saved.map_or(false, |saved| saved > written) {
+        return None;
+    }
+    fs::read_to_string(recovery_path).ok().map(Rope::from)
+}
+
+/// Deletes a recovery file once its changes were saved or discarded
+pub fn remove(recovery_path: &Path) {
+    match fs::remove_file(recovery_path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => log::warn!(
+            "Could not remove recovery file `{}`: {}",
+            recovery_path.display(),
+            error
+        ),
+        _ => {}
+    }
+}
+
+/// How often modified buffers are written to their recovery files
+pub fn interval(seconds: u64) -> Option<Duration> {
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn file_path_escapes_separators() {
+        let config_dir = Path::new("/config");
+        assert_eq!(
+            file_path(config_dir, Path::new("/src/a.rs")),
+            Path::new("/config/recovery/%2Fsrc%2Fa.rs")
+        );
+        assert_eq!(
+            file_path(config_dir, Path::new("C:\\src\\a.rs")),
+            Path::new("/config/recovery/C%3A%5Csrc%5Ca.rs")
+        );
+        assert_ne!(
+            file_path(config_dir, Path::new("/a%/b")),
+            file_path(config_dir, Path::new("/a/%b"))
+        );
+        assert_ne!(
+            file_path(config_dir, Path::new("/a%2Fb")),
+            file_path(config_dir, Path::new("/a/b"))
+        );
+    }
+
+    #[test]
+    fn read_back_written_text() {
+        let dir = TempDir::new("recovery-round-trip");
+        let file_path = dir.write("a.txt", "saved\n");
+        let recovery_path = super::file_path(dir.path(), &file_path);
+        assert_eq!(read(&recovery_path, &file_path), None);
+
+        write(&recovery_path, &Rope::from("unsaved\n")).unwrap();
+        assert_eq!(
+            read(&recovery_path, &file_path),
+            Some(Rope::from("unsaved\n"))
+        );
+
+        remove(&recovery_path);
+        assert_eq!(read(&recovery_path, &file_path), None);
+    }
+
+    #[test]
+    fn ignore_recovery_older_than_the_file() {
+        let dir = TempDir::new("recovery-outdated");
+        let file_path = dir.write("a.txt", "saved\n");
+        let recovery_path = super::file_path(dir.path(), &file_path);
+        write(&recovery_path, &Rope::from("unsaved\n")).unwrap();
+
+        // Save the file until its modification time is after the recovery
+        // file's, whatever the resolution of the file system's timestamps
+        let written = fs::metadata(&recovery_path).unwrap().modified().unwrap();
+        while fs::metadata(&file_path).unwrap().modified().unwrap() <= written {
+            std::thread::sleep(Duration::from_millis(10));
+            fs::write(&file_path, "saved again\n").unwrap();
+        }
+        assert_eq!(read(&recovery_path, &file_path), None);
+    }
+}
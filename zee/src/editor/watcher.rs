@@ -0,0 +1,99 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use zi::ComponentLink;
+
+use super::{Editor, Message};
+
+/// Watches the files of open buffers for changes made by other programs, e.g.
+/// a `git checkout` or a formatter. Changes are sent to the editor as
+/// `Message::FileChanged`.
+///
+/// The parent directories are watched rather than the files themselves, as
+/// files which are replaced by renaming another file over them, like zee does
+/// when saving, would otherwise stop being watched.
+pub struct FileWatcher {
+    watcher: Option<RecommendedWatcher>,
+    // Watched directories and the number of open files in each
+    directories: HashMap<PathBuf, usize>,
+}
+
+impl FileWatcher {
+    pub fn new(link: ComponentLink<Editor>) -> Self {
+        let watcher =
+            notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+                Ok(event) if is_change(&event.kind) => {
+                    for path in event.paths {
+                        link.send(Message::FileChanged(path));
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => log::warn!("Error while watching files: {}", error),
+            })
+            .map_err(|error| log::error!("Could not watch files for changes: {}", error))
+            .ok();
+        Self {
+            watcher,
+            directories: HashMap::new(),
+        }
+    }
+
+    pub fn watch(&mut self, file_path: &Path) {
+        let (watcher, directory) = match (self.watcher.as_mut(), watched_directory(file_path)) {
+            (Some(watcher), Some(directory)) => (watcher, directory),
+            _ => return,
+        };
+        if let Some(count) = self.directories.get_mut(&directory) {
+            *count += 1;
+            return;
+        }
+        // Only count directories which are actually watched
+        match watcher.watch(&directory, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                self.directories.insert(directory, 1);
+            }
+            Err(error) => log::warn!("Could not watch {}: {}", directory.display(), error),
+        }
+    }
+
+    pub fn unwatch(&mut self, file_path: &Path) {
+        let (watcher, directory) = match (self.watcher.as_mut(), watched_directory(file_path)) {
+            (Some(watcher), Some(directory)) => (watcher, directory),
+            _ => return,
+        };
+        let count = match self.directories.get_mut(&directory) {
+            Some(count) => count,
+            None => return,
+        };
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            self.directories.remove(&directory);
+            if let Err(error) = watcher.unwatch(&directory) {
+                log::warn!("Could not stop watching {}: {}", directory.display(), error);
+            }
+        }
+    }
+}
+
+/// Returns the canonical path of a file, as reported in watcher events. The
+/// file itself may not exist yet.
+pub fn canonical_path(file_path: &Path) -> Option<PathBuf> {
+    let directory = watched_directory(file_path)?;
+    Some(directory.join(file_path.file_name()?))
+}
+
+fn watched_directory(file_path: &Path) -> Option<PathBuf> {
+    let parent = match file_path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+        Some(parent) => parent,
+        None => return None,
+    };
+    parent.canonicalize().ok()
+}
+
+#[inline]
+fn is_change(kind: &EventKind) -> bool {
+    matches!(kind, EventKind::Create(_) | EventKind::Modify(_))
+}
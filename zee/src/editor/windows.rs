@@ -2,7 +2,7 @@ use std::{
     fmt::Display,
     ops::{Add, Rem},
 };
-use zi::{Container, FlexDirection, Item, Layout};
+use zi::{Container, FlexDirection, Item, Layout, Size};
 
 pub(super) enum CycleFocus {
     Next,
@@ -19,6 +19,14 @@ pub(super) struct WindowTree<IdT> {
     nodes: Vec<Node<IdT>>,
     focused_index: WindowIndex,
     num_windows: WindowIndex,
+    // The windows to restore when the focused window is zoomed
+    zoomed: Option<ZoomedTree<IdT>>,
+}
+
+struct ZoomedTree<IdT> {
+    nodes: Vec<Node<IdT>>,
+    focused_index: WindowIndex,
+    num_windows: WindowIndex,
 }
 
 impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
@@ -27,20 +35,30 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
             nodes: Vec::new(),
             focused_index: WindowIndex(0),
             num_windows: WindowIndex(0),
+            zoomed: None,
         }
     }
 
     pub fn clear(&mut self) {
+        self.zoomed = None;
         self.nodes.clear();
         self.focused_index = WindowIndex(0);
         self.num_windows = WindowIndex(0);
     }
 
+    /// The windows, including the ones hidden while a window is zoomed
     pub fn nodes_mut(&mut self) -> impl Iterator<Item = &mut IdT> {
-        self.nodes.iter_mut().filter_map(|node| match node {
-            Node::Window(id) => Some(id),
-            _ => None,
-        })
+        self.nodes
+            .iter_mut()
+            .chain(
+                self.zoomed
+                    .iter_mut()
+                    .flat_map(|zoomed| zoomed.nodes.iter_mut()),
+            )
+            .filter_map(|node| match node {
+                Node::Window(id, _) => Some(id),
+                _ => None,
+            })
     }
 
     pub fn is_empty(&self) -> bool {
@@ -48,26 +66,40 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
     }
 
     pub fn add(&mut self, id: IdT) {
-        self.nodes.push(Node::Window(id));
+        self.zoomed = None;
+        self.nodes.push(Node::Window(id, None));
         self.focused_index = self.num_windows; // Focus the newly added window
         self.num_windows = self.num_windows.increment();
     }
 
     pub fn delete_focused(&mut self) {
+        self.zoomed = None;
         let focused = self.find_focused_window();
         self.nodes.remove(focused.node_index);
         self.num_windows = self.num_windows.saturating_decrement();
         self.focused_index = self.focused_index.saturating_decrement();
+        self.remove_redundant_containers();
+    }
 
+    /// Removes containers left empty and moves windows left on their own to
+    /// the parent container
+    fn remove_redundant_containers(&mut self) {
         let mut node_index = 0;
         while node_index < self.nodes.len() {
             match self.nodes[node_index..] {
-                [Node::ContainerStart(_), window @ Node::Window(_), Node::ContainerEnd, ..] => {
-                    self.nodes
-                        .splice(node_index..node_index + 3, std::iter::once(window));
+                [Node::ContainerStart(_), Node::Window(id, _), Node::ContainerEnd, ..] => {
+                    // The window moves to the parent container, whose direction
+                    // may differ, so its size no longer applies
+                    self.nodes.splice(
+                        node_index..node_index + 3,
+                        std::iter::once(Node::Window(id, None)),
+                    );
+                    // The parent may now be redundant too
+                    node_index = node_index.saturating_sub(1);
                 }
                 [Node::ContainerStart(_), Node::ContainerEnd, ..] => {
                     self.nodes.drain(node_index..node_index + 2);
+                    node_index = node_index.saturating_sub(1);
                 }
                 _ => {
                     node_index += 1;
@@ -76,10 +108,76 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
         }
     }
 
+    /// Creates a tree from nodes as returned by `to_nodes`. The nodes may be
+    /// missing windows, e.g. ones that couldn't be restored.
+    pub fn from_nodes(nodes: Vec<Node<IdT>>, focused_index: usize) -> Self {
+        let num_windows = nodes
+            .iter()
+            .filter(|node| matches!(node, Node::Window(..)))
+            .count();
+        let mut tree = Self {
+            nodes,
+            focused_index: WindowIndex(focused_index.min(num_windows.saturating_sub(1))),
+            num_windows: WindowIndex(num_windows),
+            zoomed: None,
+        };
+        tree.remove_redundant_containers();
+        tree
+    }
+
+    /// The nodes of the tree in layout order, with each window's id mapped
+    /// using `map_id`, and the index of the focused window
+    pub fn to_nodes<T>(&self, mut map_id: impl FnMut(IdT) -> T) -> (Vec<Node<T>>, usize) {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| match *node {
+                Node::Window(id, size) => Node::Window(map_id(id), size),
+                Node::ContainerStart(direction) => Node::ContainerStart(direction),
+                Node::ContainerEnd => Node::ContainerEnd,
+            })
+            .collect();
+        (nodes, self.focused_index.0)
+    }
+
     pub fn delete_all_except_focused(&mut self) {
+        self.zoomed = None;
+        self.keep_only_focused();
+    }
+
+    /// Shows only the focused window, or restores the other windows exactly as
+    /// they were if it's already zoomed. Changing the windows while zoomed,
+    /// e.g. by splitting, discards the hidden windows.
+    pub fn toggle_zoom(&mut self) {
+        match self.zoomed.take() {
+            Some(zoomed) => {
+                // The zoomed window may have switched to a different buffer
+                let focused = self.get_focused();
+                self.nodes = zoomed.nodes;
+                self.focused_index = zoomed.focused_index;
+                self.num_windows = zoomed.num_windows;
+                if let Some(id) = focused {
+                    self.set_focused(id);
+                }
+            }
+            None if self.num_windows > WindowIndex(1) => {
+                self.zoomed = Some(ZoomedTree {
+                    nodes: self.nodes.clone(),
+                    focused_index: self.focused_index,
+                    num_windows: self.num_windows,
+                });
+                self.keep_only_focused();
+            }
+            None => {}
+        }
+    }
+
+    fn keep_only_focused(&mut self) {
         let focused = self.nodes.remove(self.find_focused_window().node_index);
         self.nodes.clear();
-        self.nodes.push(focused);
+        if let Node::Window(id, _) = focused {
+            self.nodes.push(Node::Window(id, None));
+        }
         self.focused_index = WindowIndex(0);
         self.num_windows = WindowIndex(1);
     }
@@ -88,9 +186,11 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
         if self.num_windows == WindowIndex(0) {
             return;
         }
+        self.zoomed = None;
 
         let focused = self.find_focused_window();
-        self.nodes.insert(focused.node_index + 1, Node::Window(id));
+        self.nodes
+            .insert(focused.node_index + 1, Node::Window(id, None));
         if direction != focused.direction {
             self.nodes
                 .insert(focused.node_index, Node::ContainerStart(direction));
@@ -127,12 +227,16 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
 
         for window in self.nodes.iter() {
             match window {
-                Node::Window(id) => {
-                    container.push(Item::auto(lay_component(Window {
+                Node::Window(id, size) => {
+                    let window = lay_component(Window {
                         id: *id,
                         focused: window_index == self.focused_index,
                         index: window_index,
-                    })));
+                    });
+                    container.push(match size {
+                        Some(size) => Item::fixed(*size)(window),
+                        None => Item::auto(window),
+                    });
                     window_index = window_index.increment();
                 }
                 Node::ContainerStart(direction) => {
@@ -153,10 +257,19 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
         container.into()
     }
 
+    /// The focused window on its own, e.g. to show it without the others
+    pub fn focused_window(&self) -> Option<Window<IdT>> {
+        self.get_focused().map(|id| Window {
+            id,
+            focused: true,
+            index: self.focused_index,
+        })
+    }
+
     pub fn get_focused(&self) -> Option<IdT> {
         let mut window_index = self.focused_index;
         for window in self.nodes.iter() {
-            if let Node::Window(id) = window {
+            if let Node::Window(id, _) = window {
                 if window_index == WindowIndex(0) {
                     return Some(*id);
                 }
@@ -169,7 +282,7 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
     pub fn set_focused(&mut self, id: IdT) {
         let mut window_index = self.focused_index;
         for window in self.nodes.iter_mut() {
-            if let Node::Window(current_id) = window {
+            if let Node::Window(current_id, _) = window {
                 if window_index == WindowIndex(0) {
                     *current_id = id;
                     return;
@@ -180,6 +293,139 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
         }
     }
 
+    /// Grows the focused window by `delta` lines or columns along the direction
+    /// of its container, shrinking its siblings. Windows are kept at least
+    /// `min_size` large. `size` is the size of the whole tree.
+    pub fn resize_focused(&mut self, delta: isize, size: Size, min_size: Size) {
+        if self.num_windows == WindowIndex(0) {
+            return;
+        }
+
+        let focused = self.find_focused_window();
+        let extents = self.window_extents(size);
+        let extent = &extents[self.focused_index.0];
+        let min_extent = main_extent(focused.direction, min_size);
+        let max_extent = extent
+            .container
+            .saturating_sub(min_extent * (extent.num_siblings - 1));
+        if extent.num_siblings == 1 || max_extent < min_extent {
+            return;
+        }
+        let new_extent =
+            ((extent.window as isize + delta).max(0) as usize).clamp(min_extent, max_extent);
+
+        // Only one window per container has a fixed size, the others share the
+        // remaining space
+        for sibling in self.siblings(focused.node_index) {
+            if let Node::Window(_, size) = &mut self.nodes[sibling] {
+                *size = None;
+            }
+        }
+        if let Node::Window(_, size) = &mut self.nodes[focused.node_index] {
+            *size = Some(new_extent);
+        }
+    }
+
+    /// Shares the space equally between windows again, undoing any resizing
+    pub fn balance(&mut self) {
+        for node in self.nodes.iter_mut() {
+            if let Node::Window(_, size) = node {
+                *size = None;
+            }
+        }
+    }
+
+    /// Estimates the extent of each window, in order, when the tree is laid out
+    /// in a frame of the given size
+    fn window_extents(&self, size: Size) -> Vec<WindowExtent> {
+        let mut extents = Vec::with_capacity(self.num_windows.0);
+        self.container_extents(0, FlexDirection::Row, size, &mut extents);
+        extents
+    }
+
+    fn container_extents(
+        &self,
+        node_index: usize,
+        direction: FlexDirection,
+        size: Size,
+        extents: &mut Vec<WindowExtent>,
+    ) {
+        let children = self.children(node_index);
+        let container = main_extent(direction, size);
+        let fixed_size = |child: usize| match self.nodes[child] {
+            Node::Window(_, size) => size,
+            _ => None,
+        };
+        let fixed: usize = children.iter().filter_map(|&child| fixed_size(child)).sum();
+        let num_auto = children
+            .iter()
+            .filter(|&&child| fixed_size(child).is_none())
+            .count();
+        let free = container.saturating_sub(fixed);
+
+        let mut auto_index = 0;
+        for &child in children.iter() {
+            let child_extent = fixed_size(child).unwrap_or_else(|| {
+                auto_index += 1;
+                free / num_auto + usize::from(auto_index <= free % num_auto)
+            });
+            let child_size = with_main_extent(direction, size, child_extent);
+            match self.nodes[child] {
+                Node::Window(..) => extents.push(WindowExtent {
+                    window: child_extent,
+                    container,
+                    num_siblings: children.len(),
+                }),
+                Node::ContainerStart(child_direction) => {
+                    self.container_extents(child + 1, child_direction, child_size, extents)
+                }
+                Node::ContainerEnd => unreachable!(),
+            }
+        }
+    }
+
+    /// The indices of the nodes in the container starting at `node_index`,
+    /// not including nested containers' children
+    fn children(&self, mut node_index: usize) -> Vec<usize> {
+        let mut children = Vec::new();
+        let mut depth = 0;
+        while node_index < self.nodes.len() {
+            match self.nodes[node_index] {
+                Node::Window(..) if depth == 0 => children.push(node_index),
+                Node::Window(..) => {}
+                Node::ContainerStart(_) => {
+                    if depth == 0 {
+                        children.push(node_index);
+                    }
+                    depth += 1;
+                }
+                Node::ContainerEnd if depth == 0 => break,
+                Node::ContainerEnd => depth -= 1,
+            }
+            node_index += 1;
+        }
+        children
+    }
+
+    /// The indices of the nodes in the same container as `node_index`
+    fn siblings(&self, node_index: usize) -> Vec<usize> {
+        // Find the start of the enclosing container
+        let mut depth = 0;
+        let mut start = 0;
+        for index in (0..node_index).rev() {
+            match self.nodes[index] {
+                Node::ContainerEnd => depth += 1,
+                Node::ContainerStart(_) if depth == 0 => {
+                    start = index + 1;
+                    break;
+                }
+                Node::ContainerStart(_) => depth -= 1,
+                Node::Window(..) => {}
+            }
+        }
+        self.children(start)
+    }
+
     fn find_focused_window(&self) -> NodeRef {
         self.find_window_node(self.focused_index)
     }
@@ -188,7 +434,7 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
         let mut container_stack = vec![FlexDirection::Row];
         for (node_index, node) in self.nodes.iter().enumerate() {
             match node {
-                Node::Window(_) => {
+                Node::Window(..) => {
                     if window_index == WindowIndex(0) {
                         return NodeRef {
                             direction: container_stack.pop().unwrap(),
@@ -218,9 +464,32 @@ struct NodeRef {
     node_index: usize,
 }
 
+struct WindowExtent {
+    // Lines or columns along the direction of the window's container
+    window: usize,
+    container: usize,
+    num_siblings: usize,
+}
+
+fn main_extent(direction: FlexDirection, size: Size) -> usize {
+    match direction {
+        FlexDirection::Row | FlexDirection::RowReverse => size.width,
+        FlexDirection::Column | FlexDirection::ColumnReverse => size.height,
+    }
+}
+
+fn with_main_extent(direction: FlexDirection, size: Size, extent: usize) -> Size {
+    match direction {
+        FlexDirection::Row | FlexDirection::RowReverse => Size::new(extent, size.height),
+        FlexDirection::Column | FlexDirection::ColumnReverse => Size::new(size.width, extent),
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
-enum Node<IdT> {
-    Window(IdT),
+pub(super) enum Node<IdT> {
+    // A window with a fixed size along its container's direction if it was
+    // resized, otherwise it shares the space equally with its siblings
+    Window(IdT, Option<usize>),
     ContainerStart(FlexDirection),
     ContainerEnd,
 }
@@ -229,7 +498,8 @@ impl<IdT: Display> Display for Node<IdT> {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Node::*;
         match self {
-            Window(id) => write!(formatter, "<{}/>", id),
+            Window(id, None) => write!(formatter, "<{}/>", id),
+            Window(id, Some(size)) => write!(formatter, "<{} size={}/>", id, size),
             ContainerStart(direction) => write!(formatter, "<Container {:?}>", direction),
             ContainerEnd => write!(formatter, "</Container>"),
         }
@@ -274,3 +544,102 @@ impl Rem<Self> for WindowIndex {
         Self(self.0 % modulus.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Size = Size::new(90, 20);
+    const MIN_SIZE: Size = Size::new(10, 2);
+
+    // Windows side by side, focusing the one at `focused_index`
+    fn windows_in_a_row(num_windows: usize, focused_index: usize) -> WindowTree<usize> {
+        let nodes = (0..num_windows).map(|id| Node::Window(id, None)).collect();
+        WindowTree::from_nodes(nodes, focused_index)
+    }
+
+    fn extents(tree: &WindowTree<usize>) -> Vec<usize> {
+        tree.window_extents(FRAME)
+            .iter()
+            .map(|extent| extent.window)
+            .collect()
+    }
+
+    #[test]
+    fn resize_clamps_to_the_minimum_size() {
+        let mut tree = windows_in_a_row(3, 0);
+        assert_eq!(extents(&tree), [30, 30, 30]);
+
+        tree.resize_focused(-100, FRAME, MIN_SIZE);
+        assert_eq!(extents(&tree), [10, 40, 40]);
+
+        // The siblings can't shrink below the minimum size either
+        tree.resize_focused(100, FRAME, MIN_SIZE);
+        assert_eq!(extents(&tree), [70, 10, 10]);
+    }
+
+    #[test]
+    fn resize_the_last_window() {
+        let mut tree = windows_in_a_row(3, 2);
+        tree.resize_focused(6, FRAME, MIN_SIZE);
+        assert_eq!(extents(&tree), [27, 27, 36]);
+        tree.resize_focused(-12, FRAME, MIN_SIZE);
+        assert_eq!(extents(&tree), [33, 33, 24]);
+
+        // A window on its own takes up the whole frame
+        let mut tree = windows_in_a_row(1, 0);
+        tree.resize_focused(6, FRAME, MIN_SIZE);
+        assert_eq!(extents(&tree), [90]);
+    }
+
+    #[test]
+    fn balance_keeps_the_splits() {
+        // Two windows side by side, the second split into two rows
+        let mut tree = WindowTree::from_nodes(
+            vec![
+                Node::Window(0, Some(60)),
+                Node::ContainerStart(FlexDirection::Column),
+                Node::Window(1, Some(15)),
+                Node::Window(2, None),
+                Node::ContainerEnd,
+            ],
+            0,
+        );
+        assert_eq!(extents(&tree), [60, 15, 5]);
+
+        tree.balance();
+        assert_eq!(extents(&tree), [45, 10, 10]);
+        let (nodes, focused) = tree.to_nodes(|id| id);
+        assert_eq!(focused, 0);
+        assert_eq!(
+            nodes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["<0/>", "<Container Column>", "<1/>", "<2/>", "</Container>"]
+        );
+    }
+
+    #[test]
+    fn cycle_focus_wraps_around() {
+        let mut tree = WindowTree::<usize>::new();
+        tree.cycle_focus(CycleFocus::Next);
+        tree.cycle_focus(CycleFocus::Previous);
+        assert_eq!(tree.get_focused(), None);
+
+        let mut tree = windows_in_a_row(1, 0);
+        tree.cycle_focus(CycleFocus::Next);
+        assert_eq!(tree.get_focused(), Some(0));
+        tree.cycle_focus(CycleFocus::Previous);
+        assert_eq!(tree.get_focused(), Some(0));
+
+        let mut tree = windows_in_a_row(3, 1);
+        let mut cycle = |direction| {
+            tree.cycle_focus(direction);
+            tree.get_focused().unwrap()
+        };
+        assert_eq!(cycle(CycleFocus::Next), 2);
+        assert_eq!(cycle(CycleFocus::Next), 0);
+        assert_eq!(cycle(CycleFocus::Previous), 2);
+        assert_eq!(cycle(CycleFocus::Previous), 1);
+        assert_eq!(cycle(CycleFocus::Previous), 0);
+        assert_eq!(cycle(CycleFocus::Previous), 2);
+    }
+}
@@ -0,0 +1,256 @@
+use globset::GlobBuilder;
+use std::{fs, path::Path};
+use zee_edit::line_ending::LineEnding;
+use zee_grammar::config::{IndentationConfig, IndentationUnit};
+
+use crate::encoding::{self, FileEncoding};
+
+/// The settings for a file from the `.editorconfig` files in its directory
+/// and the directories above it, see <https://editorconfig.org>. Settings
+/// which aren't set by any section matching the file are `None`.
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+    pub indent_style: Option<IndentationUnit>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub end_of_line: Option<LineEnding>,
+    pub charset: Option<FileEncoding>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+}
+
+impl Settings {
+    /// Reads the settings for `file_path`. Files closer to it take precedence
+    /// and the search stops at a file with `root = true`. Unreadable files
+    /// are ignored.
+    pub fn find(file_path: &Path) -> Self {
+        let file_path = fs::canonicalize(file_path).unwrap_or_else(|_| file_path.to_owned());
+        let mut files = Vec::new();
+        for directory in file_path.ancestors().skip(1) {
+            let contents = match fs::read_to_string(directory.join(EDITORCONFIG_FILE_NAME)) {
+                Ok(contents) => contents,
+                Err(_) => continue,
+            };
+            let is_root = is_root(&contents);
+            files.push((directory.to_owned(), contents));
+            if is_root {
+                break;
+            }
+        }
+
+        let mut config = Self::default();
+        for (directory, contents) in files.iter().rev() {
+            config.apply_file(directory, contents, &file_path);
+        }
+        config
+    }
+
+    /// The indentation of `mode_indentation` with the indent style and size
+    /// replaced by the ones which are set
+    pub fn indentation(&self, mode_indentation: &IndentationConfig) -> IndentationConfig {
+        let unit = self
+            .indent_style
+            .clone()
+            .unwrap_or_else(|| mode_indentation.unit.clone());
+        // With tabs, the width of a tab is also the width of one level
+        let width = match unit {
+            IndentationUnit::Space => self.indent_size.or(self.tab_width),
+            IndentationUnit::Tab => self.tab_width.or(self.indent_size),
+        };
+        IndentationConfig {
+            width: width.unwrap_or(mode_indentation.width),
+            unit,
+            indent_after: mode_indentation.indent_after.clone(),
+        }
+    }
+
+    fn apply_file(&mut self, directory: &Path, contents: &str, file_path: &Path) {
+        let relative_path = match file_path.strip_prefix(directory) {
+            Ok(relative_path) => relative_path,
+            Err(_) => return,
+        };
+        let mut section_matches = false;
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(pattern) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section_matches = section_matches_path(pattern, relative_path);
+            } else if let Some((key, value)) = line.split_once('=') {
+                if section_matches {
+                    self.set(&key.trim().to_lowercase(), &value.trim().to_lowercase());
+                }
+            }
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let parse_bool = || match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        match key {
+            "indent_style" => {
+                self.indent_style = match value {
+                    "space" => Some(IndentationUnit::Space),
+                    "tab" => Some(IndentationUnit::Tab),
+                    _ => None,
+                }
+            }
+            // `indent_size = tab` means the width of a tab, which is the
+            // fallback when the size isn't set
+            "indent_size" => self.indent_size = value.parse().ok().filter(|&size| size > 0),
+            "tab_width" => self.tab_width = value.parse().ok().filter(|&size| size > 0),
+            "end_of_line" => {
+                self.end_of_line = match value {
+                    "lf" => Some(LineEnding::Lf),
+                    "crlf" => Some(LineEnding::CrLf),
+                    _ => None,
+                }
+            }
+            "charset" => {
+                self.charset = match value {
+                    "utf-8-bom" => Some(FileEncoding {
+                        bom: true,
+                        ..FileEncoding::UTF8
+                    }),
+                    label => encoding::for_label(label).map(|encoding| FileEncoding {
+                        encoding,
+                        bom: false,
+                    }),
+                }
+            }
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = parse_bool(),
+            "insert_final_newline" => self.insert_final_newline = parse_bool(),
+            _ => {}
+        }
+    }
+}
+
+/// Whether the preamble of an `.editorconfig` file, before the first section,
+/// has `root = true`
+fn is_root(contents: &str) -> bool {
+    contents
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .any(|(key, value)| {
+            key.trim().eq_ignore_ascii_case("root") && value.trim().eq_ignore_ascii_case("true")
+        })
+}
+
+/// Whether a section's glob matches a path relative to the `.editorconfig`
+/// file. Globs without a `/` match files in any subdirectory, e.g. `*.rs`.
+fn section_matches_path(pattern: &str, relative_path: &Path) -> bool {
+    let pattern = match pattern.strip_prefix('/') {
+        Some(pattern) => pattern.to_owned(),
+        None if pattern.contains('/') => pattern.to_owned(),
+        None => format!("**/{}", pattern),
+    };
+    GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher().is_match(relative_path))
+        .unwrap_or(false)
+}
+
+const EDITORCONFIG_FILE_NAME: &str = ".editorconfig";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn match_section_globs() {
+        assert!(section_matches_path("*", Path::new("a.rs")));
+        assert!(section_matches_path("*.rs", Path::new("src/a.rs")));
+        assert!(!section_matches_path("*.rs", Path::new("src/a.py")));
+        assert!(section_matches_path("*.{rs,py}", Path::new("a.py")));
+        assert!(!section_matches_path("*.{rs,py}", Path::new("a.md")));
+
+        // Globs with a slash are relative to the `.editorconfig` file
+        assert!(section_matches_path("src/*.rs", Path::new("src/a.rs")));
+        assert!(!section_matches_path("src/*.rs", Path::new("lib/src/a.rs")));
+        assert!(!section_matches_path("src/*.rs", Path::new("src/b/a.rs")));
+        assert!(section_matches_path(
+            "src/**/*.rs",
+            Path::new("src/b/c/a.rs")
+        ));
+        assert!(section_matches_path("src/**/*.rs", Path::new("src/a.rs")));
+        assert!(section_matches_path("/a.rs", Path::new("a.rs")));
+        assert!(!section_matches_path("/a.rs", Path::new("src/a.rs")));
+    }
+
+    #[test]
+    fn nearer_files_override_farther_ones() {
+        let dir = TempDir::new("editorconfig-nearer");
+        dir.write(
+            ".editorconfig",
+            "[*]\nindent_style = tab\ntab_width = 8\n[*.rs]\nindent_size = 2\n",
+        );
+        dir.write("src/.editorconfig", "[*.rs]\nindent_style = space\n");
+        let file_path = dir.write("src/a.rs", "");
+
+        let settings = Settings::find(&file_path);
+        assert!(matches!(
+            settings.indent_style,
+            Some(IndentationUnit::Space)
+        ));
+        assert_eq!(settings.indent_size, Some(2));
+        assert_eq!(settings.tab_width, Some(8));
+        assert_eq!(settings.end_of_line, None);
+    }
+
+    #[test]
+    fn root_stops_the_search() {
+        let dir = TempDir::new("editorconfig-root");
+        dir.write(".editorconfig", "[*]\ntab_width = 8\nend_of_line = crlf\n");
+        dir.write("src/.editorconfig", "root = true\n[*]\nend_of_line = lf\n");
+        let file_path = dir.write("src/a.rs", "");
+
+        let settings = Settings::find(&file_path);
+        assert_eq!(settings.end_of_line, Some(LineEnding::Lf));
+        assert_eq!(settings.tab_width, None);
+    }
+
+    #[test]
+    fn indentation_overrides_the_mode() {
+        let mode = IndentationConfig {
+            width: 4,
+            unit: IndentationUnit::Space,
+            indent_after: vec!['{'],
+        };
+        let indentation = |contents: &str| {
+            let mut settings = Settings::default();
+            settings.apply_file(Path::new("/"), contents, Path::new("/a.rs"));
+            settings.indentation(&mode)
+        };
+
+        let unchanged = indentation("");
+        assert!(matches!(unchanged.unit, IndentationUnit::Space));
+        assert_eq!(unchanged.width, 4);
+        assert_eq!(unchanged.indent_after, vec!['{']);
+
+        let spaces = indentation("[*]\nindent_size = 2\ntab_width = 8\n");
+        assert!(matches!(spaces.unit, IndentationUnit::Space));
+        assert_eq!(spaces.width, 2);
+
+        // A tab is as wide as `tab_width`, falling back to `indent_size`
+        let tabs = indentation("[*]\nindent_style = tab\nindent_size = 2\ntab_width = 8\n");
+        assert!(matches!(tabs.unit, IndentationUnit::Tab));
+        assert_eq!(tabs.width, 8);
+        let tabs = indentation("[*]\nindent_style = tab\nindent_size = 3\n");
+        assert_eq!(tabs.width, 3);
+
+        // `indent_size = tab` leaves the size to `tab_width`
+        let tab_size = indentation("[*]\nindent_size = tab\ntab_width = 6\n");
+        assert!(matches!(tab_size.unit, IndentationUnit::Space));
+        assert_eq!(tab_size.width, 6);
+    }
+}
@@ -0,0 +1,340 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use ropey::Rope;
+use std::{cmp, fmt, fmt::Write, io, str};
+
+/// The character encoding of a file on disk. Buffers are always UTF-8 in
+/// memory, files are decoded when opened and encoded again when saved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileEncoding {
+    pub encoding: &'static Encoding,
+    /// Whether the file starts with a byte order mark
+    pub bom: bool,
+}
+
+impl FileEncoding {
+    pub const UTF8: Self = Self {
+        encoding: UTF_8,
+        bom: false,
+    };
+}
+
+impl Default for FileEncoding {
+    fn default() -> Self {
+        Self::UTF8
+    }
+}
+
+impl fmt::Display for FileEncoding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.encoding.name())?;
+        if self.bom {
+            write!(formatter, " with BOM")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the encoding for a label like `latin1` or `utf-16le`, as defined by
+/// the WHATWG Encoding Standard
+pub fn for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label_no_replacement(label.trim().as_bytes())
+}
+
+/// The decoded contents of a file
+pub struct DecodedText {
+    pub text: Rope,
+    pub encoding: FileEncoding,
+    /// Whether some bytes couldn't be decoded and were replaced by U+FFFD
+    pub had_errors: bool,
+}
+
+/// Decodes the contents of a file, detecting its encoding. A byte order mark
+/// takes precedence, then valid UTF-8 is assumed to be UTF-8. Otherwise, the
+/// encoding is guessed from the bytes.
+pub fn decode(bytes: &[u8]) -> DecodedText {
+    let encoding = match Encoding::for_bom(bytes) {
+        Some((encoding, _)) => encoding,
+        None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+        None => {
+            let mut detector = chardetng::EncodingDetector::new();
+            detector.feed(bytes, true);
+            detector.guess(None, true)
+        }
+    };
+    decode_with(bytes, encoding)
+}
+
+/// Decodes the contents of a file using `encoding`, unless the file starts
+/// with a byte order mark for a different encoding
+pub fn decode_with(bytes: &[u8], encoding: &'static Encoding) -> DecodedText {
+    let bom = Encoding::for_bom(bytes);
+    let (text, encoding, had_errors) = encoding.decode(bytes);
+    DecodedText {
+        text: Rope::from(text.as_ref()),
+        encoding: FileEncoding {
+            encoding,
+            bom: bom.map(|(bom_encoding, _)| bom_encoding == encoding) == Some(true),
+        },
+        had_errors,
+    }
+}
+
+/// Whether the contents of a file look like binary data rather than text.
+/// Like git, a NUL byte near the start gives it away, except in UTF-16 files
+/// which have a byte order mark. Otherwise it's binary if much of the start
+/// isn't valid UTF-8 and has control characters, as text in legacy encodings
+/// such as Shift_JIS or Latin-1 isn't valid UTF-8 either, but rarely has
+/// control characters.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if Encoding::for_bom(bytes).is_some() {
+        return false;
+    }
+    let sample = &bytes[..cmp::min(bytes.len(), BINARY_DETECTION_LENGTH)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control_len = sample
+        .iter()
+        .filter(|&&byte| {
+            (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)) || byte == 0x7f
+        })
+        .count();
+    invalid_utf8_len(sample) * 100 > sample.len() * BINARY_INVALID_UTF8_PERCENT
+        && control_len * 100 > sample.len() * BINARY_CONTROL_PERCENT
+}
+
+/// The number of bytes which aren't part of valid UTF-8 characters. A
+/// character cut off at the end isn't counted.
+fn invalid_utf8_len(mut bytes: &[u8]) -> usize {
+    let mut invalid_len = 0;
+    while let Err(error) = str::from_utf8(bytes) {
+        match error.error_len() {
+            Some(error_len) => {
+                invalid_len += error_len;
+                bytes = &bytes[error.valid_up_to() + error_len..];
+            }
+            None => break,
+        }
+    }
+    invalid_len
+}
+
+/// Formats binary data as lines of 16 bytes, showing the offset of the first
+/// byte, the bytes in hex and the printable ASCII characters. Only the start
+/// of large files is shown.
+pub fn hex_dump(bytes: &[u8]) -> DecodedText {
+    let shown = &bytes[..cmp::min(bytes.len(), MAX_HEX_DUMP_LENGTH)];
+    let mut dump = String::with_capacity(shown.len() / HEX_DUMP_LINE_LENGTH * 80 + 80);
+    for (index, line) in shown.chunks(HEX_DUMP_LINE_LENGTH).enumerate() {
+        let _ = write!(dump, "{:08x} ", index * HEX_DUMP_LINE_LENGTH);
+        for column in 0..HEX_DUMP_LINE_LENGTH {
+            if column % 8 == 0 {
+                dump.push(' ');
+            }
+            match line.get(column) {
+                Some(byte) => {
+                    let _ = write!(dump, "{:02x} ", byte);
+                }
+                None => dump.push_str("   "),
+            }
+        }
+        dump.push_str(" |");
+        dump.extend(line.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        }));
+        dump.push_str("|\n");
+    }
+    if bytes.len() > shown.len() {
+        let _ = writeln!(dump, "... {} more bytes", bytes.len() - shown.len());
+    }
+    DecodedText {
+        text: Rope::from(dump),
+        encoding: FileEncoding::UTF8,
+        had_errors: false,
+    }
+}
+
+/// Encodes `text` to be written to disk. Fails rather than losing data if
+/// some characters cannot be represented in the encoding.
+pub fn encode(text: &Rope, encoding: FileEncoding) -> io::Result<Vec<u8>> {
+    let FileEncoding { encoding, bom } = encoding;
+    let text: String = text.chars().collect();
+
+    // The WHATWG standard doesn't define encoders for UTF-16, so
+    // `encoding_rs` would write UTF-8 instead
+    if is_utf16(encoding) {
+        let to_bytes = |code_unit: u16| {
+            if encoding == UTF_16LE {
+                code_unit.to_le_bytes()
+            } else {
+                code_unit.to_be_bytes()
+            }
+        };
+        let mut bytes = Vec::with_capacity(2 * text.len() + 2);
+        if bom {
+            bytes.extend_from_slice(&to_bytes(0xfeff));
+        }
+        for code_unit in text.encode_utf16() {
+            bytes.extend_from_slice(&to_bytes(code_unit));
+        }
+        return Ok(bytes);
+    }
+
+    let (encoded, _, had_unmappable) = encoding.encode(&text);
+    if had_unmappable {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the text cannot be encoded as {}", encoding.name()),
+        ));
+    }
+    let mut bytes = Vec::with_capacity(encoded.len() + 3);
+    if bom && encoding == UTF_8 {
+        bytes.extend_from_slice(b"\xef\xbb\xbf");
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
+
+#[inline]
+fn is_utf16(encoding: &'static Encoding) -> bool {
+    encoding == UTF_16LE || encoding == UTF_16BE
+}
+
+/// How many bytes at the start of a file `is_binary` looks at
+pub const BINARY_DETECTION_LENGTH: usize = 8000;
+/// The percentages of invalid UTF-8 and of control bytes above which
+/// `is_binary` takes data without NUL bytes to be binary
+const BINARY_INVALID_UTF8_PERCENT: usize = 30;
+const BINARY_CONTROL_PERCENT: usize = 5;
+const HEX_DUMP_LINE_LENGTH: usize = 16;
+const MAX_HEX_DUMP_LENGTH: usize = 16 * 1024 * 1024;
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::WINDOWS_1252;
+
+    use super::*;
+
+    #[test]
+    fn decode_utf8_with_bom() {
+        let bytes = b"\xef\xbb\xbfhello\n";
+        let decoded = decode(bytes);
+        assert_eq!(decoded.text, "hello\n");
+        assert_eq!(
+            decoded.encoding,
+            FileEncoding {
+                encoding: UTF_8,
+                bom: true
+            }
+        );
+        assert!(!decoded.had_errors);
+        assert_eq!(encode(&decoded.text, decoded.encoding).unwrap(), bytes);
+    }
+
+    #[test]
+    fn decode_utf16le_with_bom() {
+        let bytes = b"\xff\xfeh\x00\xe9\x00\n\x00";
+        let decoded = decode(bytes);
+        assert_eq!(decoded.text, "h\u{e9}\n");
+        assert_eq!(
+            decoded.encoding,
+            FileEncoding {
+                encoding: UTF_16LE,
+                bom: true
+            }
+        );
+        assert!(!decoded.had_errors);
+        assert_eq!(encode(&decoded.text, decoded.encoding).unwrap(), bytes);
+
+        // The byte order mark wins over the encoding asked for
+        assert_eq!(decode_with(bytes, UTF_8).encoding.encoding, UTF_16LE);
+    }
+
+    #[test]
+    fn invalid_bytes_are_replaced() {
+        let decoded = decode_with(b"a\xffb", UTF_8);
+        assert_eq!(decoded.text, "a\u{fffd}b");
+        assert!(decoded.had_errors);
+    }
+
+    #[test]
+    fn invalid_utf8_falls_back_to_a_guess() {
+        let bytes = b"Le caf\xe9 est tr\xe8s bon, merci beaucoup.\n";
+        let decoded = decode(bytes);
+        assert_eq!(
+            decoded.text,
+            "Le caf\u{e9} est tr\u{e8}s bon, merci beaucoup.\n"
+        );
+        assert_eq!(decoded.encoding.encoding, WINDOWS_1252);
+        assert!(!decoded.had_errors);
+        assert_eq!(encode(&decoded.text, decoded.encoding).unwrap(), bytes);
+    }
+
+    #[test]
+    fn encode_fails_on_unmappable_characters() {
+        let text = Rope::from("\u{65e5}\u{672c}");
+        let encoding = FileEncoding {
+            encoding: WINDOWS_1252,
+            bom: false,
+        };
+        assert!(encode(&text, encoding).is_err());
+        assert!(encode(&text, FileEncoding::UTF8).is_ok());
+    }
+
+    #[test]
+    fn detect_binary_data() {
+        assert!(is_binary(b"\x7fELF\x02\x01\x00\x00"));
+        assert!(!is_binary(b"plain text\n"));
+        assert!(!is_binary(b"\xff\xfeh\x00"));
+
+        // Without NUL bytes, mostly invalid UTF-8 with control characters
+        let bytes: Vec<u8> = (1..=255).cycle().take(BINARY_DETECTION_LENGTH).collect();
+        assert!(is_binary(&bytes));
+    }
+
+    #[test]
+    fn text_in_legacy_encodings_is_not_binary() {
+        // Shift_JIS, none of which is valid UTF-8
+        let shift_jis = b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\n".repeat(100);
+        assert!(!is_binary(&shift_jis));
+
+        let latin1 = b"\xe9\xe8\xe0\xfc\xf6 \xe7a\r\n".repeat(100);
+        assert!(!is_binary(&latin1));
+
+        let colored = b"\x1b[31merror\x1b[0m: \xff\xfe\n".repeat(100);
+        assert!(!is_binary(&colored));
+    }
+
+    #[test]
+    fn count_invalid_utf8() {
+        assert_eq!(invalid_utf8_len(b"caf\xc3\xa9"), 0);
+        assert_eq!(invalid_utf8_len(b"\xff\xfea\x80"), 3);
+        // A character cut off at the end is fine
+        assert_eq!(invalid_utf8_len(b"a\xe2\x82"), 0);
+    }
+
+    #[test]
+    fn hex_dump_lines() {
+        let bytes: Vec<u8> = (0x41..0x41 + 20).collect();
+        let dump = hex_dump(&bytes);
+        assert_eq!(
+            dump.text,
+            "00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|\n\
+             00000010  51 52 53 54                                       |QRST|\n"
+        );
+        assert_eq!(dump.encoding, FileEncoding::UTF8);
+        assert!(!dump.had_errors);
+    }
+
+    #[test]
+    fn hex_dump_shows_non_printable_bytes_as_dots() {
+        assert_eq!(
+            hex_dump(b"a\x00 \n\xff").text,
+            "00000000  61 00 20 0a ff                                    |a. ..|\n"
+        );
+    }
+}
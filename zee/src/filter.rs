@@ -0,0 +1,72 @@
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Runs a shell command with `input` as its stdin and returns its stdout,
+/// e.g. to replace a selection with its sorted lines using `sort`. If the
+/// command fails, the error is its stderr, or its exit status if it printed
+/// nothing.
+pub fn run(command: &str, input: String, current_dir: &Path) -> Result<String, String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(current_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+
+    // Write the input on another thread, as the command may fill up its
+    // stdout before reading all of it
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+    // Commands don't have to read their input, e.g. `date`
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => output.status.to_string(),
+            stderr => stderr.to_owned(),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|_| "the output is not valid UTF-8".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_input_with_output() {
+        let current_dir = std::env::temp_dir();
+        assert_eq!(
+            run("sort", "pear\napple\nfig\n".to_owned(), &current_dir),
+            Ok("apple\nfig\npear\n".to_owned())
+        );
+        // The command doesn't have to read its input
+        assert_eq!(
+            run("echo done", "ignored".repeat(100_000), &current_dir),
+            Ok("done\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn failing_commands_are_errors() {
+        let current_dir = std::env::temp_dir();
+        assert_eq!(
+            run("echo oops >&2; exit 3", String::new(), &current_dir),
+            Err("oops".to_owned())
+        );
+        let error = run("false", String::new(), &current_dir).unwrap_err();
+        assert!(error.contains("exit status: 1"), "{}", error);
+        assert!(run("printf '\\377'", String::new(), &current_dir).is_err());
+    }
+}
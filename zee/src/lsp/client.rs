@@ -0,0 +1,306 @@
+use ropey::Rope;
+use serde_json::{json, Value};
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+use zee_grammar::config::LanguageServerConfig;
+
+use super::{file_uri, parse_diagnostics, Event, RequestId};
+
+/// A language server running in a child process, which communicates using
+/// JSON-RPC over its stdin and stdout. Messages are written and read on two
+/// background threads, so a slow server never blocks the editor.
+pub struct Client {
+    process: Child,
+    outgoing: Sender<Outgoing>,
+    next_request_id: RequestId,
+}
+
+enum Outgoing {
+    Message(Value),
+    /// The full text of a changed document, which is only converted to a
+    /// string on the writer thread
+    DidChange {
+        uri: String,
+        version: usize,
+        text: Rope,
+    },
+}
+
+impl Client {
+    /// Starts the server in `root` and initializes it. Messages sent before
+    /// the server replied to `initialize` are queued. Everything the server
+    /// sends is passed to `on_event` on the reader thread.
+    pub fn start(
+        config: &LanguageServerConfig,
+        root: &Path,
+        on_event: impl Fn(Event) + Send + 'static,
+    ) -> io::Result<Self> {
+        let mut process = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = process.stdin.take().expect("stdin is piped");
+        let stdout = process.stdout.take().expect("stdout is piped");
+
+        let initialize = request(
+            INITIALIZE_REQUEST_ID,
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": file_uri(root),
+                "clientInfo": {"name": "zee"},
+                "capabilities": {
+                    "textDocument": {
+                        "synchronization": {"didSave": true},
+                        "publishDiagnostics": {},
+                        "hover": {"contentFormat": ["plaintext", "markdown"]},
+                    },
+                },
+            }),
+        );
+        let (outgoing, outgoing_receiver) = mpsc::channel();
+        let (initialized, initialized_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            write_messages(stdin, initialize, initialized_receiver, outgoing_receiver)
+        });
+        let replies = outgoing.clone();
+        thread::spawn(move || {
+            read_messages(BufReader::new(stdout), initialized, replies, on_event)
+        });
+
+        Ok(Self {
+            process,
+            outgoing,
+            next_request_id: INITIALIZE_REQUEST_ID + 1,
+        })
+    }
+
+    pub fn notify(&self, method: &str, params: Value) {
+        self.send(Outgoing::Message(notification(method, params)));
+    }
+
+    /// Sends a request, its result is passed to `on_event` as a
+    /// `Event::Response` with the returned id
+    pub fn request(&mut self, method: &str, params: Value) -> RequestId {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        self.send(Outgoing::Message(request(id, method, params)));
+        id
+    }
+
+    /// Sends the new content of a document, which is synced in full
+    pub fn did_change(&self, uri: String, version: usize, text: Rope) {
+        self.send(Outgoing::DidChange { uri, version, text });
+    }
+
+    fn send(&self, message: Outgoing) {
+        // The writer thread only stops once the server exited, which the
+        // reader thread reports
+        let _ = self.outgoing.send(message);
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+fn write_messages(
+    mut stdin: ChildStdin,
+    initialize: Value,
+    initialized: Receiver<()>,
+    outgoing: Receiver<Outgoing>,
+) {
+    if write_message(&mut stdin, &initialize).is_err()
+        || initialized.recv().is_err()
+        || write_message(&mut stdin, &notification("initialized", json!({}))).is_err()
+    {
+        return;
+    }
+    for message in outgoing {
+        let message = match message {
+            Outgoing::Message(message) => message,
+            Outgoing::DidChange { uri, version, text } => notification(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": {"uri": uri, "version": version},
+                    "contentChanges": [{"text": text.to_string()}],
+                }),
+            ),
+        };
+        if let Err(error) = write_message(&mut stdin, &message) {
+            log::warn!("Could not write to the language server: {}", error);
+            return;
+        }
+    }
+}
+
+fn read_messages(
+    mut stdout: impl BufRead,
+    initialized: Sender<()>,
+    replies: Sender<Outgoing>,
+    on_event: impl Fn(Event),
+) {
+    loop {
+        let mut message = match read_message(&mut stdout) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(error) => {
+                on_event(Event::Error(error.to_string()));
+                break;
+            }
+        };
+        let method = message.get("method").and_then(Value::as_str);
+        match (method, message.get("id")) {
+            // A request from the server. Only the configuration is answered
+            // with something other than `null`: the defaults, one `null` for
+            // each item asked for.
+            (Some(method), Some(id)) => {
+                let result = match (method, message.pointer("/params/items")) {
+                    ("workspace/configuration", Some(Value::Array(items))) => {
+                        Value::Array(vec![Value::Null; items.len()])
+                    }
+                    _ => Value::Null,
+                };
+                let _ = replies.send(Outgoing::Message(
+                    json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                ));
+            }
+            (Some("textDocument/publishDiagnostics"), None) => {
+                if let Some(event) = parse_diagnostics(message["params"].take()) {
+                    on_event(event);
+                }
+            }
+            // Other notifications, e.g. progress or log messages
+            (Some(_), None) => {}
+            (None, Some(id)) => {
+                let id = id.as_u64();
+                if let Some(error) = message.get("error") {
+                    let error = error
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown error");
+                    on_event(Event::Error(error.to_owned()));
+                    if id == Some(INITIALIZE_REQUEST_ID) {
+                        break;
+                    }
+                }
+                match id {
+                    Some(INITIALIZE_REQUEST_ID) => {
+                        let _ = initialized.send(());
+                    }
+                    Some(id) => on_event(Event::Response {
+                        id,
+                        result: message["result"].take(),
+                    }),
+                    None => {}
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    on_event(Event::Exited);
+}
+
+/// Writes a message with the `Content-Length` header of the base protocol
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+/// Reads the next message, or `None` at the end of the output
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut content = vec![0; content_length];
+    input.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn request(id: RequestId, method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
+
+const INITIALIZE_REQUEST_ID: RequestId = 0;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            request(1, "textDocument/hover", json!({"position": {"line": 0}})),
+            notification("exit", json!(null)),
+        ];
+        let mut output = Vec::new();
+        for message in messages.iter() {
+            write_message(&mut output, message).unwrap();
+        }
+        assert!(output.starts_with(b"Content-Length: "));
+
+        let mut input = &output[..];
+        for message in messages.iter() {
+            assert_eq!(read_message(&mut input).unwrap().as_ref(), Some(message));
+        }
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn read_headers_in_any_case() {
+        let mut input = &b"content-length: 2\r\nContent-Type: utf-8\r\n\r\n{}"[..];
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({})));
+    }
+
+    #[test]
+    fn reject_bad_content_lengths() {
+        let mut input = &b"Content-Length: two\r\n\r\n{}"[..];
+        let error = read_message(&mut input).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // The content is cut short
+        let mut input = &b"Content-Length: 10\r\n\r\n{}"[..];
+        let error = read_message(&mut input).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
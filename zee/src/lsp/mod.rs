@@ -0,0 +1,276 @@
+mod client;
+
+pub use self::client::Client;
+
+use ropey::Rope;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    cmp,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use url::Url;
+use zee_edit::CharIndex;
+
+pub type RequestId = u64;
+
+/// What a language server sent to the editor
+#[derive(Debug)]
+pub enum Event {
+    /// The current diagnostics of a file, replacing the previous ones
+    Diagnostics {
+        file_path: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// The result of a request, `null` if the request failed
+    Response {
+        id: RequestId,
+        result: Value,
+    },
+    Error(String),
+    /// The server exited or closed its output
+    Exited,
+}
+
+/// A position in a document as used by the protocol, where the character
+/// offset in the line counts UTF-16 code units
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(from = "u64")]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Information => "info",
+            Self::Hint => "hint",
+        }
+    }
+}
+
+impl From<u64> for Severity {
+    fn from(severity: u64) -> Self {
+        match severity {
+            2 => Self::Warning,
+            3 => Self::Information,
+            4 => Self::Hint,
+            _ => Self::Error,
+        }
+    }
+}
+
+// The client decides how to show diagnostics without a severity
+impl Default for Severity {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Diagnostic {
+    pub range: Range<Position>,
+    #[serde(default)]
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// The characters covered by the diagnostic. Empty ranges are extended to
+    /// the next character so that they remain visible.
+    pub fn char_range(&self, text: &Rope) -> Range<CharIndex> {
+        let start = char_index(text, self.range.start);
+        let end = char_index(text, self.range.end);
+        if end > start {
+            start..end
+        } else {
+            start..cmp::min(start + 1, text.len_chars())
+        }
+    }
+}
+
+/// Converts a protocol position to a character index. Positions past the end
+/// of a line or of the text are clamped to it.
+pub fn char_index(text: &Rope, position: Position) -> CharIndex {
+    if position.line >= text.len_lines() {
+        return text.len_chars();
+    }
+    let line_start = text.line_to_char(position.line);
+    let line_end = line_start + text.line(position.line).len_chars();
+    let utf16_start = text.char_to_utf16_cu(line_start);
+    let utf16_end = text.char_to_utf16_cu(line_end);
+    text.utf16_cu_to_char(cmp::min(utf16_start + position.character, utf16_end))
+}
+
+/// Converts a character index to a protocol position
+pub fn position(text: &Rope, char_index: CharIndex) -> Position {
+    let line = text.char_to_line(char_index);
+    Position {
+        line,
+        character: text.char_to_utf16_cu(char_index)
+            - text.char_to_utf16_cu(text.line_to_char(line)),
+    }
+}
+
+/// The `file://` URI of an absolute path
+pub fn file_uri(file_path: &Path) -> Option<String> {
+    Url::from_file_path(file_path).ok().map(String::from)
+}
+
+/// The lines of a hover result's contents, without the markdown code fences
+/// and rules servers use to separate signatures from documentation
+pub fn hover_lines(result: &Value) -> Vec<String> {
+    result
+        .get("contents")
+        .map(markup_text)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("```") && *line != "---")
+        .map(String::from)
+        .collect()
+}
+
+// Hover contents are a string, a `{ language, value }` or `{ kind, value }`
+// object, or an array of these
+fn markup_text(contents: &Value) -> String {
+    match contents {
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(markup_text).collect::<Vec<_>>().join("\n"),
+        Value::Object(object) => object
+            .get("value")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned(),
+        _ => String::new(),
+    }
+}
+
+#[derive(Deserialize)]
+struct PublishDiagnosticsParams {
+    uri: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn parse_diagnostics(params: Value) -> Option<Event> {
+    let params: PublishDiagnosticsParams = serde_json::from_value(params).ok()?;
+    Some(Event::Diagnostics {
+        file_path: Url::parse(&params.uri).ok()?.to_file_path().ok()?,
+        diagnostics: params.diagnostics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn at(line: usize, character: usize) -> Position {
+        Position { line, character }
+    }
+
+    #[test]
+    fn positions_count_utf16_code_units() {
+        // The emoji is one character, but two UTF-16 code units
+        let text = Rope::from("a\u{1f600}b\n\u{e9}c\n");
+        assert_eq!(char_index(&text, at(0, 0)), 0);
+        assert_eq!(char_index(&text, at(0, 1)), 1);
+        assert_eq!(char_index(&text, at(0, 3)), 2);
+        assert_eq!(char_index(&text, at(1, 1)), 5);
+        assert_eq!(position(&text, 2), at(0, 3));
+        assert_eq!(position(&text, 3), at(0, 4));
+        assert_eq!(position(&text, 5), at(1, 1));
+
+        for char_index in 0..=text.len_chars() {
+            assert_eq!(
+                super::char_index(&text, position(&text, char_index)),
+                char_index
+            );
+        }
+    }
+
+    #[test]
+    fn positions_past_the_end_are_clamped() {
+        let text = Rope::from("ab\ncd");
+        assert_eq!(char_index(&text, at(0, 10)), 3);
+        assert_eq!(char_index(&text, at(1, 10)), 5);
+        assert_eq!(char_index(&text, at(5, 0)), 5);
+    }
+
+    #[test]
+    fn parse_severities() {
+        assert_eq!(Severity::from(1), Severity::Error);
+        assert_eq!(Severity::from(2), Severity::Warning);
+        assert_eq!(Severity::from(3), Severity::Information);
+        assert_eq!(Severity::from(4), Severity::Hint);
+        assert_eq!(Severity::from(0), Severity::Error);
+
+        let diagnostic: Diagnostic = serde_json::from_value(json!({
+            "range": {
+                "start": {"line": 0, "character": 1},
+                "end": {"line": 0, "character": 2},
+            },
+            "message": "unused",
+        }))
+        .unwrap();
+        assert_eq!(diagnostic.severity, Severity::Error);
+    }
+
+    #[test]
+    fn diagnostic_ranges_stay_in_the_text() {
+        let text = Rope::from("ab\ncd");
+        let diagnostic = |start, end| Diagnostic {
+            range: start..end,
+            severity: Severity::Error,
+            message: String::new(),
+        };
+        assert_eq!(diagnostic(at(0, 1), at(1, 1)).char_range(&text), 1..4);
+        // Empty ranges cover the next character, if there's one
+        assert_eq!(diagnostic(at(1, 0), at(1, 0)).char_range(&text), 3..4);
+        assert_eq!(diagnostic(at(1, 2), at(1, 2)).char_range(&text), 5..5);
+        assert_eq!(diagnostic(at(1, 1), at(9, 0)).char_range(&text), 4..5);
+        assert_eq!(diagnostic(at(9, 0), at(9, 5)).char_range(&text), 5..5);
+    }
+
+    #[test]
+    fn hover_contents() {
+        // Deprecated `MarkedString`s, alone or in an array
+        assert_eq!(
+            hover_lines(&json!({"contents": "fn main()"})),
+            vec!["fn main()"]
+        );
+        assert_eq!(
+            hover_lines(&json!({"contents": [
+                {"language": "rust", "value": "fn main()"},
+                "The entry point",
+            ]})),
+            vec!["fn main()", "The entry point"]
+        );
+
+        // `MarkupContent`, without the code fences and rules
+        assert_eq!(
+            hover_lines(&json!({"contents": {
+                "kind": "markdown",
+                "value": "```rust\nfn main()\n```\n---\n\nThe entry point  \n",
+            }})),
+            vec!["fn main()", "The entry point"]
+        );
+
+        assert!(hover_lines(&json!({})).is_empty());
+        assert!(hover_lines(&Value::Null).is_empty());
+        assert_eq!(markup_text(&json!(1)), "");
+    }
+}
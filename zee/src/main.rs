@@ -1,23 +1,31 @@
 #![allow(clippy::reversed_empty_ranges)]
 
+mod browser;
 mod clipboard;
 mod components;
 mod config;
 mod editor;
+mod editorconfig;
+mod encoding;
 mod error;
+mod filter;
 mod logging;
+mod lsp;
 mod panicking;
+mod state;
 mod syntax;
 mod task;
+#[cfg(test)]
+mod testing;
 mod utils;
 mod versioned;
 
 use clap::Parser;
-use std::{env, path::PathBuf};
+use std::{cell::Cell, env, path::PathBuf, process, rc::Rc};
 use zi::ComponentExt;
 
 use crate::{
-    editor::{Editor, Properties as EditorProperties},
+    editor::{Editor, ExitStatus, Properties as EditorProperties},
     error::Result,
     task::TaskPool,
 };
@@ -26,7 +34,8 @@ use crate::{
 #[clap(about, version)]
 struct Args {
     #[clap(name = "file", parse(from_os_str))]
-    /// Open these files to edit after starting zee
+    /// Open these files to edit after starting zee, optionally at a position
+    /// given as `path:line` or `path:line:column`
     files: Vec<PathBuf>,
 
     #[clap(long = "config-dir", parse(from_os_str))]
@@ -54,7 +63,7 @@ struct Args {
     verbose: bool,
 }
 
-fn start_editor() -> Result<()> {
+fn start_editor() -> Result<ExitStatus> {
     let args = Args::parse();
 
     if args.initialise || args.build {
@@ -84,7 +93,8 @@ fn start_editor() -> Result<()> {
 
     // Finds the editor configuration. If we cannot for any reason, we'll use the
     // default ones to ensure the editor opens in any environment.
-    let editor_config = config::find_editor_config(args.config_dir);
+    let config_dir = config::find_config_dir(args.config_dir);
+    let editor_config = config::find_editor_config(config_dir.as_deref());
 
     // Download and build tree sitter parsers if requested
     if args.build {
@@ -95,26 +105,36 @@ fn start_editor() -> Result<()> {
     }
 
     if args.build || args.initialise {
-        return Ok(());
+        return Ok(ExitStatus::Quit);
     }
 
-    // Instantiate the editor, open any files specified as arguments and start the UI loop
+    // Instantiate the editor, open any files specified as arguments and start the UI loop.
+    // The loop also returns when interrupted with `C-c`, in which case the editor didn't
+    // get to quit.
+    let exit_status = Rc::new(Cell::new(ExitStatus::Interrupted));
     zi_term::incremental()?.run_event_loop(Editor::with(EditorProperties {
         args_files: args.files,
         current_working_dir: env::current_dir()?,
+        config_dir,
         config: editor_config,
         task_pool: TaskPool::new()?,
         clipboard: clipboard::create()?,
+        exit_status: exit_status.clone(),
     }))?;
 
-    Ok(())
+    Ok(exit_status.get())
 }
 
 fn main() -> Result<()> {
-    panicking::print_panic_after_unwind(|| {
+    let exit_status = panicking::print_panic_after_unwind(|| {
         start_editor().map_err(|error| {
             log::error!("Zee exited with: {}", error);
             error
         })
-    })
+    })?;
+    if exit_status == ExitStatus::Interrupted {
+        log::warn!("Zee was interrupted, unsaved changes were not saved");
+        process::exit(exit_status.code());
+    }
+    Ok(())
 }
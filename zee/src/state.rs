@@ -0,0 +1,220 @@
+use parking_lot::Mutex;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    error::{Context, Result},
+    task::TaskPool,
+};
+
+/// State of the editor that is remembered between sessions, stored in
+/// `state.ron` in the configuration directory. Unlike the configuration file,
+/// it's written by the editor itself.
+///
+/// Several instances of zee can run at the same time, so only the changes
+/// made in this session are written, on top of the state file as it is when
+/// it's written.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename = "ZeeState")]
+pub struct EditorState {
+    /// The name of the last selected theme
+    #[serde(default)]
+    pub theme: Option<String>,
+    /// Recently opened files, the most recent first
+    #[serde(default)]
+    pub recent_files: Vec<PathBuf>,
+    /// The windows open when zee last quit, for each working directory
+    #[serde(default)]
+    pub layouts: Vec<SavedLayout>,
+    /// Where the cursor was in recently closed files, the most recent first
+    #[serde(default)]
+    pub file_positions: Vec<FilePosition>,
+    /// The changes not written to the state file yet, shared with the
+    /// background task writing them
+    #[serde(skip)]
+    unsaved: Arc<Mutex<Vec<Update>>>,
+}
+
+/// A change to the state made in this session
+#[derive(Clone, Debug)]
+enum Update {
+    Theme(String),
+    RecentFile(PathBuf),
+    Layout(SavedLayout),
+    FilePosition(FilePosition),
+}
+
+/// The position of the cursor in a file when its buffer was last closed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FilePosition {
+    pub file_path: PathBuf,
+    pub line: usize,
+    /// The column counted in grapheme clusters
+    pub column: usize,
+}
+
+/// The windows of a session and the files they show
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SavedLayout {
+    /// The working directory of the session
+    pub directory: PathBuf,
+    /// The windows and splits, in the order they're laid out
+    pub nodes: Vec<SavedNode>,
+    /// The index of the focused window
+    pub focused: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum SavedNode {
+    /// A window with its size if it was resized
+    Window {
+        file_path: PathBuf,
+        size: Option<usize>,
+    },
+    /// The start of a split, laying out its windows in a column (one below
+    /// the other) or in a row
+    Split { column: bool },
+    /// The end of the last split that was started
+    EndSplit,
+}
+
+impl EditorState {
+    /// Reads the state file, falling back to the default state if it's
+    /// missing or invalid, e.g. after an incompatible upgrade
+    pub fn load(config_dir: Option<&Path>) -> Self {
+        let path = match config_dir.map(state_file_path) {
+            Some(path) if path.exists() => path,
+            _ => return Default::default(),
+        };
+        fs::read_to_string(&path)
+            .with_context(|| format!("Could not read state file `{}`", path.display()))
+            .and_then(|contents| {
+                ron::de::from_str(&contents)
+                    .with_context(|| format!("Could not parse state file `{}`", path.display()))
+            })
+            .map_err(|error| log::error!("{:#}", error))
+            .unwrap_or_default()
+    }
+
+    /// Remembers the name of the selected theme
+    pub fn set_theme(&mut self, theme: String) {
+        self.record(Update::Theme(theme));
+    }
+
+    /// Moves `path` to the front of the recently opened files
+    pub fn add_recent_file(&mut self, path: PathBuf) {
+        self.record(Update::RecentFile(path));
+    }
+
+    /// The windows saved for a working directory
+    pub fn layout(&self, directory: &Path) -> Option<&SavedLayout> {
+        self.layouts
+            .iter()
+            .find(|layout| layout.directory == directory)
+    }
+
+    /// Replaces the windows saved for the layout's directory
+    pub fn set_layout(&mut self, layout: SavedLayout) {
+        self.record(Update::Layout(layout));
+    }
+
+    /// The cursor position saved for a file
+    pub fn file_position(&self, file_path: &Path) -> Option<&FilePosition> {
+        self.file_positions
+            .iter()
+            .find(|position| position.file_path == file_path)
+    }
+
+    /// Replaces the cursor position saved for the position's file
+    pub fn set_file_position(&mut self, position: FilePosition) {
+        self.record(Update::FilePosition(position));
+    }
+
+    /// Writes the unsaved changes to the state file in the background
+    pub fn save_in_background(&self, config_dir: Option<PathBuf>, task_pool: &TaskPool) {
+        let unsaved = Arc::clone(&self.unsaved);
+        task_pool.spawn(move |_| {
+            if let Err(error) = write_changes(&unsaved, config_dir.as_deref()) {
+                log::error!("{:#}", error);
+            }
+        });
+    }
+
+    /// Writes the unsaved changes to the state file, e.g. when quitting. A
+    /// write in the background is waited for.
+    pub fn save(&self, config_dir: Option<&Path>) -> Result<()> {
+        write_changes(&self.unsaved, config_dir)
+    }
+
+    fn record(&mut self, update: Update) {
+        self.apply(update.clone());
+        self.unsaved.lock().push(update);
+    }
+
+    fn apply(&mut self, update: Update) {
+        match update {
+            Update::Theme(theme) => self.theme = Some(theme),
+            Update::RecentFile(path) => {
+                self.recent_files.retain(|recent| *recent != path);
+                self.recent_files.insert(0, path);
+                self.recent_files.truncate(MAX_RECENT_FILES);
+            }
+            Update::Layout(layout) => {
+                self.layouts
+                    .retain(|saved| saved.directory != layout.directory);
+                self.layouts.insert(0, layout);
+                self.layouts.truncate(MAX_SAVED_LAYOUTS);
+            }
+            Update::FilePosition(position) => {
+                self.file_positions
+                    .retain(|saved| saved.file_path != position.file_path);
+                self.file_positions.insert(0, position);
+                self.file_positions.truncate(MAX_FILE_POSITIONS);
+            }
+        }
+    }
+}
+
+/// Applies the unsaved changes to the state file as it is on disk, which
+/// another instance of zee may have written since it was read. The lock is
+/// held while writing, so writes of this instance don't overlap.
+fn write_changes(unsaved: &Mutex<Vec<Update>>, config_dir: Option<&Path>) -> Result<()> {
+    let path = match config_dir.map(state_file_path) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let mut unsaved = unsaved.lock();
+    if unsaved.is_empty() {
+        return Ok(());
+    }
+    let mut state = EditorState::load(config_dir);
+    for update in unsaved.iter().cloned() {
+        state.apply(update);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Could not create config directory `{}`", parent.display()))?;
+    }
+    let contents = ron::ser::to_string_pretty(&state, Default::default())
+        .context("Could not serialize the editor state")?;
+    // Other instances reading the file never see it half written
+    let temp_path = path.with_extension(format!("ron.{}.tmp", std::process::id()));
+    fs::write(&temp_path, contents)
+        .and_then(|()| fs::rename(&temp_path, &path))
+        .with_context(|| format!("Could not write state file `{}`", path.display()))?;
+    unsaved.clear();
+    Ok(())
+}
+
+fn state_file_path(config_dir: &Path) -> PathBuf {
+    config_dir.join("state.ron")
+}
+
+const MAX_RECENT_FILES: usize = 50;
+const MAX_SAVED_LAYOUTS: usize = 20;
+const MAX_FILE_POSITIONS: usize = 500;
@@ -1,3 +1,4 @@
+use serde_derive::{Deserialize, Serialize};
 use zi::terminal::{Background, Style};
 
 use zee_edit::{CharIndex, Cursor};
@@ -9,6 +10,11 @@ pub struct Theme {
     pub selection_background: Background,
     pub text: Style,
     pub text_current_line: Style,
+    pub search_match: Style,
+    pub matching_bracket: Style,
+    pub ruler_background: Background,
+    pub whitespace: Style,
+    pub trailing_whitespace: Style,
     pub code_char: Style,
     pub code_comment: Style,
     pub code_comment_doc: Style,
@@ -25,6 +31,22 @@ pub struct Theme {
     pub code_variant: Style,
 }
 
+/// How the cursor is drawn in buffers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CursorShape {
+    /// The character under the cursor is drawn with the cursor's colours
+    Block,
+    /// The character under the cursor keeps its colours and is underlined
+    Underline,
+}
+
+impl Default for CursorShape {
+    fn default() -> Self {
+        Self::Block
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 #[inline]
 pub fn text_style_at_char(
     theme: &Theme,
@@ -34,6 +56,9 @@ pub fn text_style_at_char(
     line_under_cursor: bool,
     scope: &str,
     is_error: bool,
+    is_search_match: bool,
+    is_matching_bracket: bool,
+    cursor_shape: CursorShape,
 ) -> Style {
     let starts = |pattern| scope.starts_with(pattern);
 
@@ -77,7 +102,9 @@ pub fn text_style_at_char(
         _ => theme.text,
     };
 
-    if char_index == cursor.range().start || cursor.range().contains(&char_index) {
+    let is_under_cursor =
+        char_index == cursor.range().start || cursor.range().contains(&char_index);
+    let style = if is_under_cursor && cursor_shape == CursorShape::Block {
         let cursor_style = if focused {
             theme.cursor_focused
         } else {
@@ -89,6 +116,26 @@ pub fn text_style_at_char(
             bold: style.bold,
             underline: style.underline,
         }
+    } else if is_matching_bracket {
+        theme.matching_bracket
+    } else if is_search_match {
+        Style {
+            background: theme.search_match.background,
+            foreground: theme.search_match.foreground,
+            bold: style.bold,
+            underline: style.underline,
+        }
+    } else if cursor.selection().contains(&char_index)
+        && style.foreground == theme.selection_background
+    {
+        // Text the colour of the selection is reversed, as in the monochrome
+        // theme
+        Style {
+            background: theme.selection_background,
+            foreground: theme.text.background,
+            bold: style.bold,
+            underline: style.underline,
+        }
     } else {
         let background = if cursor.selection().contains(&char_index) {
             theme.selection_background
@@ -103,5 +150,15 @@ pub fn text_style_at_char(
             bold: style.bold,
             underline: style.underline,
         }
+    };
+
+    // An underline cursor keeps the style of the text under it
+    if is_under_cursor && cursor_shape == CursorShape::Underline {
+        Style {
+            underline: true,
+            ..style
+        }
+    } else {
+        style
     }
 }
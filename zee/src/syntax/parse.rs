@@ -19,6 +19,7 @@ use crate::{
 };
 
 pub struct ParserStatus {
+    pool_id: usize,
     task_id: TaskId,
     parser: CancelableParser,
     parsed: Option<ParsedSyntax>, // None if the parsing operation has been cancelled
@@ -61,19 +62,25 @@ impl DerefMut for ParseTree {
 
 pub struct ParserPool {
     pub tree: Option<ParseTree>,
+    id: usize,
     language: Language,
     parsers: Vec<CancelableParser>,
     current_parse_task: Option<(TaskId, CancelFlag)>,
 }
 
 impl ParserPool {
-    pub fn new(language: Language) -> Self {
-        Self {
+    pub fn new(language: Language) -> Result<Self> {
+        // Fail early if the grammar was built against an incompatible version
+        // of tree sitter
+        let mut parser = Parser::new();
+        parser.set_language(language)?;
+        Ok(Self {
+            id: NEXT_PARSER_POOL_ID.fetch_add(1, Ordering::SeqCst),
             language,
-            parsers: vec![],
+            parsers: vec![CancelableParser::new(parser)],
             tree: None,
             current_parse_task: None,
-        }
+        })
     }
 
     pub fn ensure_tree(
@@ -98,11 +105,12 @@ impl ParserPool {
             let mut parser = Parser::new();
             parser
                 .set_language(self.language)
-                .expect("Incompatible language grammar");
+                .expect("Language grammar was checked when the pool was created");
             CancelableParser::new(parser)
         });
 
         let cancel_flag = parser.cancel_flag().clone();
+        let pool_id = self.id;
         let raw_tree = self.tree.clone().map(|tree| tree.tree);
         let task_id = task_pool.spawn(move |task_id| {
             let maybe_tree = parser.parse_with(
@@ -119,11 +127,13 @@ impl ParserPool {
 
             on_parse(match maybe_tree {
                 Some(tree) => Ok(ParserStatus {
+                    pool_id,
                     task_id,
                     parser,
                     parsed: Some(ParsedSyntax { tree, text }),
                 }),
                 None => Ok(ParserStatus {
+                    pool_id,
                     task_id,
                     parser,
                     parsed: None,
@@ -136,13 +146,22 @@ impl ParserPool {
         self.current_parse_task = Some((task_id, cancel_flag));
     }
 
-    pub fn handle_parse_syntax_done(&mut self, version: usize, status: ParserStatus) {
+    /// Stores the syntax tree of a finished parser task. Returns `true` if the
+    /// tree was updated, `false` if the result was stale or cancelled.
+    pub fn handle_parse_syntax_done(&mut self, version: usize, status: ParserStatus) -> bool {
         let ParserStatus {
+            pool_id,
             task_id,
             parser,
             parsed,
         } = status;
 
+        // The parser was created by a different pool, possibly for another
+        // language, after the buffer's mode has changed
+        if pool_id != self.id {
+            return false;
+        }
+
         // Collect the parser for later reuse
         parser.cancel_flag().clear();
         self.parsers.push(parser);
@@ -154,14 +173,18 @@ impl ParserPool {
             .map(|(expected_task_id, _)| *expected_task_id != task_id)
             .unwrap_or(true)
         {
-            return;
+            return false;
         }
         self.current_parse_task = None;
 
         // If the parser task hasn't been cancelled, store the new syntax tree
-        if let Some(ParsedSyntax { tree, text }) = parsed {
-            assert!(tree.root_node().end_byte() == text.len_bytes());
-            self.tree = Some(ParseTree { version, tree });
+        match parsed {
+            Some(ParsedSyntax { tree, text }) => {
+                assert!(tree.root_node().end_byte() == text.len_bytes());
+                self.tree = Some(ParseTree { version, tree });
+                true
+            }
+            None => false,
         }
     }
 
@@ -242,5 +265,7 @@ impl DerefMut for CancelableParser {
     }
 }
 
+static NEXT_PARSER_POOL_ID: AtomicUsize = AtomicUsize::new(0);
+
 const CANCEL_FLAG_UNSET: usize = 0;
 const CANCEL_FLAG_SET: usize = 1;
@@ -1,7 +1,12 @@
 use rayon::{ThreadPool, ThreadPoolBuilder};
 use std::{
+    collections::HashMap,
     num::NonZeroUsize,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use crate::error::Result;
@@ -9,10 +14,89 @@ use crate::error::Result;
 #[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub struct TaskId(usize);
 
+/// Asks a running task to stop early. Long running tasks check it periodically
+/// and return without a result once it's cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Lets a running task describe how far along it is, e.g. `listing files
+/// (1200)`. The description is shown in the prompt while the task runs.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    id: TaskId,
+    active: ActiveTasks,
+}
+
+impl Progress {
+    pub fn report(&self, description: impl Into<String>) {
+        if let Some(task) = self.active.lock().unwrap().get_mut(&self.id) {
+            task.progress = Some(description.into());
+        }
+    }
+}
+
+/// A task spawned with [`TaskPool::spawn_cancelable`]
+#[derive(Debug)]
+pub struct TaskHandle {
+    id: TaskId,
+    token: CancelToken,
+}
+
+impl TaskHandle {
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+}
+
+/// What's shown in the prompt while tasks are running
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskPoolStatus {
+    pub num_active: usize,
+    /// The progress reported by the longest running task, if any
+    pub progress: Option<String>,
+}
+
+#[derive(Debug)]
+struct ActiveTask {
+    started: Instant,
+    progress: Option<String>,
+}
+
+type ActiveTasks = Arc<Mutex<HashMap<TaskId, ActiveTask>>>;
+
+// Removes a task from the active ones when it returns, or panics
+struct ActiveTaskGuard {
+    id: TaskId,
+    active: ActiveTasks,
+}
+
+impl Drop for ActiveTaskGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(&self.id);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TaskPool {
     thread_pool: ThreadPool,
     next_task_id: AtomicUsize,
+    active: ActiveTasks,
 }
 
 impl TaskPool {
@@ -34,14 +118,69 @@ impl TaskPool {
         Ok(Self {
             thread_pool: ThreadPoolBuilder::new().num_threads(num_threads).build()?,
             next_task_id: AtomicUsize::new(0),
+            active: ActiveTasks::default(),
         })
     }
 
     pub fn spawn(&self, task: impl FnOnce(TaskId) + Send + 'static) -> TaskId {
         let id = TaskId(self.next_task_id.fetch_add(1, Ordering::SeqCst));
-        self.thread_pool.spawn(move || task(id));
+        self.active.lock().unwrap().insert(
+            id,
+            ActiveTask {
+                started: Instant::now(),
+                progress: None,
+            },
+        );
+        let guard = ActiveTaskGuard {
+            id,
+            active: self.active.clone(),
+        };
+        self.thread_pool.spawn(move || {
+            let _guard = guard;
+            task(id)
+        });
         id
     }
+
+    /// Spawns a task which can be cancelled with the returned handle, e.g.
+    /// when its result isn't needed anymore. The task can report its progress
+    /// while it runs.
+    pub fn spawn_cancelable(
+        &self,
+        task: impl FnOnce(TaskId, CancelToken, Progress) + Send + 'static,
+    ) -> TaskHandle {
+        let token = CancelToken::default();
+        let task_token = token.clone();
+        let active = self.active.clone();
+        let id = self.spawn(move |id| task(id, task_token, Progress { id, active }));
+        TaskHandle { id, token }
+    }
+
+    /// Whether any task is running, including ones too recent to be reported
+    /// by [`TaskPool::status`]
+    pub fn has_active_tasks(&self) -> bool {
+        !self.active.lock().unwrap().is_empty()
+    }
+
+    /// The tasks which have been running for a while, `None` if there aren't
+    /// any. Short tasks, like reparsing a buffer after an edit, are left out
+    /// so the prompt doesn't flicker on every keystroke.
+    pub fn status(&self) -> Option<TaskPoolStatus> {
+        let active = self.active.lock().unwrap();
+        let mut long_running: Vec<_> = active
+            .values()
+            .filter(|task| task.started.elapsed() >= STATUS_DELAY)
+            .collect();
+        if long_running.is_empty() {
+            return None;
+        }
+        long_running.sort_by_key(|task| task.started);
+        Some(TaskPoolStatus {
+            num_active: long_running.len(),
+            progress: long_running.iter().find_map(|task| task.progress.clone()),
+        })
+    }
 }
 
 const MAX_NUMBER_OF_THREADS: usize = 8;
+const STATUS_DELAY: Duration = Duration::from_millis(250);
@@ -1,4 +1,10 @@
-use ropey::Rope;
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsStr,
+    fs,
+    path::{Component, Path, PathBuf},
+};
 
 #[derive(Copy)]
 pub struct StaticRefEq<T: 'static>(&'static T);
@@ -29,8 +35,207 @@ impl<T> From<&'static T> for StaticRefEq<T> {
     }
 }
 
-pub fn ensure_trailing_newline_with_content(text: &mut Rope) {
-    if text.len_chars() == 0 || text.char(text.len_chars() - 1) != '\n' {
-        text.insert_char(text.len_chars(), '\n');
+/// Parses a one-based `line` or `line:column` position, returning zero-based
+/// indices. Returns `None` if `input` is not a valid position.
+pub fn parse_line_column(input: &str) -> Option<(usize, Option<usize>)> {
+    let parse_index = |number: &str| {
+        number
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .map(|number| number.saturating_sub(1))
+    };
+    match input.split_once(':') {
+        Some((line, column)) => Some((parse_index(line)?, Some(parse_index(column)?))),
+        None => Some((parse_index(input)?, None)),
+    }
+}
+
+/// Splits a `path:line` or `path:line:column` suffix, as printed by compilers
+/// and grep, from a path. Returns the path and the zero-based position, or
+/// `None` if there is no such suffix.
+pub fn split_path_position(path: &Path) -> Option<(PathBuf, usize, Option<usize>)> {
+    let path = path.to_str()?;
+    path.match_indices(':').find_map(|(index, _)| {
+        let (file_path, position) = (&path[..index], &path[index + 1..]);
+        let position = position.strip_suffix(':').unwrap_or(position);
+        if file_path.is_empty()
+            || !position
+                .chars()
+                .all(|character| character.is_ascii_digit() || character == ':')
+        {
+            return None;
+        }
+        let (line, column) = parse_line_column(position)?;
+        Some((PathBuf::from(file_path), line, column))
+    })
+}
+
+/// The path of a file with symbolic links resolved, used as a key for the
+/// file, e.g. in the list of recent files. Paths of files which don't exist
+/// are made absolute with `.` and `..` removed instead.
+pub fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| {
+        let current_dir = env::current_dir().unwrap_or_default();
+        normalize_path(&current_dir.join(path))
+    })
+}
+
+/// Removes `.` and `..` components from a path without accessing the file
+/// system, so `..` after a symbolic link goes back to the link's directory
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // The root has no parent
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                // A relative path may start with `..`
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The file a symbolic link points to, with any further links resolved.
+/// Returns `None` if `path` isn't a link or the link is broken.
+pub fn symlink_target(path: &Path) -> Option<PathBuf> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if !metadata.file_type().is_symlink() {
+        return None;
+    }
+    fs::canonicalize(path).ok()
+}
+
+/// The files conventionally paired with a file, e.g. `foo.c` for `foo.h`,
+/// given file name suffixes mapped to the suffixes of their counterparts. The
+/// longest suffix of the file name is used, so that `_test.go` takes
+/// precedence over `.go`.
+pub fn related_files(file_path: &Path, rules: &HashMap<String, Vec<String>>) -> Vec<PathBuf> {
+    let file_name = match file_path.file_name().and_then(OsStr::to_str) {
+        Some(file_name) => file_name,
+        None => return Vec::new(),
+    };
+    let (suffix, counterparts) = match rules
+        .iter()
+        .filter(|(suffix, _)| {
+            file_name.len() > suffix.len() && file_name.ends_with(suffix.as_str())
+        })
+        .max_by_key(|(suffix, _)| suffix.len())
+    {
+        Some(rule) => rule,
+        None => return Vec::new(),
+    };
+    let stem = &file_name[..file_name.len() - suffix.len()];
+    counterparts
+        .iter()
+        .map(|counterpart| file_path.with_file_name(format!("{}{}", stem, counterpart)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_positions() {
+        assert_eq!(parse_line_column("12"), Some((11, None)));
+        assert_eq!(parse_line_column(" 3:7 "), Some((2, Some(6))));
+        assert_eq!(parse_line_column("0"), Some((0, None)));
+        assert_eq!(parse_line_column(""), None);
+        assert_eq!(parse_line_column("3:"), None);
+        assert_eq!(parse_line_column("a:1"), None);
+    }
+
+    #[test]
+    fn split_positions_from_paths() {
+        assert_eq!(
+            split_path_position(Path::new("a.rs:3")),
+            Some((PathBuf::from("a.rs"), 2, None))
+        );
+        assert_eq!(
+            split_path_position(Path::new("a.rs:3:7")),
+            Some((PathBuf::from("a.rs"), 2, Some(6)))
+        );
+        // As printed by grep
+        assert_eq!(
+            split_path_position(Path::new("a.rs:3:")),
+            Some((PathBuf::from("a.rs"), 2, None))
+        );
+        assert_eq!(split_path_position(Path::new("a.rs")), None);
+        assert_eq!(split_path_position(Path::new(":3")), None);
+        // A drive letter isn't a position
+        assert_eq!(split_path_position(Path::new(r"C:\x")), None);
+    }
+
+    #[test]
+    fn normalize_paths() {
+        assert_eq!(normalize_path(Path::new("/a/./b/../c")), Path::new("/a/c"));
+        assert_eq!(normalize_path(Path::new("/../a")), Path::new("/a"));
+        assert_eq!(normalize_path(Path::new("../a/..")), Path::new(".."));
+        assert_eq!(normalize_path(Path::new("./a/.")), Path::new("a"));
+        assert_eq!(normalize_path(Path::new(".")), Path::new(""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_symlinks() {
+        let directory = crate::testing::TempDir::new("symlink-target");
+        let file = directory.write("file", "");
+        let link = directory.path().join("link");
+        let link_to_link = directory.path().join("link-to-link");
+        let broken = directory.path().join("broken");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        std::os::unix::fs::symlink(&link, &link_to_link).unwrap();
+        std::os::unix::fs::symlink(directory.path().join("missing"), &broken).unwrap();
+
+        let file = fs::canonicalize(file).unwrap();
+        assert_eq!(symlink_target(&link), Some(file.clone()));
+        assert_eq!(symlink_target(&link_to_link), Some(file.clone()));
+        assert_eq!(symlink_target(&file), None);
+        assert_eq!(symlink_target(&broken), None);
+    }
+
+    #[test]
+    fn find_related_files() {
+        let rules: HashMap<String, Vec<String>> = [
+            (".h", vec![".c", ".cpp"]),
+            (".c", vec![".h"]),
+            (".go", vec!["_test.go"]),
+            ("_test.go", vec![".go"]),
+        ]
+        .iter()
+        .map(|(suffix, counterparts)| {
+            let counterparts = counterparts.iter().map(|&suffix| suffix.to_owned());
+            (suffix.to_string(), counterparts.collect())
+        })
+        .collect();
+
+        assert_eq!(
+            related_files(Path::new("src/foo.h"), &rules),
+            [PathBuf::from("src/foo.c"), PathBuf::from("src/foo.cpp")]
+        );
+        assert_eq!(
+            related_files(Path::new("foo.c"), &rules),
+            [PathBuf::from("foo.h")]
+        );
+        // The longest suffix wins
+        assert_eq!(
+            related_files(Path::new("foo_test.go"), &rules),
+            [PathBuf::from("foo.go")]
+        );
+        assert_eq!(
+            related_files(Path::new("foo.go"), &rules),
+            [PathBuf::from("foo_test.go")]
+        );
+        // A file named like the suffix has no stem
+        assert!(related_files(Path::new(".h"), &rules).is_empty());
+        assert!(related_files(Path::new("foo.rs"), &rules).is_empty());
     }
 }